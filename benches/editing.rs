@@ -0,0 +1,109 @@
+//! Timings for typing, pasting, redrawing and searching in a large file, so a change to
+//! the editing or drawing code can be compared against the numbers from before it:
+//!
+//! ```text
+//! cargo bench                    # or e.g. cargo bench -- redraw to run just one
+//! ```
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use ratatui::{backend::TestBackend, layout::Rect, Terminal};
+use zepto::Editor;
+
+/// Lines in the generated "large file" the editing benchmarks work on.
+const LARGE_FILE_LINES: usize = 100_000;
+
+/// Rust-looking source with a mix of keywords, strings, comments and indentation, so
+/// highlighting has real work to do.
+fn sample_lines(count: usize) -> Vec<String> {
+    (0..count)
+        .map(|i| match i % 4 {
+            0 => format!("fn item_{}(value: usize) -> usize {{", i),
+            1 => format!("    let name = \"line {}\"; // keeps the tokenizer busy", i),
+            2 => format!("    value * {} + name.len()", i % 97),
+            _ => "}".to_string(),
+        })
+        .collect()
+}
+
+fn large_file() -> Editor<TestBackend> {
+    Editor::with_lines("bench.rs", sample_lines(LARGE_FILE_LINES))
+}
+
+fn content_area() -> Rect {
+    Rect::new(0, 0, 300, 100)
+}
+
+/// Typing a line's worth of characters into the middle of a large file, including the
+/// bookkeeping every edit triggers. Each iteration's typing is undone, untimed, so every
+/// one starts from the same file and history.
+fn insert_middle(c: &mut Criterion) {
+    let mut editor = large_file();
+    c.bench_function("insert_middle_100k_lines", |b| {
+        b.iter_custom(|iters| {
+            let mut typing = Duration::ZERO;
+            for _ in 0..iters {
+                editor.set_cursor((LARGE_FILE_LINES / 2, 4), content_area());
+                let start = Instant::now();
+                for c in "let inserted = 42;".chars() {
+                    editor.type_char(c, content_area());
+                }
+                typing += start.elapsed();
+                editor.undo_step(content_area());
+            }
+            typing
+        })
+    });
+}
+
+/// Pasting a megabyte of text into the middle of a large file.
+fn paste_1mb(c: &mut Criterion) {
+    let mut clipboard = sample_lines(LARGE_FILE_LINES).join("\n");
+    clipboard.truncate(1 << 20);
+    let mut group = c.benchmark_group("paste");
+    group.sample_size(10);
+    group.bench_function("paste_1mb", |b| {
+        b.iter_batched(
+            || {
+                let mut editor = large_file();
+                editor.set_cursor((LARGE_FILE_LINES / 2, 0), content_area());
+                editor
+            },
+            |mut editor| {
+                editor.paste_text(&clipboard, content_area());
+                editor
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+/// Drawing a 300x100 terminal where every cell changes, by moving a whole page each frame.
+fn redraw_300x100(c: &mut Criterion) {
+    let mut editor = large_file();
+    let mut terminal = Terminal::new(TestBackend::new(300, 100)).expect("test backend");
+    let mut page = 0;
+    c.bench_function("redraw_300x100", |b| {
+        b.iter(|| {
+            page = (page + 1) % 500;
+            editor.set_cursor((page * 100, 0), content_area());
+            editor.render(&mut terminal).expect("test backend");
+        })
+    });
+}
+
+/// Searching forward from the top of 100k lines for a match on the last one.
+fn search_100k_lines(c: &mut Criterion) {
+    let mut lines = sample_lines(LARGE_FILE_LINES);
+    lines.push("needle".to_string());
+    let editor = Editor::<TestBackend>::with_lines("bench.rs", lines);
+    c.bench_function("search_100k_lines", |b| b.iter(|| black_box(editor.find("needle"))));
+}
+
+criterion_group!(benches, insert_middle, paste_1mb, redraw_300x100, search_100k_lines);
+criterion_main!(benches);
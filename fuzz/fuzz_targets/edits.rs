@@ -0,0 +1,176 @@
+#![no_main]
+
+//! Applies random edits, selection deletes, undos and redos to a buffer and checks after
+//! each one that positions stay inside the text, that the lines agree with the same edits
+//! made to a plain string, and that the editor's undo history puts back exactly what was
+//! there.
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use zepto::{
+    buffer::{Buffer, Position},
+    selection::{Selection, SelectionKind},
+    undo::History,
+};
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Insert { at: (u16, u16), text: String },
+    Delete { start: (u16, u16), end: (u16, u16) },
+    DeleteSelection { anchor: (u16, u16), head: (u16, u16), kind: Kind },
+    Undo,
+    Redo,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Kind {
+    Char,
+    Line,
+    Block,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    lines: Vec<String>,
+    ops: Vec<Op>,
+}
+
+/// The text before and after an undo step, to check undo and redo against.
+struct Step {
+    before: String,
+    after: String,
+}
+
+fuzz_target!(|input: Input| {
+    let mut buffer = Buffer::new();
+    // Lines never hold a newline; the buffer splits text into lines itself.
+    let lines = input.lines.iter().map(|line| line.replace('\n', "")).collect();
+    buffer.reset(lines);
+    buffer.take_changes();
+    let mut history = History::default();
+    let mut done: Vec<Step> = Vec::new();
+    let mut undone: Vec<Step> = Vec::new();
+
+    for op in input.ops.into_iter().take(64) {
+        let before = text(&buffer);
+        let cursor = match op {
+            Op::Insert { at, text: inserted } => {
+                let at = clamp(&buffer, at);
+                let end = buffer.insert(at, &inserted);
+                assert_in_bounds(&buffer, end);
+                let offset = offset(&before, at);
+                let expected = format!("{}{}{}", &before[..offset], inserted, &before[offset..]);
+                assert_eq!(text(&buffer), expected);
+                assert_eq!(self::offset(&expected, end), offset + inserted.len());
+                at
+            }
+            Op::Delete { start, end } => {
+                let (start, end) = ordered(clamp(&buffer, start), clamp(&buffer, end));
+                let removed = buffer.delete(start, end);
+                let (from, to) = (offset(&before, start), offset(&before, end));
+                assert_eq!(removed, before[from..to]);
+                assert_eq!(text(&buffer), format!("{}{}", &before[..from], &before[to..]));
+                start
+            }
+            Op::DeleteSelection { anchor, head, kind } => {
+                let kind = match kind {
+                    Kind::Char => SelectionKind::Char,
+                    Kind::Line => SelectionKind::Line,
+                    Kind::Block => SelectionKind::Block,
+                };
+                let selection = Selection::new(clamp(&buffer, anchor), clamp(&buffer, head), kind);
+                let selected = selection.text(&buffer);
+                let swapped = Selection::new(selection.head, selection.anchor, kind);
+                assert_eq!(swapped.ordered(), selection.ordered());
+                assert_eq!(swapped.text(&buffer), selected);
+                let (start, end) = selection.ordered();
+                if kind == SelectionKind::Char {
+                    assert_eq!(selected, before[offset(&before, start)..offset(&before, end)]);
+                }
+                let whole_buffer = kind == SelectionKind::Line && start.0.min(end.0) == 0 && end.0 == buffer.len() - 1;
+                let cursor = selection.delete(&mut buffer);
+                assert_in_bounds(&buffer, cursor);
+                assert_eq!(text(&buffer).len(), before.len() - removed_len(kind, &selected, whole_buffer));
+                start
+            }
+            Op::Undo => {
+                let cursor = history.undo(&mut buffer);
+                assert_eq!(cursor.is_some(), !done.is_empty());
+                if let (Some(cursor), Some(step)) = (cursor, done.pop()) {
+                    assert_eq!(text(&buffer), step.before);
+                    assert_in_bounds(&buffer, cursor);
+                    undone.push(step);
+                }
+                buffer.take_changes();
+                continue;
+            }
+            Op::Redo => {
+                let cursor = history.redo(&mut buffer);
+                assert_eq!(cursor.is_some(), !undone.is_empty());
+                if let (Some(cursor), Some(step)) = (cursor, undone.pop()) {
+                    assert_eq!(text(&buffer), step.after);
+                    assert_in_bounds(&buffer, cursor);
+                    done.push(step);
+                }
+                buffer.take_changes();
+                continue;
+            }
+        };
+        assert!(!buffer.is_empty(), "a buffer always has at least one line");
+        // Each op is one step, recorded the way the editor records a key's edits.
+        let changes = buffer.take_changes();
+        if !changes.is_empty() {
+            for event in &changes {
+                history.record(&event.change, cursor);
+            }
+            history.seal(usize::MAX);
+            done.push(Step { before, after: text(&buffer) });
+            undone.clear();
+        }
+    }
+
+    // Undoing everything that is left gets back to the starting text.
+    while let Some(step) = done.pop() {
+        assert!(history.undo(&mut buffer).is_some());
+        assert_eq!(text(&buffer), step.before);
+    }
+    assert!(history.undo(&mut buffer).is_none());
+});
+
+fn text(buffer: &Buffer) -> String {
+    buffer.join("\n")
+}
+
+/// A position inside the buffer on a character boundary, as the cursor always is.
+fn clamp(buffer: &Buffer, (row, col): (u16, u16)) -> Position {
+    let row = row as usize % buffer.len();
+    (row, buffer[row].floor_char_boundary(col as usize))
+}
+
+fn ordered(a: Position, b: Position) -> (Position, Position) {
+    if a <= b { (a, b) } else { (b, a) }
+}
+
+fn assert_in_bounds(buffer: &Buffer, (row, col): Position) {
+    assert!(row < buffer.len(), "row {} past the last line {}", row, buffer.len() - 1);
+    let line = &buffer[row];
+    assert!(col <= line.len(), "column {} past the end of {:?}", col, line);
+    assert!(line.is_char_boundary(col), "column {} inside a character of {:?}", col, line);
+}
+
+/// Byte offset of `(row, col)` in the lines joined with newlines.
+fn offset(text: &str, (row, col): Position) -> usize {
+    text.split('\n').take(row).map(|line| line.len() + 1).sum::<usize>() + col
+}
+
+/// How much shorter deleting `selected` makes the text. Line selections take a newline
+/// next to the lines unless they are the whole buffer; block selections drop only their
+/// columns, not the newlines `text` puts between them.
+fn removed_len(kind: SelectionKind, selected: &str, whole_buffer: bool) -> usize {
+    match kind {
+        SelectionKind::Char => selected.len(),
+        SelectionKind::Line if whole_buffer => selected.len() - 1,
+        SelectionKind::Line => selected.len(),
+        SelectionKind::Block => selected.len() - selected.matches('\n').count(),
+    }
+}
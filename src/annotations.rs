@@ -0,0 +1,143 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::buffer::{Change, ChangeEvent};
+
+/// Where a project keeps its notes, relative to the project root.
+const NOTES_FILE: &str = ".zepto/annotations.toml";
+
+/// A note on the lines `start..=end`, 0-based.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct NotesFile {
+    #[serde(default)]
+    note: Vec<StoredNote>,
+}
+
+/// One `[[note]]` table, with 1-based lines as people count them.
+#[derive(Debug, Deserialize, Serialize)]
+struct StoredNote {
+    file: String,
+    line: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    end_line: Option<usize>,
+    text: String,
+}
+
+/// The notes on one file. They live in the project's `.zepto/annotations.toml` rather
+/// than in the file, so reviewing or keeping TODOs leaves the file untouched.
+pub struct Annotations {
+    notes_path: PathBuf,
+    /// The file's path relative to the project root, as stored.
+    file: String,
+    notes: Vec<Annotation>,
+    /// Edits have moved notes since they were last written out.
+    moved: bool,
+}
+
+/// The nearest directory above `path` holding `.zepto` or `.git`, or the file's own
+/// directory when there is none.
+fn project_root(path: &Path) -> PathBuf {
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    dir.ancestors()
+        .find(|ancestor| ancestor.join(".zepto").is_dir() || ancestor.join(".git").exists())
+        .unwrap_or(&dir)
+        .to_path_buf()
+}
+
+fn read_notes(path: &Path) -> io::Result<NotesFile> {
+    match fs::read_to_string(path) {
+        Ok(text) => toml::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(NotesFile::default()),
+        Err(e) => Err(e),
+    }
+}
+
+impl Annotations {
+    pub fn load(path: &str) -> io::Result<Self> {
+        let root = project_root(Path::new(path));
+        let absolute = fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+        let file = absolute.strip_prefix(&root).unwrap_or(&absolute).to_string_lossy().replace('\\', "/");
+        let notes_path = root.join(NOTES_FILE);
+        let notes = read_notes(&notes_path)?
+            .note
+            .into_iter()
+            .filter(|note| note.file == file && note.line > 0)
+            .map(|note| Annotation {
+                start: note.line - 1,
+                end: note.end_line.unwrap_or(note.line).max(note.line) - 1,
+                text: note.text,
+            })
+            .collect();
+        Ok(Annotations { notes_path, file, notes, moved: false })
+    }
+
+    pub fn at(&self, row: usize) -> Option<&Annotation> {
+        self.notes.iter().find(|note| (note.start..=note.end).contains(&row))
+    }
+
+    pub fn has_moved(&self) -> bool {
+        self.moved
+    }
+
+    /// Notes `start..=end`, replacing any note that overlaps those lines.
+    pub fn set(&mut self, start: usize, end: usize, text: String) {
+        self.notes.retain(|note| note.end < start || note.start > end);
+        self.notes.push(Annotation { start, end, text });
+        self.notes.sort_by_key(|note| note.start);
+    }
+
+    pub fn remove(&mut self, row: usize) -> bool {
+        let before = self.notes.len();
+        self.notes.retain(|note| !(note.start..=note.end).contains(&row));
+        self.notes.len() != before
+    }
+
+    /// Keeps notes on the same text as lines are added and removed above or inside them.
+    pub fn apply_change(&mut self, event: &ChangeEvent) {
+        let shift = |line: usize| match &event.change {
+            Change::Inserted { at, text } if line > at.0 => line + text.matches('\n').count(),
+            Change::Deleted { start, end, .. } if line > end.0 => line - (end.0 - start.0),
+            Change::Deleted { start, .. } if line > start.0 => start.0,
+            _ => line,
+        };
+        for note in &mut self.notes {
+            let (start, end) = (shift(note.start), shift(note.end));
+            if (start, end) != (note.start, note.end) {
+                (note.start, note.end) = (start, end);
+                self.moved = true;
+            }
+        }
+    }
+
+    /// Writes this file's notes back, leaving other files' notes as they are on disk.
+    pub fn save(&mut self) -> io::Result<()> {
+        let mut stored = read_notes(&self.notes_path)?;
+        stored.note.retain(|note| note.file != self.file);
+        stored.note.extend(self.notes.iter().map(|note| StoredNote {
+            file: self.file.clone(),
+            line: note.start + 1,
+            end_line: (note.end > note.start).then_some(note.end + 1),
+            text: note.text.clone(),
+        }));
+        stored.note.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+        if let Some(dir) = self.notes_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string(&stored).map_err(|e| io::Error::other(e.to_string()))?;
+        fs::write(&self.notes_path, text)?;
+        self.moved = false;
+        Ok(())
+    }
+}
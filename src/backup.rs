@@ -0,0 +1,38 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::paths;
+
+/// Where the version of `path` from before its last save is kept: the absolute path with
+/// each separator turned into `%`, as vim names files in a `backupdir` ending in `//`.
+pub fn backup_path(path: &str) -> PathBuf {
+    let absolute = fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+    let name = absolute.to_string_lossy().replace(['/', '\\', ':'], "%");
+    paths::state_dir().join("backups").join(name)
+}
+
+/// Copies what is on disk at `path` aside before `content` replaces it. Nothing is copied
+/// for a new file or a save that changes nothing, so the backup is always the version
+/// before the last real change.
+pub fn back_up(path: &str, content: &str) -> io::Result<()> {
+    let old = match fs::read(path) {
+        Ok(old) => old,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    if old == content.as_bytes() {
+        return Ok(());
+    }
+    let backup = backup_path(path);
+    if let Some(dir) = backup.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(backup, old)
+}
+
+/// The backup of `path`, if there is one.
+pub fn latest(path: &str) -> Option<PathBuf> {
+    Some(backup_path(path)).filter(|backup| Path::new(backup).is_file())
+}
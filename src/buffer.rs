@@ -0,0 +1,167 @@
+use std::ops::Deref;
+
+/// A `(row, col)` position in the buffer, with `col` as a byte offset into the line.
+pub type Position = (usize, usize);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Inserted { at: Position, text: String },
+    Deleted { start: Position, end: Position, text: String },
+    Reset,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeEvent {
+    pub revision: u64,
+    pub change: Change,
+}
+
+/// The lines of a document. Every mutation bumps the revision and queues a change event,
+/// which the editor hands to each subsystem that needs to follow edits.
+#[derive(Debug)]
+pub struct Buffer {
+    lines: Vec<String>,
+    revision: u64,
+    pending: Vec<ChangeEvent>,
+}
+
+impl Buffer {
+    pub fn new() -> Self {
+        Buffer {
+            lines: vec![String::new()],
+            revision: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Whether edits were made since the last call to `take_changes`.
+    pub fn has_pending_changes(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub fn take_changes(&mut self) -> Vec<ChangeEvent> {
+        std::mem::take(&mut self.pending)
+    }
+
+    fn record(&mut self, change: Change) {
+        self.revision += 1;
+        self.pending.push(ChangeEvent { revision: self.revision, change });
+    }
+
+    pub fn reset(&mut self, mut lines: Vec<String>) {
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        self.lines = lines;
+        self.record(Change::Reset);
+    }
+
+    /// Inserts `text` (which may contain newlines) and returns the position just past it.
+    pub fn insert(&mut self, at: Position, text: &str) -> Position {
+        let (row, col) = at;
+        let mut parts = text.split('\n');
+        let first = parts.next().unwrap_or_default();
+        let rest: Vec<&str> = parts.collect();
+
+        let end = if rest.is_empty() {
+            self.lines[row].insert_str(col, first);
+            (row, col + first.len())
+        } else {
+            let tail = self.lines[row].split_off(col);
+            self.lines[row].push_str(first);
+            let last_idx = rest.len() - 1;
+            let new_lines = rest.iter().enumerate().map(|(i, part)| {
+                if i == last_idx { format!("{}{}", part, tail) } else { part.to_string() }
+            });
+            self.lines.splice(row + 1..row + 1, new_lines);
+            (row + rest.len(), rest[last_idx].len())
+        };
+
+        if !text.is_empty() {
+            self.record(Change::Inserted { at, text: text.to_string() });
+        }
+        end
+    }
+
+    /// Removes the text between `start` and `end` (exclusive) and returns it.
+    pub fn delete(&mut self, start: Position, end: Position) -> String {
+        let ((start_row, start_col), (end_row, end_col)) = (start, end);
+        let removed = if start_row == end_row {
+            self.lines[start_row].drain(start_col..end_col).collect::<String>()
+        } else {
+            let mut removed = self.lines[start_row].split_off(start_col);
+            let tail = self.lines[end_row].split_off(end_col);
+            for line in self.lines.drain(start_row + 1..=end_row) {
+                removed.push('\n');
+                removed.push_str(&line);
+            }
+            self.lines[start_row].push_str(&tail);
+            removed
+        };
+
+        if !removed.is_empty() {
+            self.record(Change::Deleted { start, end, text: removed.clone() });
+        }
+        removed
+    }
+}
+
+impl Default for Buffer {
+    fn default() -> Self {
+        Buffer::new()
+    }
+}
+
+impl Deref for Buffer {
+    type Target = [String];
+
+    fn deref(&self) -> &[String] {
+        &self.lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{
+        testing::{edit, lines, record, spot, text},
+        undo::History,
+    };
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2000))]
+
+        #[test]
+        fn undoing_edits_restores_the_text_byte_for_byte(lines in lines(6), edits in prop::collection::vec(edit(), 0..12)) {
+            let mut buffer = Buffer::new();
+            buffer.reset(lines);
+            buffer.take_changes();
+            let mut history = History::default();
+            let original = buffer.join("\n");
+            for edit in &edits {
+                edit.apply(&mut buffer);
+                prop_assert!(!buffer.is_empty());
+                record(&mut history, &mut buffer);
+            }
+            while history.undo(&mut buffer).is_some() {}
+            prop_assert_eq!(buffer.join("\n"), original);
+        }
+
+        #[test]
+        fn insert_returns_the_end_of_the_inserted_text(lines in lines(4), at in spot(), text in text(8)) {
+            let mut buffer = Buffer::new();
+            buffer.reset(lines);
+            let at = at.in_lines(&buffer);
+            let end = buffer.insert(at, &text);
+            let lines: Vec<&str> = text.split('\n').collect();
+            let inserted_end = if lines.len() == 1 { &buffer[end.0][at.1..end.1] } else { &buffer[end.0][..end.1] };
+            prop_assert_eq!(inserted_end, lines[lines.len() - 1]);
+        }
+    }
+}
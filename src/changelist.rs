@@ -0,0 +1,76 @@
+use crate::buffer::{Change, ChangeEvent, Position};
+
+/// Oldest entries are dropped beyond this, as in vim.
+const CAPACITY: usize = 100;
+
+/// Where recent edits happened, for `g;` and `g,`. Unlike the jump list this only grows
+/// when the text changes, and an edit on the same line as the last one moves that entry
+/// instead of adding another, so a burst of typing is one stop.
+#[derive(Default)]
+pub struct ChangeList {
+    positions: Vec<Position>,
+    /// The entry the last `g;`/`g,` went to; `None` is just past the newest.
+    current: Option<usize>,
+}
+
+impl ChangeList {
+    pub fn apply_change(&mut self, event: &ChangeEvent) {
+        let at = match &event.change {
+            Change::Inserted { at, text } => {
+                let added = text.matches('\n').count();
+                for position in &mut self.positions {
+                    if position.0 > at.0 {
+                        position.0 += added;
+                    }
+                }
+                *at
+            }
+            Change::Deleted { start, end, .. } => {
+                for position in &mut self.positions {
+                    if position.0 > end.0 {
+                        position.0 -= end.0 - start.0;
+                    } else if position.0 > start.0 {
+                        *position = *start;
+                    }
+                }
+                *start
+            }
+            Change::Reset => {
+                *self = ChangeList::default();
+                return;
+            }
+        };
+        match self.positions.last_mut() {
+            Some(last) if last.0 == at.0 => *last = at,
+            _ => {
+                if self.positions.len() == CAPACITY {
+                    self.positions.remove(0);
+                }
+                self.positions.push(at);
+            }
+        }
+        self.current = None;
+    }
+
+    /// Steps `count` entries back (`older`) or forward through the list, stopping at the
+    /// ends. `Err` explains why nothing moved.
+    pub fn step(&mut self, older: bool, count: usize) -> Result<Position, &'static str> {
+        if self.positions.is_empty() {
+            return Err("No changes yet");
+        }
+        let current = self.current.unwrap_or(self.positions.len());
+        let target = if older {
+            if current == 0 {
+                return Err("At start of change list");
+            }
+            current.saturating_sub(count)
+        } else {
+            if current + 1 >= self.positions.len() {
+                return Err("At end of change list");
+            }
+            (current + count).min(self.positions.len() - 1)
+        };
+        self.current = Some(target);
+        Ok(self.positions[target])
+    }
+}
@@ -0,0 +1,48 @@
+use std::path::PathBuf;
+
+use log::LevelFilter;
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub file: Option<String>,
+    pub log_level: Option<LevelFilter>,
+    pub log_file: Option<PathBuf>,
+    pub diff: Option<(String, String)>,
+    /// Page through the file, or standard input, without editing it.
+    pub view: bool,
+}
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--log-level" => {
+                let value = args.next().ok_or("--log-level needs a value")?;
+                let level = value.parse::<LevelFilter>()
+                    .map_err(|_| format!("invalid log level '{}'", value))?;
+                parsed.log_level = Some(level);
+            }
+            "--log-file" => {
+                let value = args.next().ok_or("--log-file needs a path")?;
+                parsed.log_file = Some(PathBuf::from(value));
+            }
+            "--diff" => {
+                let left = args.next().ok_or("--diff needs two files")?;
+                let right = args.next().ok_or("--diff needs two files")?;
+                parsed.diff = Some((left, right));
+            }
+            "--view" => parsed.view = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown option '{}'", arg)),
+            _ => {
+                if parsed.file.is_some() {
+                    return Err(format!("unexpected argument '{}'", arg));
+                }
+                parsed.file = Some(arg);
+            }
+        }
+    }
+
+    Ok(parsed)
+}
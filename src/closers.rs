@@ -0,0 +1,48 @@
+use std::path::Path;
+
+/// Filetypes with block keywords that zepto has no highlighting for, by extension.
+const EXTENSIONS: &[(&str, &str)] = &[("rb", "ruby"), ("rake", "ruby"), ("gemspec", "ruby"), ("lua", "lua")];
+
+/// The filetype closers go by: the highlighting's, or else one known from the extension.
+pub fn filetype<'a>(syntax: Option<&'a str>, path: Option<&str>) -> Option<&'a str> {
+    syntax.or_else(|| {
+        let extension = Path::new(path?).extension()?.to_str()?;
+        EXTENSIONS.iter().find(|(known, _)| *known == extension).map(|(_, filetype)| *filetype)
+    })
+}
+
+/// The keyword closing the block `line` opens, as `fi` closes `if ...; then` in shell, or
+/// `None` when it opens none or closes it on the same line.
+pub fn closer(filetype: &str, line: &str) -> Option<&'static str> {
+    let comment = if filetype == "lua" { "--" } else { "#" };
+    let code = match line.find(comment) {
+        Some(start) if start == 0 || line[..start].ends_with(char::is_whitespace) => &line[..start],
+        _ => line,
+    };
+    let words: Vec<&str> = code.split(|c: char| c.is_whitespace() || c == ';').filter(|word| !word.is_empty()).collect();
+    let (first, last) = (*words.first()?, *words.last()?);
+    let opens = |keyword: &str| words.iter().filter(|word| **word == keyword).count();
+    match filetype {
+        "shell" => match (first, last) {
+            ("if", "then") if opens("fi") == 0 => Some("fi"),
+            ("for" | "while" | "until" | "select", "do") if opens("done") == 0 => Some("done"),
+            ("case", "in") if opens("esac") == 0 => Some("esac"),
+            _ => None,
+        },
+        "ruby" => {
+            let opener = matches!(first, "def" | "class" | "module" | "if" | "unless" | "while" | "until" | "case" | "begin" | "for")
+                || last == "do"
+                || (last.ends_with('|') && words.contains(&"do"));
+            // `def name = value` is a whole method already.
+            let endless = first == "def" && words.contains(&"=");
+            (opener && !endless && opens("end") == 0 && !code.trim_end().ends_with('}')).then_some("end")
+        }
+        "lua" => {
+            let function = words.iter().any(|word| *word == "function" || word.starts_with("function("))
+                && code.trim_end().ends_with(')');
+            let opener = function || (first == "if" && last == "then") || last == "do";
+            (opener && opens("end") == 0).then_some("end")
+        }
+        _ => None,
+    }
+}
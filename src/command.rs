@@ -0,0 +1,259 @@
+use toml::Value;
+
+use crate::{config::Config, expand::expand_path, macros, settings};
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Write(Option<String>),
+    Quit { force: bool },
+    WriteQuit,
+    /// `:set` changes options for the current buffer only; `:setglobal` changes the defaults.
+    Set { args: Vec<String>, global: bool },
+    /// Jumps to a ctags definition; without a name, the identifier under the cursor.
+    Tag(Option<String>),
+    Pop,
+    /// In a diff or patch buffer: jump to the source of the hunk under the cursor.
+    Hunk,
+    ApplyHunk,
+    NoHighlight,
+    /// Converts all indentation to the configured style (`expand_tab`, `tab_width`).
+    Retab,
+    /// `:42` moves to that line.
+    GoToLine(usize),
+    /// Starts a selection that every move extends, or ends it.
+    Select,
+    /// Changes how this buffer indents; without an argument, says how it does.
+    Indent(Option<IndentChange>),
+    /// Writes the buffer to a new file and edits that file from then on.
+    SaveAs(String),
+    /// Moves the buffer's file on disk.
+    Rename(String),
+    DeleteFile,
+    /// Attaches a note to the current line or selected lines; no text removes it.
+    Note(String),
+    /// Lists TODO, FIXME and HACK comments in the buffer, or with `project` in every file.
+    Todos { project: bool },
+    /// Edits zepto's own config file, which is applied again whenever it is saved.
+    Config,
+    /// Edits the keys recorded into a register, which it runs once saved.
+    Macro(char),
+    /// Switches to a theme and saves it to the config; without a name, previews each in turn.
+    Theme(Option<String>),
+    /// Writes the keys in effect to a Markdown cheat sheet.
+    Keymap(Option<String>),
+    /// Moves to the next invisible or lookalike character; with `fix`, replaces them all.
+    Hidden { fix: bool },
+    /// Reads the file from disk again; `force` (`:e!`) discards unsaved changes.
+    Reload { force: bool },
+    /// Compares the buffer with the file's backup to take hunks back from it.
+    Backup,
+    /// Lists the saved versions of the file, to compare with or go back to.
+    Timeline,
+    /// Says which ignore rule, if any, keeps a path out of file lists.
+    Ignored(String),
+    /// `:s/pattern/replacement/g` on the current line or selected lines, or with `%` on
+    /// every line. An empty pattern is the last search.
+    Substitute { whole: bool, pattern: String, replacement: String, global: bool },
+    /// Opens a panel for trying out a regex and a replacement on the buffer.
+    RegexTester(Option<String>),
+    /// `:!command` runs a shell command in the background.
+    Shell(String),
+}
+
+/// What `:indent` switches the buffer to.
+#[derive(Debug, PartialEq)]
+pub enum IndentChange {
+    Tabs(Option<u16>),
+    Spaces(Option<u16>),
+    /// Back to what the file uses, or the configured style.
+    Detect,
+}
+
+/// Commands whose argument is a file name.
+const PATH_COMMANDS: &[&str] = &["w", "write", "saveas", "sav", "rename", "keymap", "ignored", "check-ignore"];
+
+/// Where the file name starts in a partly typed command line, for commands that take one.
+pub fn path_argument_start(line: &str) -> Option<usize> {
+    let command = line.trim_start_matches([' ', ':']);
+    let (name, rest) = command.split_once(char::is_whitespace)?;
+    PATH_COMMANDS.contains(&name).then(|| line.len() - rest.trim_start().len())
+}
+
+pub fn parse(line: &str) -> Result<Command, String> {
+    let line = line.trim().trim_start_matches(':');
+    if let Some(substitute) = parse_substitute(line) {
+        return substitute;
+    }
+    if let Some(shell) = line.strip_prefix('!') {
+        return match shell.trim() {
+            "" => Err("! needs a shell command".to_string()),
+            shell => Ok(Command::Shell(shell.to_string())),
+        };
+    }
+    let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let rest = rest.trim();
+    let args: Vec<String> = rest.split_whitespace().map(str::to_string).collect();
+
+    match name {
+        "w" | "write" if rest.is_empty() => Ok(Command::Write(None)),
+        "w" | "write" => Ok(Command::Write(Some(expand_path(rest)?))),
+        "q" | "quit" => Ok(Command::Quit { force: false }),
+        "q!" | "quit!" => Ok(Command::Quit { force: true }),
+        "wq" | "x" => Ok(Command::WriteQuit),
+        "set" | "se" => Ok(Command::Set { args, global: false }),
+        "setglobal" | "setg" => Ok(Command::Set { args, global: true }),
+        "tag" | "ta" => Ok(Command::Tag((!rest.is_empty()).then(|| rest.to_string()))),
+        "pop" | "po" => Ok(Command::Pop),
+        "hunk" => Ok(Command::Hunk),
+        "noh" | "nohlsearch" => Ok(Command::NoHighlight),
+        "applyhunk" => Ok(Command::ApplyHunk),
+        "retab" | "ret" => Ok(Command::Retab),
+        "select" | "mark" if rest.is_empty() => Ok(Command::Select),
+        line if rest.is_empty() && let Ok(line) = line.parse::<usize>() => Ok(Command::GoToLine(line)),
+        "indent" => {
+            let width = match args.get(1).map(|width| width.parse::<u16>()) {
+                None => None,
+                Some(Ok(width)) if width > 0 && args.len() == 2 => Some(width),
+                Some(_) => return Err("indent takes tabs or spaces and a width, such as: indent spaces 2".to_string()),
+            };
+            match args.first().map(String::as_str) {
+                None => Ok(Command::Indent(None)),
+                Some("tabs") => Ok(Command::Indent(Some(IndentChange::Tabs(width)))),
+                Some("spaces") => Ok(Command::Indent(Some(IndentChange::Spaces(width)))),
+                Some("detect") if args.len() == 1 => Ok(Command::Indent(Some(IndentChange::Detect))),
+                Some(_) => Err("indent takes tabs, spaces or detect".to_string()),
+            }
+        }
+        "saveas" | "sav" if !rest.is_empty() => Ok(Command::SaveAs(expand_path(rest)?)),
+        "rename" if !rest.is_empty() => Ok(Command::Rename(expand_path(rest)?)),
+        "saveas" | "sav" | "rename" => Err(format!("{} needs a file name", name)),
+        "delete-file" => Ok(Command::DeleteFile),
+        "note" | "annotate" => Ok(Command::Note(rest.to_string())),
+        "todos" | "todo" if rest.is_empty() => Ok(Command::Todos { project: false }),
+        "todos" | "todo" if rest == "project" => Ok(Command::Todos { project: true }),
+        "config" => Ok(Command::Config),
+        "macro" => match rest.chars().next() {
+            Some(register) if rest.len() == 1 && macros::is_register(register) => Ok(Command::Macro(register)),
+            _ => Err("macro needs a register, a-z or 0-9".to_string()),
+        },
+        "keymap" if rest.is_empty() => Ok(Command::Keymap(None)),
+        "keymap" => Ok(Command::Keymap(Some(expand_path(rest)?))),
+        "theme" | "colorscheme" | "colo" => Ok(Command::Theme((!rest.is_empty()).then(|| rest.to_string()))),
+        "e" | "edit" if rest.is_empty() => Ok(Command::Reload { force: false }),
+        "e!" | "edit!" if rest.is_empty() => Ok(Command::Reload { force: true }),
+        "e" | "edit" | "e!" | "edit!" => Err(format!("{} only reloads the current file; it takes no file name", name)),
+        "backup" => Ok(Command::Backup),
+        "timeline" => Ok(Command::Timeline),
+        "regex" | "retest" => Ok(Command::RegexTester((!rest.is_empty()).then(|| rest.to_string()))),
+        "ignored" | "check-ignore" if !rest.is_empty() => Ok(Command::Ignored(expand_path(rest)?)),
+        "ignored" | "check-ignore" => Err(format!("{} needs a path", name)),
+        "hidden" | "invisible" if rest.is_empty() => Ok(Command::Hidden { fix: false }),
+        "hidden" | "invisible" if rest == "fix" => Ok(Command::Hidden { fix: true }),
+        "hidden" | "invisible" => Err(format!("{} takes no argument or \"fix\"", name)),
+        "todos" | "todo" => Err(format!("{} takes no argument or \"project\"", name)),
+        "" => Err("no command given".to_string()),
+        other => Err(format!("not an editor command: {}", other)),
+    }
+}
+
+/// `[%]s/pattern/replacement/[g]`, where any punctuation can stand in for `/` and a
+/// backslash before it takes it literally. `None` when `line` isn't a substitution.
+fn parse_substitute(line: &str) -> Option<Result<Command, String>> {
+    let (whole, rest) = match line.strip_prefix('%') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let rest = rest.strip_prefix("substitute").or_else(|| rest.strip_prefix('s'))?;
+    let delimiter = rest.chars().next().filter(|c| c.is_ascii_punctuation() && *c != '\\' && *c != '"')?;
+    let mut parts = vec![String::new()];
+    let mut chars = rest[1..].chars();
+    while let Some(c) = chars.next() {
+        let part = parts.last_mut()?;
+        match c {
+            '\\' => match chars.next() {
+                Some(next) if next == delimiter => part.push(next),
+                Some(next) => {
+                    part.push('\\');
+                    part.push(next);
+                }
+                None => part.push('\\'),
+            },
+            c if c == delimiter => parts.push(String::new()),
+            c => part.push(c),
+        }
+    }
+    if parts.len() > 3 {
+        return Some(Err(format!("trailing characters after {}{}", delimiter, parts[2])));
+    }
+    let mut parts = parts.into_iter();
+    let pattern = parts.next().unwrap_or_default();
+    let replacement = parts.next().unwrap_or_default();
+    let flags = parts.next().unwrap_or_default();
+    if let Some(flag) = flags.chars().find(|flag| *flag != 'g') {
+        return Some(Err(format!("unknown flag for :s: {}", flag)));
+    }
+    Some(Ok(Command::Substitute { whole, pattern, replacement, global: !flags.is_empty() }))
+}
+
+/// Short vim-style names for the options people reach for most; any full dotted config
+/// key (`main_section.frame.hide`) is accepted as well.
+const OPTION_ALIASES: &[(&[&str], &str)] = &[
+    (&["number", "nu"], "main_section.line_numbers.enabled"),
+    (&["tabstop", "ts"], "editor_behavior.tab_width"),
+    (&["expandtab", "et"], "editor_behavior.expand_tab"),
+    (&["syntax", "syn"], "main_section.syntax_highlighting.enabled"),
+    (&["virtualedit", "ve"], "editor_behavior.virtual_edit"),
+    (&["typewriter", "tw"], "editor_behavior.typewriter_scrolling"),
+    (&["smartcase", "scs"], "editor_behavior.smartcase"),
+    (&["regex", "re"], "editor_behavior.regex_search"),
+    (&["paste"], "editor_behavior.paste"),
+    (&["wrap"], "main_section.soft_wrap.enabled"),
+];
+
+fn resolve_option(name: &str) -> &str {
+    OPTION_ALIASES.iter()
+        .find(|(aliases, _)| aliases.contains(&name))
+        .map(|(_, key)| *key)
+        .unwrap_or(name)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SetAction {
+    Assign { key: String, value: Value },
+    Query { key: String, value: Value },
+}
+
+/// Interprets one `:set` argument against the current config:
+/// `name`, `noname`, `invname`/`name!`, `name=value` and `name?`.
+pub fn parse_set_arg(arg: &str, config: &Config) -> Result<SetAction, String> {
+    let current = |key: &str| {
+        settings::flatten(config).into_iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+            .ok_or_else(|| format!("unknown option: {}", key))
+    };
+
+    if let Some((name, text)) = arg.split_once('=') {
+        let key = resolve_option(name).to_string();
+        let value = settings::parse_like(&current(&key)?, text)?;
+        return Ok(SetAction::Assign { key, value });
+    }
+    if let Some(name) = arg.strip_suffix('?') {
+        let key = resolve_option(name).to_string();
+        let value = current(&key)?;
+        return Ok(SetAction::Query { key, value });
+    }
+
+    let toggle = arg.strip_suffix('!').or_else(|| arg.strip_prefix("inv"));
+    let (name, flag) = match (toggle, arg.strip_prefix("no")) {
+        (Some(name), _) => (name, None),
+        (None, Some(name)) if current(resolve_option(name)).is_ok() => (name, Some(false)),
+        _ => (arg, Some(true)),
+    };
+    let key = resolve_option(name).to_string();
+    match current(&key)? {
+        Value::Boolean(b) => Ok(SetAction::Assign { key, value: Value::Boolean(flag.unwrap_or(!b)) }),
+        value if flag == Some(true) => Ok(SetAction::Query { key, value }),
+        _ => Err(format!("{} is not a boolean option", name)),
+    }
+}
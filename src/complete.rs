@@ -0,0 +1,85 @@
+use std::{fs, path::MAIN_SEPARATOR_STR};
+
+use crate::{expand::expand_path, platform};
+
+/// Files and directories whose path starts with `partial`, sorted, with directories
+/// ending in a separator. Hidden entries only match once the name typed starts with a dot. `~`
+/// and variables are looked up but left unexpanded in the candidates.
+pub fn paths(partial: &str) -> Vec<String> {
+    let (dir, name_prefix) = match partial.rfind(platform::is_separator) {
+        Some(at) => partial.split_at(at + 1),
+        None => ("", partial),
+    };
+    let search_dir = if dir.is_empty() { ".".to_string() } else { expand_path(dir).unwrap_or_else(|_| dir.to_string()) };
+    let Ok(entries) = fs::read_dir(&search_dir) else {
+        return Vec::new();
+    };
+    let mut candidates: Vec<String> = entries.flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(name_prefix) || (name.starts_with('.') && !name_prefix.starts_with('.')) {
+                return None;
+            }
+            let separator = if entry.path().is_dir() { MAIN_SEPARATOR_STR } else { "" };
+            Some(format!("{}{}{}", dir, name, separator))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+/// The longest start all of `candidates` share.
+pub fn common_prefix(candidates: &[String]) -> &str {
+    let Some((first, rest)) = candidates.split_first() else {
+        return "";
+    };
+    let mut prefix = first.as_str();
+    for candidate in rest {
+        let len = prefix.char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(prefix.len().min(candidate.len()), |((at, _), _)| at);
+        prefix = &prefix[..len];
+    }
+    prefix
+}
+
+/// Cycling through the candidates for the word at the end of a prompt, from byte `start`.
+/// Nothing is selected until the first step.
+pub struct Completion {
+    pub start: usize,
+    candidates: Vec<String>,
+    selected: Option<usize>,
+}
+
+impl Completion {
+    pub fn new(start: usize, candidates: Vec<String>) -> Self {
+        Completion { start, candidates, selected: None }
+    }
+
+    /// Selects the next candidate, or the previous one going `backward`, and returns it.
+    pub fn step(&mut self, backward: bool) -> &str {
+        let len = self.candidates.len();
+        let selected = match self.selected {
+            None if backward => len - 1,
+            None => 0,
+            Some(i) if backward => (i + len - 1) % len,
+            Some(i) => (i + 1) % len,
+        };
+        self.selected = Some(selected);
+        &self.candidates[selected]
+    }
+
+    /// The candidates by file name, the selected one in brackets.
+    pub fn summary(&self) -> String {
+        self.candidates.iter()
+            .enumerate()
+            .map(|(i, candidate)| {
+                let name = candidate.trim_end_matches(platform::is_separator);
+                let name = &candidate[name.rfind(platform::is_separator).map_or(0, |at| at + 1)..];
+                if self.selected == Some(i) { format!("[{}]", name) } else { name.to_string() }
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+    }
+}
@@ -1,28 +1,60 @@
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf};
-use dirs;
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+use crate::{paths, settings};
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct Config {
     #[serde(default)]
     pub main_section: MainSection,
     #[serde(default)]
     pub editor_behavior: EditorBehavior,
+    /// Commands of the user's own: a name and the command lines it runs in turn.
+    #[serde(default)]
+    pub commands: BTreeMap<String, Vec<String>>,
+    /// Keys, named as in macro files (`<C-k>`, `<F5>`), and the command line each runs.
+    #[serde(default)]
+    pub keys: BTreeMap<String, String>,
+    /// Shell commands run when saving, by filetype; `*` is for filetypes without their own.
+    #[serde(default)]
+    pub hooks: BTreeMap<String, SaveHooks>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SaveHooks {
+    /// Run in the file's directory before it is written, with the text to save on stdin.
+    #[serde(default)]
+    pub before_save: Vec<String>,
+    /// Run in the file's directory once it is written.
+    #[serde(default)]
+    pub after_save: Vec<String>,
+    /// A failing `before_save` command stops the save rather than just saying so.
+    #[serde(default = "default_block_on_failure")]
+    pub block_on_failure: bool,
 }
 
-impl Default for Config {
+impl Default for SaveHooks {
     fn default() -> Self {
         Self {
-            main_section: MainSection::default(),
-            editor_behavior: EditorBehavior::default(),
+            before_save: Vec::new(),
+            after_save: Vec::new(),
+            block_on_failure: default_block_on_failure(),
         }
     }
 }
 
+fn default_block_on_failure() -> bool { true }
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct MainSection {
     #[serde(default = "default_background_color")]
     pub background_color: String,
+    /// A theme whose options take the place of the ones in this file; empty for none.
+    #[serde(default)]
+    pub theme: String,
+    /// Paints hex color literals such as `#ff8800` in the color they name.
+    #[serde(default = "default_color_swatches")]
+    pub color_swatches: bool,
     #[serde(default)]
     pub frame: Frame,
     #[serde(default)]
@@ -31,16 +63,63 @@ pub struct MainSection {
     pub status_panel: StatusPanel,
     #[serde(default)]
     pub prompt_panel: PromptPanel,
+    #[serde(default)]
+    pub syntax_highlighting: SyntaxHighlighting,
+    #[serde(default)]
+    pub line_length: LineLength,
+    #[serde(default)]
+    pub long_lines: LongLines,
+    #[serde(default)]
+    pub breadcrumbs: Breadcrumbs,
+    #[serde(default)]
+    pub search: SearchHighlight,
+    #[serde(default)]
+    pub title: Title,
+    #[serde(default)]
+    pub prose: Prose,
+    #[serde(default)]
+    pub validation: Validation,
+    #[serde(default)]
+    pub annotations: AnnotationStyle,
+    #[serde(default)]
+    pub links: Links,
+    #[serde(default)]
+    pub hidden_characters: HiddenCharacters,
+    #[serde(default)]
+    pub rainbow_brackets: RainbowBrackets,
+    #[serde(default)]
+    pub toasts: Toasts,
+    #[serde(default)]
+    pub end_of_buffer: EndOfBuffer,
+    #[serde(default)]
+    pub soft_wrap: SoftWrap,
 }
 
 impl Default for MainSection {
     fn default() -> Self {
         Self {
             background_color: default_background_color(),
+            theme: String::new(),
+            color_swatches: default_color_swatches(),
             frame: Frame::default(),
             line_numbers: LineNumbers::default(),
             status_panel: StatusPanel::default(),
             prompt_panel: PromptPanel::default(),
+            syntax_highlighting: SyntaxHighlighting::default(),
+            line_length: LineLength::default(),
+            long_lines: LongLines::default(),
+            breadcrumbs: Breadcrumbs::default(),
+            search: SearchHighlight::default(),
+            title: Title::default(),
+            prose: Prose::default(),
+            validation: Validation::default(),
+            annotations: AnnotationStyle::default(),
+            links: Links::default(),
+            hidden_characters: HiddenCharacters::default(),
+            rainbow_brackets: RainbowBrackets::default(),
+            toasts: Toasts::default(),
+            end_of_buffer: EndOfBuffer::default(),
+            soft_wrap: SoftWrap::default(),
         }
     }
 }
@@ -49,10 +128,18 @@ impl Default for MainSection {
 pub struct Frame {
     #[serde(default = "default_frame_corner")]
     pub corner: String,
+    /// Blank cells around the outside of the frame.
     #[serde(default = "default_margin")]
     pub margin: u16,
+    /// Blank cells between the frame and the text.
+    #[serde(default = "default_frame_padding")]
+    pub padding: u16,
     #[serde(default = "default_frame_color")]
     pub color: String,
+    /// Frame and gutter color while input goes elsewhere: an overlay such as the file
+    /// finder, or another window when the terminal is not focused.
+    #[serde(default = "default_frame_inactive_color")]
+    pub inactive_color: String,
     #[serde(default = "default_frame_hide")]
     pub hide: bool,
 }
@@ -62,7 +149,9 @@ impl Default for Frame {
         Frame {
             corner: default_frame_corner(),
             margin: default_margin(),
+            padding: default_frame_padding(),
             color: default_frame_color(),
+            inactive_color: default_frame_inactive_color(),
             hide: default_frame_hide(),
         }
     }
@@ -71,7 +160,9 @@ impl Default for Frame {
 fn default_frame_corner() -> String { "plain".to_string() }
 fn default_margin() -> u16 { 0 }
 fn default_frame_color() -> String { "#0000FF".to_string() }
+fn default_frame_inactive_color() -> String { "#555555".to_string() }
 fn default_frame_hide() -> bool { false }
+fn default_frame_padding() -> u16 { 0 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct LineNumbers {
@@ -149,16 +240,539 @@ fn default_prompt_panel_enabled() -> bool { true }
 fn default_prompt_panel_background_color() -> String { "#808080".to_string() }
 fn default_prompt_panel_foreground_color() -> String { "#FFFFFF".to_string() }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SyntaxHighlighting {
+    #[serde(default = "default_syntax_highlighting_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_syntax_keyword_color")]
+    pub keyword_color: String,
+    #[serde(default = "default_syntax_string_color")]
+    pub string_color: String,
+    #[serde(default = "default_syntax_comment_color")]
+    pub comment_color: String,
+    #[serde(default = "default_syntax_number_color")]
+    pub number_color: String,
+    #[serde(default = "default_syntax_added_color")]
+    pub added_color: String,
+    #[serde(default = "default_syntax_removed_color")]
+    pub removed_color: String,
+    #[serde(default = "default_syntax_todo_color")]
+    pub todo_color: String,
+}
+
+impl Default for SyntaxHighlighting {
+    fn default() -> Self {
+        SyntaxHighlighting {
+            enabled: default_syntax_highlighting_enabled(),
+            keyword_color: default_syntax_keyword_color(),
+            string_color: default_syntax_string_color(),
+            comment_color: default_syntax_comment_color(),
+            number_color: default_syntax_number_color(),
+            added_color: default_syntax_added_color(),
+            removed_color: default_syntax_removed_color(),
+            todo_color: default_syntax_todo_color(),
+        }
+    }
+}
+
+fn default_syntax_highlighting_enabled() -> bool { true }
+fn default_syntax_keyword_color() -> String { "#C678DD".to_string() }
+fn default_syntax_string_color() -> String { "#98C379".to_string() }
+fn default_syntax_comment_color() -> String { "#7F848E".to_string() }
+fn default_syntax_number_color() -> String { "#D19A66".to_string() }
+fn default_syntax_added_color() -> String { "#98C379".to_string() }
+fn default_syntax_removed_color() -> String { "#E06C75".to_string() }
+fn default_syntax_todo_color() -> String { "#E5C07B".to_string() }
+
+/// Soft limit only: nothing is wrapped or rejected, the overflow is just tinted.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct LineLength {
+    #[serde(default = "default_line_length_max")]
+    pub max: usize,
+    #[serde(default = "default_line_length_color")]
+    pub color: String,
+    #[serde(default = "default_line_length_show_count")]
+    pub show_count: bool,
+    /// Overrides `max` by filetype name (`rust`, `python`, ...); 0 turns the warning off.
+    #[serde(default)]
+    pub per_filetype: BTreeMap<String, usize>,
+}
+
+impl Default for LineLength {
+    fn default() -> Self {
+        LineLength {
+            max: default_line_length_max(),
+            color: default_line_length_color(),
+            show_count: default_line_length_show_count(),
+            per_filetype: BTreeMap::new(),
+        }
+    }
+}
+
+impl LineLength {
+    pub fn max_for(&self, filetype: Option<&str>) -> usize {
+        filetype.and_then(|name| self.per_filetype.get(name)).copied().unwrap_or(self.max)
+    }
+}
+
+fn default_line_length_max() -> usize { 100 }
+fn default_line_length_color() -> String { "#5C2B2B".to_string() }
+fn default_line_length_show_count() -> bool { true }
+
+/// Lines so long, as in minified files, that styling them makes scrolling crawl.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct LongLines {
+    /// Characters; 0 never treats a line as too long.
+    #[serde(default = "default_long_lines_limit")]
+    pub limit: usize,
+    /// Whether such lines are drawn without highlighting or selection: "ask" when a file
+    /// has them, "always" or "never".
+    #[serde(default = "default_long_lines_plain")]
+    pub plain: String,
+}
+
+impl Default for LongLines {
+    fn default() -> Self {
+        LongLines {
+            limit: default_long_lines_limit(),
+            plain: default_long_lines_plain(),
+        }
+    }
+}
+
+fn default_long_lines_limit() -> usize { 10_000 }
+fn default_long_lines_plain() -> String { "ask".to_string() }
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Breadcrumbs {
+    #[serde(default = "default_breadcrumbs_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_breadcrumbs_separator")]
+    pub separator: String,
+}
+
+impl Default for Breadcrumbs {
+    fn default() -> Self {
+        Breadcrumbs {
+            enabled: default_breadcrumbs_enabled(),
+            separator: default_breadcrumbs_separator(),
+        }
+    }
+}
+
+fn default_breadcrumbs_enabled() -> bool { true }
+fn default_breadcrumbs_separator() -> String { " > ".to_string() }
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SearchHighlight {
+    #[serde(default = "default_search_highlight_color")]
+    pub highlight_color: String,
+    /// Matches stop being highlighted this long after the last search; 0 keeps them.
+    #[serde(default = "default_search_highlight_timeout_ms")]
+    pub highlight_timeout_ms: u64,
+}
+
+impl Default for SearchHighlight {
+    fn default() -> Self {
+        SearchHighlight {
+            highlight_color: default_search_highlight_color(),
+            highlight_timeout_ms: default_search_highlight_timeout_ms(),
+        }
+    }
+}
+
+fn default_search_highlight_color() -> String { "#5C5326".to_string() }
+fn default_search_highlight_timeout_ms() -> u64 { 10_000 }
+
+/// Templates may use `{filename}`, `{modified}`, `{filetype}`, `{line}`, `{column}` and
+/// `{lines}`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Title {
+    #[serde(default = "default_title_template")]
+    pub template: String,
+    /// "frame" puts the title in the frame border, "top_bar" in a bar of its own above the
+    /// editor and "hidden" nowhere.
+    #[serde(default = "default_title_position")]
+    pub position: String,
+    /// Shown right-aligned in the top bar.
+    #[serde(default = "default_title_right_template")]
+    pub right_template: String,
+}
+
+impl Default for Title {
+    fn default() -> Self {
+        Title {
+            template: default_title_template(),
+            position: default_title_position(),
+            right_template: default_title_right_template(),
+        }
+    }
+}
+
+fn default_title_template() -> String { "Zepto - {filename} {modified}".to_string() }
+fn default_title_position() -> String { "frame".to_string() }
+fn default_title_right_template() -> String { "{filetype}  {line}:{column}".to_string() }
+
+/// Word count, reading time and goal progress in the status bar for prose filetypes.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Prose {
+    #[serde(default = "default_prose_enabled")]
+    pub enabled: bool,
+    /// Filetype names to count words in; "text" is any file without a syntax.
+    #[serde(default = "default_prose_filetypes")]
+    pub filetypes: Vec<String>,
+    #[serde(default = "default_prose_words_per_minute")]
+    pub words_per_minute: usize,
+    /// Words to aim for; 0 shows no progress.
+    #[serde(default = "default_prose_word_goal")]
+    pub word_goal: usize,
+}
+
+impl Default for Prose {
+    fn default() -> Self {
+        Prose {
+            enabled: default_prose_enabled(),
+            filetypes: default_prose_filetypes(),
+            words_per_minute: default_prose_words_per_minute(),
+            word_goal: default_prose_word_goal(),
+        }
+    }
+}
+
+fn default_prose_enabled() -> bool { true }
+fn default_prose_filetypes() -> Vec<String> { vec!["markdown".to_string(), "text".to_string()] }
+fn default_prose_words_per_minute() -> usize { 230 }
+fn default_prose_word_goal() -> usize { 0 }
+
+/// Checking JSON, YAML and TOML files for syntax errors when they are saved.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Validation {
+    #[serde(default = "default_validation_on_save")]
+    pub on_save: bool,
+    /// Marks the line number and the spot of the error.
+    #[serde(default = "default_validation_error_color")]
+    pub error_color: String,
+}
+
+impl Default for Validation {
+    fn default() -> Self {
+        Validation {
+            on_save: default_validation_on_save(),
+            error_color: default_validation_error_color(),
+        }
+    }
+}
+
+fn default_validation_on_save() -> bool { true }
+fn default_validation_error_color() -> String { "#E05050".to_string() }
+
+/// How notes attached with `:note` are shown.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AnnotationStyle {
+    /// Color of the gutter marker on annotated lines.
+    #[serde(default = "default_annotation_color")]
+    pub color: String,
+    /// Shows the note in a box under the cursor line.
+    #[serde(default = "default_annotation_popup")]
+    pub popup: bool,
+}
+
+impl Default for AnnotationStyle {
+    fn default() -> Self {
+        AnnotationStyle {
+            color: default_annotation_color(),
+            popup: default_annotation_popup(),
+        }
+    }
+}
+
+fn default_annotation_color() -> String { "#D7BA7D".to_string() }
+fn default_annotation_popup() -> bool { true }
+
+/// URLs and `path:line` references, which `gx` and `gf` open.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Links {
+    #[serde(default = "default_links_underline")]
+    pub underline: bool,
+    /// Program that opens URLs, with any arguments before the URL; empty uses the
+    /// desktop's default handler.
+    #[serde(default)]
+    pub browser: String,
+}
+
+impl Default for Links {
+    fn default() -> Self {
+        Links {
+            underline: default_links_underline(),
+            browser: String::new(),
+        }
+    }
+}
+
+fn default_links_underline() -> bool { true }
+
+/// Unusual spaces and characters that pass for ASCII ones, which `:hidden` lists.
+/// Zero-width characters are always drawn as a marker, as they would not show at all.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct HiddenCharacters {
+    #[serde(default = "default_hidden_characters_highlight")]
+    pub highlight: bool,
+    #[serde(default = "default_hidden_characters_color")]
+    pub color: String,
+}
+
+impl Default for HiddenCharacters {
+    fn default() -> Self {
+        HiddenCharacters {
+            highlight: default_hidden_characters_highlight(),
+            color: default_hidden_characters_color(),
+        }
+    }
+}
+
+fn default_hidden_characters_highlight() -> bool { true }
+fn default_hidden_characters_color() -> String { "#C678DD".to_string() }
+
+/// Brackets in code colored by how deeply they nest, cycling through `colors`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RainbowBrackets {
+    #[serde(default = "default_rainbow_brackets_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_rainbow_brackets_colors")]
+    pub colors: Vec<String>,
+}
+
+impl Default for RainbowBrackets {
+    fn default() -> Self {
+        RainbowBrackets {
+            enabled: default_rainbow_brackets_enabled(),
+            colors: default_rainbow_brackets_colors(),
+        }
+    }
+}
+
+fn default_rainbow_brackets_enabled() -> bool { true }
+fn default_rainbow_brackets_colors() -> Vec<String> { vec!["#E5C07B".to_string(), "#61AFEF".to_string(), "#56B6C2".to_string()] }
+
+/// The rows below the last line, which show `marker` as vim's `~` does so the end of a
+/// short file can't be mistaken for blank lines.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct EndOfBuffer {
+    /// Empty leaves the rows blank.
+    #[serde(default = "default_end_of_buffer_marker")]
+    pub marker: String,
+}
+
+impl Default for EndOfBuffer {
+    fn default() -> Self {
+        EndOfBuffer {
+            marker: default_end_of_buffer_marker(),
+        }
+    }
+}
+
+fn default_end_of_buffer_marker() -> String { "~".to_string() }
+
+/// Lines too long for the window carry on over the rows below instead of scrolling
+/// sideways.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SoftWrap {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Starts each row a line carries on over.
+    #[serde(default = "default_soft_wrap_marker")]
+    pub marker: String,
+    /// Indents those rows as far as the line itself is indented.
+    #[serde(default = "default_soft_wrap_indent")]
+    pub indent: bool,
+}
+
+impl Default for SoftWrap {
+    fn default() -> Self {
+        SoftWrap {
+            enabled: false,
+            marker: default_soft_wrap_marker(),
+            indent: default_soft_wrap_indent(),
+        }
+    }
+}
+
+fn default_soft_wrap_marker() -> String { "↪ ".to_string() }
+fn default_soft_wrap_indent() -> bool { true }
+
+/// Notifications stacked in the editor's bottom right corner, such as save confirmations
+/// and finished commands, instead of in the status bar.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Toasts {
+    #[serde(default = "default_toasts_enabled")]
+    pub enabled: bool,
+    /// How long each stays up.
+    #[serde(default = "default_toasts_duration_ms")]
+    pub duration_ms: u64,
+    /// How many show at once; older ones go early.
+    #[serde(default = "default_toasts_max")]
+    pub max: usize,
+    #[serde(default = "default_toasts_info_color")]
+    pub info_color: String,
+    #[serde(default = "default_toasts_warning_color")]
+    pub warning_color: String,
+    #[serde(default = "default_toasts_error_color")]
+    pub error_color: String,
+}
+
+impl Default for Toasts {
+    fn default() -> Self {
+        Toasts {
+            enabled: default_toasts_enabled(),
+            duration_ms: default_toasts_duration_ms(),
+            max: default_toasts_max(),
+            info_color: default_toasts_info_color(),
+            warning_color: default_toasts_warning_color(),
+            error_color: default_toasts_error_color(),
+        }
+    }
+}
+
+fn default_toasts_enabled() -> bool { true }
+fn default_toasts_duration_ms() -> u64 { 4000 }
+fn default_toasts_max() -> usize { 3 }
+fn default_toasts_info_color() -> String { "#61AFEF".to_string() }
+fn default_toasts_warning_color() -> String { "#E5C07B".to_string() }
+fn default_toasts_error_color() -> String { "#E06C75".to_string() }
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct EditorBehavior {
     #[serde(default = "default_vim_mode")]
     pub vim: bool,
+    #[serde(default = "default_escape_timeout_ms")]
+    pub escape_timeout_ms: u64,
+    #[serde(default = "default_crash_report_contents")]
+    pub crash_report_contents: bool,
+    #[serde(default = "default_tab_width")]
+    pub tab_width: u16,
+    #[serde(default = "default_expand_tab")]
+    pub expand_tab: bool,
+    /// Indents as the opened file already does, tabs or so many spaces, over `tab_width`
+    /// and `expand_tab`.
+    #[serde(default = "default_detect_indent")]
+    pub detect_indent: bool,
+    /// Fits pasted lines to the indentation of the line they go into.
+    #[serde(default = "default_reindent_paste")]
+    pub reindent_paste: bool,
+    /// Paste mode, for pasting into terminals without bracketed paste: typed text goes in
+    /// as is, with no closing keywords or reindenting.
+    #[serde(default)]
+    pub paste: bool,
+    #[serde(default = "default_save_on_focus_lost")]
+    pub save_on_focus_lost: bool,
+    /// Quitting warns about unsaved changes older than this; 0 turns the warning off.
+    #[serde(default = "default_unsaved_warning_minutes")]
+    pub unsaved_warning_minutes: u64,
+    /// Where the cursor may go past the end of a line, after vim's `virtualedit`: "none",
+    /// "onemore" (normal mode may rest just past the last character) or "all" (vertical
+    /// moves keep their column beyond the end of shorter lines).
+    #[serde(default = "default_virtual_edit")]
+    pub virtual_edit: String,
+    /// Typing a character or Enter with text selected replaces the selection instead of
+    /// just deselecting it.
+    #[serde(default = "default_typing_replaces_selection")]
+    pub typing_replaces_selection: bool,
+    /// Going to a line or a search match moves the end of the selection there instead of
+    /// dropping it, as PageUp and PageDown do with Shift held.
+    #[serde(default = "default_jumps_extend_selection")]
+    pub jumps_extend_selection: bool,
+    /// Takes over the mouse: clicks move the cursor and drags select, and in the gutter
+    /// they select whole lines. Terminals then only select text with Shift held. Read at
+    /// startup.
+    #[serde(default)]
+    pub mouse: bool,
+    /// Keeps the cursor line in the middle of the screen, scrolling the text instead.
+    #[serde(default = "default_typewriter_scrolling")]
+    pub typewriter_scrolling: bool,
+    /// How often the screen is refreshed while idle, so spinners and "saved 2m ago" move.
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// Upper bound on redraws per second however fast events arrive.
+    #[serde(default = "default_max_fps")]
+    pub max_fps: u32,
+    /// Redraws only when something happens: no idle refresh, so a quiet editor costs no
+    /// CPU or terminal traffic. Clocks such as "saved 2m ago" then update on the next key.
+    #[serde(default = "default_low_power")]
+    pub low_power: bool,
+    /// Pasting a single file path into an empty, unnamed buffer offers to open the file,
+    /// which is what dragging a file onto the terminal does.
+    #[serde(default = "default_open_dropped_files")]
+    pub open_dropped_files: bool,
+    /// "mac" adds macOS shortcuts (Cmd+S, Option+Left, ...), "standard" leaves them out
+    /// and "auto" picks by platform.
+    #[serde(default = "default_keymap")]
+    pub keymap: String,
+    /// Keeps the version a save replaces in the state directory, for `:backup`.
+    #[serde(default = "default_backup")]
+    pub backup: bool,
+    /// How many saved versions of each file `:timeline` keeps; 0 keeps none.
+    #[serde(default = "default_timeline_size")]
+    pub timeline_size: usize,
+    /// How many entries each prompt's history keeps across sessions; 0 keeps none.
+    #[serde(default = "default_history_size")]
+    pub history_size: usize,
+    /// Searches ignore case unless the pattern has a capital letter in it.
+    #[serde(default = "default_smartcase")]
+    pub smartcase: bool,
+    /// Reads search patterns as regular expressions, whose groups `:s` replacements can use.
+    #[serde(default = "default_regex_search")]
+    pub regex_search: bool,
+    /// Filetypes in which Enter after a line opening a block, such as `if ...; then` in
+    /// shell, puts in the keyword closing it.
+    #[serde(default = "default_close_keywords")]
+    pub close_keywords: Vec<String>,
+    /// Starts new files from the template for their filetype in the templates directory.
+    #[serde(default = "default_templates")]
+    pub templates: bool,
+    /// Fills in `{{author}}` in templates; empty uses the login name.
+    #[serde(default)]
+    pub author: String,
+    /// Gitignore-style patterns for what the file finder and project scans leave out.
+    #[serde(default = "default_ignore")]
+    pub ignore: Vec<String>,
+    /// Also honor `.gitignore` files and `.git/info/exclude`.
+    #[serde(default = "default_use_gitignore")]
+    pub use_gitignore: bool,
 }
 
 impl Default for EditorBehavior {
     fn default() -> Self {
         Self {
-            vim: false,
+            vim: default_vim_mode(),
+            escape_timeout_ms: default_escape_timeout_ms(),
+            crash_report_contents: default_crash_report_contents(),
+            tab_width: default_tab_width(),
+            expand_tab: default_expand_tab(),
+            detect_indent: default_detect_indent(),
+            reindent_paste: default_reindent_paste(),
+            paste: false,
+            save_on_focus_lost: default_save_on_focus_lost(),
+            unsaved_warning_minutes: default_unsaved_warning_minutes(),
+            virtual_edit: default_virtual_edit(),
+            typing_replaces_selection: default_typing_replaces_selection(),
+            jumps_extend_selection: default_jumps_extend_selection(),
+            mouse: false,
+            typewriter_scrolling: default_typewriter_scrolling(),
+            poll_interval_ms: default_poll_interval_ms(),
+            max_fps: default_max_fps(),
+            low_power: default_low_power(),
+            open_dropped_files: default_open_dropped_files(),
+            keymap: default_keymap(),
+            backup: default_backup(),
+            timeline_size: default_timeline_size(),
+            history_size: default_history_size(),
+            smartcase: default_smartcase(),
+            regex_search: default_regex_search(),
+            close_keywords: default_close_keywords(),
+            templates: default_templates(),
+            author: String::new(),
+            ignore: default_ignore(),
+            use_gitignore: default_use_gitignore(),
         }
     }
 }
@@ -167,17 +781,216 @@ fn default_vim_mode() -> bool {
     false
 }
 
+fn default_escape_timeout_ms() -> u64 { 50 }
+fn default_crash_report_contents() -> bool { false }
+fn default_tab_width() -> u16 { 4 }
+fn default_expand_tab() -> bool { true }
+fn default_detect_indent() -> bool { true }
+fn default_reindent_paste() -> bool { true }
+fn default_save_on_focus_lost() -> bool { false }
+fn default_unsaved_warning_minutes() -> u64 { 10 }
+fn default_virtual_edit() -> String { "none".to_string() }
+fn default_typing_replaces_selection() -> bool { true }
+fn default_jumps_extend_selection() -> bool { false }
+fn default_typewriter_scrolling() -> bool { false }
+fn default_poll_interval_ms() -> u64 { 50 }
+fn default_max_fps() -> u32 { 60 }
+fn default_low_power() -> bool { false }
+fn default_open_dropped_files() -> bool { true }
+fn default_keymap() -> String { "auto".to_string() }
+fn default_backup() -> bool { true }
+fn default_timeline_size() -> usize { 20 }
+fn default_history_size() -> usize { 100 }
+fn default_smartcase() -> bool { true }
+fn default_regex_search() -> bool { false }
+fn default_close_keywords() -> Vec<String> { vec!["shell".to_string(), "ruby".to_string(), "lua".to_string()] }
+fn default_templates() -> bool { true }
+fn default_ignore() -> Vec<String> { vec![".*".to_string(), "target/".to_string(), "node_modules/".to_string()] }
+fn default_use_gitignore() -> bool { true }
+
 fn default_background_color() -> String { "#000000".to_string() }
+fn default_color_swatches() -> bool { true }
 
-pub fn load_config() -> Config {
-    let config_path = if let Some(config_dir) = dirs::config_dir() {
-        let mut path = config_dir;
-        path.push("zepto");
-        path.push("config.toml");
-        path
-    } else {
-        PathBuf::from("config.toml")
+pub fn config_path() -> PathBuf {
+    paths::config_file()
+}
+
+/// The config.toml in the repository, which says what each option does.
+const EXAMPLE_CONFIG: &str = include_str!("../config.toml");
+
+/// What a new config file holds: the example config, comments and all, with every option
+/// in it at its default.
+pub fn default_config_text() -> String {
+    let (Ok(toml::Value::Table(defaults)), Ok(example)) = (toml::Value::try_from(Config::default()), EXAMPLE_CONFIG.parse::<toml::Table>()) else {
+        return EXAMPLE_CONFIG.to_string();
+    };
+    let mut text = EXAMPLE_CONFIG.to_string();
+    let mut reset = Vec::new();
+    for (key, _) in settings::flatten_table(&example) {
+        let parts: Vec<&str> = key.split('.').collect();
+        // An entry of a map that is empty by default, as per_filetype is, empties the map.
+        let Some((len, value)) = (1..=parts.len()).rev().find_map(|len| lookup(&defaults, &parts[..len]).map(|value| (len, value))) else {
+            continue;
+        };
+        let option = parts[..len].join(".");
+        if !reset.contains(&option) {
+            text = set_option_in_text(&text, &option, value);
+            reset.push(option);
+        }
+    }
+    text
+}
+
+fn lookup<'a>(table: &'a toml::Table, parts: &[&str]) -> Option<&'a toml::Value> {
+    let (first, rest) = parts.split_first()?;
+    match table.get(*first)? {
+        toml::Value::Table(inner) if !rest.is_empty() => lookup(inner, rest),
+        value if rest.is_empty() => Some(value),
+        _ => None,
+    }
+}
+
+/// Writes out `default_config_text`, for when there is no config file yet.
+pub fn write_default_config() -> io::Result<PathBuf> {
+    let path = config_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, default_config_text())?;
+    Ok(path)
+}
+
+/// Sets the dotted options in `changes` (`main_section.theme`) in the config file. Only
+/// their lines are rewritten, so the rest of the file, comments included, stays as the user
+/// left it; a file that isn't there yet starts from `default_config_text`.
+pub fn save_options(changes: &[(String, toml::Value)]) -> io::Result<PathBuf> {
+    let path = config_path();
+    let mut text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            default_config_text()
+        }
+        Err(e) => return Err(e),
     };
+    for (key, value) in changes {
+        text = set_option_in_text(&text, key, value);
+    }
+    fs::write(&path, text)?;
+    Ok(path)
+}
+
+/// `text` with the option `key` set to `value`: on its own line, keeping whatever comment
+/// follows, or else added at the end of its table, which is added too if need be.
+fn set_option_in_text(text: &str, key: &str, value: &toml::Value) -> String {
+    let (table, name) = key.rsplit_once('.').unwrap_or(("", key));
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    let mut in_table = table.is_empty();
+    // Where a missing option goes: after the last one in its table, if the table is there.
+    let mut insert_at = table.is_empty().then_some(0);
+    for row in 0..lines.len() {
+        let line = lines[row].trim();
+        if let Some(header) = table_header(line) {
+            in_table = header == table;
+            if in_table {
+                insert_at = Some(row + 1);
+            }
+            continue;
+        }
+        if !in_table || line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        insert_at = Some(row + 1);
+        let Some(equals) = lines[row].find('=') else {
+            continue;
+        };
+        if unquote(lines[row][..equals].trim()) != name {
+            continue;
+        }
+        let start = equals + 1;
+        let (end_row, end) = value_end(&lines, row, start).unwrap_or((row, lines[row].len()));
+        let mut rest = lines[end_row][end..].to_string();
+        let value = value.to_string();
+        // A comment lined up with the ones around it stays in its column where it fits.
+        let old = lines[row][start..].trim_start();
+        if end_row == row && rest.trim_start().starts_with('#') {
+            let old_len = old.len() - (lines[row].len() - end);
+            let pad = rest.len() - rest.trim_start().len();
+            rest = format!("{}{}", " ".repeat((pad + old_len).saturating_sub(value.len()).max(1)), rest.trim_start());
+        }
+        let replaced = format!("{} {}{}", &lines[row][..start], value, rest);
+        lines.splice(row..=end_row, [replaced]);
+        return join_lines(lines, text);
+    }
+    let option = format!("{} = {}", quote_key(name), value);
+    match insert_at {
+        Some(row) => lines.insert(row, option),
+        None => {
+            if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push(format!("[{}]", table.split('.').map(quote_key).collect::<Vec<_>>().join(".")));
+            lines.push(option);
+        }
+    }
+    join_lines(lines, text)
+}
+
+/// The dotted name of the table a `[table]` line starts, without quotes.
+fn table_header(line: &str) -> Option<String> {
+    let inner = line.strip_prefix('[')?.split(']').next()?;
+    if inner.starts_with('[') {
+        return None;
+    }
+    Some(inner.split('.').map(|part| unquote(part.trim())).collect::<Vec<_>>().join("."))
+}
+
+/// Where the value starting at column `start` of `lines[row]` ends: the shortest stretch,
+/// up to a `#` or the end of a line, that parses as a value, so a `#` in a string or an
+/// array over several lines is taken whole.
+fn value_end(lines: &[String], row: usize, start: usize) -> Option<(usize, usize)> {
+    let mut value = String::new();
+    for (end_row, line) in lines.iter().enumerate().skip(row) {
+        let offset = if end_row == row { start } else { 0 };
+        let line = &line[offset..];
+        for cut in line.match_indices('#').map(|(at, _)| at).chain([line.len()]) {
+            if toml::from_str::<toml::Table>(&format!("v = {}{}", value, &line[..cut])).is_ok() {
+                return Some((end_row, offset + line[..cut].trim_end().len()));
+            }
+        }
+        value.push_str(line);
+        value.push('\n');
+    }
+    None
+}
+
+fn unquote(key: &str) -> String {
+    key.strip_prefix('"').and_then(|key| key.strip_suffix('"')).unwrap_or(key).to_string()
+}
+
+fn quote_key(key: &str) -> String {
+    let bare = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare { key.to_string() } else { toml::Value::String(key.to_string()).to_string() }
+}
+
+fn join_lines(lines: Vec<String>, original: &str) -> String {
+    let mut text = lines.join("\n");
+    if original.ends_with('\n') || original.is_empty() {
+        text.push('\n');
+    }
+    text
+}
+
+/// Reads the config file again, for applying it after it was edited.
+pub fn reload_config() -> Result<Config, String> {
+    let content = fs::read_to_string(config_path()).map_err(|e| e.to_string())?;
+    toml::from_str(&content).map_err(|e| e.to_string())
+}
+
+pub fn load_config() -> Config {
+    let config_path = config_path();
 
     println!("Attempting to load config from: {}", config_path.display());
 
@@ -186,6 +999,7 @@ pub fn load_config() -> Config {
             match toml::from_str(&content) {
                 Ok(config) => config,
                 Err(e) => {
+                    log::warn!("error parsing {}: {}", config_path.display(), e);
                     eprintln!("Error parsing config.toml: {}. Using default configuration.", e);
                     Config::default()
                 }
@@ -193,21 +1007,47 @@ pub fn load_config() -> Config {
         },
         Err(e) => {
             eprintln!("Could not read config file at {}: {}. Using default configuration.", config_path.display(), e);
-            if let Some(mut default_config_dir) = dirs::config_dir() {
-                default_config_dir.push("zepto");
-                if let Err(create_err) = fs::create_dir_all(&default_config_dir) {
-                    eprintln!("Error creating config directory {:?}: {}", default_config_dir, create_err);
-                } else {
-                    let default_config = toml::to_string_pretty(&Config::default()).unwrap();
-                    let full_config_path = default_config_dir.join("config.toml");
-                    if let Err(write_err) = fs::write(&full_config_path, default_config) {
-                        eprintln!("Error writing default config to {}: {}", full_config_path.display(), write_err);
-                    } else {
-                        println!("Created default config file at {}", full_config_path.display());
-                    }
-                }
+            match write_default_config() {
+                Ok(path) => println!("Created default config file at {}", path.display()),
+                Err(write_err) => eprintln!("Error writing default config to {}: {}", config_path.display(), write_err),
             }
             Config::default()
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn setting_an_option_keeps_the_rest_of_the_file() {
+        let text = "# zepto\n[main_section]\ntheme = \"dark\"   # the theme\ncolor = \"#fff\" # white\n\n[editor_behavior]\nignore = [\n  \".*\",  # dotfiles\n]\nvim = false\n";
+        let set = |text: &str, key: &str, value: toml::Value| set_option_in_text(text, key, &value);
+
+        let themed = set(text, "main_section.theme", toml::Value::String("light".to_string()));
+        assert_eq!(themed, text.replace("\"dark\"   # the theme", "\"light\"  # the theme"));
+
+        let colored = set(text, "main_section.color", toml::Value::String("#000".to_string()));
+        assert_eq!(colored, text.replace("\"#fff\" # white", "\"#000\" # white"));
+
+        let ignored = set(text, "editor_behavior.ignore", toml::Value::Array(Vec::new()));
+        assert_eq!(ignored, text.replace("[\n  \".*\",  # dotfiles\n]", "[]"));
+
+        let added = set(text, "editor_behavior.mouse", toml::Value::Boolean(true));
+        assert_eq!(added, text.replace("vim = false\n", "vim = false\nmouse = true\n"));
+
+        let bound = set(text, "keys.<F10>", toml::Value::String("set paste!".to_string()));
+        assert_eq!(bound, format!("{}\n[keys]\n\"<F10>\" = \"set paste!\"\n", text));
+    }
+
+    #[test]
+    fn the_default_config_file_is_the_example_at_the_defaults() {
+        let text = default_config_text();
+        let parsed: Config = toml::from_str(&text).unwrap();
+        assert_eq!(settings::changed_options(&Config::default(), &parsed), Vec::new());
+        assert_eq!(settings::changed_options(&parsed, &Config::default()), Vec::new());
+        let comments = |text: &str| text.lines().filter(|line| line.contains('#')).count();
+        assert_eq!(comments(&text), comments(EXAMPLE_CONFIG));
+    }
+}
@@ -0,0 +1,131 @@
+/// Characters that take up no room, so nothing shows where they are.
+const INVISIBLE: &[(char, &str)] = &[
+    ('\u{00AD}', "soft hyphen"),
+    ('\u{200B}', "zero-width space"),
+    ('\u{200C}', "zero-width non-joiner"),
+    ('\u{200D}', "zero-width joiner"),
+    ('\u{2060}', "word joiner"),
+    ('\u{FEFF}', "zero-width no-break space"),
+];
+
+/// Spaces that look like the ordinary one but are not whitespace to most parsers.
+const SPACES: &[(char, &str)] = &[
+    ('\u{00A0}', "no-break space"),
+    ('\u{2002}', "en space"),
+    ('\u{2003}', "em space"),
+    ('\u{2007}', "figure space"),
+    ('\u{2008}', "punctuation space"),
+    ('\u{2009}', "thin space"),
+    ('\u{200A}', "hair space"),
+    ('\u{202F}', "narrow no-break space"),
+];
+
+/// Punctuation that word processors and chat apps put in place of the ASCII one. Fine in
+/// prose, so only flagged in code.
+const TYPOGRAPHY: &[(char, &str, &str)] = &[
+    ('\u{2018}', "left single quotation mark", "'"),
+    ('\u{2019}', "right single quotation mark", "'"),
+    ('\u{201C}', "left double quotation mark", "\""),
+    ('\u{201D}', "right double quotation mark", "\""),
+    ('\u{2010}', "hyphen", "-"),
+    ('\u{2011}', "non-breaking hyphen", "-"),
+    ('\u{2013}', "en dash", "-"),
+    ('\u{2014}', "em dash", "-"),
+    ('\u{2212}', "minus sign", "-"),
+    ('\u{037E}', "Greek question mark", ";"),
+    ('\u{FF1B}', "fullwidth semicolon", ";"),
+];
+
+/// Cyrillic and Greek letters drawn like Latin ones. Only flagged inside a word that
+/// also has ASCII letters, so text written in those scripts is left alone.
+const HOMOGLYPHS: &[(char, &str, &str)] = &[
+    ('а', "Cyrillic", "a"), ('е', "Cyrillic", "e"), ('о', "Cyrillic", "o"), ('р', "Cyrillic", "p"),
+    ('с', "Cyrillic", "c"), ('у', "Cyrillic", "y"), ('х', "Cyrillic", "x"), ('і', "Cyrillic", "i"),
+    ('ј', "Cyrillic", "j"), ('ѕ', "Cyrillic", "s"), ('А', "Cyrillic", "A"), ('В', "Cyrillic", "B"),
+    ('Е', "Cyrillic", "E"), ('К', "Cyrillic", "K"), ('М', "Cyrillic", "M"), ('Н', "Cyrillic", "H"),
+    ('О', "Cyrillic", "O"), ('Р', "Cyrillic", "P"), ('С', "Cyrillic", "C"), ('Т', "Cyrillic", "T"),
+    ('Х', "Cyrillic", "X"), ('ο', "Greek", "o"), ('ν', "Greek", "v"), ('Α', "Greek", "A"),
+    ('Β', "Greek", "B"), ('Ε', "Greek", "E"), ('Ζ', "Greek", "Z"), ('Η', "Greek", "H"),
+    ('Ι', "Greek", "I"), ('Κ', "Greek", "K"), ('Μ', "Greek", "M"), ('Ν', "Greek", "N"),
+    ('Ο', "Greek", "O"), ('Ρ', "Greek", "P"), ('Τ', "Greek", "T"), ('Χ', "Greek", "X"),
+];
+
+/// A character that is easy to miss or to take for another one.
+#[derive(Debug, Clone)]
+pub struct Suspect {
+    /// Byte offset in the line.
+    pub start: usize,
+    pub character: char,
+    pub description: String,
+    /// What `:hidden fix` puts in its place.
+    pub replacement: &'static str,
+}
+
+impl Suspect {
+    pub fn end(&self) -> usize {
+        self.start + self.character.len_utf8()
+    }
+}
+
+/// Whether `c` is one of the characters that take up no room.
+pub fn is_invisible(c: char) -> bool {
+    INVISIBLE.iter().any(|(invisible, _)| *invisible == c)
+}
+
+/// The suspicious characters in `line`, in order. `code` adds typographic quotes and
+/// dashes, which only cause trouble where a parser reads them.
+pub fn find(line: &str, code: bool) -> Vec<Suspect> {
+    let mut suspects = Vec::new();
+    if line.is_ascii() {
+        return suspects;
+    }
+    for (start, c) in line.char_indices() {
+        let found = if let Some((_, name)) = INVISIBLE.iter().find(|(other, _)| *other == c) {
+            Some((name.to_string(), ""))
+        } else if let Some((_, name)) = SPACES.iter().find(|(other, _)| *other == c) {
+            Some((name.to_string(), " "))
+        } else if let Some((_, name, ascii)) = TYPOGRAPHY.iter().find(|(other, _, _)| code && *other == c) {
+            Some((format!("{}, looks like {}", name, ascii), *ascii))
+        } else if let Some((_, script, ascii)) = HOMOGLYPHS.iter().find(|(other, _, _)| *other == c)
+            && in_latin_word(line, start)
+        {
+            Some((format!("{} {}, looks like {}", script, c, ascii), *ascii))
+        } else {
+            None
+        };
+        if let Some((name, replacement)) = found {
+            suspects.push(Suspect {
+                start,
+                character: c,
+                description: format!("U+{:04X} {}", c as u32, name),
+                replacement,
+            });
+        }
+    }
+    suspects
+}
+
+/// Whether the word around byte `at` of `line` has ASCII letters in it.
+fn in_latin_word(line: &str, at: usize) -> bool {
+    let start = line[..at].rfind(|c: char| !c.is_alphanumeric()).map_or(0, |i| i + line[i..].chars().next().map_or(1, char::len_utf8));
+    let end = line[at..].find(|c: char| !c.is_alphanumeric()).map_or(line.len(), |i| at + i);
+    line[start..end].contains(|c: char| c.is_ascii_alphabetic())
+}
+
+/// `line` with every suspicious character replaced by what it stands in for, or `None`
+/// when there are none.
+pub fn normalize(line: &str, code: bool) -> Option<String> {
+    let suspects = find(line, code);
+    if suspects.is_empty() {
+        return None;
+    }
+    let mut normalized = String::with_capacity(line.len());
+    let mut copied = 0;
+    for suspect in &suspects {
+        normalized.push_str(&line[copied..suspect.start]);
+        normalized.push_str(suspect.replacement);
+        copied = suspect.end();
+    }
+    normalized.push_str(&line[copied..]);
+    Some(normalized)
+}
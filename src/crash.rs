@@ -0,0 +1,66 @@
+use std::{
+    backtrace::Backtrace,
+    fs,
+    io::{self, stdout},
+    panic,
+    path::PathBuf,
+    sync::Mutex,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crossterm::{
+    event::{DisableBracketedPaste, DisableFocusChange, DisableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, LeaveAlternateScreen},
+};
+
+use crate::{display, input, paths};
+
+static LAST_PANIC: Mutex<Option<String>> = Mutex::new(None);
+
+/// Puts the terminal back into a usable state before anything is printed, and keeps the
+/// panic message and backtrace around for the crash report written once unwinding is done.
+/// Only a panic on the thread that installs it, the one drawing the UI, is a crash: tasks
+/// catch their own panics and report them as failures, so for theirs the panic is logged and
+/// handed to the hook that was there before.
+pub fn install_panic_hook() {
+    let ui_thread = thread::current().id();
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if thread::current().id() != ui_thread {
+            log::error!("panic on {}: {}", thread::current().name().unwrap_or("unnamed thread"), info);
+            previous(info);
+            return;
+        }
+        let _ = disable_raw_mode();
+        let _ = input::stop_reporting_super_key(&mut stdout());
+        let _ = display::restore_bidi_layout(&mut stdout());
+        let _ = execute!(stdout(), DisableFocusChange, DisableBracketedPaste, DisableMouseCapture, LeaveAlternateScreen, crossterm::cursor::Show);
+
+        let details = format!("{}\n\nbacktrace:\n{}", info, Backtrace::force_capture());
+        log::error!("panic: {}", info);
+        eprintln!("zepto crashed: {}", info);
+        if let Ok(mut last) = LAST_PANIC.lock() {
+            *last = Some(details);
+        }
+    }));
+}
+
+pub fn take_panic() -> Option<String> {
+    LAST_PANIC.lock().ok()?.take()
+}
+
+pub fn write_report(sections: &[(&str, String)]) -> io::Result<PathBuf> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let mut path = paths::state_dir();
+    fs::create_dir_all(&path)?;
+    path.push(format!("crash-{}.txt", timestamp));
+
+    let mut report = format!("zepto {} crash report\n", env!("CARGO_PKG_VERSION"));
+    for (title, body) in sections {
+        report.push_str(&format!("\n== {} ==\n{}\n", title, body));
+    }
+    fs::write(&path, report)?;
+    Ok(path)
+}
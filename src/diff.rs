@@ -0,0 +1,328 @@
+use std::{fs, io, ops::Range};
+
+use crate::platform::LineEnding;
+
+/// Beyond this many differing lines the remaining middle is reported as one replaced block
+/// instead of searching for the shortest edit script. The search keeps a copy of its
+/// frontier per step for the backtrack, about D² words after D steps, so this caps it
+/// near 2 MB.
+const MAX_EDIT_DISTANCE: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edit {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Myers' O(ND) line diff between `a` and `b`, after trimming the common prefix and suffix.
+pub fn diff_lines<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Edit> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut edits: Vec<Edit> = (0..prefix).map(|i| Edit::Equal(i, i)).collect();
+    match myers(a_mid, b_mid) {
+        Some(middle) => edits.extend(middle.into_iter().map(|edit| match edit {
+            Edit::Equal(x, y) => Edit::Equal(x + prefix, y + prefix),
+            Edit::Delete(x) => Edit::Delete(x + prefix),
+            Edit::Insert(y) => Edit::Insert(y + prefix),
+        })),
+        None => {
+            edits.extend((0..a_mid.len()).map(|x| Edit::Delete(x + prefix)));
+            edits.extend((0..b_mid.len()).map(|y| Edit::Insert(y + prefix)));
+        }
+    }
+    edits.extend((0..suffix).map(|i| Edit::Equal(a.len() - suffix + i, b.len() - suffix + i)));
+    edits
+}
+
+/// The runs of `edits` that change something, as the lines of `a` they replace and the
+/// lines of `b` that replace them. Either range is empty for a pure insertion or deletion.
+pub fn hunks(edits: &[Edit]) -> Vec<(Range<usize>, Range<usize>)> {
+    let mut hunks = Vec::new();
+    let mut current: Option<(Range<usize>, Range<usize>)> = None;
+    let (mut x, mut y) = (0, 0);
+    for edit in edits {
+        match *edit {
+            Edit::Equal(a, b) => {
+                hunks.extend(current.take());
+                (x, y) = (a + 1, b + 1);
+            }
+            Edit::Delete(a) => {
+                current.get_or_insert((x..x, y..y)).0.end = a + 1;
+                x = a + 1;
+            }
+            Edit::Insert(b) => {
+                current.get_or_insert((x..x, y..y)).1.end = b + 1;
+                y = b + 1;
+            }
+        }
+    }
+    hunks.extend(current);
+    hunks
+}
+
+fn myers<T: PartialEq>(a: &[T], b: &[T]) -> Option<Vec<Edit>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    // trace[d] holds v[-(d+1)..=d+1] as it was before step d.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    for d in 0..=max.min(MAX_EDIT_DISTANCE) as isize {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let index = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, n, m));
+            }
+        }
+    }
+    None
+}
+
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<Edit> {
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) { k + 1 } else { k - 1 };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Equal(x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == prev_x {
+                edits.push(Edit::Insert((y - 1) as usize));
+            } else {
+                edits.push(Edit::Delete((x - 1) as usize));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    edits.reverse();
+    edits
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RowKind {
+    Same,
+    /// Only on the left.
+    Removed,
+    /// Only on the right.
+    Added,
+    /// On both sides but different.
+    Changed,
+}
+
+/// One screen row of the side-by-side view; a missing side is drawn as filler.
+#[derive(Debug, Clone, Copy)]
+pub struct Row {
+    pub left: Option<usize>,
+    pub right: Option<usize>,
+    pub kind: RowKind,
+}
+
+/// Pairs up the edits of each hunk so changed lines sit next to each other.
+pub fn align(edits: &[Edit]) -> Vec<Row> {
+    let mut rows = Vec::new();
+    let mut deleted = Vec::new();
+    let mut inserted = Vec::new();
+    let flush = |rows: &mut Vec<Row>, deleted: &mut Vec<usize>, inserted: &mut Vec<usize>| {
+        for i in 0..deleted.len().max(inserted.len()) {
+            let (left, right) = (deleted.get(i).copied(), inserted.get(i).copied());
+            let kind = match (left, right) {
+                (Some(_), Some(_)) => RowKind::Changed,
+                (Some(_), None) => RowKind::Removed,
+                _ => RowKind::Added,
+            };
+            rows.push(Row { left, right, kind });
+        }
+        deleted.clear();
+        inserted.clear();
+    };
+    for edit in edits {
+        match *edit {
+            Edit::Equal(x, y) => {
+                flush(&mut rows, &mut deleted, &mut inserted);
+                rows.push(Row { left: Some(x), right: Some(y), kind: RowKind::Same });
+            }
+            Edit::Delete(x) => deleted.push(x),
+            Edit::Insert(y) => inserted.push(y),
+        }
+    }
+    flush(&mut rows, &mut deleted, &mut inserted);
+    rows
+}
+
+pub struct DiffSide {
+    pub path: String,
+    pub lines: Vec<String>,
+    pub modified: bool,
+    line_ending: LineEnding,
+}
+
+impl DiffSide {
+    fn open(path: &str) -> io::Result<DiffSide> {
+        let content = fs::read_to_string(path)?;
+        Ok(DiffSide {
+            path: path.to_string(),
+            lines: content.lines().map(str::to_string).collect(),
+            modified: false,
+            line_ending: LineEnding::detect(&content),
+        })
+    }
+
+    pub fn save(&mut self) -> io::Result<()> {
+        fs::write(&self.path, self.lines.join(self.line_ending.as_str()))?;
+        self.modified = false;
+        Ok(())
+    }
+}
+
+/// Two files shown side by side with a shared cursor row, so both panes always scroll
+/// together.
+pub struct DiffView {
+    pub left: DiffSide,
+    pub right: DiffSide,
+    pub rows: Vec<Row>,
+    pub cursor: usize,
+    pub scroll_y: usize,
+    pub scroll_x: usize,
+    /// Set when the left side is an older version to take hunks from, such as a backup,
+    /// and the right side the buffer being edited, which gets the result instead of a
+    /// file. Names the older version.
+    pub restore_from: Option<String>,
+}
+
+impl DiffView {
+    pub fn open(left: &str, right: &str) -> io::Result<DiffView> {
+        let mut view = DiffView {
+            left: DiffSide::open(left)?,
+            right: DiffSide::open(right)?,
+            rows: Vec::new(),
+            cursor: 0,
+            scroll_y: 0,
+            scroll_x: 0,
+            restore_from: None,
+        };
+        view.recompute();
+        Ok(view)
+    }
+
+    /// Compares `lines`, the buffer editing `path`, with the older version in `old`,
+    /// described by `label`.
+    pub fn restore(old: &str, label: String, path: &str, lines: Vec<String>, line_ending: LineEnding) -> io::Result<DiffView> {
+        let mut view = DiffView {
+            left: DiffSide::open(old)?,
+            right: DiffSide { path: path.to_string(), lines, modified: false, line_ending },
+            rows: Vec::new(),
+            cursor: 0,
+            scroll_y: 0,
+            scroll_x: 0,
+            restore_from: Some(label),
+        };
+        view.recompute();
+        Ok(view)
+    }
+
+    fn recompute(&mut self) {
+        self.rows = align(&diff_lines(&self.left.lines, &self.right.lines));
+        self.cursor = self.cursor.min(self.rows.len().saturating_sub(1));
+    }
+
+    pub fn hunk_count(&self) -> usize {
+        self.hunks().count()
+    }
+
+    /// Row ranges of consecutive differing rows.
+    fn hunks(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let mut start = None;
+        (0..=self.rows.len()).filter_map(move |i| {
+            let differs = self.rows.get(i).is_some_and(|row| row.kind != RowKind::Same);
+            match (differs, start) {
+                (true, None) => {
+                    start = Some(i);
+                    None
+                }
+                (false, Some(from)) => {
+                    start = None;
+                    Some((from, i))
+                }
+                _ => None,
+            }
+        })
+    }
+
+    fn hunk_at_cursor(&self) -> Option<(usize, usize)> {
+        self.hunks().find(|&(from, to)| (from..to).contains(&self.cursor))
+    }
+
+    pub fn next_hunk(&mut self) -> bool {
+        let next = self.hunks().find(|&(from, _)| from > self.cursor);
+        if let Some((from, _)) = next {
+            self.cursor = from;
+        }
+        next.is_some()
+    }
+
+    pub fn previous_hunk(&mut self) -> bool {
+        let current = self.hunk_at_cursor().map_or(self.cursor, |(from, _)| from);
+        let previous = self.hunks().filter(|&(from, _)| from < current).last();
+        if let Some((from, _)) = previous {
+            self.cursor = from;
+        }
+        previous.is_some()
+    }
+
+    /// Replaces the hunk under the cursor on the right with the left side's version.
+    pub fn take_left(&mut self) -> bool {
+        self.take_hunk(true)
+    }
+
+    /// Replaces the hunk under the cursor on the left with the right side's version.
+    pub fn take_right(&mut self) -> bool {
+        self.take_hunk(false)
+    }
+
+    fn take_hunk(&mut self, from_left: bool) -> bool {
+        let Some((from, to)) = self.hunk_at_cursor() else {
+            return false;
+        };
+        let hunk = &self.rows[from..to];
+        let source_lines: Vec<usize> = hunk.iter().filter_map(|row| if from_left { row.left } else { row.right }).collect();
+        let target_lines: Vec<usize> = hunk.iter().filter_map(|row| if from_left { row.right } else { row.left }).collect();
+        // Where an empty side of the hunk goes: right after the last line before it.
+        let insert_at = {
+            let before = self.rows[..from].iter().rev()
+                .find_map(|row| if from_left { row.right } else { row.left });
+            target_lines.first().copied().unwrap_or(before.map_or(0, |line| line + 1))
+        };
+
+        let (source, target) = if from_left { (&self.left, &mut self.right) } else { (&self.right, &mut self.left) };
+        let replacement: Vec<String> = source_lines.iter().map(|&i| source.lines[i].clone()).collect();
+        target.lines.splice(insert_at..insert_at + target_lines.len(), replacement);
+        target.modified = true;
+        self.recompute();
+        true
+    }
+}
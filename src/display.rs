@@ -0,0 +1,75 @@
+use std::io::{self, Write};
+
+use unicode_width::UnicodeWidthChar;
+
+use crate::confusables;
+
+/// Terminal cells `c` takes up: two for wide glyphs such as CJK and most emoji, none for
+/// combining marks and control characters. Characters drawn as a marker take one.
+pub fn char_width(c: char) -> usize {
+    if marker(c).is_some() {
+        return 1;
+    }
+    c.width().unwrap_or(0)
+}
+
+/// Cells taken up by the characters `from..to` of `line`, counted in chars.
+pub fn columns_width(line: &str, from: usize, to: usize) -> usize {
+    line.chars().skip(from).take(to.saturating_sub(from)).map(char_width).sum()
+}
+
+/// Marks that draw on top of the character before them, such as the accent of a
+/// decomposed `é`. The cursor moves over them together with that character.
+pub fn is_combining(c: char) -> bool {
+    char_width(c) == 0 && !c.is_control()
+}
+
+/// Characters that set which way text runs, such as the overrides used to make code read
+/// differently than it compiles. They draw nothing on their own, so they are shown as
+/// markers instead.
+pub fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{061C}' | '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+/// The embeddings, overrides and isolates, which reorder everything up to the end of
+/// the line rather than nudging a single character like the marks do.
+pub fn is_bidi_override(c: char) -> bool {
+    matches!(c, '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+/// What is drawn in place of `c`, when it is not drawn as itself.
+pub fn marker(c: char) -> Option<char> {
+    if is_bidi_control(c) {
+        Some('⇆')
+    } else if confusables::is_invisible(c) {
+        Some('◌')
+    } else {
+        None
+    }
+}
+
+/// The first of `lines` holding override characters, and how many there are in all.
+pub fn bidi_overrides<'a>(lines: impl Iterator<Item = &'a String>) -> Option<(usize, usize)> {
+    let mut found = None;
+    for (row, line) in lines.enumerate() {
+        let count = line.chars().filter(|c| is_bidi_override(*c)).count();
+        if count > 0 {
+            found.get_or_insert((row, 0)).1 += count;
+        }
+    }
+    found
+}
+
+/// Asks terminals that lay out right-to-left text themselves to draw cells in the order
+/// they are written (ECMA-48 BDSM reset), so the screen shows the file's logical order.
+/// Terminals without bidi support ignore it.
+pub fn keep_logical_order(out: &mut impl Write) -> io::Result<()> {
+    out.write_all(b"\x1b[8l")?;
+    out.flush()
+}
+
+/// Undoes `keep_logical_order`.
+pub fn restore_bidi_layout(out: &mut impl Write) -> io::Result<()> {
+    out.write_all(b"\x1b[8h")?;
+    out.flush()
+}
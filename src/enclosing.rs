@@ -0,0 +1,144 @@
+use crate::{buffer::Position, indent};
+
+/// Openers and the closers that match them.
+const BRACKETS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}')];
+
+/// How lines closing an indented block start, so the block and its closer go together.
+const BLOCK_CLOSERS: &[&str] = &["}", ")", "]", "end", "fi", "done", "esac"];
+
+/// The next larger range around `start..end` (end exclusive): the word, the inside of a
+/// string, then the string, the inside of brackets, then the brackets, the line, the
+/// indented block, the block with its header and closer, and at last the whole buffer.
+/// `None` once the whole buffer is covered.
+pub fn expand(lines: &[String], (start, end): (Position, Position), tab_width: usize) -> Option<(Position, Position)> {
+    let mut candidates = Vec::new();
+    if start.0 == end.0 {
+        candidates.extend(word(&lines[start.0], start));
+        candidates.extend(quoted(&lines[start.0], start.0));
+    }
+    candidates.extend(bracketed(lines, start, end));
+    let (first, last) = (start.0, end.0);
+    let text_start = lines[first].len() - lines[first].trim_start().len();
+    candidates.push(((first, text_start.min(start.1)), (last, lines[last].trim_end().len().max(end.1))));
+    candidates.push(((first, 0), (last, lines[last].len())));
+    candidates.extend(blocks(lines, first, last, tab_width));
+    let last_row = lines.len() - 1;
+    candidates.push(((0, 0), (last_row, lines[last_row].len())));
+    candidates.into_iter()
+        .filter(|&(from, to)| from <= start && to >= end && (from, to) != (start, end))
+        .min_by_key(|&(from, to)| offset(lines, to) - offset(lines, from))
+}
+
+fn offset(lines: &[String], (row, col): Position) -> usize {
+    lines[..row].iter().map(|line| line.len() + 1).sum::<usize>() + col
+}
+
+fn word(line: &str, (row, col): Position) -> Option<(Position, Position)> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let from = line[..col].char_indices().rev().take_while(|&(_, c)| is_word(c)).last().map_or(col, |(offset, _)| offset);
+    let to = line[col..].char_indices().find(|&(_, c)| !is_word(c)).map_or(line.len(), |(offset, _)| col + offset);
+    (from < to).then_some(((row, from), (row, to)))
+}
+
+/// The insides and outsides of the strings on `line`, skipping escaped quotes.
+fn quoted(line: &str, row: usize) -> Vec<(Position, Position)> {
+    let mut ranges = Vec::new();
+    let mut open: Option<(usize, char)> = None;
+    let mut escaped = false;
+    for (offset, c) in line.char_indices() {
+        match open {
+            _ if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some((from, quote)) if c == quote => {
+                ranges.push(((row, from + 1), (row, offset)));
+                ranges.push(((row, from), (row, offset + 1)));
+                open = None;
+            }
+            None if matches!(c, '"' | '\'' | '`') => open = Some((offset, c)),
+            _ => {}
+        }
+    }
+    ranges
+}
+
+/// The insides and outsides of the brackets around `start..end`, innermost first.
+fn bracketed(lines: &[String], start: Position, end: Position) -> Vec<(Position, Position)> {
+    let mut ranges = Vec::new();
+    let mut closers = Vec::new();
+    for row in (0..=start.0).rev() {
+        let line = &lines[row];
+        let before = if row == start.0 { &line[..start.1] } else { line.as_str() };
+        for (col, c) in before.char_indices().rev() {
+            if BRACKETS.iter().any(|&(_, closer)| closer == c) {
+                closers.push(c);
+            } else if let Some(&(_, closer)) = BRACKETS.iter().find(|&&(opener, _)| opener == c) {
+                if closers.last() == Some(&closer) {
+                    closers.pop();
+                } else if closers.is_empty()
+                    && let Some(close) = matching(lines, (row, col), c, closer)
+                    && close >= end
+                {
+                    ranges.push(((row, col + 1), close));
+                    ranges.push(((row, col), (close.0, close.1 + 1)));
+                    if ranges.len() >= 4 {
+                        return ranges;
+                    }
+                }
+            }
+        }
+    }
+    ranges
+}
+
+/// Where the bracket closing the `opener` at `from` is.
+fn matching(lines: &[String], from: Position, opener: char, closer: char) -> Option<Position> {
+    let mut depth = 0;
+    for (row, line) in lines.iter().enumerate().skip(from.0) {
+        let skip = if row == from.0 { from.1 } else { 0 };
+        for (col, c) in line.char_indices().filter(|&(col, _)| col >= skip) {
+            if c == opener {
+                depth += 1;
+            } else if c == closer {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((row, col));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// The whole lines indented at least as deep as the shallowest of `first..=last`, and
+/// those with the line above that opens them and the line below that closes them.
+fn blocks(lines: &[String], first: usize, last: usize, tab_width: usize) -> Vec<(Position, Position)> {
+    let blank = |row: usize| lines[row].trim().is_empty();
+    let depth = |row: usize| indent::width(&lines[row], tab_width);
+    let Some(level) = (first..=last).filter(|&row| !blank(row)).map(depth).min() else {
+        return Vec::new();
+    };
+    let within = |row: usize| blank(row) || depth(row) >= level;
+    let mut top = first;
+    while top > 0 && within(top - 1) {
+        top -= 1;
+    }
+    let mut bottom = last;
+    while bottom + 1 < lines.len() && within(bottom + 1) {
+        bottom += 1;
+    }
+    while top < first && blank(top) {
+        top += 1;
+    }
+    while bottom > last && blank(bottom) {
+        bottom -= 1;
+    }
+    let whole = |top: usize, bottom: usize| ((top, 0), (bottom, lines[bottom].len()));
+    let mut ranges = vec![whole(top, bottom)];
+    if top > 0 {
+        let header = top - 1;
+        let closes = bottom + 1 < lines.len() && depth(bottom + 1) == depth(header)
+            && BLOCK_CLOSERS.iter().any(|closer| lines[bottom + 1].trim_start().starts_with(closer));
+        ranges.push(whole(header, if closes { bottom + 1 } else { bottom }));
+    }
+    ranges
+}
@@ -0,0 +1,68 @@
+use std::{env, fs};
+
+use crate::platform;
+
+/// Expands a leading `~` or `~user` and any `$VAR` or `${VAR}` in `path`, as a shell
+/// would. A `$` not followed by a variable name is kept as it is, and `$$`, or `\$` outside
+/// Windows where the backslash separates directories, is a `$` of its own.
+pub fn expand_path(path: &str) -> Result<String, String> {
+    let path = expand_home(path)?;
+    let mut expanded = String::with_capacity(path.len());
+    let mut rest = path.as_str();
+    while let Some(at) = rest.find('$') {
+        let after = &rest[at + 1..];
+        if !platform::WINDOWS && let Some(before) = rest[..at].strip_suffix('\\') {
+            expanded.push_str(before);
+            expanded.push('$');
+            rest = after;
+            continue;
+        }
+        expanded.push_str(&rest[..at]);
+        if let Some(escaped) = after.strip_prefix('$') {
+            expanded.push('$');
+            rest = escaped;
+            continue;
+        }
+        let (name, skip) = match after.strip_prefix('{') {
+            Some(braced) => {
+                let end = braced.find('}').ok_or_else(|| format!("missing '}}' after ${{ in {}", path))?;
+                (&braced[..end], end + 2)
+            }
+            None => {
+                let end = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+        if name.is_empty() && skip == 0 {
+            expanded.push('$');
+        } else {
+            let value = env::var(name).map_err(|_| format!("undefined variable ${} in {}", name, path))?;
+            expanded.push_str(&value);
+        }
+        rest = &after[skip..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn expand_home(path: &str) -> Result<String, String> {
+    let Some(after_tilde) = path.strip_prefix('~') else {
+        return Ok(path.to_string());
+    };
+    let (user, rest) = after_tilde.split_at(after_tilde.find(platform::is_separator).unwrap_or(after_tilde.len()));
+    let home = if user.is_empty() {
+        dirs::home_dir().ok_or("cannot expand ~: no home directory")?.display().to_string()
+    } else {
+        home_of(user).ok_or_else(|| format!("cannot expand ~{}: no such user", user))?
+    };
+    Ok(format!("{}{}", home, rest))
+}
+
+/// Another user's home directory, from the password file.
+fn home_of(user: &str) -> Option<String> {
+    let passwd = fs::read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|entry| {
+        let fields: Vec<&str> = entry.split(':').collect();
+        (fields.len() > 5 && fields[0] == user).then(|| fields[5].to_string())
+    })
+}
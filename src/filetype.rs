@@ -0,0 +1,101 @@
+use crate::highlight::Syntax;
+
+/// Lines at each end of a file searched for a modeline, as vim does.
+const MODELINE_LINES: usize = 5;
+
+/// Interpreters named in shebangs, by the syntax their scripts are written in.
+const INTERPRETERS: &[(&str, &str)] = &[
+    ("python", "python"),
+    ("sh", "shell"),
+    ("bash", "shell"),
+    ("zsh", "shell"),
+    ("dash", "shell"),
+    ("ksh", "shell"),
+    ("node", "javascript"),
+    ("nodejs", "javascript"),
+    ("deno", "javascript"),
+    ("bun", "javascript"),
+];
+
+/// Picks the syntax for a file. A modeline naming the filetype wins, then the extension,
+/// then the shebang, and last a look at the content for files that are plainly XML or JSON.
+pub fn detect(path: &str, lines: &[String]) -> Option<&'static Syntax> {
+    modeline(lines)
+        .or_else(|| Syntax::for_path(path))
+        .or_else(|| lines.first().and_then(|line| shebang(line)))
+        .or_else(|| sniff(lines))
+}
+
+/// `# vim: set ft=python:`, `// vi: filetype=rust` or `-*- mode: shell -*-` near either
+/// end of the file.
+fn modeline(lines: &[String]) -> Option<&'static Syntax> {
+    let tail = lines.len().saturating_sub(MODELINE_LINES).max(MODELINE_LINES.min(lines.len()));
+    lines[..MODELINE_LINES.min(lines.len())].iter()
+        .chain(&lines[tail..])
+        .find_map(|line| vim_modeline(line).or_else(|| emacs_modeline(line)))
+        .and_then(Syntax::by_name)
+}
+
+fn vim_modeline(line: &str) -> Option<&str> {
+    let start = ["vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        line.match_indices(marker)
+            .find(|&(at, _)| at == 0 || line[..at].ends_with(char::is_whitespace))
+            .map(|(at, _)| at + marker.len())
+    })?;
+    let options = line[start..].trim_start();
+    let options = options.strip_prefix("set ").unwrap_or(options);
+    options.split([' ', ':', '\t'])
+        .filter_map(|option| option.split_once('='))
+        .find(|(name, _)| matches!(*name, "ft" | "filetype" | "syn" | "syntax"))
+        .map(|(_, value)| value)
+}
+
+fn emacs_modeline(line: &str) -> Option<&str> {
+    let (_, rest) = line.split_once("-*-")?;
+    let (variables, _) = rest.split_once("-*-")?;
+    if !variables.contains(':') {
+        return Some(variables.trim());
+    }
+    variables.split(';')
+        .filter_map(|variable| variable.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("mode"))
+        .map(|(_, value)| value.trim())
+}
+
+/// `#!/usr/bin/python3`, or `#!/usr/bin/env -S node --flag`, with any version number
+/// dropped from the interpreter.
+fn shebang(line: &str) -> Option<&'static Syntax> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|word| !word.starts_with('-') && !word.contains('='))?;
+    }
+    let program = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    INTERPRETERS.iter()
+        .find(|(interpreter, _)| *interpreter == program)
+        .and_then(|(_, name)| Syntax::by_name(name))
+}
+
+/// XML declarations and doctypes, or a document that opens and closes like a JSON object
+/// or array.
+fn sniff(lines: &[String]) -> Option<&'static Syntax> {
+    let mut content = lines.iter().map(|line| line.trim()).filter(|line| !line.is_empty());
+    let first = content.next()?;
+    let last = content.next_back().unwrap_or(first);
+    if first.starts_with("<?xml") || first.starts_with("<!DOCTYPE") {
+        return Syntax::by_name("xml");
+    }
+    // A lone `[section]` header opens and closes with brackets too, but no JSON array
+    // starts with a bare word.
+    let array_start = |line: &str| {
+        let Some(rest) = line.strip_prefix('[').map(str::trim_start) else {
+            return false;
+        };
+        rest.is_empty()
+            || rest.starts_with(['[', '{', '"', '-', ']'])
+            || rest.starts_with(|c: char| c.is_ascii_digit())
+            || ["true", "false", "null"].iter().any(|word| rest.starts_with(word))
+    };
+    let json = (first.starts_with('{') && last.ends_with('}')) || (array_start(first) && last.ends_with(']'));
+    if json { Syntax::by_name("json") } else { None }
+}
@@ -0,0 +1,179 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+use crate::{filetype, highlight, ignore, tasks::TaskContext};
+
+/// Stops the directory walk from running away in huge trees such as a home directory.
+const MAX_FILES: usize = 50_000;
+const PREVIEW_LINES: usize = 200;
+const PREVIEW_MAX_BYTES: u64 = 256 * 1024;
+
+pub struct Preview {
+    pub path: String,
+    pub lines: Vec<String>,
+    pub syntax: Option<&'static highlight::Syntax>,
+    pub binary: bool,
+}
+
+/// Fuzzy file picker over every file below the working directory, with a read-only preview
+/// of the highlighted entry.
+pub struct FileFinder {
+    files: Option<Vec<String>>,
+    pub query: String,
+    pub matches: Vec<usize>,
+    pub selected: usize,
+    preview: Option<Preview>,
+}
+
+impl FileFinder {
+    pub fn new() -> Self {
+        FileFinder {
+            files: None,
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+            preview: None,
+        }
+    }
+
+    pub fn is_scanning(&self) -> bool {
+        self.files.is_none()
+    }
+
+    pub fn set_files(&mut self, files: Vec<String>) {
+        self.files = Some(files);
+        self.refilter();
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        self.query.push(c);
+        self.refilter();
+    }
+
+    pub fn pop_char(&mut self) {
+        self.query.pop();
+        self.refilter();
+    }
+
+    pub fn set_query(&mut self, query: String) {
+        self.query = query;
+        self.refilter();
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.matches.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn file(&self, match_index: usize) -> Option<&str> {
+        let files = self.files.as_ref()?;
+        self.matches.get(match_index).map(|&i| files[i].as_str())
+    }
+
+    pub fn selected_file(&self) -> Option<&str> {
+        self.file(self.selected)
+    }
+
+    /// Loads the preview lazily so scrolling through the list only reads the files the
+    /// cursor actually stops on.
+    pub fn preview(&mut self) -> Option<&Preview> {
+        let path = self.selected_file()?.to_string();
+        if self.preview.as_ref().is_none_or(|preview| preview.path != path) {
+            self.preview = Some(load_preview(path));
+        }
+        self.preview.as_ref()
+    }
+
+    fn refilter(&mut self) {
+        let Some(files) = &self.files else {
+            return;
+        };
+        let query = self.query.to_lowercase();
+        let mut scored: Vec<(usize, usize)> = files.iter().enumerate()
+            .filter_map(|(i, file)| fuzzy_score(&query, file).map(|score| (score, i)))
+            .collect();
+        scored.sort();
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+    }
+}
+
+/// Lower is better. Every query character must appear in order; gaps between matched
+/// characters and matches outside the file name cost points.
+fn fuzzy_score(query: &str, path: &str) -> Option<usize> {
+    let file_name_start = path.rfind('/').map_or(0, |i| i + 1);
+    let mut score = 0;
+    let mut last_match = None;
+    let mut chars = path.char_indices();
+    for wanted in query.chars() {
+        let (offset, _) = chars.by_ref().find(|(_, c)| c.to_lowercase().eq(wanted.to_lowercase()))?;
+        score += match last_match {
+            Some(last) => offset - last - 1,
+            None => offset.saturating_sub(file_name_start),
+        };
+        if offset < file_name_start {
+            score += 1;
+        }
+        last_match = Some(offset);
+    }
+    Some(score * 1000 + path.len())
+}
+
+fn load_preview(path: String) -> Preview {
+    let mut lines = Vec::new();
+    let mut binary = false;
+    if let Ok(file) = fs::File::open(&path) {
+        let reader = BufReader::new(file.take(PREVIEW_MAX_BYTES));
+        for line in reader.split(b'\n').take(PREVIEW_LINES) {
+            let Ok(bytes) = line else {
+                break;
+            };
+            if bytes.contains(&0) {
+                binary = true;
+                lines.clear();
+                break;
+            }
+            lines.push(String::from_utf8_lossy(&bytes).trim_end_matches('\r').to_string());
+        }
+    }
+    let syntax = filetype::detect(&path, &lines);
+    Preview { path, lines, syntax, binary }
+}
+
+/// Lists files below `root` as `/`-separated relative paths, leaving out what `rules`
+/// ignore (hidden entries and build output, by default) so the list stays about source
+/// files.
+pub fn scan<T>(root: &Path, mut rules: ignore::Rules, context: &TaskContext<T>) -> Result<Vec<String>, String> {
+    let mut files = Vec::new();
+    let mut pending = vec![String::new()];
+    while let Some(dir) = pending.pop() {
+        context.check_cancelled()?;
+        let Ok(entries) = fs::read_dir(root.join(&dir)) else {
+            continue;
+        };
+        rules.enter(root, &dir);
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let relative = if dir.is_empty() { name.to_string_lossy().into_owned() } else { format!("{}/{}", dir, name.to_string_lossy()) };
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() && !rules.is_ignored(&relative, true) => pending.push(relative),
+                Ok(kind) if kind.is_file() && !rules.is_ignored(&relative, false) => files.push(relative),
+                _ => {}
+            }
+        }
+        context.progress(files.len() as u64, None);
+        if files.len() >= MAX_FILES {
+            break;
+        }
+    }
+    files.sort();
+    Ok(files)
+}
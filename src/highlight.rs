@@ -0,0 +1,513 @@
+use crate::buffer::{Change, ChangeEvent};
+use std::{
+    borrow::Cow,
+    path::Path,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+/// Lines longer than this many bytes are never sent to the tokenizer.
+pub const MAX_HIGHLIGHT_LINE_LEN: usize = 10_000;
+
+/// Bytes of lines the worker has no tokens for yet that a frame may tokenize itself.
+/// Drawing them plain and then again in color once the worker catches up would send
+/// every such line to the terminal twice.
+pub const INLINE_HIGHLIGHT_BUDGET: usize = 32 * 1024;
+
+/// Words in comments that mark work left to do. They stand out from the rest of the
+/// comment and are what the TODO panel lists.
+pub const TODO_TAGS: &[&str] = &["TODO", "FIXME", "HACK"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Keyword,
+    String,
+    Comment,
+    Number,
+    Added,
+    Removed,
+    Todo,
+}
+
+/// A styled run on a single line, in char columns (`end` is exclusive).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub start: usize,
+    pub end: usize,
+    pub kind: TokenKind,
+}
+
+/// How the symbol provider finds the scopes a line belongs to.
+#[derive(Debug)]
+pub enum Scopes {
+    /// Lines starting with one of these keywords open a scope covering the more indented
+    /// lines below them.
+    Definitions(&'static [&'static str]),
+    /// Markdown `#` headings, nested by level.
+    Headings,
+    /// `[table]` headers, as in TOML and INI files.
+    Sections,
+}
+
+#[derive(Debug)]
+pub struct Syntax {
+    pub name: &'static str,
+    extensions: &'static [&'static str],
+    keywords: &'static [&'static str],
+    line_comment: Option<&'static str>,
+    string_delimiters: &'static [char],
+    /// Lines starting with one of these prefixes are colored whole; the first match wins.
+    line_kinds: &'static [(&'static str, TokenKind)],
+    pub scopes: Scopes,
+}
+
+static SYNTAXES: &[Syntax] = &[
+    Syntax {
+        name: "rust",
+        extensions: &["rs"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+            "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+            "trait", "true", "type", "unsafe", "use", "where", "while",
+        ],
+        line_comment: Some("//"),
+        string_delimiters: &['"'],
+        line_kinds: &[],
+        scopes: Scopes::Definitions(&["fn", "struct", "enum", "impl", "trait", "mod", "union"]),
+    },
+    Syntax {
+        name: "c",
+        extensions: &["c", "h", "cc", "cpp", "hpp", "cxx"],
+        keywords: &[
+            "auto", "break", "case", "char", "class", "const", "continue", "default", "do",
+            "double", "else", "enum", "extern", "float", "for", "goto", "if", "int", "long",
+            "namespace", "private", "protected", "public", "return", "short", "signed", "sizeof",
+            "static", "struct", "switch", "template", "typedef", "union", "unsigned", "void",
+            "volatile", "while",
+        ],
+        line_comment: Some("//"),
+        string_delimiters: &['"', '\''],
+        line_kinds: &[],
+        scopes: Scopes::Definitions(&["struct", "class", "namespace", "enum", "union"]),
+    },
+    Syntax {
+        name: "python",
+        extensions: &["py", "pyw"],
+        keywords: &[
+            "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class",
+            "continue", "def", "del", "elif", "else", "except", "finally", "for", "from", "global",
+            "if", "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise",
+            "return", "try", "while", "with", "yield",
+        ],
+        line_comment: Some("#"),
+        string_delimiters: &['"', '\''],
+        line_kinds: &[],
+        scopes: Scopes::Definitions(&["def", "class"]),
+    },
+    Syntax {
+        name: "javascript",
+        extensions: &["js", "mjs", "cjs", "jsx", "ts", "tsx"],
+        keywords: &[
+            "async", "await", "break", "case", "catch", "class", "const", "continue", "default",
+            "delete", "do", "else", "export", "extends", "false", "finally", "for", "function",
+            "if", "import", "in", "instanceof", "let", "new", "null", "return", "super", "switch",
+            "this", "throw", "true", "try", "typeof", "undefined", "var", "void", "while", "yield",
+        ],
+        line_comment: Some("//"),
+        string_delimiters: &['"', '\'', '`'],
+        line_kinds: &[],
+        scopes: Scopes::Definitions(&["function", "class", "interface"]),
+    },
+    Syntax {
+        name: "shell",
+        extensions: &["sh", "bash", "zsh"],
+        keywords: &[
+            "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+            "in", "local", "return", "then", "until", "while",
+        ],
+        line_comment: Some("#"),
+        string_delimiters: &['"', '\''],
+        line_kinds: &[],
+        scopes: Scopes::Definitions(&["function"]),
+    },
+    Syntax {
+        name: "toml",
+        extensions: &["toml"],
+        keywords: &["true", "false"],
+        line_comment: Some("#"),
+        string_delimiters: &['"', '\''],
+        line_kinds: &[],
+        scopes: Scopes::Sections,
+    },
+    Syntax {
+        name: "markdown",
+        extensions: &["md", "markdown"],
+        keywords: &[],
+        line_comment: None,
+        string_delimiters: &['`'],
+        line_kinds: &[],
+        scopes: Scopes::Headings,
+    },
+    Syntax {
+        name: "json",
+        extensions: &["json"],
+        keywords: &["true", "false", "null"],
+        line_comment: None,
+        string_delimiters: &['"'],
+        line_kinds: &[],
+        scopes: Scopes::Definitions(&[]),
+    },
+    Syntax {
+        name: "yaml",
+        extensions: &["yaml", "yml"],
+        keywords: &["true", "false", "null"],
+        line_comment: Some("#"),
+        string_delimiters: &['"', '\''],
+        line_kinds: &[("---", TokenKind::Keyword)],
+        scopes: Scopes::Definitions(&[]),
+    },
+    Syntax {
+        name: "xml",
+        extensions: &["xml", "svg", "xsl", "plist"],
+        keywords: &[],
+        line_comment: None,
+        string_delimiters: &['"', '\''],
+        line_kinds: &[("<!--", TokenKind::Comment), ("<?", TokenKind::Keyword)],
+        scopes: Scopes::Definitions(&[]),
+    },
+    Syntax {
+        name: "diff",
+        extensions: &["diff", "patch"],
+        keywords: &[],
+        line_comment: None,
+        string_delimiters: &[],
+        line_kinds: &[
+            ("+++", TokenKind::Keyword),
+            ("---", TokenKind::Keyword),
+            ("diff ", TokenKind::Keyword),
+            ("index ", TokenKind::Keyword),
+            ("@@", TokenKind::Number),
+            ("+", TokenKind::Added),
+            ("-", TokenKind::Removed),
+        ],
+        scopes: Scopes::Definitions(&[]),
+    },
+];
+
+impl Syntax {
+    pub fn for_path(path: &str) -> Option<&'static Syntax> {
+        let extension = Path::new(path).extension()?.to_str()?;
+        Self::for_extension(extension)
+    }
+
+    pub fn for_extension(extension: &str) -> Option<&'static Syntax> {
+        SYNTAXES.iter().find(|syntax| syntax.extensions.contains(&extension))
+    }
+
+    /// Looks a syntax up by its name or, failing that, one of its extensions, so `sh` and
+    /// `py` work as well as `shell` and `python`.
+    pub fn by_name(name: &str) -> Option<&'static Syntax> {
+        SYNTAXES.iter().find(|syntax| syntax.name == name).or_else(|| Self::for_extension(name))
+    }
+
+    pub fn highlight_line(&self, line: &str) -> Vec<Token> {
+        if let Some((_, kind)) = self.line_kinds.iter().find(|(prefix, _)| line.starts_with(prefix)) {
+            return vec![Token { start: 0, end: line.chars().count(), kind: *kind }];
+        }
+        let chars: Vec<char> = line.chars().collect();
+        let comment: Option<Vec<char>> = self.line_comment.map(|c| c.chars().collect());
+        let mut tokens = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+
+            if let Some(comment) = &comment
+                && chars[i..].starts_with(comment)
+            {
+                comment_tokens(&chars, i, &mut tokens);
+                break;
+            }
+
+            if self.string_delimiters.contains(&c) {
+                let start = i;
+                i += 1;
+                while i < chars.len() && chars[i] != c {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                i = (i + 1).min(chars.len());
+                tokens.push(Token { start, end: i, kind: TokenKind::String });
+                continue;
+            }
+
+            if c.is_alphanumeric() || c == '_' {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                if c.is_ascii_digit() {
+                    tokens.push(Token { start, end: i, kind: TokenKind::Number });
+                } else if self.keywords.contains(&word.as_str()) {
+                    tokens.push(Token { start, end: i, kind: TokenKind::Keyword });
+                }
+                continue;
+            }
+
+            i += 1;
+        }
+        tokens
+    }
+}
+
+/// Tokens for a comment running from `start` to the end of the line: the comment, broken
+/// around any TODO tags in it.
+fn comment_tokens(chars: &[char], start: usize, tokens: &mut Vec<Token>) {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut from = start;
+    let mut i = start;
+    while i < chars.len() {
+        let tag = TODO_TAGS.iter().find(|tag| {
+            let end = i + tag.len();
+            chars[i..].iter().take(tag.len()).copied().eq(tag.chars())
+                && (i == 0 || !is_word(chars[i - 1]))
+                && chars.get(end).is_none_or(|c| !is_word(*c))
+        });
+        let Some(tag) = tag else {
+            i += 1;
+            continue;
+        };
+        if from < i {
+            tokens.push(Token { start: from, end: i, kind: TokenKind::Comment });
+        }
+        tokens.push(Token { start: i, end: i + tag.len(), kind: TokenKind::Todo });
+        i += tag.len();
+        from = i;
+    }
+    if from < chars.len() {
+        tokens.push(Token { start: from, end: chars.len(), kind: TokenKind::Comment });
+    }
+}
+
+/// The brackets of `line` outside its strings and comments, in char columns, each with
+/// the depth it is drawn at, and the depth the line leaves open. A closing bracket is drawn
+/// at the depth of the one it closes.
+pub fn brackets(line: &str, tokens: &[Token], mut depth: usize) -> (Vec<(usize, usize)>, usize) {
+    let mut found = Vec::new();
+    let mut skipped = tokens.iter().filter(|token| matches!(token.kind, TokenKind::String | TokenKind::Comment | TokenKind::Todo)).peekable();
+    for (col, c) in line.chars().enumerate() {
+        while skipped.next_if(|token| token.end <= col).is_some() {}
+        if skipped.peek().is_some_and(|token| token.start <= col) {
+            continue;
+        }
+        match c {
+            '(' | '[' | '{' => {
+                found.push((col, depth));
+                depth += 1;
+            }
+            ')' | ']' | '}' => {
+                depth = depth.saturating_sub(1);
+                found.push((col, depth));
+            }
+            _ => {}
+        }
+    }
+    (found, depth)
+}
+
+/// Bracket depth at the start of each line, from the top of the buffer down to the
+/// furthest line asked about. An edit cuts it back to the line it starts on, so only the
+/// lines from there down are scanned again.
+#[derive(Default)]
+pub struct BracketDepths {
+    syntax: Option<&'static str>,
+    starts: Vec<usize>,
+}
+
+impl BracketDepths {
+    /// The depth at the start of `row` of `lines`.
+    pub fn at(&mut self, row: usize, lines: &[String], syntax: &'static Syntax) -> usize {
+        if self.syntax != Some(syntax.name) {
+            self.syntax = Some(syntax.name);
+            self.starts.clear();
+        }
+        if self.starts.is_empty() {
+            self.starts.push(0);
+        }
+        while self.starts.len() <= row.min(lines.len().saturating_sub(1)) {
+            let previous = self.starts.len() - 1;
+            let (line, depth) = (&lines[previous], self.starts[previous]);
+            let after = match line.len() > MAX_HIGHLIGHT_LINE_LEN {
+                true => depth,
+                false => brackets(line, &syntax.highlight_line(line), depth).1,
+            };
+            self.starts.push(after);
+        }
+        self.starts.get(row).copied().unwrap_or_default()
+    }
+
+    pub fn apply_change(&mut self, event: &ChangeEvent) {
+        let row = match &event.change {
+            Change::Inserted { at: (row, _), .. } => *row,
+            Change::Deleted { start: (row, _), .. } => *row,
+            Change::Reset => 0,
+        };
+        self.starts.truncate(row + 1);
+    }
+}
+
+pub struct HighlightRequest {
+    pub revision: u64,
+    pub first_line: usize,
+    pub lines: Vec<String>,
+    pub syntax: &'static Syntax,
+}
+
+/// Tokens for a window of lines, together with the text they were computed from so the
+/// renderer can keep using them for lines that have not changed since.
+pub struct HighlightResult {
+    pub revision: u64,
+    pub first_line: usize,
+    pub lines: Vec<(String, Vec<Token>)>,
+}
+
+impl HighlightResult {
+    pub fn tokens_for(&self, line_idx: usize, line: &str) -> Option<&[Token]> {
+        let (text, tokens) = self.lines.get(line_idx.checked_sub(self.first_line)?)?;
+        (text == line).then_some(tokens.as_slice())
+    }
+}
+
+/// Tokenizes on a worker thread so typing never waits on the highlighter.
+pub struct Highlighter {
+    requests: Sender<HighlightRequest>,
+    results: Receiver<HighlightResult>,
+    latest: Option<HighlightResult>,
+    requested: Option<(u64, usize, usize)>,
+}
+
+impl Highlighter {
+    pub fn new() -> Self {
+        let (request_tx, request_rx) = mpsc::channel::<HighlightRequest>();
+        let (result_tx, result_rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            while let Ok(mut request) = request_rx.recv() {
+                // Only the newest request matters; anything queued behind it is already stale.
+                while let Ok(newer) = request_rx.try_recv() {
+                    request = newer;
+                }
+                let lines = request.lines.into_iter()
+                    .map(|line| {
+                        let tokens = request.syntax.highlight_line(&line);
+                        (line, tokens)
+                    })
+                    .collect();
+                let result = HighlightResult { revision: request.revision, first_line: request.first_line, lines };
+                if result_tx.send(result).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Highlighter {
+            requests: request_tx,
+            results: result_rx,
+            latest: None,
+            requested: None,
+        }
+    }
+
+    /// Asks the worker for the given window unless it is already covered at this revision.
+    pub fn request(&mut self, revision: u64, first_line: usize, lines: &[String], syntax: &'static Syntax) {
+        let key = (revision, first_line, lines.len());
+        if self.requested == Some(key) {
+            return;
+        }
+        self.requested = Some(key);
+        let lines = lines.iter()
+            .map(|line| if line.len() > MAX_HIGHLIGHT_LINE_LEN { String::new() } else { line.clone() })
+            .collect();
+        let _ = self.requests.send(HighlightRequest { revision, first_line, lines, syntax });
+    }
+
+    /// Collects finished results, dropping any that are older than the one already held.
+    /// Returns whether anything new arrived.
+    pub fn poll(&mut self) -> bool {
+        let mut updated = false;
+        while let Ok(result) = self.results.try_recv() {
+            if self.latest.as_ref().is_none_or(|latest| result.revision >= latest.revision) {
+                self.latest = Some(result);
+                updated = true;
+            }
+        }
+        updated
+    }
+
+    /// Whether the worker still owes a result for the last request.
+    pub fn is_pending(&self) -> bool {
+        self.requested.is_some_and(|(revision, first_line, _)| {
+            self.latest.as_ref().is_none_or(|latest| latest.revision != revision || latest.first_line != first_line)
+        })
+    }
+
+    pub fn tokens_for(&self, line_idx: usize, line: &str) -> Option<&[Token]> {
+        self.latest.as_ref()?.tokens_for(line_idx, line)
+    }
+
+    /// The worker's tokens for a line, or, while `budget` lasts, tokens made on the spot.
+    pub fn tokens_or_highlight(&self, line_idx: usize, line: &str, syntax: &Syntax, budget: &mut usize) -> Option<Cow<'_, [Token]>> {
+        if let Some(tokens) = self.tokens_for(line_idx, line) {
+            return Some(Cow::Borrowed(tokens));
+        }
+        if line.len() > MAX_HIGHLIGHT_LINE_LEN || line.len() > *budget {
+            return None;
+        }
+        *budget -= line.len();
+        Some(Cow::Owned(syntax.highlight_line(line)))
+    }
+
+    /// Keeps the cached window aligned with the buffer when lines are added or removed
+    /// above or inside it, so unchanged lines stay styled until fresh tokens arrive.
+    pub fn apply_change(&mut self, event: &ChangeEvent) {
+        let Some(latest) = self.latest.as_mut() else {
+            return;
+        };
+        match &event.change {
+            Change::Inserted { at: (row, _), text } => {
+                let added = text.matches('\n').count();
+                if added == 0 {
+                    return;
+                }
+                if latest.first_line > *row {
+                    latest.first_line += added;
+                } else if let Some(offset) = (row + 1).checked_sub(latest.first_line).filter(|&o| o <= latest.lines.len()) {
+                    latest.lines.splice(offset..offset, std::iter::repeat_n((String::new(), Vec::new()), added));
+                }
+            }
+            Change::Deleted { start: (start_row, _), end: (end_row, _), .. } => {
+                let removed = end_row - start_row;
+                if removed == 0 {
+                    return;
+                }
+                let first = latest.first_line;
+                let drop_from = (start_row + 1).max(first) - first;
+                let drop_to = (end_row + 1).max(first) - first;
+                latest.lines.drain(drop_from.min(latest.lines.len())..drop_to.min(latest.lines.len()));
+                latest.first_line = if first > *end_row {
+                    first - removed
+                } else {
+                    first.min(start_row + 1)
+                };
+            }
+            Change::Reset => {
+                self.latest = None;
+                self.requested = None;
+            }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
+
+use crate::paths;
+
+/// History of the command line's `:` prompt.
+pub const COMMANDS: &str = "command";
+/// History shared by the `/` and `?` prompts.
+pub const SEARCHES: &str = "search";
+/// History of the file finder's queries.
+pub const FINDER: &str = "finder";
+
+fn history_file() -> PathBuf {
+    paths::state_dir().join("prompt_history.toml")
+}
+
+/// What was typed at each prompt, oldest first, under the prompt's name.
+#[derive(Debug, Default)]
+pub struct History {
+    entries: BTreeMap<String, Vec<String>>,
+    max: usize,
+}
+
+fn read() -> io::Result<BTreeMap<String, Vec<String>>> {
+    match fs::read_to_string(history_file()) {
+        Ok(text) => toml::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Adds `entry` to the end of `entries`, dropping an earlier copy and the oldest ones
+/// beyond `max`.
+fn push(entries: &mut Vec<String>, entry: &str, max: usize) {
+    entries.retain(|existing| existing != entry);
+    entries.push(entry.to_string());
+    entries.drain(..entries.len().saturating_sub(max));
+}
+
+impl History {
+    /// The histories saved by earlier sessions, each cut to `max` entries.
+    pub fn load(max: usize) -> io::Result<Self> {
+        let mut entries = if max == 0 { BTreeMap::new() } else { read()? };
+        for list in entries.values_mut() {
+            list.drain(..list.len().saturating_sub(max));
+        }
+        Ok(History { entries, max })
+    }
+
+    pub fn set_max(&mut self, max: usize) {
+        self.max = max;
+        for list in self.entries.values_mut() {
+            list.drain(..list.len().saturating_sub(max));
+        }
+    }
+
+    pub fn entries(&self, namespace: &str) -> &[String] {
+        self.entries.get(namespace).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Adds `entry` to the `namespace` history and saves it. The file is read again first
+    /// so that another zepto saving its own history in the meantime loses nothing.
+    pub fn record(&mut self, namespace: &str, entry: &str) -> io::Result<()> {
+        if self.max == 0 || entry.trim().is_empty() {
+            return Ok(());
+        }
+        push(self.entries.entry(namespace.to_string()).or_default(), entry, self.max);
+        let mut saved = read().unwrap_or_default();
+        push(saved.entry(namespace.to_string()).or_default(), entry, self.max);
+        let file = history_file();
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&file, toml::to_string(&saved).map_err(|e| io::Error::other(e.to_string()))?)
+    }
+}
+
+/// Walking back through a history from a prompt. Only entries starting with what had been
+/// typed are visited, and stepping past the newest brings that text back.
+#[derive(Debug, Clone)]
+pub struct Recall {
+    pub namespace: &'static str,
+    typed: String,
+    /// Index of the entry shown; the history's length while showing what was typed.
+    index: usize,
+}
+
+impl Recall {
+    pub fn new(history: &History, namespace: &'static str, typed: &str) -> Self {
+        Recall { namespace, typed: typed.to_string(), index: history.entries(namespace).len() }
+    }
+
+    /// The text for the prompt after stepping to an older entry, or a newer one, or `None`
+    /// when there is nothing further that way.
+    pub fn step(&mut self, history: &History, older: bool) -> Option<String> {
+        let entries = history.entries(self.namespace);
+        let matches = |i: &usize| entries[*i].starts_with(&self.typed);
+        let index = if older {
+            (0..self.index.min(entries.len())).rev().find(matches)?
+        } else {
+            (self.index + 1..entries.len()).find(matches).unwrap_or(entries.len())
+        };
+        if index == self.index {
+            return None;
+        }
+        self.index = index;
+        Some(entries.get(index).cloned().unwrap_or_else(|| self.typed.clone()))
+    }
+}
@@ -0,0 +1,182 @@
+use std::{fs, path::Path};
+
+/// One ignore pattern, with what it applies to and where it came from.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    /// The glob, without the `!`, leading `/` or trailing `/` that say how to use it.
+    glob: Vec<char>,
+    /// Directory the rule was read in, relative to the root; empty for the root and config.
+    base: String,
+    negated: bool,
+    dir_only: bool,
+    /// Matched against the whole path below `base` rather than against any file name.
+    anchored: bool,
+    /// The pattern as written and where, e.g. `target/ (.gitignore:3)`.
+    pub source: String,
+}
+
+impl Rule {
+    fn parse(line: &str, base: &str, source: String) -> Option<Rule> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let line = if line.ends_with("\\ ") { line } else { line.trim_end() };
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, pattern) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').filter(|rest| rest.starts_with(['#', '!'])).unwrap_or(line)),
+        };
+        let (dir_only, pattern) = match pattern.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+        let anchored = pattern.contains('/');
+        let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+        (!pattern.is_empty()).then(|| Rule {
+            glob: pattern.chars().collect(),
+            base: base.to_string(),
+            negated,
+            dir_only,
+            anchored,
+            source: format!("{} ({})", line, source),
+        })
+    }
+
+    /// Whether the rule covers `path`, relative to the root and `/`-separated.
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let below = if self.base.is_empty() {
+            path
+        } else {
+            match path.strip_prefix(self.base.as_str()).and_then(|rest| rest.strip_prefix('/')) {
+                Some(below) => below,
+                None => return false,
+            }
+        };
+        let subject = if self.anchored { below } else { below.rsplit('/').next().unwrap_or(below) };
+        glob_match(&self.glob, &subject.chars().collect::<Vec<_>>())
+    }
+}
+
+/// Matches gitignore globs: `*` and `?` stay within a path component, `**` crosses them
+/// and `[...]` is a character class.
+fn glob_match(glob: &[char], text: &[char]) -> bool {
+    match glob.first() {
+        None => text.is_empty(),
+        Some('*') if glob.get(1) == Some(&'*') => {
+            let rest = &glob[2..];
+            let rest_after_slash = rest.strip_prefix(&['/']).unwrap_or(rest);
+            (0..=text.len()).any(|skip| {
+                glob_match(rest, &text[skip..]) || ((skip == 0 || text[skip - 1] == '/') && glob_match(rest_after_slash, &text[skip..]))
+            })
+        }
+        Some('*') => {
+            let run = text.iter().take_while(|c| **c != '/').count();
+            (0..=run).any(|skip| glob_match(&glob[1..], &text[skip..]))
+        }
+        Some('?') => text.first().is_some_and(|c| *c != '/') && glob_match(&glob[1..], &text[1..]),
+        Some('[') => {
+            let Some(close) = glob.iter().skip(2).position(|c| *c == ']').map(|i| i + 2) else {
+                return text.first() == Some(&'[') && glob_match(&glob[1..], &text[1..]);
+            };
+            let Some(&c) = text.first().filter(|c| **c != '/') else {
+                return false;
+            };
+            let class = &glob[1..close];
+            let (negated, class) = match class.first() {
+                Some('!') | Some('^') => (true, &class[1..]),
+                _ => (false, class),
+            };
+            let mut found = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    found |= (class[i]..=class[i + 2]).contains(&c);
+                    i += 3;
+                } else {
+                    found |= class[i] == c;
+                    i += 1;
+                }
+            }
+            found != negated && glob_match(&glob[close + 1..], &text[1..])
+        }
+        Some('\\') if glob.len() > 1 => text.first() == Some(&glob[1]) && glob_match(&glob[2..], &text[1..]),
+        Some(&literal) => text.first() == Some(&literal) && glob_match(&glob[1..], &text[1..]),
+    }
+}
+
+/// The ignore rules for a project: the configured patterns, then `.git/info/exclude`,
+/// then `.gitignore` files as directories holding them are reached. Later rules win, so
+/// a `.gitignore` deeper down can undo one above it with `!`.
+#[derive(Debug, Clone)]
+pub struct Rules {
+    rules: Vec<Rule>,
+    use_gitignore: bool,
+}
+
+impl Rules {
+    pub fn new(patterns: &[String], use_gitignore: bool, root: &Path) -> Self {
+        let mut rules = Rules {
+            rules: patterns.iter().filter_map(|pattern| Rule::parse(pattern, "", "config".to_string())).collect(),
+            use_gitignore,
+        };
+        if use_gitignore {
+            rules.read(&root.join(".git").join("info").join("exclude"), "", ".git/info/exclude");
+        }
+        rules
+    }
+
+    fn read(&mut self, file: &Path, base: &str, name: &str) {
+        let Ok(text) = fs::read_to_string(file) else {
+            return;
+        };
+        for (number, line) in text.lines().enumerate() {
+            self.rules.extend(Rule::parse(line, base, format!("{}:{}", name, number + 1)));
+        }
+    }
+
+    /// Adds the `.gitignore` of `dir`, relative to `root`, when gitignore files are used.
+    pub fn enter(&mut self, root: &Path, dir: &str) {
+        if self.use_gitignore {
+            let name = if dir.is_empty() { ".gitignore".to_string() } else { format!("{}/.gitignore", dir) };
+            self.read(&root.join(&name), dir, &name);
+        }
+    }
+
+    /// The rule that decides whether `path` is ignored, if any does; it ignores the path
+    /// unless it is a `!` rule.
+    fn deciding_rule(&self, path: &str, is_dir: bool) -> Option<&Rule> {
+        self.rules.iter().rev().find(|rule| rule.matches(path, is_dir))
+    }
+
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        self.deciding_rule(path, is_dir).is_some_and(|rule| !rule.negated)
+    }
+
+    /// Why `path` below `root` is or isn't left out of file lists, reading the
+    /// `.gitignore` files on the way down to it.
+    pub fn explain(mut self, root: &Path, path: &str) -> String {
+        let components: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+        let is_dir = root.join(path).is_dir();
+        self.enter(root, "");
+        for depth in 1..=components.len() {
+            let prefix = components[..depth].join("/");
+            let prefix_is_dir = depth < components.len() || is_dir;
+            if let Some(rule) = self.deciding_rule(&prefix, prefix_is_dir) {
+                if !rule.negated {
+                    let what = if depth < components.len() { format!("its directory {}", prefix) } else { path.to_string() };
+                    return format!("{} is ignored: {} matches {}", path, rule.source, what);
+                }
+                if depth == components.len() {
+                    return format!("{} is not ignored: {} brings it back", path, rule.source);
+                }
+            }
+            if prefix_is_dir {
+                self.enter(root, &prefix);
+            }
+        }
+        format!("{} is not ignored", path)
+    }
+}
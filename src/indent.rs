@@ -0,0 +1,119 @@
+/// How many lines are indented with tabs and how many with spaces.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct IndentCounts {
+    pub tabs: usize,
+    pub spaces: usize,
+}
+
+impl IndentCounts {
+    pub fn is_mixed(&self) -> bool {
+        self.tabs > 0 && self.spaces > 0
+    }
+}
+
+fn indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Counts indented lines by style. A lone leading space is usually alignment (a ` *`
+/// comment continuation) and spaces after tabs usually align too, so neither counts as
+/// space indentation; a space before a tab does.
+pub fn count(lines: &[String]) -> IndentCounts {
+    let mut counts = IndentCounts::default();
+    for line in lines {
+        let indent = indentation(line);
+        if line.len() == indent.len() {
+            continue;
+        }
+        if indent.contains('\t') {
+            counts.tabs += 1;
+        }
+        if indent.starts_with("  ") || indent.contains(" \t") {
+            counts.spaces += 1;
+        }
+    }
+    counts
+}
+
+/// The indentation a file's lines use.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Detected {
+    Tabs,
+    Spaces(u16),
+}
+
+/// Guesses the indentation `lines` use: tabs if most indented lines use them, or else
+/// spaces, as many as the step most often taken into a deeper line. `None` when nothing
+/// is indented.
+pub fn detect(lines: &[String]) -> Option<Detected> {
+    let counts = count(lines);
+    if counts.tabs > counts.spaces {
+        return Some(Detected::Tabs);
+    }
+    if counts.spaces == 0 {
+        return None;
+    }
+    let mut steps = [0usize; 9];
+    let mut previous = 0;
+    for line in lines {
+        let indent = indentation(line);
+        if line.len() == indent.len() || indent.contains('\t') {
+            continue;
+        }
+        // A single space in is alignment rather than a level.
+        if indent.len() > previous + 1 && indent.len() - previous < steps.len() {
+            steps[indent.len() - previous] += 1;
+        }
+        previous = indent.len();
+    }
+    let width = (2..steps.len()).rev().max_by_key(|&step| steps[step]).filter(|&step| steps[step] > 0)?;
+    Some(Detected::Spaces(width as u16))
+}
+
+/// How many columns `line`'s indentation takes, with tabs going to the next tab stop.
+pub fn width(line: &str, tab_width: usize) -> usize {
+    let tab_width = tab_width.max(1);
+    indentation(line).chars().fold(0, |width, c| if c == '\t' { (width / tab_width + 1) * tab_width } else { width + 1 })
+}
+
+/// `text` moved to `indentation`, as vim's `]p` does: lines after the first lose the
+/// indentation they all share and start with `indentation` instead, keeping their depth
+/// relative to one another. With `whole_first_line` the first line starts a line of its
+/// own and is moved likewise; otherwise it goes on after text already there as it is.
+pub fn reindent(text: &str, indentation: &str, whole_first_line: bool, expand_tab: bool, tab_width: usize) -> String {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let moved = |row: usize| row > 0 || whole_first_line;
+    let shared = lines.iter().enumerate()
+        .filter(|&(row, line)| moved(row) && !line.trim().is_empty())
+        .map(|(_, line)| width(line, tab_width))
+        .min()
+        .unwrap_or(0);
+    let tab_width = tab_width.max(1);
+    let lines: Vec<String> = lines.iter().enumerate().map(|(row, line)| {
+        if !moved(row) {
+            line.to_string()
+        } else if line.trim().is_empty() {
+            String::new()
+        } else {
+            let depth = width(line, tab_width) - shared;
+            let extra = if expand_tab { " ".repeat(depth) } else { format!("{}{}", "\t".repeat(depth / tab_width), " ".repeat(depth % tab_width)) };
+            format!("{}{}{}", indentation, extra, line.trim_start_matches([' ', '\t']))
+        }
+    }).collect();
+    lines.join("\n")
+}
+
+/// `line`'s indentation redone in the configured style, with the byte length of the
+/// indentation it replaces, or `None` when it is already in that style. Any spaces left
+/// over after the last full tab stop stay spaces.
+pub fn retab_line(line: &str, expand_tab: bool, tab_width: usize) -> Option<(usize, String)> {
+    let tab_width = tab_width.max(1);
+    let indent = indentation(line);
+    let width = width(line, tab_width);
+    let retabbed = if expand_tab {
+        " ".repeat(width)
+    } else {
+        format!("{}{}", "\t".repeat(width / tab_width), " ".repeat(width % tab_width))
+    };
+    (retabbed != indent).then_some((indent.len(), retabbed))
+}
@@ -0,0 +1,186 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags},
+    execute, terminal,
+};
+
+use crate::platform;
+
+/// Where the editor loop gets its events from. The terminal is the usual source, but tests,
+/// macro replay and batch runs can hand the loop a prepared sequence instead.
+pub trait EventSource {
+    /// Waits up to `timeout` for the next event, returning `None` if nothing arrived.
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<Event>>;
+}
+
+pub struct TerminalEvents;
+
+/// Set while the terminal has been asked to report the Super (Cmd) modifier.
+static REPORTING_SUPER: AtomicBool = AtomicBool::new(false);
+
+/// Asks the terminal to report Cmd as the Super modifier, which only terminals that speak
+/// the kitty keyboard protocol can do; the rest keep Cmd for themselves.
+pub fn report_super_key(out: &mut impl Write) -> io::Result<()> {
+    if platform::WINDOWS || !terminal::supports_keyboard_enhancement().unwrap_or(false) {
+        return Ok(());
+    }
+    execute!(out, PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES))?;
+    REPORTING_SUPER.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Undoes `report_super_key`, if it did anything.
+pub fn stop_reporting_super_key(out: &mut impl Write) -> io::Result<()> {
+    if REPORTING_SUPER.swap(false, Ordering::Relaxed) {
+        execute!(out, PopKeyboardEnhancementFlags)?;
+    }
+    Ok(())
+}
+
+impl EventSource for TerminalEvents {
+    fn next_event(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        if event::poll(timeout)? {
+            event::read().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Sits between the terminal and the key handlers and decides whether an Esc was a key
+/// press on its own or the prefix of an Alt chord.
+///
+/// Terminals send Alt+x as `ESC x`, so depending on timing crossterm reports either a
+/// single Alt+x event or a bare Esc followed by `x`. An Esc followed by a key within the
+/// timeout is joined into the Alt chord, as long as the editor has something to do with
+/// that chord; otherwise, and for a merged Alt+x nothing handles, it is Esc then `x`. In
+/// vim's insert mode that keeps a quick Esc from turning the next command into a chord.
+pub struct KeyDecoder {
+    escape_timeout: Duration,
+    pending_escape: Option<Instant>,
+    ready: VecDeque<KeyEvent>,
+}
+
+impl KeyDecoder {
+    pub fn new(escape_timeout: Duration) -> Self {
+        KeyDecoder {
+            escape_timeout,
+            pending_escape: None,
+            ready: VecDeque::new(),
+        }
+    }
+
+    /// Takes in a key from the terminal. `alt_wanted` says whether the editor handles the
+    /// Alt chord made of this key.
+    pub fn feed(&mut self, key: KeyEvent, alt_wanted: bool) {
+        if key.kind == KeyEventKind::Release {
+            return;
+        }
+        let key = platform::normalize_key(key);
+
+        if !alt_wanted && key.modifiers.contains(KeyModifiers::ALT) && matches!(key.code, KeyCode::Char(_)) {
+            self.flush_escape();
+            self.ready.push_back(escape_key());
+            self.ready.push_back(KeyEvent::new(key.code, key.modifiers - KeyModifiers::ALT));
+            return;
+        }
+
+        if key.code == KeyCode::Esc && key.modifiers.is_empty() {
+            self.flush_escape();
+            if platform::ALT_SENDS_ESCAPE {
+                self.pending_escape = Some(Instant::now());
+            } else {
+                self.ready.push_back(key);
+            }
+            return;
+        }
+
+        match self.pending_escape.take() {
+            Some(pressed) if pressed.elapsed() < self.escape_timeout && alt_wanted => {
+                self.ready.push_back(KeyEvent::new(key.code, key.modifiers | KeyModifiers::ALT));
+            }
+            Some(_) => {
+                self.ready.push_back(escape_key());
+                self.ready.push_back(key);
+            }
+            None => self.ready.push_back(key),
+        }
+    }
+
+    /// Next decoded key, releasing a held Esc once its timeout has run out.
+    pub fn next_key(&mut self) -> Option<KeyEvent> {
+        if self.ready.is_empty()
+            && let Some(pressed) = self.pending_escape
+            && pressed.elapsed() >= self.escape_timeout
+        {
+            self.flush_escape();
+        }
+        self.ready.pop_front()
+    }
+
+    /// How long the event loop may block before a held Esc has to be released.
+    pub fn poll_timeout(&self, default: Duration) -> Duration {
+        match self.pending_escape {
+            Some(pressed) => self.escape_timeout.saturating_sub(pressed.elapsed()).min(default),
+            None => default,
+        }
+    }
+
+    fn flush_escape(&mut self) {
+        if self.pending_escape.take().is_some() {
+            self.ready.push_back(escape_key());
+        }
+    }
+}
+
+fn escape_key() -> KeyEvent {
+    KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)
+}
+
+/// The file a terminal pasted when one was dragged onto it: a single absolute path, quoted
+/// or with backslash-escaped spaces depending on the terminal, or a `file://` URI.
+pub fn dropped_path(text: &str) -> Option<String> {
+    let text = text.trim();
+    if text.contains('\n') {
+        return None;
+    }
+    let unquoted = match (text.chars().next(), text.chars().next_back()) {
+        (Some(open @ ('\'' | '"')), Some(close)) if open == close && text.len() > 1 => &text[1..text.len() - 1],
+        _ => text,
+    };
+    let path = match unquoted.strip_prefix("file://") {
+        Some(uri) => {
+            let path = percent_decode(uri.strip_prefix("localhost").unwrap_or(uri))?;
+            // file:///C:/dir names a drive on Windows.
+            match path.strip_prefix('/') {
+                Some(drive) if platform::WINDOWS && drive.get(1..2) == Some(":") => drive.to_string(),
+                _ => path,
+            }
+        }
+        None if platform::WINDOWS => unquoted.to_string(),
+        None => unquoted.replace("\\ ", " "),
+    };
+    std::path::Path::new(&path).is_absolute().then_some(path)
+}
+
+fn percent_decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, after)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(after.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &after[2..];
+        } else {
+            bytes.push(byte);
+            rest = after;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
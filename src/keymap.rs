@@ -0,0 +1,248 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// The help screen's list of keys outside vim mode.
+pub const STANDARD_KEYS: &[&str] = &[
+    "Ctrl+X: Exit (prompts to save if modified)",
+    "Ctrl+W: Save File",
+    "Ctrl+Q: Quit without saving (prompts if modified)",
+    "Ctrl+H: Show this Help",
+    "Ctrl+T: Command line (set, w, q; Tab completes file names, Up/Down recall history)",
+    "  note text: Note the selected lines (no text removes the note)",
+    "  todos [project]: List TODO/FIXME/HACK comments",
+    "  config: Edit config.toml (applied when saved)",
+    "  theme [name]: Preview themes in turn, or switch to one",
+    "  keymap [file]: Write these keys to a Markdown file",
+    "  indent [tabs|spaces [width]|detect]: Change how this buffer indents",
+    "  42: Go to line 42",
+    "  set wrap!: Wrap long lines onto the rows below",
+    "  select: Set or unset the mark, like Ctrl+6",
+    "  hidden [fix]: Go to the next invisible or lookalike character, or replace them all",
+    "  e, e!: Reload the file from disk (e! drops unsaved changes)",
+    "  backup: Compare with the version the last save replaced (> restores a hunk)",
+    "  timeline: List the saved versions of the file to compare with or restore",
+    "  ignored path: Say why the file finder lists a path or leaves it out",
+    "  s/pattern/text/g: Replace on this line or the selected lines (%s/.../g: every line)",
+    "  regex [pattern]: Try out a regex and replacement on the buffer",
+    "  !command: Run a shell command (its output goes to the log)",
+    "  name: Run a command of your own from [commands] in config.toml",
+    "F2: Settings",
+    "F8: Go to the syntax error found on save",
+    "F12: Show performance figures",
+    "Ctrl+F: Search, ignoring case if all lowercase (Esc Esc or :noh clears highlights)",
+    "F3 / Shift+F3: Find the last search again, forward/backward",
+    "Ctrl+P: Find and open a file (Alt+Up/Alt+Down recall earlier queries)",
+    "Ctrl+]: Jump to tag under cursor (:pop to go back)",
+    "Alt+; / Alt+,: Go to older/newer edit location",
+    "Alt+O: Open the URL or path:line under the cursor",
+    "Alt+N: Show/hide line numbers",
+    "",
+    "Arrow Keys: Move Cursor",
+    "Shift+Arrow Keys: Select Text",
+    "Ctrl+6: Set the mark; moves select from it until Ctrl+6 again, a copy or a cut",
+    "Alt+Shift+Up/Down: Select a block (column) of text",
+    "Alt+Up/Down: Expand/shrink selection to word, string, brackets, line, block",
+    "Tab / Shift+Tab: Indent/outdent the selected lines",
+    "Ctrl+C / Alt+6: Copy Selection",
+    "Ctrl+U: Cut Selection",
+    "Ctrl+V: Paste, fitting lines to the indentation (Alt+V: paste as is)",
+    "Ctrl+Z / Ctrl+Y: Undo/redo",
+    "Ctrl+Left/Right: Move cursor by word",
+    "Ctrl+Up/Down: Move cursor by paragraph",
+    "Alt+Left/Right: Move cursor by sentence",
+    "PageUp/PageDown: Scroll through file",
+    "Home/End: Go to start/end of line",
+    "Ctrl+Home/Ctrl+End: Go to start/end of file",
+    "Backspace: Delete character backward",
+    "Delete: Delete character forward",
+    "Enter: New line",
+    "Esc: Clear selection",
+];
+
+/// The help screen's list of keys in vim mode, by mode.
+pub const VIM_KEYS: &[&str] = &[
+    "GLOBAL COMMANDS:",
+    "  Ctrl+X: Exit (prompts to save if modified)",
+    "  Ctrl+W: Save File",
+    "  Ctrl+Q: Quit without saving (prompts if modified)",
+    "  Ctrl+H: Show this Help",
+    "  F2: Settings",
+    "  F8: Go to the syntax error found on save",
+    "  F12: Show performance figures",
+    "  Ctrl+P: Find and open a file",
+    "",
+    "COMMAND LINE (:):",
+    "  :w [file], :q, :q!, :wq (:w file writes a copy; :saveas file moves to it)",
+    "  :set option / nooption / option=value / option?",
+    "  :set paste!: Paste mode, text goes in as typed (no closers or reindenting)",
+    "  :set nu!: Show/hide line numbers",
+    "  :set wrap!: Wrap long lines onto the rows below",
+    "  :setglobal option=value",
+    "  :tag name, :pop",
+    "  :noh (clear search highlights, also Esc Esc)",
+    "  :hunk, :applyhunk (in .diff/.patch files)",
+    "  :retab (convert indentation to tabs or spaces per expandtab)",
+    "  :indent [tabs|spaces [width]|detect]: How this buffer indents",
+    "  :select: Set the mark; moves select from it (:select again unsets it)",
+    "  :saveas file, :rename file, :delete-file",
+    "  :note text: Note the selected lines (:note alone removes it)",
+    "  :todos [project]: List TODO/FIXME/HACK comments (Tab switches)",
+    "  :config: Edit config.toml (applied when saved)",
+    "  :theme [name]: Preview themes in turn, or switch to one",
+    "  :keymap [file]: Write these keys to a Markdown file (zepto-keys.md)",
+    "  :hidden [fix]: Go to the next invisible or lookalike character, or replace them all",
+    "  :e, :e!: Reload the file from disk (:e! drops unsaved changes, Ctrl+Z/u brings them back)",
+    "  :backup: Compare with the version the last save replaced (> restores a hunk)",
+    "  :timeline: List the saved versions of the file to compare with or restore",
+    "  :ignored path: Say why the file finder lists a path or leaves it out",
+    "  :s/pattern/text/[g], :%s/pattern/text/[g]: Replace (with :set regex, $1 or \\1 and \\u, \\l)",
+    "  :regex [pattern]: Try out a regex and replacement on the buffer",
+    "  :macro x: Edit the keys recorded in register x (saved back into it)",
+    "  :!command: Run a shell command (its output goes to the log)",
+    "  :name: Run a command of your own from [commands] in config.toml",
+    "  Tab, Shift+Tab: Complete file names after :w, :saveas, :rename",
+    "  Up, Down: Recall earlier commands (or searches after / and ?) starting with what is typed",
+    "",
+    "NORMAL MODE:",
+    "  i: Insert before cursor",
+    "  a: Insert after cursor",
+    "  o: Insert new line below",
+    "  O: Insert new line above",
+    "  h, j, k, l: Move cursor (Left, Down, Up, Right)",
+    "  w, b, e, ge: Move by word (Forward, Backward, End, End backward)",
+    "  W, B, E, gE: Same by WORD (runs of non-blank characters)",
+    "  {, }: Previous/next paragraph   (, ): Previous/next sentence",
+    "  Alt+Up/Down: Expand/shrink selection (word, string, brackets, line, block)",
+    "  f/F x: To next/previous x on the line   t/T x: Till x",
+    "  ;, ,: Repeat last f/t/F/T forward, backward",
+    "  d, c, y + motion: Delete, change, yank (e.g. dw, df,, ct), d/end, dgg, yG)",
+    "  dd, cc, yy: Delete, change, yank line (3dw, d3w, 2dd: counts repeat the motion)",
+    "  .: Repeat last change",
+    "  qx ... q: Record keys into register x (a-z, 0-9)",
+    "  @x, @@: Run register x, the last one run (3@x: 3 times)",
+    "  gg, G: Go to first/last line (5gg or 5G: go to line 5)",
+    "  g;, g,: Go to older/newer edit location (Alt+; / Alt+, too)",
+    "  Alt+N: Show/hide line numbers",
+    "  gx, gf: Open the URL, or the file (path:line), under the cursor",
+    "  H, M, L: Go to top/middle/bottom of screen",
+    "  0: Go to start of line",
+    "  $: Go to end of line",
+    "  x: Delete character under cursor",
+    "  u, Ctrl+R: Undo, redo",
+    "  /, ?: Search forward, backward, ignoring case if all lowercase (Enter alone repeats)",
+    "  n, N: Repeat the last search in the same, opposite direction (F3, Shift+F3: forward, backward)",
+    "  Ctrl+]: Jump to tag under cursor",
+    "  Ctrl+T: Jump back from tag",
+    "  Ctrl+C: Copy Selection (Visual Mode needed for full power)",
+    "  Ctrl+U: Cut Selection (Visual Mode needed for full power)",
+    "  Ctrl+V: Paste, fitting lines to the indentation (Alt+V: as is)",
+    "  Esc: Clear selection (if active)",
+    "",
+    "INSERT MODE:",
+    "  Typing: Insert characters",
+    "  Enter: New line",
+    "  Backspace/Delete: Delete characters",
+    "  Arrow Keys: Move cursor",
+    "  Shift+Arrow Keys: Select text",
+    "  Alt+Shift+Up/Down: Select a block of text",
+    "  Tab / Shift+Tab: Indent/outdent the selected lines",
+    "  Esc: Exit to Normal Mode",
+];
+
+/// A macOS shortcut and the key it does the work of.
+struct MacKey {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+    same_as: (KeyCode, KeyModifiers),
+    keys: &'static str,
+    action: &'static str,
+}
+
+const fn mac_key(code: KeyCode, modifiers: KeyModifiers, same_as: (KeyCode, KeyModifiers), keys: &'static str, action: &'static str) -> MacKey {
+    MacKey { code, modifiers, same_as, keys, action }
+}
+
+const CMD: KeyModifiers = KeyModifiers::SUPER;
+const CTRL: KeyModifiers = KeyModifiers::CONTROL;
+const NONE: KeyModifiers = KeyModifiers::NONE;
+
+/// Shortcuts as on macOS. Terminals only pass Cmd on when they speak the kitty keyboard
+/// protocol (kitty, WezTerm, Ghostty, iTerm2 with CSI u), so each stands in for a key
+/// that works everywhere.
+const MAC_KEYS: &[MacKey] = &[
+    mac_key(KeyCode::Char('s'), CMD, (KeyCode::Char('w'), CTRL), "Cmd+S", "Save File"),
+    mac_key(KeyCode::Char('q'), CMD, (KeyCode::Char('q'), CTRL), "Cmd+Q", "Quit"),
+    mac_key(KeyCode::Char('f'), CMD, (KeyCode::Char('f'), CTRL), "Cmd+F", "Search"),
+    mac_key(KeyCode::Char('p'), CMD, (KeyCode::Char('p'), CTRL), "Cmd+P", "Find and open a file"),
+    mac_key(KeyCode::Char('c'), CMD, (KeyCode::Char('c'), CTRL), "Cmd+C", "Copy Selection"),
+    mac_key(KeyCode::Char('x'), CMD, (KeyCode::Char('u'), CTRL), "Cmd+X", "Cut Selection"),
+    mac_key(KeyCode::Char('v'), CMD, (KeyCode::Char('v'), CTRL), "Cmd+V", "Paste"),
+    mac_key(KeyCode::Left, CMD, (KeyCode::Home, NONE), "Cmd+Left", "Go to start of line"),
+    mac_key(KeyCode::Right, CMD, (KeyCode::End, NONE), "Cmd+Right", "Go to end of line"),
+    mac_key(KeyCode::Up, CMD, (KeyCode::Home, CTRL), "Cmd+Up", "Go to start of file"),
+    mac_key(KeyCode::Down, CMD, (KeyCode::End, CTRL), "Cmd+Down", "Go to end of file"),
+    mac_key(KeyCode::Left, KeyModifiers::ALT, (KeyCode::Left, CTRL), "Option+Left", "Move cursor by word"),
+    mac_key(KeyCode::Right, KeyModifiers::ALT, (KeyCode::Right, CTRL), "Option+Right", "Move cursor by word"),
+];
+
+/// Whether the `keymap` setting ("auto", "mac" or "standard") asks for macOS shortcuts.
+pub fn is_mac(keymap: &str) -> bool {
+    match keymap {
+        "mac" | "macos" => true,
+        "auto" => cfg!(target_os = "macos"),
+        _ => false,
+    }
+}
+
+/// Turns a macOS shortcut into the key it stands for; Shift is kept, so Cmd+Shift+Left
+/// selects to the start of the line. Other keys pass through.
+pub fn translate(key: KeyEvent) -> KeyEvent {
+    let shift = key.modifiers & KeyModifiers::SHIFT;
+    let found = MAC_KEYS.iter().find(|mac| mac.code == key.code && mac.modifiers == key.modifiers - KeyModifiers::SHIFT);
+    match found {
+        Some(mac) => KeyEvent { code: mac.same_as.0, modifiers: mac.same_as.1 | shift, ..key },
+        None => key,
+    }
+}
+
+/// The macOS shortcuts for the help screen, in the same `keys: action` form.
+pub fn mac_lines() -> Vec<String> {
+    MAC_KEYS.iter().map(|mac| format!("{}: {}", mac.keys, mac.action)).collect()
+}
+
+/// A Markdown cheat sheet of the keys in effect: the help screen's list, with section
+/// headings for vim's modes and the macOS shortcuts when those are on.
+pub fn cheat_sheet(vim: bool, mac: bool) -> String {
+    let mut sheet = format!("# zepto keys ({})\n", if vim { "vim mode" } else { "standard" });
+    let mut lines: Vec<String> = (if vim { VIM_KEYS } else { STANDARD_KEYS }).iter().map(|line| line.to_string()).collect();
+    if mac {
+        lines.push(String::new());
+        lines.push("macOS:".to_string());
+        lines.extend(mac_lines());
+    }
+    let mut in_list = false;
+    let mut under_heading = false;
+    for line in &lines {
+        // Entries under a heading are indented on the help screen; elsewhere an indented
+        // entry belongs to the one above it.
+        let indent = if line.starts_with("  ") && !under_heading { "  " } else { "" };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(heading) = line.strip_suffix(':') {
+            sheet.push_str(&format!("\n## {}\n\n", heading));
+            in_list = true;
+            under_heading = true;
+            continue;
+        }
+        if !in_list {
+            sheet.push('\n');
+            in_list = true;
+        }
+        match line.split_once(": ") {
+            Some((keys, action)) => sheet.push_str(&format!("{}- `{}`: {}\n", indent, keys, action)),
+            None => sheet.push_str(&format!("{}- {}\n", indent, line)),
+        }
+    }
+    sheet
+}
@@ -0,0 +1,5726 @@
+use std::{
+    collections::VecDeque,
+    io::{self, stdout, IsTerminal, Read},
+    fs,
+    env,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    process,
+    time::Instant,
+};
+
+use crossterm::{
+    event::{
+        DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste, EnableFocusChange, EnableMouseCapture,
+        Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
+    terminal::{
+        enable_raw_mode, disable_raw_mode,
+        EnterAlternateScreen, LeaveAlternateScreen,
+    },
+    execute,
+    ExecutableCommand,
+};
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap, BorderType},
+    text::Span,
+};
+
+mod annotations;
+mod backup;
+// Public for the fuzzer, which edits, selects and undoes through these three.
+#[doc(hidden)]
+pub mod buffer;
+mod changelist;
+mod cli;
+mod closers;
+mod command;
+mod complete;
+mod config;
+mod confusables;
+mod crash;
+mod diff;
+mod display;
+mod enclosing;
+mod expand;
+mod filetype;
+mod finder;
+mod highlight;
+mod history;
+mod ignore;
+mod indent;
+mod input;
+mod keymap;
+mod links;
+mod logging;
+mod macros;
+mod metrics;
+mod motion;
+mod patch;
+mod platform;
+mod popup;
+mod project;
+mod regex;
+mod pager;
+mod paths;
+mod search;
+#[doc(hidden)]
+pub mod selection;
+mod settings;
+mod shell;
+mod swatch;
+mod symbols;
+mod tags;
+mod tasks;
+mod templates;
+mod tester;
+mod timeline;
+#[cfg(test)]
+mod testing;
+mod theme;
+mod toasts;
+mod todos;
+#[doc(hidden)]
+pub mod undo;
+mod validate;
+mod wordcount;
+mod wrap;
+
+#[derive(PartialEq)]
+enum ApplicationMode {
+    Editing,
+    Help,
+    PromptSave,
+    Settings,
+    Command,
+    Finder,
+    /// The TODO panel.
+    Todos,
+    /// The saved versions of the file, from `:timeline`.
+    Timeline,
+    /// Trying out a regex on the buffer, from `:regex`.
+    RegexTester,
+    /// Cycling through themes with `:theme`.
+    Theme,
+    Diff,
+    /// Waiting for y/n on a `Confirmation`.
+    Confirm,
+}
+
+/// A file operation that only happens once the user says yes.
+enum Confirmation {
+    /// Save under a name that already belongs to another file.
+    SaveAs(String),
+    Rename(String),
+    DeleteFile,
+    /// Create the missing directories above a file, then save to it.
+    CreateDirectories(String),
+    /// Open a file whose path was pasted, or paste the text after all if not.
+    OpenDropped { path: String, text: String },
+    /// Draw lines over `long_lines.limit` without any styling.
+    PlainLongLines,
+}
+
+enum TaskOutput {
+    FileLoaded { path: String, lines: Vec<String>, line_ending: platform::LineEnding },
+    FilesListed(Vec<String>),
+    TodosFound(Vec<todos::Todo>),
+    /// A shell command that ran as a step of a command, the steps still to run after it and
+    /// how deep in commands of the user's own they are.
+    ShellFinished { finished: shell::Finished, then: Vec<String>, depth: usize },
+    /// The save hooks that ran, up to the first to fail.
+    HooksFinished { before_save: bool, ran: Vec<shell::Finished> },
+}
+
+struct RunningTask {
+    id: tasks::TaskId,
+    label: String,
+    done: u64,
+    total: Option<u64>,
+    /// Whether typing in the buffer cancels it, for work that only feeds a panel the
+    /// user has left.
+    cancel_on_input: bool,
+}
+
+/// A short, coarse age such as `45s`, `2m` or `3h`.
+fn format_age(age: std::time::Duration) -> String {
+    match age.as_secs() {
+        secs @ 0..60 => format!("{}s", secs),
+        secs @ 60..3600 => format!("{}m", secs / 60),
+        secs @ 3600..86_400 => format!("{}h", secs / 3600),
+        secs => format!("{}d", secs / 86_400),
+    }
+}
+
+/// The directory `path` is in, `.` for a bare file name.
+fn file_dir(path: &str) -> &Path {
+    Path::new(path).parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."))
+}
+
+/// A byte count in the largest unit that keeps it above 1, such as `312 B` or `4.5 KiB`.
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+/// `1234567` as `1,234,567`.
+fn format_thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// How many commands and non-text keys a crash report lists.
+const RECENT_ACTIONS_LIMIT: usize = 50;
+
+/// How many steps Ctrl+Z or `u` can go back.
+const UNDO_LEVELS: usize = 1000;
+
+const VIEW_HINT: &str = "-- VIEW --  q quit  Space/b page  / search  n/N next/previous";
+
+/// How long low-power mode sleeps when nothing at all is going on.
+const LOW_POWER_WAIT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Rows the regex tester takes below the status panels: two fields and three lines about
+/// the current match, inside a border.
+const REGEX_TESTER_HEIGHT: u16 = 7;
+/// How deep macros may run macros, so one that runs itself stops.
+const MACRO_DEPTH_LIMIT: usize = 20;
+/// How deep commands from `[commands]` may run one another.
+const COMMAND_DEPTH_LIMIT: usize = 20;
+
+const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// Where to put the cursor once a file that is still loading has arrived.
+enum Jump {
+    Address(tags::Address),
+    Cursor(usize, usize),
+}
+
+/// A normal mode command that acts on the text a following motion moves over.
+#[derive(Clone, Copy, PartialEq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+impl Operator {
+    fn from_key(c: char) -> Option<Operator> {
+        match c {
+            'd' => Some(Operator::Delete),
+            'c' => Some(Operator::Change),
+            'y' => Some(Operator::Yank),
+            _ => None,
+        }
+    }
+
+    fn key(self) -> char {
+        match self {
+            Operator::Delete => 'd',
+            Operator::Change => 'c',
+            Operator::Yank => 'y',
+        }
+    }
+}
+
+/// What an Alt+character chord does while editing. The mode handlers run chords from here,
+/// and in vim the key decoder only reads Esc and a key as a chord the mode has.
+#[derive(Clone, Copy)]
+enum AltChord {
+    CopySelection,
+    PasteRaw,
+    OlderChange,
+    NewerChange,
+    OpenLink,
+    ToggleLineNumbers,
+}
+
+impl AltChord {
+    fn for_key(c: char, mode: &InputMode) -> Option<AltChord> {
+        match (c, mode) {
+            ('6', InputMode::Insert) => Some(AltChord::CopySelection),
+            ('v', _) => Some(AltChord::PasteRaw),
+            (';', _) => Some(AltChord::OlderChange),
+            (',', _) => Some(AltChord::NewerChange),
+            ('o', InputMode::Insert) => Some(AltChord::OpenLink),
+            ('n', _) => Some(AltChord::ToggleLineNumbers),
+            _ => None,
+        }
+    }
+}
+
+/// The themes `:theme` cycles through, the first being none, and the one showing.
+struct ThemePreview {
+    names: Vec<String>,
+    index: usize,
+}
+
+/// How a save that didn't fail went.
+enum SaveOutcome {
+    Written,
+    /// Waiting on the `before_save` hooks, which `pending_save` tracks.
+    RunningHooks,
+    /// Waiting on the user to say whether to create the file's directory.
+    Confirming,
+}
+
+/// A save waiting on its `before_save` hooks.
+struct PendingSave {
+    task: tasks::TaskId,
+    /// The buffer's revision when the hooks were handed its text.
+    revision: u64,
+    block_on_failure: bool,
+    /// Quit once the file is saved, as `:wq` does.
+    quit: bool,
+}
+
+struct TagStackEntry {
+    filename: Option<String>,
+    cursor_y: usize,
+    cursor_x: usize,
+}
+
+#[derive(PartialEq)]
+enum InputMode {
+    Normal,
+    Insert,
+}
+
+/// The editor: its buffer, everything shown around it and the state of every mode. Only
+/// the headless API below is public.
+pub struct Editor<B: Backend> {
+    buffer: buffer::Buffer,
+    cursor_x: usize,
+    cursor_y: usize,
+    scroll_x: usize,
+    scroll_y: usize,
+    filename: Option<String>,
+    application_mode: ApplicationMode,
+    input_mode: InputMode,
+    vim_enabled: bool,
+    status_message: String,
+    prompt_message: String,
+    config: config::Config,
+    global_config: config::Config,
+    option_overrides: Vec<(String, toml::Value)>,
+    toasts: toasts::Toasts,
+    /// The indentation the file was found to use, which `detect_indent` puts over the
+    /// user's config; a project's settings still win.
+    detected_indent: Option<indent::Detected>,
+    /// The `.zepto.toml` of the project the file belongs to.
+    project_config: Option<project::ProjectConfig>,
+    theme_preview: Option<ThemePreview>,
+    command_line: String,
+    /// `:` for commands, `/` or `?` for a search forward or backward.
+    command_prompt: char,
+    search: Option<search::Search>,
+    last_key_was_escape: bool,
+    /// First key of a two-key normal mode command such as `ge` or `fx`.
+    pending_normal_key: Option<char>,
+    pending_operator: Option<Operator>,
+    /// The count typed before the pending operator, as the 3 in `3dw`.
+    operator_count: Option<usize>,
+    /// Digits typed before a command, as in `5G`.
+    pending_count: Option<usize>,
+    /// The column, in characters, up/down movement aims for, and where the last vertical move left the
+    /// cursor; any other movement makes the current column the wanted one again.
+    wanted_column: Option<(usize, buffer::Position)>,
+    last_char_find: Option<motion::CharFind>,
+    /// Keys of the vim command in progress, through any insert it starts, and the buffer
+    /// revision it started at.
+    change_keys: Vec<KeyEvent>,
+    change_start_revision: u64,
+    /// Keys of the last command that changed the buffer, which `.` replays.
+    last_change: Vec<KeyEvent>,
+    /// Keys from `[keys]` in the config and the command line each runs.
+    bindings: Vec<(KeyEvent, String)>,
+    /// The register `q` is recording into and the keys so far.
+    recording: Option<(char, Vec<KeyEvent>)>,
+    /// The register `@@` runs again.
+    last_macro: Option<char>,
+    /// How many macros are running inside one another.
+    macro_depth: usize,
+    clipboard: String,
+    selection: Option<selection::Selection>,
+    /// What Alt+Down goes back to: the selection and cursor before each Alt+Up, with the
+    /// selection it made.
+    expansions: Vec<(Option<selection::Selection>, buffer::Position, selection::Selection)>,
+    /// Where the cursor was before the key being handled moved it, which is where a
+    /// selection started by that key is anchored.
+    cursor_before_key: buffer::Position,
+    /// The mark set by Ctrl+6 or `:select`: every move extends the selection, Shift or
+    /// not, until it is dropped.
+    sticky_selection: bool,
+    /// The line a press in the gutter started on, while the button is held.
+    gutter_drag: Option<usize>,
+    undo_history: undo::History,
+    /// Set while an undo or redo edits the buffer, so its changes aren't recorded as new ones.
+    undoing: bool,
+    /// Whether the last key typed a word character, which the next one joins in one undo step.
+    last_key_typed: bool,
+    syntax: Option<&'static highlight::Syntax>,
+    highlighter: highlight::Highlighter,
+    bracket_depths: highlight::BracketDepths,
+    word_count: wordcount::WordCount,
+    change_list: changelist::ChangeList,
+    /// Notes on the current file, when it has a name.
+    annotations: Option<annotations::Annotations>,
+    /// `--view`: a pager over the buffer, with no way to change or save it.
+    view_only: bool,
+    debug_overlay: bool,
+    frame_stats: metrics::FrameStats,
+    /// When input arrived that no frame has shown yet.
+    input_at: Option<Instant>,
+    /// Written between lines on save: whatever the file had, or the platform's own.
+    line_ending: platform::LineEnding,
+    /// The syntax error the last save found, marked until the next edit.
+    validation_error: Option<validate::SyntaxError>,
+    key_decoder: input::KeyDecoder,
+    tasks: tasks::TaskRunner<TaskOutput>,
+    loading_file: Option<tasks::TaskId>,
+    pending_save: Option<PendingSave>,
+    running_tasks: Vec<RunningTask>,
+    started_at: Instant,
+    /// Whether the terminal window has focus, as far as focus events tell.
+    terminal_focused: bool,
+    saved_at: Option<Instant>,
+    modified_at: Option<Instant>,
+    /// When the oldest change not yet written to disk was made.
+    unsaved_since: Option<Instant>,
+    /// Recent commands and non-text keys, for crash reports. Typed characters are left
+    /// out, as they would give away what was written.
+    recent_actions: VecDeque<String>,
+    settings: Option<settings::SettingsView>,
+    finder: Option<finder::FileFinder>,
+    todo_list: Option<todos::TodoList>,
+    timeline: Option<timeline::Timeline>,
+    regex_tester: Option<tester::RegexTester>,
+    diff: Option<diff::DiffView>,
+    tag_file: Option<tags::TagFile>,
+    tag_stack: Vec<TagStackEntry>,
+    pending_jump: Option<Jump>,
+    pending_confirmation: Option<Confirmation>,
+    completion: Option<complete::Completion>,
+    history: history::History,
+    /// Where Up and Down have got to in the history of the prompt being typed at.
+    recall: Option<history::Recall>,
+    /// Editor area from the last layout, for jumps that happen outside of key handling.
+    view_area: Rect,
+    /// `(revision, limit, count)` so the status bar only rescans after an edit.
+    long_line_count: Option<(u64, usize, usize)>,
+    /// `(revision, matcher, positions)` of the highlighted search's matches, for the
+    /// status bar's "match 3 of 27".
+    search_matches: Option<(u64, search::Matcher, Vec<buffer::Position>)>,
+    /// `(revision, row, scopes)` so breadcrumbs are only recomputed after moving or editing.
+    breadcrumbs: Option<(u64, usize, Vec<String>)>,
+    /// Said yes to drawing this file's very long lines plain.
+    plain_long_lines: bool,
+    _phantom: std::marker::PhantomData<B>,
+}
+
+impl<B: Backend> Editor<B> {
+    fn new_with_backend(config: config::Config) -> Self {
+        let vim_enabled = config.editor_behavior.vim;
+        let escape_timeout = std::time::Duration::from_millis(config.editor_behavior.escape_timeout_ms);
+        let initial_input_mode = if vim_enabled { InputMode::Normal } else { InputMode::Insert };
+        let initial_status_message = if vim_enabled {
+            "-- NORMAL --".to_string()
+        } else {
+            "Ctrl+X Exit | Ctrl+W Save | Ctrl+H Help".to_string()
+        };
+        let history = history::History::load(config.editor_behavior.history_size).unwrap_or_else(|e| {
+            log::warn!("error reading prompt history: {}", e);
+            history::History::default()
+        });
+
+        Editor {
+            buffer: buffer::Buffer::new(),
+            cursor_x: 0,
+            cursor_y: 0,
+            scroll_x: 0,
+            scroll_y: 0,
+            filename: None,
+            application_mode: ApplicationMode::Editing,
+            input_mode: initial_input_mode,
+            vim_enabled,
+            status_message: initial_status_message,
+            prompt_message: String::new(),
+            global_config: config.clone(),
+            config,
+            option_overrides: Vec::new(),
+            toasts: toasts::Toasts::default(),
+            detected_indent: None,
+            project_config: None,
+            theme_preview: None,
+            command_line: String::new(),
+            command_prompt: ':',
+            search: history.entries(history::SEARCHES).last().map(|pattern| search::Search::remembered(pattern.clone())),
+            last_key_was_escape: false,
+            pending_normal_key: None,
+            pending_operator: None,
+            operator_count: None,
+            pending_count: None,
+            wanted_column: None,
+            last_char_find: None,
+            change_keys: Vec::new(),
+            change_start_revision: 0,
+            last_change: Vec::new(),
+            bindings: Vec::new(),
+            recording: None,
+            last_macro: None,
+            macro_depth: 0,
+            clipboard: String::new(),
+            selection: None,
+            expansions: Vec::new(),
+            cursor_before_key: (0, 0),
+            sticky_selection: false,
+            gutter_drag: None,
+            undo_history: undo::History::default(),
+            undoing: false,
+            last_key_typed: false,
+            syntax: None,
+            highlighter: highlight::Highlighter::new(),
+            bracket_depths: highlight::BracketDepths::default(),
+            word_count: wordcount::WordCount::new(),
+            change_list: changelist::ChangeList::default(),
+            annotations: None,
+            view_only: false,
+            debug_overlay: false,
+            frame_stats: metrics::FrameStats::default(),
+            input_at: None,
+            line_ending: platform::LineEnding::native(),
+            validation_error: None,
+            key_decoder: input::KeyDecoder::new(escape_timeout),
+            tasks: tasks::TaskRunner::new(),
+            loading_file: None,
+            pending_save: None,
+            running_tasks: Vec::new(),
+            started_at: Instant::now(),
+            terminal_focused: true,
+            saved_at: None,
+            modified_at: None,
+            unsaved_since: None,
+            recent_actions: VecDeque::with_capacity(RECENT_ACTIONS_LIMIT),
+            settings: None,
+            finder: None,
+            todo_list: None,
+            timeline: None,
+            regex_tester: None,
+            diff: None,
+            tag_file: None,
+            tag_stack: Vec::new(),
+            pending_jump: None,
+            pending_confirmation: None,
+            completion: None,
+            history,
+            recall: None,
+            view_area: Rect::default(),
+            long_line_count: None,
+            search_matches: None,
+            breadcrumbs: None,
+            plain_long_lines: false,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    fn default_status_message(&self) -> String {
+        if self.vim_enabled {
+            match self.input_mode {
+                InputMode::Normal => "-- NORMAL --".to_string(),
+                InputMode::Insert => "-- INSERT --".to_string(),
+            }
+        } else {
+            "Ctrl+X Exit | Ctrl+W Save | Ctrl+H Help".to_string()
+        }
+    }
+
+    /// Swaps in a new global configuration, keeping this buffer's `:set` overrides on top.
+    fn apply_config(&mut self, global: config::Config) {
+        self.global_config = global;
+        self.refresh_effective_config();
+    }
+
+    /// Tells the user something that shouldn't take over the status bar: in a toast that
+    /// goes away by itself, or in the status bar when toasts are off.
+    fn notify(&mut self, severity: toasts::Severity, message: String) {
+        let toasts = &self.config.main_section.toasts;
+        if toasts.enabled {
+            self.toasts.push(severity, message, toasts.max);
+        } else {
+            self.status_message = message;
+        }
+    }
+
+    /// Reads the `.zepto.toml` that applies to files in `dir`, and says so if it has
+    /// options that could not be applied.
+    fn load_project_config(&mut self, dir: &Path) {
+        self.project_config = match project::load(dir) {
+            Ok(project_config) => project_config,
+            Err(e) => {
+                log::warn!("{}", e);
+                self.notify(toasts::Severity::Error, e);
+                None
+            }
+        };
+        if let Some(project_config) = &self.project_config {
+            log::info!("using project settings from {}", project_config.path.display());
+            if let (_, Some(problem)) = project_config.apply(&self.global_config) {
+                log::warn!("{}", problem);
+                self.notify(toasts::Severity::Warning, problem);
+            }
+        }
+        self.refresh_effective_config();
+    }
+
+    /// Rebuilds the effective config and updates the state that was derived from it at
+    /// startup. Later layers win: the global config, the indentation the file uses, the
+    /// project's `.zepto.toml`, the theme either of them picks, then this buffer's `:set`
+    /// overrides.
+    fn refresh_effective_config(&mut self) {
+        let mut config = self.global_config.clone();
+        if config.editor_behavior.detect_indent {
+            match self.detected_indent {
+                Some(indent::Detected::Tabs) => config.editor_behavior.expand_tab = false,
+                Some(indent::Detected::Spaces(width)) => {
+                    config.editor_behavior.expand_tab = true;
+                    config.editor_behavior.tab_width = width;
+                }
+                None => {}
+            }
+        }
+        if let Some(project_config) = &self.project_config {
+            config = project_config.apply(&config).0;
+        }
+        let theme = match &self.theme_preview {
+            Some(preview) => preview.names[preview.index].clone(),
+            None => config.main_section.theme.clone(),
+        };
+        if !theme.is_empty() {
+            match theme::apply(&config, &theme) {
+                Ok(themed) => config = themed,
+                Err(e) => {
+                    log::warn!("{}", e);
+                    self.notify(toasts::Severity::Warning, e);
+                }
+            }
+        }
+        for (key, value) in &self.option_overrides {
+            if let Ok(updated) = settings::set_option(&config, key, value.clone()) {
+                config = updated;
+            }
+        }
+
+        let vim_enabled = config.editor_behavior.vim;
+        if vim_enabled != self.vim_enabled {
+            self.vim_enabled = vim_enabled;
+            self.input_mode = if vim_enabled { InputMode::Normal } else { InputMode::Insert };
+            self.status_message = self.default_status_message();
+        }
+        self.key_decoder = input::KeyDecoder::new(std::time::Duration::from_millis(config.editor_behavior.escape_timeout_ms));
+        self.history.set_max(config.editor_behavior.history_size);
+        self.bindings = config.keys.iter().filter_map(|(name, line)| match macros::parse(name).as_deref() {
+            Ok([key]) => Some((*key, line.clone())),
+            _ => {
+                let problem = format!("[keys]: {} is not a key name such as <C-k> or <F5>", name);
+                log::warn!("{}", problem);
+                self.notify(toasts::Severity::Warning, problem);
+                None
+            }
+        }).collect();
+        self.config = config;
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.buffer.has_pending_changes() || self.undo_history.is_modified()
+    }
+
+    /// Notes that the buffer matches the file, once edits so far are in the undo history.
+    fn mark_saved(&mut self) {
+        self.follow_changes();
+        self.undo_history.mark_saved();
+    }
+
+    /// A reminder to prefix quit prompts with when unsaved changes are older than
+    /// `unsaved_warning_minutes`.
+    fn unsaved_warning(&self) -> String {
+        let minutes = self.config.editor_behavior.unsaved_warning_minutes;
+        match self.unsaved_since {
+            Some(since) if minutes > 0 && self.is_dirty() && since.elapsed().as_secs() >= minutes * 60 => {
+                format!("Changes from {} ago were never saved! ", format_age(since.elapsed()))
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// "saved 2m ago", plus how long ago the buffer was last edited when that was since.
+    fn save_age_indicator(&self) -> Option<String> {
+        let saved = self.saved_at.map(|at| format!("saved {} ago", format_age(at.elapsed())));
+        let edited = self.modified_at.filter(|_| self.is_dirty()).map(|at| format!("edited {} ago", format_age(at.elapsed())));
+        match (saved, edited) {
+            (Some(saved), Some(edited)) => Some(format!("{}, {}", saved, edited)),
+            (saved, edited) => saved.or(edited),
+        }
+    }
+
+    /// "1,204 words, 6 min read, 60% of 2,000" for the filetypes set up as prose.
+    fn prose_indicator(&mut self) -> Option<String> {
+        let prose = &self.config.main_section.prose;
+        let filetype = self.syntax.map_or("text", |syntax| syntax.name);
+        if !prose.enabled || !prose.filetypes.iter().any(|name| name == filetype) {
+            return None;
+        }
+        let (words_per_minute, goal) = (prose.words_per_minute.max(1), prose.word_goal);
+        let words = self.word_count.total(&self.buffer);
+        let mut indicator = format!(
+            "{} word{}, {} min read",
+            format_thousands(words),
+            if words == 1 { "" } else { "s" },
+            words.div_ceil(words_per_minute),
+        );
+        if let Some(percent) = (words * 100).checked_div(goal) {
+            indicator.push_str(&format!(", {}% of {}", percent, format_thousands(goal)));
+        }
+        Some(indicator)
+    }
+
+    fn max_line_length(&self) -> usize {
+        self.config.main_section.line_length.max_for(self.syntax.map(|syntax| syntax.name))
+    }
+
+    fn count_long_lines(&mut self) -> usize {
+        let limit = self.max_line_length();
+        let revision = self.buffer.revision();
+        if let Some((cached_revision, cached_limit, count)) = self.long_line_count
+            && cached_revision == revision
+            && cached_limit == limit
+        {
+            return count;
+        }
+        let count = self.buffer.iter()
+            .filter(|line| line.len() > limit && line.chars().count() > limit)
+            .count();
+        self.long_line_count = Some((revision, limit, count));
+        count
+    }
+
+    /// Where the cursor is among the matches of the highlighted search, if there is one.
+    fn search_match_indicator(&mut self) -> Option<String> {
+        let search = self.search.as_ref().filter(|search| search.highlighted)?;
+        let matcher = self.search_matcher(&search.pattern).ok()?;
+        let revision = self.buffer.revision();
+        let stale = !self.search_matches.as_ref().is_some_and(|(cached_revision, cached_matcher, _)| {
+            *cached_revision == revision && *cached_matcher == matcher
+        });
+        if stale {
+            let positions = search::positions(&self.buffer, &matcher, search::COUNT_LIMIT);
+            self.search_matches = Some((revision, matcher, positions));
+        }
+        let (_, _, positions) = self.search_matches.as_ref()?;
+        Some(search::describe_matches(positions, (self.cursor_y, self.cursor_x), search::COUNT_LIMIT))
+    }
+
+    fn enclosing_scopes(&mut self) -> &[String] {
+        let revision = self.buffer.revision();
+        let stale = self.breadcrumbs.as_ref()
+            .is_none_or(|(cached_revision, row, _)| *cached_revision != revision || *row != self.cursor_y);
+        if stale {
+            let scopes = match self.syntax {
+                Some(syntax) => symbols::enclosing_scopes(&self.buffer, self.cursor_y, syntax),
+                None => Vec::new(),
+            };
+            self.breadcrumbs = Some((revision, self.cursor_y, scopes));
+        }
+        self.breadcrumbs.as_ref().map(|(_, _, scopes)| scopes.as_slice()).unwrap_or_default()
+    }
+
+    fn start_viewing(&mut self) {
+        self.view_only = true;
+        self.input_mode = InputMode::Normal;
+        self.status_message = VIEW_HINT.to_string();
+    }
+
+    /// Shows text that came from a pipe rather than a file.
+    fn show_text(&mut self, lines: Vec<String>) {
+        self.buffer.reset(lines);
+        self.mark_saved();
+        self.syntax = filetype::detect("", &self.buffer);
+    }
+
+    fn open_file(&mut self, path: &str) {
+        let owned_path = path.to_string();
+        let id = self.tasks.spawn("open", move |context| {
+            let mut file = fs::File::open(&owned_path).map_err(|e| e.to_string())?;
+            let total = file.metadata().map(|m| m.len()).ok();
+            let mut bytes = Vec::with_capacity(total.unwrap_or(0) as usize);
+            let mut chunk = vec![0; 1 << 20];
+            loop {
+                context.check_cancelled()?;
+                let read = file.read(&mut chunk).map_err(|e| e.to_string())?;
+                if read == 0 {
+                    break;
+                }
+                bytes.extend_from_slice(&chunk[..read]);
+                context.progress(bytes.len() as u64, total);
+            }
+            let content = String::from_utf8(bytes).map_err(|e| e.to_string())?;
+            let lines = content.lines().map(|s| s.to_string()).collect();
+            let line_ending = platform::LineEnding::detect(&content);
+            Ok(TaskOutput::FileLoaded { path: owned_path, lines, line_ending })
+        });
+        log::info!("opening {}", path);
+        self.loading_file = Some(id);
+        self.running_tasks.push(RunningTask { id, label: format!("Opening {}", path), done: 0, total: None, cancel_on_input: false });
+    }
+
+    /// Takes in what a background task sent. Returns whether a command it finished running
+    /// quit the editor.
+    fn handle_task_event(&mut self, event: tasks::TaskEvent<TaskOutput>) -> bool {
+        match event {
+            tasks::TaskEvent::Progress { id, done, total } => {
+                if let Some(task) = self.running_tasks.iter_mut().find(|task| task.id == id) {
+                    task.done = done;
+                    task.total = total;
+                }
+            }
+            tasks::TaskEvent::Finished { id, result } => {
+                let Some(index) = self.running_tasks.iter().position(|task| task.id == id) else {
+                    return false;
+                };
+                let task = self.running_tasks.remove(index);
+                if self.loading_file == Some(id) {
+                    self.loading_file = None;
+                }
+                if self.pending_save.as_ref().is_some_and(|pending| pending.task == id) && result.is_err() {
+                    self.pending_save = None;
+                }
+                match result {
+                    Ok(TaskOutput::FileLoaded { path, lines, line_ending }) => {
+                        self.line_ending = line_ending;
+                        self.finish_open_file(&path, lines);
+                    }
+                    Ok(TaskOutput::FilesListed(files)) => {
+                        if let Some(finder) = self.finder.as_mut() {
+                            finder.set_files(files);
+                        }
+                    }
+                    Ok(TaskOutput::TodosFound(found)) => {
+                        if let Some(list) = self.todo_list.as_mut().filter(|list| list.project) {
+                            list.set_todos(found);
+                        }
+                    }
+                    Ok(TaskOutput::HooksFinished { before_save, ran }) => {
+                        for finished in &ran {
+                            log::info!("hook !{} exited with {:?}:\n{}", finished.command, finished.code, finished.output.trim_end());
+                        }
+                        if !before_save {
+                            self.finish_after_save_hooks(&ran);
+                        } else if self.finish_pending_save(&ran) {
+                            return true;
+                        }
+                    }
+                    Ok(TaskOutput::ShellFinished { finished, then, depth }) => {
+                        log::info!("{} exited with {:?}:\n{}", task.label, finished.code, finished.output.trim_end());
+                        let severity = if finished.success { toasts::Severity::Info } else { toasts::Severity::Error };
+                        self.notify(severity, finished.summary());
+                        if finished.success && self.run_steps(then, depth) {
+                            return true;
+                        }
+                    }
+                    Err(e) => {
+                        log::error!("{} failed: {}", task.label, e);
+                        self.notify(toasts::Severity::Error, format!("{} failed: {}", task.label, e));
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    fn cancel_running_tasks(&mut self) {
+        if let Some(task) = self.running_tasks.last() {
+            self.status_message = format!("Cancelled: {}", task.label);
+        }
+        for task in self.running_tasks.drain(..) {
+            self.tasks.cancel(task.id);
+        }
+        self.loading_file = None;
+        self.pending_save = None;
+        if self.finder.as_ref().is_some_and(|finder| finder.is_scanning()) {
+            self.finder = None;
+            self.application_mode = ApplicationMode::Editing;
+        }
+        if self.todo_list.as_ref().is_some_and(|list| list.scanning) {
+            self.todo_list = None;
+            self.application_mode = ApplicationMode::Editing;
+        }
+    }
+
+    /// Typing in the buffer supersedes work such as file scans whose panel was closed, as
+    /// their results would be stale anyway. Keys sent to the panel itself don't.
+    fn cancel_tasks_superseded_by_input(&mut self) {
+        if self.application_mode != ApplicationMode::Editing {
+            return;
+        }
+        let tasks = &mut self.tasks;
+        self.running_tasks.retain(|task| {
+            if task.cancel_on_input {
+                tasks.cancel(task.id);
+            }
+            !task.cancel_on_input
+        });
+    }
+
+    fn progress_indicator(&self) -> Option<String> {
+        let task = self.running_tasks.last()?;
+        let frame = (self.started_at.elapsed().as_millis() / 100) as usize % SPINNER_FRAMES.len();
+        let amount = match task.total {
+            Some(total) if total > 0 => format!("{}%", task.done * 100 / total),
+            _ => String::new(),
+        };
+        Some(format!("{} {} {} (Esc to cancel)", SPINNER_FRAMES[frame], task.label, amount))
+    }
+
+    fn finish_open_file(&mut self, path: &str, lines: Vec<String>) {
+        self.buffer.reset(lines);
+        self.filename = Some(path.to_string());
+        self.syntax = filetype::detect(path, &self.buffer);
+        self.load_annotations(path);
+        self.mark_saved();
+        self.unsaved_since = None;
+        self.status_message = if self.view_only {
+            VIEW_HINT.to_string()
+        } else if self.vim_enabled {
+            "-- NORMAL --".to_string()
+        } else {
+            format!("Opened: {}", path)
+        };
+        self.cursor_x = 0;
+        self.cursor_y = 0;
+        self.scroll_x = 0;
+        self.scroll_y = 0;
+        self.clear_selection();
+        self.detected_indent = indent::detect(&self.buffer);
+        let indents = indent::count(&self.buffer);
+        if indents.is_mixed() {
+            self.status_message = format!(
+                "Mixed indentation: {} lines use tabs, {} use spaces (:retab to convert)",
+                indents.tabs, indents.spaces
+            );
+        }
+        if let Some((row, count)) = display::bidi_overrides(self.buffer.iter()) {
+            self.status_message = format!(
+                "Warning: {} bidirectional override character{}, first on line {}, shown as ⇆",
+                count, if count == 1 { "" } else { "s" }, row + 1
+            );
+        }
+        self.load_project_config(file_dir(path));
+        self.plain_long_lines = false;
+        self.check_long_lines();
+        if let Some(jump) = self.pending_jump.take() {
+            self.apply_jump(jump);
+        }
+    }
+
+    /// Starts a file that doesn't exist yet, from the template for its filetype if there is
+    /// one. Nothing is written until it is saved.
+    fn new_file(&mut self, path: &str) {
+        self.finish_open_file(path, Vec::new());
+        self.status_message = format!("New file: {}", path);
+        if !self.config.editor_behavior.templates {
+            return;
+        }
+        let Some(template) = templates::find(path, self.syntax.map(|syntax| syntax.name)) else {
+            return;
+        };
+        let text = match fs::read_to_string(&template) {
+            Ok(text) => text,
+            Err(e) => {
+                self.status_message = format!("Can't read template {}: {}", template.display(), e);
+                return;
+            }
+        };
+        let placeholders = templates::Placeholders::new(path, &self.config.editor_behavior.author);
+        let (lines, cursor) = templates::fill(&text, &placeholders);
+        self.replace_lines(lines);
+        // A shebang or modeline in the template can say more than the name did.
+        self.syntax = filetype::detect(path, &self.buffer);
+        (self.cursor_y, self.cursor_x) = cursor.unwrap_or((0, 0));
+        self.status_message = format!("New file: {} (from {})", path, template.display());
+    }
+
+    /// Warns about lines over `long_lines.limit` and, unless configured otherwise, offers
+    /// to draw them plain.
+    fn check_long_lines(&mut self) {
+        let long_lines = &self.config.main_section.long_lines;
+        let limit = long_lines.limit;
+        if limit == 0 {
+            return;
+        }
+        let count = self.buffer.iter().filter(|line| line.len() > limit && line.chars().count() > limit).count();
+        if count == 0 {
+            return;
+        }
+        let lines = format!("{} line{} over {} characters", count, if count == 1 { "" } else { "s" }, format_thousands(limit));
+        match long_lines.plain.as_str() {
+            "always" => self.status_message = format!("{}, drawn plain", lines),
+            "never" => self.status_message = format!("Warning: {}", lines),
+            _ => {
+                let them = if count == 1 { "it" } else { "them" };
+                self.confirm(format!("{}. Draw {} without highlighting?", lines, them), Confirmation::PlainLongLines);
+            }
+        }
+    }
+
+    /// Whether `line` is drawn without highlighting, selection or any other styling.
+    fn is_plain_line(&self, line: &str) -> bool {
+        let long_lines = &self.config.main_section.long_lines;
+        let plain = match long_lines.plain.as_str() {
+            "always" => true,
+            "never" => false,
+            _ => self.plain_long_lines,
+        };
+        plain && long_lines.limit > 0 && line.len() > long_lines.limit && line.chars().count() > long_lines.limit
+    }
+
+    /// Reads the file again and brings the buffer up to date with ordinary edits rather
+    /// than starting over, so notes, the change list and the cursor follow the lines that
+    /// stayed, and the reload shows up as changes like any other.
+    fn reload_file(&mut self) {
+        let Some(path) = self.filename.clone() else {
+            self.status_message = "No file to reload".to_string();
+            return;
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                self.status_message = format!("Error reading {}: {}", path, e);
+                return;
+            }
+        };
+        let lines: Vec<String> = content.lines().map(str::to_string).collect();
+        self.line_ending = platform::LineEnding::detect(&content);
+        let changed = self.replace_lines(lines);
+        self.mark_saved();
+        self.unsaved_since = None;
+        self.status_message = match changed {
+            0 => format!("{} is unchanged on disk", path),
+            n => format!("Reloaded {} ({} line{} changed)", path, n, if n == 1 { "" } else { "s" }),
+        };
+    }
+
+    /// Makes the buffer hold `lines` by editing only what differs, and returns how many
+    /// lines that touched.
+    fn replace_lines(&mut self, mut lines: Vec<String>) -> usize {
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        let hunks = diff::hunks(&diff::diff_lines(&self.buffer, &lines));
+
+        // Lines above the cursor that came or went move it; inside a changed block it keeps
+        // its offset into the block as far as the new block reaches.
+        let row = match hunks.iter().rev().find(|(old, _)| old.start <= self.cursor_y) {
+            Some((old, new)) if old.end > self.cursor_y => new.start + (self.cursor_y - old.start).min(new.len().saturating_sub(1)),
+            Some((old, new)) => new.end + (self.cursor_y - old.end),
+            None => self.cursor_y,
+        };
+        for (old, new) in hunks.iter().rev() {
+            self.replace_rows(old.clone(), &lines[new.clone()]);
+        }
+
+        self.cursor_y = row.min(self.buffer.len() - 1);
+        let line = &self.buffer[self.cursor_y];
+        self.cursor_x = self.cursor_x.min(line.len());
+        while !line.is_char_boundary(self.cursor_x) {
+            self.cursor_x -= 1;
+        }
+        self.clear_selection();
+        hunks.iter().map(|(old, new)| old.len().max(new.len())).sum()
+    }
+
+    /// Shows the buffer next to the version its last save replaced, so hunks can be taken
+    /// back from it with `>`.
+    fn compare_with_backup(&mut self) {
+        let Some(path) = self.filename.clone() else {
+            self.status_message = "No file, so no backup".to_string();
+            return;
+        };
+        let Some(backup) = backup::latest(&path) else {
+            self.status_message = format!("No backup of {} yet; one is kept each time it is saved", path);
+            return;
+        };
+        let label = format!("Backup of {}", path);
+        match diff::DiffView::restore(&backup.to_string_lossy(), label, &path, self.buffer.to_vec(), self.line_ending) {
+            Ok(view) => self.open_diff(view),
+            Err(e) => self.status_message = format!("Error reading {}: {}", backup.display(), e),
+        }
+    }
+
+    fn open_timeline(&mut self) {
+        let Some(path) = self.filename.clone() else {
+            self.status_message = "No file, so no timeline".to_string();
+            return;
+        };
+        match timeline::versions(&path) {
+            Ok(versions) if versions.is_empty() => {
+                self.status_message = format!("No saved versions of {} yet", path);
+            }
+            Ok(versions) => {
+                self.timeline = Some(timeline::Timeline::new(versions));
+                self.application_mode = ApplicationMode::Timeline;
+            }
+            Err(e) => self.status_message = format!("Error reading the timeline of {}: {}", path, e),
+        }
+    }
+
+    fn handle_key_regex_tester_mode(&mut self, key_event: KeyEvent) -> bool {
+        let Some(tester) = self.regex_tester.as_mut() else {
+            self.application_mode = ApplicationMode::Editing;
+            return false;
+        };
+        match key_event.code {
+            KeyCode::Tab | KeyCode::BackTab => tester.editing_replacement = !tester.editing_replacement,
+            KeyCode::Backspace => {
+                tester.field().pop();
+            }
+            KeyCode::Char(c) if !key_event.modifiers.contains(KeyModifiers::CONTROL) => tester.field().push(c),
+            KeyCode::Up | KeyCode::Down => {
+                let matcher = tester.matcher(self.config.editor_behavior.smartcase);
+                match matcher.map(|matcher| search::find(&self.buffer, (self.cursor_y, self.cursor_x), &matcher, key_event.code == KeyCode::Down)) {
+                    Ok(Some((y, x))) => self.apply_jump(Jump::Cursor(y, x)),
+                    Ok(None) => self.status_message = "No matches".to_string(),
+                    Err(e) => self.status_message = e,
+                }
+            }
+            KeyCode::Enter => {
+                let Some(tester) = self.regex_tester.take() else {
+                    return false;
+                };
+                self.application_mode = ApplicationMode::Editing;
+                if !self.config.editor_behavior.regex_search && let Err(e) = self.set_option("regex", false) {
+                    self.status_message = e;
+                    return false;
+                }
+                if tester.replacement.is_empty() {
+                    self.remember(history::SEARCHES, &tester.pattern);
+                    self.search_for(tester.pattern, true);
+                } else {
+                    // Left on the command line to look over before it runs.
+                    self.open_command_line();
+                    self.command_line = tester.substitute_command();
+                }
+            }
+            KeyCode::Esc => {
+                self.regex_tester = None;
+                self.application_mode = ApplicationMode::Editing;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn handle_key_timeline_mode(&mut self, key_event: KeyEvent) -> bool {
+        let (Some(list), Some(path)) = (self.timeline.as_mut(), self.filename.clone()) else {
+            self.application_mode = ApplicationMode::Editing;
+            return false;
+        };
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => list.select_previous(),
+            KeyCode::Char('p') if ctrl => list.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => list.select_next(),
+            KeyCode::Char('n') if ctrl => list.select_next(),
+            KeyCode::Enter | KeyCode::Char('r') => {
+                let Some(version) = list.selected_version().cloned() else {
+                    return false;
+                };
+                self.timeline = None;
+                self.application_mode = ApplicationMode::Editing;
+                let object = timeline::object_path(&version.hash);
+                let age = format_age(version.age());
+                if key_event.code == KeyCode::Char('r') {
+                    match fs::read_to_string(&object) {
+                        Ok(content) => {
+                            let changed = self.replace_lines(content.lines().map(str::to_string).collect());
+                            self.status_message = format!("Went back to the version saved {} ago ({} lines changed, not saved yet)", age, changed);
+                        }
+                        Err(e) => self.status_message = format!("Error reading {}: {}", object.display(), e),
+                    }
+                    return false;
+                }
+                let label = format!("{} as saved {} ago", path, age);
+                match diff::DiffView::restore(&object.to_string_lossy(), label, &path, self.buffer.to_vec(), self.line_ending) {
+                    Ok(view) => self.open_diff(view),
+                    Err(e) => self.status_message = format!("Error reading {}: {}", object.display(), e),
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.timeline = None;
+                self.application_mode = ApplicationMode::Editing;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Leaves a comparison with an older version, putting the hunks taken from it into
+    /// the buffer unless `keep` is false.
+    fn finish_restoring(&mut self, keep: bool) {
+        let Some(view) = self.diff.take() else {
+            return;
+        };
+        self.application_mode = ApplicationMode::Editing;
+        if !keep || !view.right.modified {
+            self.status_message = "Nothing restored".to_string();
+            return;
+        }
+        let changed = self.replace_lines(view.right.lines);
+        self.status_message = format!("Restored {} line{} (not saved yet)", changed, if changed == 1 { "" } else { "s" });
+    }
+
+    /// Puts `new` in place of the buffer's lines `rows`, as a deletion and an insertion.
+    fn replace_rows(&mut self, rows: std::ops::Range<usize>, new: &[String]) {
+        let len = self.buffer.len();
+        if rows.end < len {
+            self.buffer.delete((rows.start, 0), (rows.end, 0));
+            let text: String = new.iter().map(|line| format!("{}\n", line)).collect();
+            self.buffer.insert((rows.start, 0), &text);
+        } else if rows.start > 0 {
+            // Nothing follows, so the newline taken is the one before the first row.
+            let before = (rows.start - 1, self.buffer[rows.start - 1].len());
+            self.buffer.delete(before, (len - 1, self.buffer[len - 1].len()));
+            let text: String = new.iter().map(|line| format!("\n{}", line)).collect();
+            self.buffer.insert(before, &text);
+        } else {
+            self.buffer.delete((0, 0), (len - 1, self.buffer[len - 1].len()));
+            self.buffer.insert((0, 0), &new.join("\n"));
+        }
+    }
+
+    /// Whether the buffer is source code rather than prose, where curly quotes and dashes
+    /// are at home.
+    fn is_code(&self) -> bool {
+        self.syntax.is_some_and(|syntax| syntax.name != "markdown")
+    }
+
+    /// Moves to the next suspicious character after the cursor, wrapping around, and says
+    /// what it is.
+    fn next_hidden_character(&mut self) {
+        let code = self.is_code();
+        let found: Vec<(usize, confusables::Suspect)> = self.buffer.iter().enumerate()
+            .flat_map(|(row, line)| confusables::find(line, code).into_iter().map(move |suspect| (row, suspect)))
+            .collect();
+        let Some(index) = found.iter().position(|(row, suspect)| (*row, suspect.start) > (self.cursor_y, self.cursor_x))
+            .or((!found.is_empty()).then_some(0))
+        else {
+            self.status_message = "No invisible or lookalike characters".to_string();
+            return;
+        };
+        let (row, suspect) = &found[index];
+        (self.cursor_y, self.cursor_x) = (*row, suspect.start);
+        self.clear_selection();
+        self.status_message = format!("{} ({} of {}, :hidden fix replaces them)", suspect.description, index + 1, found.len());
+    }
+
+    fn fix_hidden_characters(&mut self) {
+        let code = self.is_code();
+        let mut fixed = 0;
+        for row in 0..self.buffer.len() {
+            let Some(normalized) = confusables::normalize(&self.buffer[row], code) else {
+                continue;
+            };
+            let old_len = self.buffer[row].len();
+            fixed += confusables::find(&self.buffer[row], code).len();
+            self.buffer.delete((row, 0), (row, old_len));
+            self.buffer.insert((row, 0), &normalized);
+            if row == self.cursor_y {
+                self.cursor_x = self.cursor_x.min(normalized.len());
+                while !normalized.is_char_boundary(self.cursor_x) {
+                    self.cursor_x -= 1;
+                }
+            }
+        }
+        self.status_message = match fixed {
+            0 => "No invisible or lookalike characters".to_string(),
+            n => format!("Replaced {} invisible or lookalike character{}", n, if n == 1 { "" } else { "s" }),
+        };
+    }
+
+    /// How new indentation is made, as the status bar shows it: `Spaces:4` or `Tabs:4`.
+    fn indentation_label(&self) -> String {
+        let style = if self.config.editor_behavior.expand_tab { "Spaces" } else { "Tabs" };
+        format!("{}:{}", style, self.config.editor_behavior.tab_width)
+    }
+
+    /// Makes this buffer indent with tabs or spaces from now on, like `:set` would.
+    /// Existing lines stay as they are; `:retab` converts them.
+    fn change_indentation(&mut self, change: command::IndentChange) {
+        let keys = ["editor_behavior.expand_tab", "editor_behavior.tab_width"];
+        self.option_overrides.retain(|(key, _)| !keys.contains(&key.as_str()));
+        let (expand_tab, width) = match change {
+            command::IndentChange::Tabs(width) => (false, width),
+            command::IndentChange::Spaces(width) => (true, width),
+            command::IndentChange::Detect => {
+                self.detected_indent = indent::detect(&self.buffer);
+                self.refresh_effective_config();
+                return;
+            }
+        };
+        self.option_overrides.push((keys[0].to_string(), toml::Value::Boolean(expand_tab)));
+        if let Some(width) = width {
+            self.option_overrides.push((keys[1].to_string(), toml::Value::Integer(width.into())));
+        }
+        self.refresh_effective_config();
+    }
+
+    fn retab(&mut self) {
+        let expand_tab = self.config.editor_behavior.expand_tab;
+        let tab_width = self.config.editor_behavior.tab_width as usize;
+        let mut changed = 0;
+        for row in 0..self.buffer.len() {
+            let Some((old_len, indentation)) = indent::retab_line(&self.buffer[row], expand_tab, tab_width) else {
+                continue;
+            };
+            self.buffer.delete((row, 0), (row, old_len));
+            self.buffer.insert((row, 0), &indentation);
+            if row == self.cursor_y {
+                self.cursor_x = if self.cursor_x >= old_len { self.cursor_x - old_len + indentation.len() } else { self.cursor_x.min(indentation.len()) };
+            }
+            changed += 1;
+        }
+        let style = if expand_tab { "spaces" } else { "tabs" };
+        self.status_message = match changed {
+            0 => format!("Indentation already uses {}", style),
+            n => format!("Converted indentation of {} line{} to {}", n, if n == 1 { "" } else { "s" }, style),
+        };
+    }
+
+    fn apply_jump(&mut self, jump: Jump) {
+        let (y, x) = match jump {
+            Jump::Address(address) => match address.resolve(&self.buffer) {
+                Some(y) => (y, 0),
+                None => {
+                    self.status_message = "Tag pattern not found in file".to_string();
+                    return;
+                }
+            },
+            Jump::Cursor(y, x) => (y, x),
+        };
+        self.cursor_y = y.min(self.buffer.len().saturating_sub(1));
+        self.cursor_x = x.min(self.buffer[self.cursor_y].len());
+        self.update_selection_on_move(self.jump_extends_selection());
+        self.ensure_cursor_in_view(self.view_area, self.config.main_section.line_numbers.enabled, self.config.main_section.line_numbers.gutter_width);
+    }
+
+    /// Moves to the syntax error the last save found and centers its line on screen.
+    fn go_to_error(&mut self) {
+        let Some(error) = self.validation_error.clone() else {
+            self.status_message = "No syntax error from the last save".to_string();
+            return;
+        };
+        let line = &self.buffer[error.line.min(self.buffer.len() - 1)];
+        let x = line.char_indices().nth(error.column).map_or(line.len(), |(at, _)| at);
+        self.apply_jump(Jump::Cursor(error.line, x));
+        let visible_height = self.view_area.height.saturating_sub(2) as usize;
+        self.scroll_y = self.cursor_y.saturating_sub(visible_height / 2);
+        self.status_message = format!("Syntax error at {}", error);
+    }
+
+    /// Jumps within the current buffer, or opens `path` and jumps once it has loaded.
+    fn jump_to(&mut self, path: &str, jump: Jump) -> Result<(), String> {
+        if self.is_current_file(path) {
+            self.apply_jump(jump);
+            return Ok(());
+        }
+        if self.is_dirty() {
+            return Err("No write since last change".to_string());
+        }
+        self.pending_jump = Some(jump);
+        self.open_file(path);
+        Ok(())
+    }
+
+    /// Opens the link under the cursor: a URL in the browser, or a file at the line a
+    /// `path:line` reference gives. `urls` and `files` say which kinds to look for.
+    fn open_link(&mut self, urls: bool, files: bool) {
+        let link = self.buffer.get(self.cursor_y).and_then(|line| links::link_at(line, self.cursor_x));
+        match link {
+            Some(links::Link::Url(url)) if urls => {
+                let browser = self.config.main_section.links.browser.clone();
+                self.status_message = match platform::open_url(&url, &browser) {
+                    Ok(()) => format!("Opening {}", url),
+                    Err(e) => format!("Can't open {}: {}", url, e),
+                };
+            }
+            Some(links::Link::File { path, line }) if files => {
+                let base = self.filename.as_deref().and_then(|filename| Path::new(filename).parent().map(Path::to_path_buf));
+                let Some(found) = links::resolve(&path, base.as_deref()) else {
+                    self.status_message = format!("Can't find file \"{}\"", path);
+                    return;
+                };
+                let row = line.map_or(0, |line| line.saturating_sub(1));
+                if let Err(e) = self.jump_to(&found.to_string_lossy(), Jump::Cursor(row, 0)) {
+                    self.status_message = e;
+                }
+            }
+            _ if urls && !files => self.status_message = "No URL under cursor".to_string(),
+            _ if files && !urls => self.status_message = "No file name under cursor".to_string(),
+            _ => self.status_message = "Nothing to open under cursor".to_string(),
+        }
+    }
+
+    /// Opens the config file, writing out the commented default one first if there is none yet.
+    fn edit_config(&mut self) -> Result<(), String> {
+        let mut path = config::config_path();
+        if !path.exists() {
+            path = config::write_default_config().map_err(|e| e.to_string())?;
+        }
+        let path = path.to_string_lossy().to_string();
+        if self.is_current_file(&path) {
+            return Ok(());
+        }
+        if self.is_dirty() {
+            return Err("No write since last change".to_string());
+        }
+        self.open_file(&path);
+        Ok(())
+    }
+
+    /// Opens a register's keys as text, to be saved back into it.
+    fn edit_macro(&mut self, register: char) -> Result<(), String> {
+        let mut path = macros::path(register);
+        if !path.exists() {
+            path = macros::save(register, &[]).map_err(|e| e.to_string())?;
+        }
+        let path = path.to_string_lossy().to_string();
+        if self.is_current_file(&path) {
+            return Ok(());
+        }
+        if self.is_dirty() {
+            return Err("No write since last change".to_string());
+        }
+        self.open_file(&path);
+        Ok(())
+    }
+
+    fn is_current_file(&self, path: &str) -> bool {
+        let Some(current) = &self.filename else {
+            return false;
+        };
+        match (fs::canonicalize(current), fs::canonicalize(path)) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => current == path,
+        }
+    }
+
+    fn word_under_cursor(&self) -> Option<String> {
+        let line = self.buffer.get(self.cursor_y)?;
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let at = line.floor_char_boundary(self.cursor_x);
+        // The cursor is on the word or just past its end.
+        let touching = line[at..].chars().next().is_some_and(is_word) || line[..at].chars().next_back().is_some_and(is_word);
+        if !touching {
+            return None;
+        }
+        let start = line[..at].char_indices().rev().take_while(|&(_, c)| is_word(c)).last().map_or(at, |(offset, _)| offset);
+        let end = line[at..].char_indices().find(|&(_, c)| !is_word(c)).map_or(line.len(), |(offset, _)| at + offset);
+        Some(line[start..end].to_string())
+    }
+
+    fn load_tag_file(&mut self) -> Result<&tags::TagFile, String> {
+        let start = self.filename.as_deref()
+            .and_then(|filename| fs::canonicalize(filename).ok())
+            .and_then(|path| path.parent().map(|dir| dir.to_path_buf()))
+            .or_else(|| env::current_dir().ok())
+            .unwrap_or_default();
+        let path = tags::find_tags_file(&start).ok_or("No tags file found")?;
+        let reload = self.tag_file.as_ref().is_none_or(|file| file.path != path || file.is_stale());
+        if reload {
+            self.tag_file = Some(tags::TagFile::load(&path).map_err(|e| format!("Error reading {}: {}", path.display(), e))?);
+        }
+        Ok(self.tag_file.as_ref().expect("tag file was just loaded"))
+    }
+
+    fn jump_to_tag(&mut self, name: &str) -> Result<(), String> {
+        let matches = self.load_tag_file()?.lookup(name);
+        let Some(tag) = matches.first().cloned() else {
+            return Err(format!("Tag not found: {}", name));
+        };
+        let count = matches.len();
+        self.tag_stack.push(TagStackEntry {
+            filename: self.filename.clone(),
+            cursor_y: self.cursor_y,
+            cursor_x: self.cursor_x,
+        });
+        if let Err(e) = self.jump_to(&tag.file.to_string_lossy(), Jump::Address(tag.address)) {
+            self.tag_stack.pop();
+            return Err(e);
+        }
+        self.status_message = if count > 1 {
+            format!("tag 1 of {}: {}", count, name)
+        } else {
+            format!("tag: {}", name)
+        };
+        Ok(())
+    }
+
+    fn pop_tag(&mut self) -> Result<(), String> {
+        let entry = self.tag_stack.pop().ok_or("At bottom of tag stack")?;
+        let Some(filename) = entry.filename.clone() else {
+            self.apply_jump(Jump::Cursor(entry.cursor_y, entry.cursor_x));
+            return Ok(());
+        };
+        if let Err(e) = self.jump_to(&filename, Jump::Cursor(entry.cursor_y, entry.cursor_x)) {
+            self.tag_stack.push(entry);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// The save hooks for the buffer's filetype, or the `*` ones.
+    fn save_hooks(&self) -> Option<config::SaveHooks> {
+        let filetype = self.syntax.map_or("text", |syntax| syntax.name);
+        self.config.hooks.get(filetype).or_else(|| self.config.hooks.get("*")).cloned()
+    }
+
+    /// Saves the buffer. With `before_save` hooks it is written once they have run in the
+    /// background, which `pending_save` waits on.
+    fn save_file(&mut self) -> io::Result<SaveOutcome> {
+        if self.pending_save.is_some() {
+            self.status_message = "Still running the before_save hooks".to_string();
+            return Ok(SaveOutcome::RunningHooks);
+        }
+        let hooks = self.save_hooks().unwrap_or_default();
+        if self.filename.is_none() || hooks.before_save.is_empty() {
+            return self.write_file();
+        }
+        let input = self.buffer.join(self.line_ending.as_str());
+        let task = self.run_hooks(true, hooks.before_save, Some(input));
+        self.pending_save = Some(PendingSave { task, revision: self.buffer.revision(), block_on_failure: hooks.block_on_failure, quit: false });
+        self.status_message = "Running the before_save hooks...".to_string();
+        Ok(SaveOutcome::RunningHooks)
+    }
+
+    /// Saves, then says whether to quit now: not while the save waits on its hooks, which
+    /// quit once it is done, or on the user.
+    fn save_and_quit(&mut self) -> bool {
+        match self.save_file() {
+            Ok(SaveOutcome::RunningHooks) => {
+                if let Some(pending) = self.pending_save.as_mut() {
+                    pending.quit = true;
+                }
+                false
+            }
+            Ok(SaveOutcome::Confirming) => false,
+            Ok(SaveOutcome::Written) => true,
+            Err(e) => {
+                log::error!("error saving: {}", e);
+                self.notify(toasts::Severity::Error, format!("Error saving: {}", e));
+                false
+            }
+        }
+    }
+
+    /// Runs save hooks one after another in the background, stopping at the first to fail.
+    fn run_hooks(&mut self, before_save: bool, commands: Vec<String>, input: Option<String>) -> tasks::TaskId {
+        let file = self.filename.clone().unwrap_or_default();
+        let label = if before_save { "before_save hooks" } else { "after_save hooks" };
+        let id = self.tasks.spawn("hooks", move |context| {
+            let mut ran = Vec::new();
+            for command in commands {
+                let finished = shell::run(&command, Some(&file), Some(file_dir(&file)), input.clone(), context)?;
+                let success = finished.success;
+                ran.push(finished);
+                if !success {
+                    break;
+                }
+            }
+            Ok(TaskOutput::HooksFinished { before_save, ran })
+        });
+        self.running_tasks.push(RunningTask { id, label: label.to_string(), done: 0, total: None, cancel_on_input: false });
+        id
+    }
+
+    /// Writes the file once its `before_save` hooks are done. Returns whether to quit.
+    fn finish_pending_save(&mut self, ran: &[shell::Finished]) -> bool {
+        let Some(pending) = self.pending_save.take() else {
+            return false;
+        };
+        // The hooks checked the text as it was, so an edit since has them check it again.
+        if self.buffer.revision() != pending.revision {
+            log::info!("buffer changed while the before_save hooks ran, running them again");
+            if pending.quit {
+                return self.save_and_quit();
+            }
+            if let Err(e) = self.save_file() {
+                log::error!("error saving: {}", e);
+                self.notify(toasts::Severity::Error, format!("Error saving: {}", e));
+            }
+            return false;
+        }
+        let failed = ran.iter().find(|finished| !finished.success);
+        if let Some(failed) = failed && pending.block_on_failure {
+            self.notify(toasts::Severity::Error, format!("Not saved: {}", failed.summary()));
+            return false;
+        }
+        match self.write_file() {
+            Ok(SaveOutcome::Confirming) => return false,
+            Ok(_) => {}
+            Err(e) => {
+                log::error!("error saving: {}", e);
+                self.notify(toasts::Severity::Error, format!("Error saving: {}", e));
+                return false;
+            }
+        }
+        if let Some(failed) = failed {
+            self.notify(toasts::Severity::Warning, format!("Saved, but {}", failed.summary()));
+        }
+        pending.quit
+    }
+
+    /// After `after_save` hooks, takes in what they did to the file, as formatters do,
+    /// unless the buffer has been changed since.
+    fn finish_after_save_hooks(&mut self, ran: &[shell::Finished]) {
+        if let Some(failed) = ran.iter().find(|finished| !finished.success) {
+            self.notify(toasts::Severity::Warning, format!("after_save hook {}", failed.summary()));
+            return;
+        }
+        let Some(filename) = self.filename.clone() else {
+            return;
+        };
+        let changed = fs::read_to_string(&filename).is_ok_and(|content| content.lines().ne(self.buffer.iter().map(String::as_str)));
+        if changed && !self.is_dirty() {
+            self.reload_file();
+        }
+    }
+
+    /// Writes the buffer to `path` without making it the buffer's file or counting as a save.
+    fn write_copy(&mut self, path: &str) -> io::Result<()> {
+        fs::write(path, self.buffer.join(self.line_ending.as_str()))?;
+        log::info!("wrote {} lines to {}", self.buffer.len(), path);
+        self.notify(toasts::Severity::Info, format!("Wrote {} lines to {}", self.buffer.len(), path));
+        Ok(())
+    }
+
+    /// Writes the buffer to its file, unless its directory is missing, which is left to a
+    /// confirmation that saves again once the directory is made.
+    fn write_file(&mut self) -> io::Result<SaveOutcome> {
+        if let Some(filename) = self.filename.clone() {
+            let filename = filename.as_str();
+            if let Some(dir) = Path::new(filename).parent().filter(|dir| !dir.as_os_str().is_empty() && !dir.exists()) {
+                let missing = dir.display().to_string();
+                self.confirm(format!("{} does not exist. Create it?", missing), Confirmation::CreateDirectories(filename.to_string()));
+                return Ok(SaveOutcome::Confirming);
+            }
+            if platform::WINDOWS && let Some(problem) = platform::windows_name_problem(filename) {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, problem));
+            }
+            let content = self.buffer.join(self.line_ending.as_str());
+            if self.config.editor_behavior.backup && let Err(e) = backup::back_up(filename, &content) {
+                log::warn!("could not back up {}: {}", filename, e);
+            }
+            fs::write(filename, &content)?;
+            log::info!("saved {} lines to {}", self.buffer.len(), filename);
+            let timeline_size = self.config.editor_behavior.timeline_size;
+            if timeline_size > 0 && let Err(e) = timeline::record(filename, &content, timeline_size) {
+                log::warn!("could not add {} to its timeline: {}", filename, e);
+            }
+            self.mark_saved();
+            self.saved_at = Some(Instant::now());
+            self.unsaved_since = None;
+            let mut saved = (toasts::Severity::Info, format!("Saved {} lines to {}", self.buffer.len(), filename));
+            if self.config.main_section.validation.on_save
+                && let Some(syntax) = self.syntax
+                && let Err(error) = validate::validate(syntax.name, &self.buffer)
+            {
+                saved = (toasts::Severity::Warning, format!("Saved, but {} has a syntax error at {} (F8 to go there)", filename, error));
+                self.validation_error = Some(error);
+            } else {
+                self.validation_error = None;
+            }
+            let is_project_file = Path::new(filename).file_name().is_some_and(|name| name == project::PROJECT_FILE);
+            let macro_register = macros::register_of(filename);
+            if self.is_current_file(&config::config_path().to_string_lossy()) {
+                match config::reload_config() {
+                    Ok(config) => {
+                        saved = (toasts::Severity::Info, format!("Saved {} and applied it", filename));
+                        self.apply_config(config);
+                    }
+                    Err(e) => saved = (toasts::Severity::Error, format!("Saved, but the config was not applied: {}", e)),
+                }
+            }
+            if let Some(register) = macro_register {
+                saved = match macros::parse(&content) {
+                    Ok(keys) => (toasts::Severity::Info, format!("Saved {} keys into register {}", keys.len(), register)),
+                    Err(e) => (toasts::Severity::Error, format!("Saved, but register {} won't run: {}", register, e)),
+                };
+            }
+            if is_project_file && let Some(filename) = self.filename.clone() {
+                saved = (toasts::Severity::Info, format!("Saved {} and applied it", filename));
+                self.load_project_config(file_dir(&filename));
+            }
+            // Notes moved by edits are only right for the file as saved.
+            if let Some(annotations) = self.annotations.as_mut()
+                && annotations.has_moved()
+                && let Err(e) = annotations.save()
+            {
+                saved = (toasts::Severity::Warning, format!("Saved, but the notes could not be updated: {}", e));
+            }
+            self.notify(saved.0, saved.1);
+            if let Some(hooks) = self.save_hooks().filter(|hooks| !hooks.after_save.is_empty()) {
+                self.run_hooks(false, hooks.after_save, None);
+            }
+            Ok(SaveOutcome::Written)
+        } else {
+            self.notify(toasts::Severity::Error, "No filename. Use :w <path> or :saveas <path>.".to_string());
+            Err(io::ErrorKind::Other.into())
+        }
+    }
+
+    /// Saves whatever is dirty and has somewhere to go; unnamed buffers are left alone.
+    fn save_on_focus_lost(&mut self) {
+        if self.is_dirty() && self.filename.is_some() && let Err(e) = self.save_file() {
+            log::error!("error auto-saving: {}", e);
+            self.status_message = format!("Error auto-saving: {}", e);
+        }
+        // Restoring from an older version only changes the buffer, once it is closed.
+        if let Some(view) = self.diff.as_mut().filter(|view| view.restore_from.is_none()) {
+            for side in [&mut view.left, &mut view.right] {
+                if side.modified && let Err(e) = side.save() {
+                    log::error!("error auto-saving {}: {}", side.path, e);
+                    self.status_message = format!("Error auto-saving {}: {}", side.path, e);
+                }
+            }
+        }
+    }
+
+    /// Undoes the last step, or with `redo` makes the last undone one again, and puts the
+    /// cursor back where that step started or left it.
+    fn undo(&mut self, redo: bool, editor_content_area: Rect) {
+        self.undoing = true;
+        let cursor = if redo { self.undo_history.redo(&mut self.buffer) } else { self.undo_history.undo(&mut self.buffer) };
+        self.follow_changes();
+        self.undoing = false;
+        let Some((y, x)) = cursor else {
+            self.status_message = if redo { "Nothing to redo" } else { "Nothing to undo" }.to_string();
+            return;
+        };
+        self.clear_selection();
+        let y = y.min(self.buffer.len().saturating_sub(1));
+        let line = &self.buffer[y];
+        self.move_cursor_to((y, line.floor_char_boundary(x.min(line.len()))), editor_content_area, false);
+        self.status_message = if redo { "Redone" } else { "Undone" }.to_string();
+    }
+
+    /// Does what `chord` does, moving `count` changes for the change list ones. Returns
+    /// whether it quits, as a key handler does.
+    fn run_alt_chord(&mut self, chord: AltChord, count: usize, editor_content_area: Rect) -> bool {
+        match chord {
+            AltChord::CopySelection => self.copy_selection(),
+            AltChord::PasteRaw => self.paste(true, editor_content_area),
+            AltChord::OlderChange => self.go_to_change(true, count, editor_content_area),
+            AltChord::NewerChange => self.go_to_change(false, count, editor_content_area),
+            AltChord::OpenLink => self.open_link(true, true),
+            AltChord::ToggleLineNumbers => return self.run_command_line("set number!", 0),
+        }
+        false
+    }
+
+    fn clear_selection(&mut self) {
+        self.selection = None;
+        self.sticky_selection = false;
+    }
+
+    /// Sets the mark, as nano's Ctrl+6 does: a selection anchored at the cursor, or kept
+    /// where a Shift selection started, that moves extend without Shift. Unsets it again.
+    fn toggle_mark(&mut self) {
+        if self.sticky_selection {
+            self.clear_selection();
+            self.status_message = "Mark unset".to_string();
+        } else {
+            let cursor = (self.cursor_y, self.cursor_x);
+            let anchor = self.selection.filter(|selection| selection.kind == selection::SelectionKind::Char).map_or(cursor, |selection| selection.anchor);
+            self.selection = Some(selection::Selection::new(anchor, cursor, selection::SelectionKind::Char));
+            self.sticky_selection = true;
+            self.status_message = "Mark set".to_string();
+        }
+    }
+
+    /// Whether going to a line or search match takes the selection along.
+    fn jump_extends_selection(&self) -> bool {
+        self.sticky_selection || (self.config.editor_behavior.jumps_extend_selection && self.selection.is_some())
+    }
+
+    /// Grows the selection to the next larger piece of code around it: word, string,
+    /// brackets, line, indented block and at last the whole buffer.
+    fn expand_selection(&mut self, editor_content_area: Rect) {
+        if self.expansions.last().is_some_and(|&(_, _, made)| self.selection != Some(made)) {
+            self.expansions.clear();
+        }
+        let cursor = (self.cursor_y, self.cursor_x);
+        let range = match self.selection {
+            Some(selection) if selection.kind == selection::SelectionKind::Line => {
+                let (start, end) = selection.ordered();
+                ((start.0, 0), (end.0, self.buffer[end.0].len()))
+            }
+            Some(selection) if selection.kind == selection::SelectionKind::Char => selection.ordered(),
+            _ => (cursor, cursor),
+        };
+        let Some((start, end)) = enclosing::expand(&self.buffer, range, self.config.editor_behavior.tab_width as usize) else {
+            return;
+        };
+        let previous = self.selection;
+        self.move_cursor_to(end, editor_content_area, false);
+        let made = selection::Selection::new(start, end, selection::SelectionKind::Char);
+        self.selection = Some(made);
+        self.expansions.push((previous, cursor, made));
+    }
+
+    /// Takes back the last Alt+Up, as long as the selection it made is still there.
+    fn shrink_selection(&mut self, editor_content_area: Rect) {
+        match self.expansions.pop() {
+            Some((previous, cursor, made)) if self.selection == Some(made) => {
+                self.move_cursor_to(cursor, editor_content_area, false);
+                self.selection = previous;
+            }
+            _ => self.expansions.clear(),
+        }
+    }
+
+    fn delete_selected_text(&mut self, editor_content_area: Rect) {
+        if let Some(selection) = self.selection {
+            (self.cursor_y, self.cursor_x) = selection.delete(&mut self.buffer);
+            self.clear_selection();
+            self.ensure_cursor_in_view(
+                editor_content_area,
+                self.config.main_section.line_numbers.enabled,
+                self.config.main_section.line_numbers.gutter_width
+            );
+        }
+    }
+
+    fn ensure_cursor_in_view(&mut self, editor_content_area: Rect, line_numbers_enabled: bool, gutter_width: u16) {
+        let line_numbers_gutter_width = if line_numbers_enabled {
+            gutter_width + 1
+        } else {
+            0
+        };
+        let effective_width = editor_content_area.width.saturating_sub(2).saturating_sub(line_numbers_gutter_width) as usize;
+
+        let visible_height = editor_content_area.height.saturating_sub(2) as usize;
+
+        if self.config.editor_behavior.typewriter_scrolling {
+            self.scroll_y = self.cursor_y.saturating_sub(visible_height / 2);
+        } else if self.cursor_y < self.scroll_y {
+            self.scroll_y = self.cursor_y;
+        } else if self.cursor_y >= self.scroll_y + visible_height {
+            self.scroll_y = self.cursor_y - visible_height + 1;
+        }
+
+        self.scroll_y = self.scroll_y.min(self.buffer.len().saturating_sub(1));
+
+        if let Some(width) = self.wrap_width(editor_content_area) && self.cursor_y < self.buffer.len() {
+            // Long lines above the cursor take up more than one row each.
+            let line = &self.buffer[self.cursor_y];
+            let column = line[..self.cursor_x.min(line.len())].chars().count();
+            let mut used = wrap::locate(&self.wrap_rows(self.cursor_y, width), column) + 1
+                + (self.scroll_y..self.cursor_y).map(|row| self.wrap_rows(row, width).len()).sum::<usize>();
+            while used > visible_height && self.scroll_y < self.cursor_y {
+                used -= self.wrap_rows(self.scroll_y, width).len();
+                self.scroll_y += 1;
+            }
+            self.scroll_x = 0;
+        } else if self.cursor_y < self.buffer.len() {
+            // scroll_x counts characters, but what has to fit is the cells they take up, as
+            // wide glyphs take two. The cursor's own cell must fit too.
+            let line = &self.buffer[self.cursor_y];
+            self.cursor_x = self.cursor_x.min(line.len());
+            let column = line[..self.cursor_x].chars().count();
+            self.scroll_x = self.scroll_x.min(line.chars().count().saturating_sub(effective_width));
+            if column < self.scroll_x {
+                self.scroll_x = column;
+            }
+            let cursor_width = line[self.cursor_x..].chars().next().map_or(1, display::char_width).max(1);
+            while self.scroll_x < column && display::columns_width(line, self.scroll_x, column) + cursor_width > effective_width {
+                self.scroll_x += 1;
+            }
+        } else {
+            self.scroll_x = 0;
+        }
+        self.cursor_x = self.cursor_x.min(self.buffer[self.cursor_y].len());
+    }
+
+    /// How many cells of a line fit on each row with soft wrap on, or `None` with it off.
+    fn wrap_width(&self, editor_content_area: Rect) -> Option<usize> {
+        let line_numbers = &self.config.main_section.line_numbers;
+        let gutter = if line_numbers.enabled { line_numbers.gutter_width + 1 } else { 0 };
+        self.config.main_section.soft_wrap.enabled.then(|| editor_content_area.width.saturating_sub(2).saturating_sub(gutter) as usize)
+    }
+
+    fn wrap_rows(&self, row: usize, width: usize) -> Vec<wrap::Row> {
+        let soft_wrap = &self.config.main_section.soft_wrap;
+        wrap::rows(&self.buffer[row], width, display::columns_width(&soft_wrap.marker, 0, usize::MAX), soft_wrap.indent)
+    }
+
+    fn update_selection_on_move(&mut self, shift_pressed: bool) {
+        if shift_pressed || self.sticky_selection {
+            let head = (self.cursor_y, self.cursor_x);
+            match self.selection.as_mut() {
+                Some(selection) => selection.head = head,
+                None => self.selection = Some(selection::Selection::new(self.cursor_before_key, head, selection::SelectionKind::Char)),
+            }
+        } else {
+            self.clear_selection();
+        }
+    }
+
+    fn move_cursor_left(&mut self, editor_content_area: Rect, shift_pressed: bool) {
+        let line_numbers_enabled = self.config.main_section.line_numbers.enabled;
+        let gutter_width = self.config.main_section.line_numbers.gutter_width;
+
+        let line = &self.buffer[self.cursor_y];
+        if self.cursor_x > 0 {
+            for c in line[..self.cursor_x].chars().rev() {
+                self.cursor_x -= c.len_utf8();
+                if !display::is_combining(c) {
+                    break;
+                }
+            }
+        } else if self.cursor_y > 0 {
+            self.cursor_y -= 1;
+            self.cursor_x = self.buffer[self.cursor_y].len();
+        }
+        self.update_selection_on_move(shift_pressed);
+        self.ensure_cursor_in_view(editor_content_area, line_numbers_enabled, gutter_width);
+    }
+
+    fn move_cursor_right(&mut self, editor_content_area: Rect, shift_pressed: bool) {
+        let line_numbers_enabled = self.config.main_section.line_numbers.enabled;
+        let gutter_width = self.config.main_section.line_numbers.gutter_width;
+
+        let line = &self.buffer[self.cursor_y];
+        if let Some(c) = line[self.cursor_x..].chars().next() {
+            self.cursor_x += c.len_utf8();
+            self.cursor_x += line[self.cursor_x..].chars().take_while(|c| display::is_combining(*c)).map(char::len_utf8).sum::<usize>();
+        } else if self.cursor_y < self.buffer.len() - 1 {
+            self.cursor_y += 1;
+            self.cursor_x = 0;
+        }
+        self.update_selection_on_move(shift_pressed);
+        self.ensure_cursor_in_view(editor_content_area, line_numbers_enabled, gutter_width);
+    }
+
+    /// Moves to `row`, in the column the cursor had before a run of vertical moves so that
+    /// passing through a short line does not lose it (vim's curswant). Columns count
+    /// characters, so the cursor never lands inside a multi-byte one.
+    fn move_to_row(&mut self, row: usize) {
+        let column = match self.wanted_column {
+            Some((column, left_at)) if left_at == (self.cursor_y, self.cursor_x) => column,
+            _ => self.buffer[self.cursor_y][..self.cursor_x].chars().count(),
+        };
+        let line = &self.buffer[row];
+        self.cursor_y = row;
+        self.cursor_x = line.char_indices().nth(column).map_or(line.len(), |(at, _)| at);
+        self.wanted_column = Some((column, (self.cursor_y, self.cursor_x)));
+    }
+
+    /// With `virtual_edit = "all"`, how far past the end of its line the cursor is shown
+    /// because a vertical move wanted a column the line does not reach.
+    fn virtual_padding(&self) -> usize {
+        match self.wanted_column {
+            Some((column, left_at)) if self.config.editor_behavior.virtual_edit == "all"
+                && column != usize::MAX
+                && left_at == (self.cursor_y, self.cursor_x) => {
+                column.saturating_sub(self.buffer[self.cursor_y][..self.cursor_x].chars().count())
+            }
+            _ => 0,
+        }
+    }
+
+    /// Typing in virtual space first fills the gap up to the cursor with spaces.
+    fn fill_virtual_space(&mut self) {
+        let padding = self.virtual_padding();
+        if padding > 0 {
+            self.cursor_x = self.buffer.insert((self.cursor_y, self.cursor_x), &" ".repeat(padding)).1;
+        }
+    }
+
+    /// Unless `virtual_edit` allows otherwise, normal mode keeps the cursor on a character
+    /// rather than just past the end of the line.
+    fn clamp_cursor_to_line(&mut self) {
+        if self.config.editor_behavior.virtual_edit != "none" {
+            return;
+        }
+        let last = self.buffer[self.cursor_y].char_indices().next_back().map_or(0, |(i, _)| i);
+        if self.cursor_x > last {
+            if let Some((_, left_at)) = self.wanted_column.as_mut() && *left_at == (self.cursor_y, self.cursor_x) {
+                *left_at = (self.cursor_y, last);
+            }
+            self.cursor_x = last;
+        }
+    }
+
+    /// After `$` or End, vertical moves stick to the end of each line.
+    fn want_line_end(&mut self) {
+        self.wanted_column = Some((usize::MAX, (self.cursor_y, self.cursor_x)));
+    }
+
+    fn move_cursor_up(&mut self, editor_content_area: Rect, shift_pressed: bool) {
+        let line_numbers_enabled = self.config.main_section.line_numbers.enabled;
+        let gutter_width = self.config.main_section.line_numbers.gutter_width;
+
+        if self.cursor_y > 0 {
+            self.move_to_row(self.cursor_y - 1);
+        }
+        self.update_selection_on_move(shift_pressed);
+        self.ensure_cursor_in_view(editor_content_area, line_numbers_enabled, gutter_width);
+    }
+
+    fn move_cursor_down(&mut self, editor_content_area: Rect, shift_pressed: bool) {
+        let line_numbers_enabled = self.config.main_section.line_numbers.enabled;
+        let gutter_width = self.config.main_section.line_numbers.gutter_width;
+
+        if self.cursor_y < self.buffer.len() - 1 {
+            self.move_to_row(self.cursor_y + 1);
+        }
+        self.update_selection_on_move(shift_pressed);
+        self.ensure_cursor_in_view(editor_content_area, line_numbers_enabled, gutter_width);
+    }
+
+    /// Scrolls the pager by `lines`, keeping the cursor on the top line so nothing pulls
+    /// the view back.
+    fn scroll_view(&mut self, lines: isize, editor_content_area: Rect) {
+        let height = editor_content_area.height.saturating_sub(2) as usize;
+        let last_top = self.buffer.len().saturating_sub(height.max(1));
+        self.scroll_y = self.scroll_y.saturating_add_signed(lines).min(last_top);
+        self.cursor_y = self.scroll_y;
+        self.cursor_x = 0;
+    }
+
+    /// Keys in `--view`, after less: nothing here can change the text.
+    fn handle_key_view_mode(&mut self, key_event: KeyEvent, editor_content_area: Rect) -> bool {
+        let page = editor_content_area.height.saturating_sub(2).max(1) as isize;
+        let control_pressed = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        match key_event.code {
+            KeyCode::Char('q' | 'Q') => return true,
+            KeyCode::Char('c') if control_pressed => return true,
+            KeyCode::Char('d') | KeyCode::Char('u') if control_pressed => {
+                self.scroll_view(if key_event.code == KeyCode::Char('d') { page / 2 } else { -page / 2 }, editor_content_area);
+            }
+            KeyCode::Char('j' | 'e') | KeyCode::Down | KeyCode::Enter => self.scroll_view(1, editor_content_area),
+            KeyCode::Char('k' | 'y') | KeyCode::Up => self.scroll_view(-1, editor_content_area),
+            KeyCode::Char(' ' | 'f') | KeyCode::PageDown => self.scroll_view(page, editor_content_area),
+            KeyCode::Char('b') | KeyCode::PageUp => self.scroll_view(-page, editor_content_area),
+            KeyCode::Char('d') => self.scroll_view(page / 2, editor_content_area),
+            KeyCode::Char('u') => self.scroll_view(-page / 2, editor_content_area),
+            KeyCode::Char('g' | '<') | KeyCode::Home => self.scroll_view(isize::MIN, editor_content_area),
+            KeyCode::Char('G' | '>') | KeyCode::End => self.scroll_view(isize::MAX, editor_content_area),
+            KeyCode::Char(c @ ('/' | '?')) => self.open_search_prompt(c),
+            KeyCode::Char(c @ ('n' | 'N')) => self.repeat_search(c == 'N'),
+            KeyCode::Char('=') => {
+                let shown = (self.scroll_y + page as usize).min(self.buffer.len());
+                self.status_message = format!(
+                    "lines {}-{} of {} ({}%)",
+                    self.scroll_y + 1, shown, self.buffer.len(), shown * 100 / self.buffer.len()
+                );
+            }
+            KeyCode::F(12) => self.debug_overlay = !self.debug_overlay,
+            _ => {}
+        }
+        false
+    }
+
+    /// `g;` / `g,`: back to where earlier edits were made, or forward again.
+    fn go_to_change(&mut self, older: bool, count: usize, editor_content_area: Rect) {
+        match self.change_list.step(older, count) {
+            Ok((row, col)) => {
+                let row = row.min(self.buffer.len() - 1);
+                let col = self.buffer[row].floor_char_boundary(col);
+                self.move_cursor_to((row, col), editor_content_area, false);
+            }
+            Err(message) => self.status_message = message.to_string(),
+        }
+    }
+
+    fn move_cursor_to(&mut self, (y, x): buffer::Position, editor_content_area: Rect, shift_pressed: bool) {
+        self.cursor_y = y;
+        self.cursor_x = x;
+        self.update_selection_on_move(shift_pressed);
+        self.ensure_cursor_in_view(editor_content_area, self.config.main_section.line_numbers.enabled, self.config.main_section.line_numbers.gutter_width);
+    }
+
+    /// Where a normal mode motion key goes, and whether an operator over it includes the
+    /// character at the far end. `prefix` is the pending first key of `ge`, `fx` and such.
+    fn motion_target(&mut self, prefix: Option<char>, code: KeyCode) -> Option<(buffer::Position, bool)> {
+        let cursor = (self.cursor_y, self.cursor_x);
+        let line = self.buffer.get(self.cursor_y).map_or("", String::as_str);
+        let line_end = line.len();
+        let before = line[..self.cursor_x].chars().next_back().map_or(0, char::len_utf8);
+        let under = line[self.cursor_x..].chars().next().map_or(0, char::len_utf8);
+        let target = match (prefix, code) {
+            (Some('g'), KeyCode::Char('e')) => (motion::word_end_backward(&self.buffer, cursor, false), true),
+            (Some('g'), KeyCode::Char('E')) => (motion::word_end_backward(&self.buffer, cursor, true), true),
+            (Some(kind @ ('f' | 't' | 'F' | 'T')), KeyCode::Char(target)) => {
+                let find = motion::CharFind { target, forward: kind.is_ascii_lowercase(), till: kind.eq_ignore_ascii_case(&'t') };
+                self.last_char_find = Some(find);
+                (motion::find_char(&self.buffer, cursor, find, false)?, find.forward)
+            }
+            (None, KeyCode::Char(';')) => {
+                let find = self.last_char_find?;
+                (motion::find_char(&self.buffer, cursor, find, true)?, find.forward)
+            }
+            (None, KeyCode::Char(',')) => {
+                let find = self.last_char_find?.reversed();
+                (motion::find_char(&self.buffer, cursor, find, true)?, find.forward)
+            }
+            (None, KeyCode::Char('h') | KeyCode::Left) => ((self.cursor_y, self.cursor_x - before), false),
+            (None, KeyCode::Char('l') | KeyCode::Right) => ((self.cursor_y, self.cursor_x + under), false),
+            (None, KeyCode::Char('0')) => ((self.cursor_y, 0), false),
+            (None, KeyCode::Char('$')) => ((self.cursor_y, line_end), false),
+            (None, KeyCode::Char('w')) => (motion::word_forward(&self.buffer, cursor, false), false),
+            (None, KeyCode::Char('W')) => (motion::word_forward(&self.buffer, cursor, true), false),
+            (None, KeyCode::Char('b')) => (motion::word_backward(&self.buffer, cursor, false), false),
+            (None, KeyCode::Char('B')) => (motion::word_backward(&self.buffer, cursor, true), false),
+            (None, KeyCode::Char('e')) => (motion::word_end_forward(&self.buffer, cursor, false), true),
+            (None, KeyCode::Char('E')) => (motion::word_end_forward(&self.buffer, cursor, true), true),
+            (None, KeyCode::Char('}')) => (motion::paragraph_forward(&self.buffer, cursor), false),
+            (None, KeyCode::Char('{')) => (motion::paragraph_backward(&self.buffer, cursor), false),
+            (None, KeyCode::Char(')')) => (motion::sentence_forward(&self.buffer, cursor), false),
+            (None, KeyCode::Char('(')) => (motion::sentence_backward(&self.buffer, cursor), false),
+            _ => return None,
+        };
+        Some(target)
+    }
+
+    /// `motion_target` with vim's special cases for operators: `cw` changes only to the end
+    /// of the word, and `dw` on the last word of a line leaves the next line alone.
+    fn operator_motion_target(&mut self, operator: Operator, prefix: Option<char>, code: KeyCode) -> Option<(buffer::Position, bool)> {
+        let cursor = (self.cursor_y, self.cursor_x);
+        let on_word = self.buffer[self.cursor_y][self.cursor_x..].chars().next().is_some_and(|c| !c.is_whitespace());
+        match (prefix, code) {
+            (None, KeyCode::Char(c @ ('w' | 'W'))) if operator == Operator::Change && on_word => {
+                Some((motion::word_end_at(&self.buffer, cursor, c == 'W'), true))
+            }
+            (None, KeyCode::Char('w' | 'W')) => {
+                let (target, inclusive) = self.motion_target(prefix, code)?;
+                if target.0 > self.cursor_y {
+                    Some(((self.cursor_y, self.buffer[self.cursor_y].len()), false))
+                } else {
+                    Some((target, inclusive))
+                }
+            }
+            _ => self.motion_target(prefix, code),
+        }
+    }
+
+    /// `operator_motion_target` repeated `count` times, each from where the last one
+    /// stopped, or `None` if the motion fails before then. A motion that stops moving, as
+    /// `w` at the end of the buffer, ends there. A repeated `f` or `t` goes on as `;` would.
+    fn counted_motion_target(&mut self, operator: Operator, prefix: Option<char>, code: KeyCode, count: usize) -> Option<(buffer::Position, bool)> {
+        let cursor = (self.cursor_y, self.cursor_x);
+        let mut target = None;
+        for step in 0..count {
+            let (prefix, code) = match prefix {
+                Some('f' | 't' | 'F' | 'T') if step > 0 => (None, KeyCode::Char(';')),
+                _ => (prefix, code),
+            };
+            target = if step + 1 == count { self.operator_motion_target(operator, prefix, code) } else { self.motion_target(prefix, code) };
+            match target {
+                Some((position, _)) if position != (self.cursor_y, self.cursor_x) => (self.cursor_y, self.cursor_x) = position,
+                _ => break,
+            }
+        }
+        (self.cursor_y, self.cursor_x) = cursor;
+        target
+    }
+
+    fn apply_operator(&mut self, operator: Operator, (target, inclusive): (buffer::Position, bool), editor_content_area: Rect) {
+        let cursor = (self.cursor_y, self.cursor_x);
+        let (start, mut end) = if target < cursor { (target, cursor) } else { (cursor, target) };
+        if inclusive {
+            end.1 += self.buffer[end.0].get(end.1..).and_then(|rest| rest.chars().next()).map_or(0, char::len_utf8);
+        }
+        if start == end {
+            return;
+        }
+        let range = selection::Selection::new(start, end, selection::SelectionKind::Char);
+        self.clipboard = range.text(&self.buffer);
+        if operator != Operator::Yank {
+            range.delete(&mut self.buffer);
+        }
+        self.move_cursor_to(start, editor_content_area, false);
+        if operator == Operator::Change {
+            self.input_mode = InputMode::Insert;
+            self.status_message = "-- INSERT --".to_string();
+        }
+    }
+
+    /// Linewise operators (`dd`, `yj`, `ck`, ...) take whole lines, newline included.
+    fn apply_operator_to_lines(&mut self, operator: Operator, first: usize, last: usize, editor_content_area: Rect) {
+        let lines = selection::Selection::new((first, 0), (last, 0), selection::SelectionKind::Line);
+        self.clipboard = lines.text(&self.buffer);
+        let cursor = match operator {
+            Operator::Yank => (first, 0),
+            // Change keeps one empty line to type into.
+            Operator::Change => {
+                self.buffer.delete((first, 0), (last, self.buffer[last].len()));
+                self.input_mode = InputMode::Insert;
+                self.status_message = "-- INSERT --".to_string();
+                (first, 0)
+            }
+            Operator::Delete => lines.delete(&mut self.buffer),
+        };
+        self.move_cursor_to(cursor, editor_content_area, false);
+    }
+
+    /// Moves to the first non-blank character of 1-based `line`, clamped to the buffer.
+    fn go_to_line(&mut self, line: usize, editor_content_area: Rect) {
+        let row = line.clamp(1, self.buffer.len()) - 1;
+        let text = &self.buffer[row];
+        let column = text.len() - text.trim_start().len();
+        self.move_cursor_to((row, column), editor_content_area, self.jump_extends_selection());
+    }
+
+    fn move_cursor_word_left(&mut self, editor_content_area: Rect, shift_pressed: bool) {
+        let target = motion::word_backward(&self.buffer, (self.cursor_y, self.cursor_x), false);
+        self.move_cursor_to(target, editor_content_area, shift_pressed);
+    }
+
+    fn move_cursor_word_right(&mut self, editor_content_area: Rect, shift_pressed: bool) {
+        let target = motion::word_forward(&self.buffer, (self.cursor_y, self.cursor_x), false);
+        self.move_cursor_to(target, editor_content_area, shift_pressed);
+    }
+
+    fn get_selected_text(&self) -> Option<String> {
+        self.selection.map(|selection| selection.text(&self.buffer))
+    }
+
+    fn copy_selection(&mut self) {
+        if let Some(text) = self.get_selected_text() {
+            self.clipboard = text;
+            if self.sticky_selection {
+                self.clear_selection();
+            }
+            self.status_message = format!("Copied {} characters.", self.clipboard.len());
+        } else {
+            self.status_message = "No selection to copy.".to_string();
+        }
+    }
+
+    fn cut_selection(&mut self, editor_content_area: Rect) {
+        if let Some(text) = self.get_selected_text() {
+            self.clipboard = text;
+            self.delete_selected_text(editor_content_area);
+            self.status_message = format!("Cut {} characters.", self.clipboard.len());
+        } else {
+            self.status_message = "No selection to cut.".to_string();
+        }
+    }
+
+    fn insert_text_at_cursor(&mut self, text: &str, editor_content_area: Rect) {
+        if self.selection.is_some() {
+            self.delete_selected_text(editor_content_area);
+        }
+
+        self.fill_virtual_space();
+        (self.cursor_y, self.cursor_x) = self.buffer.insert((self.cursor_y, self.cursor_x), text);
+
+        self.ensure_cursor_in_view(
+            editor_content_area,
+            self.config.main_section.line_numbers.enabled,
+            self.config.main_section.line_numbers.gutter_width
+        );
+    }
+
+    /// Pastes the clipboard, fitting lines of code to the cursor line's indentation
+    /// unless `raw`, `reindent_paste` is off or paste mode is on.
+    fn paste(&mut self, raw: bool, editor_content_area: Rect) {
+        let mut clipboard_content = self.clipboard.clone();
+        if !clipboard_content.is_empty() {
+            let behavior = &self.config.editor_behavior;
+            if !raw && behavior.reindent_paste && !behavior.paste && clipboard_content.contains('\n') {
+                if self.selection.is_some() {
+                    self.delete_selected_text(editor_content_area);
+                }
+                clipboard_content = self.reindent_for_cursor(&clipboard_content);
+            }
+            self.insert_text_at_cursor(&clipboard_content, editor_content_area);
+            self.status_message = format!("Pasted {} characters.", clipboard_content.len());
+        } else {
+            self.status_message = "Clipboard is empty.".to_string();
+        }
+    }
+
+    /// `text` reindented to go in at the cursor, under the cursor line's indentation.
+    fn reindent_for_cursor(&self, text: &str) -> String {
+        let line = &self.buffer[self.cursor_y];
+        let indentation = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+        let before = &line[..self.cursor_x.min(line.len())];
+        // In the indentation the first line is a whole one, and the cursor already
+        // stands behind part of its new indentation.
+        let whole_first_line = before.trim().is_empty();
+        let behavior = &self.config.editor_behavior;
+        let text = indent::reindent(text, indentation, whole_first_line, behavior.expand_tab, behavior.tab_width as usize);
+        match text.strip_prefix(before) {
+            Some(rest) if whole_first_line => rest.to_string(),
+            _ => text,
+        }
+    }
+
+    /// Typing over a selection replaces it, or with `typing_replaces_selection` off just
+    /// drops the selection.
+    fn prepare_to_type(&mut self, editor_content_area: Rect) {
+        if self.config.editor_behavior.typing_replaces_selection {
+            self.delete_selected_text(editor_content_area);
+        } else {
+            self.clear_selection();
+        }
+    }
+
+    fn insert_char(&mut self, c: char, editor_content_area: Rect) {
+        let line_numbers_enabled = self.config.main_section.line_numbers.enabled;
+        let gutter_width = self.config.main_section.line_numbers.gutter_width;
+        self.prepare_to_type(editor_content_area);
+        self.fill_virtual_space();
+        (self.cursor_y, self.cursor_x) = self.buffer.insert((self.cursor_y, self.cursor_x), c.encode_utf8(&mut [0; 4]));
+        self.ensure_cursor_in_view(editor_content_area, line_numbers_enabled, gutter_width);
+    }
+
+    /// Indents, or with `outdent` takes one level of indentation off, every line of a
+    /// selection spanning lines. The selection stays on the same text, so pressing Tab
+    /// again keeps moving the block.
+    fn shift_selected_lines(&mut self, outdent: bool, editor_content_area: Rect) {
+        let Some(mut selection) = self.selection else {
+            return;
+        };
+        let (start, end) = selection.ordered();
+        // A selection ending at the start of a line leaves that line alone.
+        let last = if end.1 == 0 && end.0 > start.0 && selection.kind == selection::SelectionKind::Char { end.0 - 1 } else { end.0 };
+        let unit = self.indent_unit();
+        let tab_width = self.config.editor_behavior.tab_width.max(1) as usize;
+        let mut shifted = 0;
+        for row in start.0..=last {
+            let line = &self.buffer[row];
+            let delta = if outdent {
+                let removed = if line.starts_with('\t') { 1 } else { line.len() - line.trim_start_matches(' ').len() }.min(tab_width);
+                if removed == 0 {
+                    continue;
+                }
+                self.buffer.delete((row, 0), (row, removed));
+                -(removed as isize)
+            } else {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                self.buffer.insert((row, 0), &unit);
+                unit.len() as isize
+            };
+            let shift = |(y, x): buffer::Position| if y == row { (y, x.saturating_add_signed(delta.max(-(x as isize)))) } else { (y, x) };
+            selection.anchor = shift(selection.anchor);
+            selection.head = shift(selection.head);
+            self.cursor_x = shift((self.cursor_y, self.cursor_x)).1;
+            shifted += 1;
+        }
+        self.selection = Some(selection);
+        let line_numbers_enabled = self.config.main_section.line_numbers.enabled;
+        let gutter_width = self.config.main_section.line_numbers.gutter_width;
+        self.ensure_cursor_in_view(editor_content_area, line_numbers_enabled, gutter_width);
+        if outdent && shifted == 0 {
+            self.status_message = "Nothing to outdent".to_string();
+        }
+    }
+
+    fn insert_tab(&mut self, editor_content_area: Rect) {
+        self.prepare_to_type(editor_content_area);
+        if self.config.editor_behavior.expand_tab {
+            let tab_width = self.config.editor_behavior.tab_width.max(1) as usize;
+            let column = self.buffer[self.cursor_y][..self.cursor_x].chars().count();
+            let spaces = " ".repeat(tab_width - column % tab_width);
+            self.insert_text_at_cursor(&spaces, editor_content_area);
+        } else {
+            self.insert_char('\t', editor_content_area);
+        }
+    }
+
+    fn insert_newline(&mut self, editor_content_area: Rect) {
+        let line_numbers_enabled = self.config.main_section.line_numbers.enabled;
+        let gutter_width = self.config.main_section.line_numbers.gutter_width;
+        self.prepare_to_type(editor_content_area);
+        let closer = self.block_closer();
+        (self.cursor_y, self.cursor_x) = self.buffer.insert((self.cursor_y, self.cursor_x), "\n");
+        if let Some((indentation, closer)) = closer {
+            let body = format!("{}{}", indentation, self.indent_unit());
+            self.buffer.insert((self.cursor_y, 0), &format!("{}\n{}{}", body, indentation, closer));
+            self.cursor_x = body.len();
+        }
+        self.ensure_cursor_in_view(editor_content_area, line_numbers_enabled, gutter_width);
+    }
+
+    /// One level of indentation, as Tab would type it at the start of a line.
+    fn indent_unit(&self) -> String {
+        match self.config.editor_behavior.expand_tab {
+            true => " ".repeat(self.config.editor_behavior.tab_width.max(1) as usize),
+            false => "\t".to_string(),
+        }
+    }
+
+    /// When Enter at the cursor ends a line that opens a block, the line's indentation and
+    /// the keyword closing the block. Not when the block has a body or its closer already.
+    fn block_closer(&self) -> Option<(String, &'static str)> {
+        if self.config.editor_behavior.paste {
+            return None;
+        }
+        let filetype = closers::filetype(self.syntax.map(|syntax| syntax.name), self.filename.as_deref())?;
+        if !self.config.editor_behavior.close_keywords.iter().any(|name| name == filetype) {
+            return None;
+        }
+        let line = &self.buffer[self.cursor_y];
+        if !line[self.cursor_x..].trim().is_empty() {
+            return None;
+        }
+        let closer = closers::closer(filetype, line)?;
+        let indentation = &line[..line.len() - line.trim_start().len()];
+        if let Some(next) = self.buffer.iter().skip(self.cursor_y + 1).find(|next| !next.trim().is_empty()) {
+            let next_indentation = next.len() - next.trim_start().len();
+            if next_indentation > indentation.len() || (next_indentation == indentation.len() && next.trim_start().starts_with(closer)) {
+                return None;
+            }
+        }
+        Some((indentation.to_string(), closer))
+    }
+
+    fn delete_char_backward(&mut self, editor_content_area: Rect) {
+        let line_numbers_enabled = self.config.main_section.line_numbers.enabled;
+        let gutter_width = self.config.main_section.line_numbers.gutter_width;
+        if self.selection.is_some() {
+            self.delete_selected_text(editor_content_area);
+            return;
+        }
+
+        let end = (self.cursor_y, self.cursor_x);
+        if let Some(c) = self.buffer[self.cursor_y][..self.cursor_x].chars().next_back() {
+            self.cursor_x -= c.len_utf8();
+            self.buffer.delete((self.cursor_y, self.cursor_x), end);
+        } else if self.cursor_y > 0 {
+            self.cursor_y -= 1;
+            self.cursor_x = self.buffer[self.cursor_y].len();
+            self.buffer.delete((self.cursor_y, self.cursor_x), end);
+        }
+        self.ensure_cursor_in_view(editor_content_area, line_numbers_enabled, gutter_width);
+    }
+
+    fn delete_char_forward(&mut self, editor_content_area: Rect) {
+        let line_numbers_enabled = self.config.main_section.line_numbers.enabled;
+        let gutter_width = self.config.main_section.line_numbers.gutter_width;
+        if self.selection.is_some() {
+            self.delete_selected_text(editor_content_area);
+            return;
+        }
+
+        let start = (self.cursor_y, self.cursor_x);
+        if let Some(c) = self.buffer[self.cursor_y][self.cursor_x..].chars().next() {
+            self.buffer.delete(start, (self.cursor_y, self.cursor_x + c.len_utf8()));
+        } else if self.cursor_y < self.buffer.len() - 1 {
+            self.buffer.delete(start, (self.cursor_y + 1, 0));
+        }
+        self.ensure_cursor_in_view(editor_content_area, line_numbers_enabled, gutter_width);
+    }
+
+    fn handle_key_insert_mode(&mut self, key_event: KeyEvent, editor_content_area: Rect) -> bool {
+        let shift_pressed = key_event.modifiers.contains(KeyModifiers::SHIFT);
+        let line_numbers_enabled = self.config.main_section.line_numbers.enabled;
+        let gutter_width = self.config.main_section.line_numbers.gutter_width;
+        let editor_visible_height = editor_content_area.height.saturating_sub(2) as usize;
+
+        match key_event.code {
+            KeyCode::Esc => {
+                if self.vim_enabled {
+                    self.input_mode = InputMode::Normal;
+                    self.status_message = "-- NORMAL --".to_string();
+                    self.clear_selection();
+                    let line = &self.buffer[self.cursor_y];
+                    self.cursor_x -= line[..self.cursor_x].chars().next_back().map_or(0, char::len_utf8);
+                }
+                false
+            }
+            KeyCode::Char(c) if key_event.modifiers.contains(KeyModifiers::ALT) && let Some(chord) = AltChord::for_key(c, &InputMode::Insert) => {
+                self.run_alt_chord(chord, 1, editor_content_area)
+            }
+            // Terminals send Ctrl+6 as Ctrl+^, which arrives as either.
+            KeyCode::Char('6' | '^') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.toggle_mark();
+                false
+            }
+            KeyCode::Char('v') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.paste(false, editor_content_area);
+                false
+            }
+            KeyCode::Char('z') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.undo(false, editor_content_area);
+                false
+            }
+            KeyCode::Char('y') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.undo(true, editor_content_area);
+                false
+            }
+            KeyCode::Char(c) => {
+                if (key_event.modifiers - KeyModifiers::SHIFT).is_empty() {
+                    self.insert_char(c, editor_content_area);
+                }
+                false
+            }
+            KeyCode::Enter => {
+                self.insert_newline(editor_content_area);
+                false
+            }
+            KeyCode::Tab | KeyCode::BackTab if self.selection.is_some_and(|selection| selection.anchor.0 != selection.head.0) => {
+                self.shift_selected_lines(key_event.code == KeyCode::BackTab, editor_content_area);
+                false
+            }
+            KeyCode::Tab => {
+                self.insert_tab(editor_content_area);
+                false
+            }
+            KeyCode::Backspace => {
+                self.delete_char_backward(editor_content_area);
+                false
+            }
+            KeyCode::Delete => {
+                self.delete_char_forward(editor_content_area);
+                false
+            }
+            KeyCode::Left => {
+                let cursor = (self.cursor_y, self.cursor_x);
+                if key_event.modifiers.contains(KeyModifiers::ALT) {
+                    self.move_cursor_to(motion::sentence_backward(&self.buffer, cursor), editor_content_area, shift_pressed);
+                } else if key_event.modifiers.contains(KeyModifiers::CONTROL) {
+                    self.move_cursor_word_left(editor_content_area, shift_pressed);
+                } else {
+                    self.move_cursor_left(editor_content_area, shift_pressed);
+                }
+                false
+            }
+            KeyCode::Right => {
+                let cursor = (self.cursor_y, self.cursor_x);
+                if key_event.modifiers.contains(KeyModifiers::ALT) {
+                    self.move_cursor_to(motion::sentence_forward(&self.buffer, cursor), editor_content_area, shift_pressed);
+                } else if key_event.modifiers.contains(KeyModifiers::CONTROL) {
+                    self.move_cursor_word_right(editor_content_area, shift_pressed);
+                } else {
+                    self.move_cursor_right(editor_content_area, shift_pressed);
+                }
+                false
+            }
+            KeyCode::Up | KeyCode::Down if key_event.modifiers.contains(KeyModifiers::ALT) && shift_pressed => {
+                if key_event.code == KeyCode::Up {
+                    self.move_cursor_up(editor_content_area, true);
+                } else {
+                    self.move_cursor_down(editor_content_area, true);
+                }
+                if let Some(selection) = self.selection.as_mut() {
+                    selection.kind = selection::SelectionKind::Block;
+                }
+                false
+            }
+            KeyCode::Up if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                let target = motion::paragraph_backward(&self.buffer, (self.cursor_y, self.cursor_x));
+                self.move_cursor_to(target, editor_content_area, shift_pressed);
+                false
+            }
+            KeyCode::Down if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                let target = motion::paragraph_forward(&self.buffer, (self.cursor_y, self.cursor_x));
+                self.move_cursor_to(target, editor_content_area, shift_pressed);
+                false
+            }
+            KeyCode::Up => {
+                self.move_cursor_up(editor_content_area, shift_pressed);
+                false
+            }
+            KeyCode::Down => {
+                self.move_cursor_down(editor_content_area, shift_pressed);
+                false
+            }
+            KeyCode::Home if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.cursor_y = 0; self.cursor_x = 0;
+                self.scroll_y = 0; self.scroll_x = 0;
+                self.update_selection_on_move(shift_pressed);
+                false
+            }
+            KeyCode::End if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.cursor_y = self.buffer.len().saturating_sub(1);
+                if self.cursor_y < self.buffer.len() { self.cursor_x = self.buffer[self.cursor_y].len(); } else { self.cursor_x = 0; }
+                self.update_selection_on_move(shift_pressed);
+                self.ensure_cursor_in_view(editor_content_area, line_numbers_enabled, gutter_width);
+                false
+            }
+            KeyCode::Home => {
+                self.cursor_x = 0; self.scroll_x = 0;
+                self.update_selection_on_move(shift_pressed);
+                self.ensure_cursor_in_view(editor_content_area, line_numbers_enabled, gutter_width);
+                false
+            }
+            KeyCode::End => {
+                if self.cursor_y < self.buffer.len() { self.cursor_x = self.buffer[self.cursor_y].len(); } else { self.cursor_x = 0; }
+                self.want_line_end();
+                self.update_selection_on_move(shift_pressed);
+                self.ensure_cursor_in_view(editor_content_area, line_numbers_enabled, gutter_width);
+                false
+            }
+            KeyCode::PageUp => {
+                self.scroll_y = self.scroll_y.saturating_sub(editor_visible_height);
+                self.move_to_row(self.cursor_y.saturating_sub(editor_visible_height).max(self.scroll_y));
+                self.update_selection_on_move(shift_pressed);
+                self.ensure_cursor_in_view(editor_content_area, line_numbers_enabled, gutter_width);
+                false
+            }
+            KeyCode::PageDown => {
+                self.scroll_y = (self.scroll_y + editor_visible_height).min(self.buffer.len().saturating_sub(1));
+                self.move_to_row((self.cursor_y + editor_visible_height).min(self.buffer.len().saturating_sub(1)));
+                self.update_selection_on_move(shift_pressed);
+                self.ensure_cursor_in_view(editor_content_area, line_numbers_enabled, gutter_width);
+                false
+            }
+            _ => false,
+        }
+    }
+
+    fn handle_key_normal_mode(&mut self, key_event: KeyEvent, editor_content_area: Rect) -> bool {
+        let line_numbers_enabled = self.config.main_section.line_numbers.enabled;
+        let gutter_width = self.config.main_section.line_numbers.gutter_width;
+        let editor_visible_height = editor_content_area.height.saturating_sub(2) as usize;
+
+        let shift_pressed = key_event.modifiers.contains(KeyModifiers::SHIFT);
+        if !shift_pressed && !self.sticky_selection && self.selection.is_some() {
+            self.clear_selection();
+        }
+
+        let control_pressed = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        let prefix = self.pending_normal_key.take();
+        // A leading 0 is the start-of-line motion rather than part of a count.
+        if prefix.is_none() && let KeyCode::Char(digit @ '0'..='9') = key_event.code
+            && (digit != '0' || self.pending_count.is_some()) {
+            let value = digit.to_digit(10).unwrap_or(0) as usize;
+            self.pending_count = Some(self.pending_count.unwrap_or(0).saturating_mul(10).saturating_add(value));
+            return false;
+        }
+        if prefix.is_none() && self.pending_operator.is_none() && key_event.code == KeyCode::Char('q')
+            && let Some((register, mut keys)) = self.recording.take() {
+            // The `q` that ends the recording was recorded too.
+            keys.pop();
+            self.status_message = match macros::save(register, &keys) {
+                Ok(_) => format!("Recorded {} keys into register {}", keys.len(), register),
+                Err(e) => format!("Can't save register {}: {}", register, e),
+            };
+            return false;
+        }
+        if prefix.is_none() && !control_pressed && let KeyCode::Char(c @ ('g' | 'f' | 't' | 'F' | 'T' | 'q' | '@')) = key_event.code {
+            self.pending_normal_key = Some(c);
+            return false;
+        }
+        let count = self.pending_count.take();
+
+        if let Some(operator) = self.pending_operator.take() {
+            // Counts on both sides multiply, as `2d3w` deletes six words.
+            let count = match (self.operator_count.take(), count) {
+                (None, None) => None,
+                (before, after) => Some(before.unwrap_or(1).saturating_mul(after.unwrap_or(1))),
+            };
+            let last_line = self.buffer.len() - 1;
+            match key_event.code {
+                // Doubling the operator (`dd`, `cc`, `yy`) acts on the whole line.
+                KeyCode::Char(c) if prefix.is_none() && c == operator.key() => {
+                    let last = self.cursor_y.saturating_add(count.unwrap_or(1) - 1).min(last_line);
+                    self.apply_operator_to_lines(operator, self.cursor_y, last, editor_content_area);
+                }
+                KeyCode::Char('j') | KeyCode::Down if prefix.is_none() && self.cursor_y < last_line => {
+                    let last = self.cursor_y.saturating_add(count.unwrap_or(1)).min(last_line);
+                    self.apply_operator_to_lines(operator, self.cursor_y, last, editor_content_area);
+                }
+                KeyCode::Char('k') | KeyCode::Up if prefix.is_none() && self.cursor_y > 0 => {
+                    let first = self.cursor_y.saturating_sub(count.unwrap_or(1));
+                    self.apply_operator_to_lines(operator, first, self.cursor_y, editor_content_area);
+                }
+                // `gg` and `G` take whole lines to the first or last line, or line `count`.
+                KeyCode::Char(c @ ('g' | 'G')) if (prefix == Some('g') && c == 'g') || (prefix.is_none() && c == 'G') => {
+                    let default = if c == 'g' { 1 } else { self.buffer.len() };
+                    let line = count.unwrap_or(default).clamp(1, self.buffer.len()) - 1;
+                    self.apply_operator_to_lines(operator, self.cursor_y.min(line), self.cursor_y.max(line), editor_content_area);
+                }
+                KeyCode::Char(c @ ('/' | '?')) if prefix.is_none() => {
+                    self.pending_operator = Some(operator);
+                    self.operator_count = count;
+                    self.open_search_prompt(c);
+                }
+                code => {
+                    if let Some(target) = self.counted_motion_target(operator, prefix, code, count.unwrap_or(1)) {
+                        self.apply_operator(operator, target, editor_content_area);
+                    }
+                }
+            }
+            return false;
+        }
+
+        if prefix == Some('q') {
+            match key_event.code {
+                KeyCode::Char(register) if macros::is_register(register) => {
+                    self.recording = Some((register, Vec::new()));
+                    self.status_message = format!("recording @{}", register);
+                }
+                _ => self.status_message = "Registers are a-z and 0-9".to_string(),
+            }
+            return false;
+        }
+        if prefix == Some('@') {
+            let register = match key_event.code {
+                KeyCode::Char('@') => self.last_macro,
+                KeyCode::Char(register) if macros::is_register(register) => Some(register),
+                _ => None,
+            };
+            return match register {
+                Some(register) => self.run_macro(register, count.unwrap_or(1), editor_content_area),
+                None => {
+                    self.status_message = "No macro to run".to_string();
+                    false
+                }
+            };
+        }
+        if prefix == Some('g') && key_event.code == KeyCode::Char('g') {
+            self.go_to_line(count.unwrap_or(1), editor_content_area);
+            return false;
+        }
+        if prefix == Some('g') && let KeyCode::Char(c @ (';' | ',')) = key_event.code {
+            self.go_to_change(c == ';', count.unwrap_or(1), editor_content_area);
+            return false;
+        }
+        if prefix == Some('g') && let KeyCode::Char(c @ ('x' | 'f')) = key_event.code {
+            self.open_link(c == 'x', c == 'f');
+            return false;
+        }
+        if prefix.is_some() {
+            if let Some((target, _)) = self.motion_target(prefix, key_event.code) {
+                self.move_cursor_to(target, editor_content_area, false);
+            }
+            return false;
+        }
+
+        match key_event.code {
+            KeyCode::Char(c) if key_event.modifiers.contains(KeyModifiers::ALT) && let Some(chord) = AltChord::for_key(c, &InputMode::Normal) => {
+                self.run_alt_chord(chord, count.unwrap_or(1), editor_content_area)
+            }
+            KeyCode::Char(c) if !control_pressed && let Some(operator) = Operator::from_key(c) => {
+                self.pending_operator = Some(operator);
+                self.operator_count = count;
+                false
+            }
+            KeyCode::Char('G') => {
+                self.go_to_line(count.unwrap_or(self.buffer.len()), editor_content_area);
+                false
+            }
+            // H and L count lines in from the top and bottom of the screen, as in vim.
+            KeyCode::Char(c @ ('H' | 'M' | 'L')) => {
+                let first = self.scroll_y;
+                let last = (self.scroll_y + editor_visible_height.max(1)).min(self.buffer.len()) - 1;
+                let offset = count.unwrap_or(1) - 1;
+                let row = match c {
+                    'H' => (first + offset).min(last),
+                    'L' => last.saturating_sub(offset).max(first),
+                    _ => first + (last - first) / 2,
+                };
+                self.go_to_line(row + 1, editor_content_area);
+                false
+            }
+            KeyCode::Char('e' | 'E' | 'W' | 'B' | '{' | '}' | '(' | ')' | ';' | ',') => {
+                if let Some((target, _)) = self.motion_target(None, key_event.code) {
+                    self.move_cursor_to(target, editor_content_area, false);
+                }
+                false
+            }
+            KeyCode::Char(':') => {
+                self.open_command_line();
+                false
+            }
+            KeyCode::Char(c @ ('/' | '?')) => {
+                self.open_search_prompt(c);
+                false
+            }
+            KeyCode::Char(c @ ('n' | 'N')) => {
+                self.repeat_search(c == 'N');
+                false
+            }
+            KeyCode::Char('i') => {
+                self.input_mode = InputMode::Insert;
+                self.status_message = "-- INSERT --".to_string();
+                false
+            }
+            KeyCode::Char('a') => {
+                self.cursor_x += self.buffer[self.cursor_y][self.cursor_x..].chars().next().map_or(0, char::len_utf8);
+                self.input_mode = InputMode::Insert;
+                self.status_message = "-- INSERT --".to_string();
+                false
+            }
+            KeyCode::Char('o') => {
+                self.cursor_x = self.buffer[self.cursor_y].len();
+                self.insert_newline(editor_content_area);
+                self.input_mode = InputMode::Insert;
+                self.status_message = "-- INSERT --".to_string();
+                false
+            }
+            KeyCode::Char('O') => {
+                self.cursor_x = 0;
+                self.insert_newline(editor_content_area);
+                self.cursor_y = self.cursor_y.saturating_sub(1);
+                self.cursor_x = 0;
+                self.input_mode = InputMode::Insert;
+                self.status_message = "-- INSERT --".to_string();
+                false
+            }
+
+            KeyCode::Char('h') | KeyCode::Left => { self.move_cursor_left(editor_content_area, shift_pressed); false }
+            KeyCode::Char('j') | KeyCode::Down => { self.move_cursor_down(editor_content_area, shift_pressed); false }
+            KeyCode::Char('k') | KeyCode::Up => { self.move_cursor_up(editor_content_area, shift_pressed); false }
+            KeyCode::Char('l') | KeyCode::Right => { self.move_cursor_right(editor_content_area, shift_pressed); false }
+
+            KeyCode::Char('b') => { self.move_cursor_word_left(editor_content_area, shift_pressed); false }
+            KeyCode::Char('w') => { self.move_cursor_word_right(editor_content_area, shift_pressed); false }
+
+            KeyCode::Char('0') => { self.cursor_x = 0; self.ensure_cursor_in_view(editor_content_area, line_numbers_enabled, gutter_width); false }
+            KeyCode::Char('$') => { if self.cursor_y < self.buffer.len() { self.cursor_x = self.buffer[self.cursor_y].len(); } else { self.cursor_x = 0; } self.want_line_end(); self.ensure_cursor_in_view(editor_content_area, line_numbers_enabled, gutter_width); false }
+
+            KeyCode::Char('x') => { self.delete_char_forward(editor_content_area); false }
+            KeyCode::Char('u') if !key_event.modifiers.contains(KeyModifiers::CONTROL) => { self.undo(false, editor_content_area); false }
+            KeyCode::Char('r') if key_event.modifiers.contains(KeyModifiers::CONTROL) => { self.undo(true, editor_content_area); false }
+
+            KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => { self.copy_selection(); false }
+            KeyCode::Char('u') if key_event.modifiers.contains(KeyModifiers::CONTROL) => { self.cut_selection(editor_content_area); false }
+            KeyCode::Char('v') if key_event.modifiers.contains(KeyModifiers::CONTROL) => { self.paste(false, editor_content_area); false }
+
+            KeyCode::Esc => {
+                self.clear_selection();
+                let line = &self.buffer[self.cursor_y];
+                if self.cursor_x > 0 && self.cursor_x == line.len() {
+                    self.cursor_x -= line.chars().next_back().map_or(0, char::len_utf8);
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+
+    fn handle_key_input(&mut self, key_event: KeyEvent, editor_content_area: Rect) -> bool {
+        if self.macro_depth == 0 && let Some((_, keys)) = self.recording.as_mut() {
+            keys.push(key_event);
+        }
+        let key_event = if keymap::is_mac(&self.config.editor_behavior.keymap) { keymap::translate(key_event) } else { key_event };
+        let is_text = matches!(key_event.code, KeyCode::Char(_))
+            && !key_event.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        // Typed text stays out of the log, as it does out of crash reports.
+        if !is_text {
+            log::debug!("key {:?} {:?}", key_event.code, key_event.modifiers);
+            self.remember_action(format!("{:?} {:?}", key_event.code, key_event.modifiers));
+        }
+
+        let double_escape = key_event.code == KeyCode::Esc && self.last_key_was_escape;
+        self.last_key_was_escape = key_event.code == KeyCode::Esc && !double_escape;
+        if double_escape && self.application_mode == ApplicationMode::Editing {
+            self.clear_search_highlight();
+            self.toasts.clear();
+        }
+
+        if key_event.code == KeyCode::Esc && !self.running_tasks.is_empty() {
+            self.cancel_running_tasks();
+            return false;
+        }
+        self.cancel_tasks_superseded_by_input();
+        // A key can change the lines a drag started in the gutter selects from.
+        self.gutter_drag = None;
+        // Edits made by the previous key are recorded before this one moves the cursor.
+        self.follow_changes();
+        let typed = self.application_mode == ApplicationMode::Editing
+            && (!self.vim_enabled || self.input_mode == InputMode::Insert)
+            && !key_event.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+            && matches!(key_event.code, KeyCode::Char(c) if c.is_alphanumeric() || c == '_');
+        if !(typed && self.last_key_typed) {
+            self.undo_history.seal(UNDO_LEVELS);
+        }
+        self.last_key_typed = typed;
+        self.cursor_before_key = (self.cursor_y, self.cursor_x);
+
+        if self.loading_file.is_some() {
+            let is_quit = key_event.modifiers.contains(KeyModifiers::CONTROL)
+                && matches!(key_event.code, KeyCode::Char('q') | KeyCode::Char('x'));
+            if !is_quit {
+                return false;
+            }
+        }
+
+        if self.view_only && self.application_mode == ApplicationMode::Editing {
+            return self.handle_key_view_mode(key_event, editor_content_area);
+        }
+        if self.application_mode == ApplicationMode::Editing && let Some(line) = self.binding_for(key_event) {
+            log::info!("key runs :{}", line);
+            return self.run_command_line(&line, 0);
+        }
+
+        let should_exit = match key_event.code {
+            KeyCode::Char('x') if key_event.modifiers.contains(KeyModifiers::CONTROL) && self.application_mode == ApplicationMode::Editing => {
+                if self.selection.is_some() {
+                    self.cut_selection(editor_content_area);
+                    false
+                } else if self.is_dirty() {
+                    self.application_mode = ApplicationMode::PromptSave;
+                    self.prompt_message = format!("{}Save modified buffer? (Y/N)", self.unsaved_warning());
+                    false
+                } else {
+                    true
+                }
+            }
+            KeyCode::Char('w') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                if self.application_mode == ApplicationMode::Editing && let Err(e) = self.save_file() {
+                    log::error!("error saving: {}", e);
+                    self.notify(toasts::Severity::Error, format!("Error saving: {}", e));
+                }
+                false
+            }
+            KeyCode::Char('q') if key_event.modifiers.contains(KeyModifiers::CONTROL) && self.application_mode == ApplicationMode::Editing => {
+                if self.is_dirty() {
+                    self.application_mode = ApplicationMode::PromptSave;
+                    self.prompt_message = format!("{}Quit without saving? (Y/N)", self.unsaved_warning());
+                    false
+                } else {
+                    true
+                }
+            }
+            // Vim users reach the command line with `:`, so Ctrl+T pops the tag stack as in vim.
+            KeyCode::Char('t') if key_event.modifiers.contains(KeyModifiers::CONTROL) && self.application_mode == ApplicationMode::Editing
+                && self.vim_enabled && self.input_mode == InputMode::Normal => {
+                if let Err(e) = self.pop_tag() {
+                    self.status_message = e;
+                }
+                return false;
+            }
+            KeyCode::Char('t') if key_event.modifiers.contains(KeyModifiers::CONTROL) && self.application_mode == ApplicationMode::Editing => {
+                self.open_command_line();
+                false
+            }
+            KeyCode::Char('f') if key_event.modifiers.contains(KeyModifiers::CONTROL) && self.application_mode == ApplicationMode::Editing => {
+                self.open_search_prompt('/');
+                return false;
+            }
+            KeyCode::Up | KeyCode::Down if key_event.modifiers == KeyModifiers::ALT && self.application_mode == ApplicationMode::Editing => {
+                if key_event.code == KeyCode::Up {
+                    self.expand_selection(editor_content_area);
+                } else {
+                    self.shrink_selection(editor_content_area);
+                }
+                return false;
+            }
+            KeyCode::F(3) if self.application_mode == ApplicationMode::Editing => {
+                self.search_for(String::new(), !key_event.modifiers.contains(KeyModifiers::SHIFT));
+                false
+            }
+            KeyCode::F(2) if self.application_mode == ApplicationMode::Editing => {
+                self.open_settings();
+                false
+            }
+            KeyCode::F(12) => {
+                self.debug_overlay = !self.debug_overlay;
+                false
+            }
+            KeyCode::F(8) if self.application_mode == ApplicationMode::Editing => {
+                self.go_to_error();
+                false
+            }
+            KeyCode::Char('p') if key_event.modifiers.contains(KeyModifiers::CONTROL) && self.application_mode == ApplicationMode::Editing => {
+                self.open_finder();
+                return false;
+            }
+            // Terminals report Ctrl+] as Ctrl+5.
+            KeyCode::Char(']') | KeyCode::Char('5') if key_event.modifiers.contains(KeyModifiers::CONTROL) && self.application_mode == ApplicationMode::Editing => {
+                let result = match self.word_under_cursor() {
+                    Some(word) => self.jump_to_tag(&word),
+                    None => Err("No identifier under cursor".to_string()),
+                };
+                if let Err(e) = result {
+                    self.status_message = e;
+                }
+                return false;
+            }
+            KeyCode::Char('h') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                if self.application_mode == ApplicationMode::Editing {
+                    self.application_mode = ApplicationMode::Help;
+                    if self.vim_enabled {
+                        self.status_message = "-- HELP --".to_string();
+                    }
+                }
+                false
+            }
+            _ => false,
+        };
+
+        if should_exit { return true; }
+
+        match self.application_mode {
+            ApplicationMode::Editing if self.vim_enabled => self.handle_key_vim(key_event, editor_content_area),
+            ApplicationMode::Editing => self.handle_key_editing_mode(key_event, editor_content_area),
+            ApplicationMode::Help => self.handle_key_help_mode(key_event),
+            ApplicationMode::PromptSave => self.handle_key_prompt_save_mode(key_event),
+            ApplicationMode::Confirm => self.handle_key_confirm_mode(key_event),
+            ApplicationMode::Settings => self.handle_key_settings_mode(key_event),
+            ApplicationMode::Command => self.handle_key_command_mode(key_event),
+            ApplicationMode::Finder => self.handle_key_finder_mode(key_event),
+            ApplicationMode::Todos => self.handle_key_todos_mode(key_event),
+            ApplicationMode::Timeline => self.handle_key_timeline_mode(key_event),
+            ApplicationMode::RegexTester => self.handle_key_regex_tester_mode(key_event),
+            ApplicationMode::Theme => self.handle_key_theme_mode(key_event),
+            ApplicationMode::Diff => self.handle_key_diff_mode(key_event),
+        }
+    }
+
+    /// Runs a key in vim mode, recording each command so `.` can repeat the last change.
+    fn handle_key_vim(&mut self, key_event: KeyEvent, editor_content_area: Rect) -> bool {
+        let idle = self.input_mode == InputMode::Normal && self.change_keys.is_empty();
+        if idle && key_event.code == KeyCode::Char('.') && key_event.modifiers.is_empty() {
+            self.repeat_last_change(editor_content_area);
+            return false;
+        }
+        if idle {
+            self.change_start_revision = self.buffer.revision();
+        }
+        self.change_keys.push(key_event);
+
+        let should_exit = self.handle_key_editing_mode(key_event, editor_content_area);
+        if self.input_mode == InputMode::Normal {
+            self.clamp_cursor_to_line();
+        }
+        let finished = self.input_mode == InputMode::Normal && self.pending_normal_key.is_none()
+            && self.pending_operator.is_none() && self.pending_count.is_none();
+        if finished {
+            self.finish_change();
+        }
+        should_exit
+    }
+
+    fn finish_change(&mut self) {
+        let keys = std::mem::take(&mut self.change_keys);
+        if self.buffer.revision() != self.change_start_revision {
+            self.last_change = keys;
+        }
+    }
+
+    fn handle_key_editing_mode(&mut self, key_event: KeyEvent, editor_content_area: Rect) -> bool {
+        match self.input_mode {
+            InputMode::Insert => self.handle_key_insert_mode(key_event, editor_content_area),
+            InputMode::Normal => self.handle_key_normal_mode(key_event, editor_content_area),
+        }
+    }
+
+    fn repeat_last_change(&mut self, editor_content_area: Rect) {
+        if self.last_change.is_empty() {
+            self.status_message = "No change to repeat".to_string();
+            return;
+        }
+        let keys = self.last_change.clone();
+        for &key_event in &keys {
+            match self.application_mode {
+                ApplicationMode::Editing => self.handle_key_editing_mode(key_event, editor_content_area),
+                ApplicationMode::Command => self.handle_key_command_mode(key_event),
+                _ => break,
+            };
+        }
+        // Replaying a search operator records it again; keep the original.
+        self.change_keys.clear();
+        self.last_change = keys;
+    }
+
+    /// Runs the keys in `register` `count` times, as if typed. Returns whether they quit.
+    fn run_macro(&mut self, register: char, count: usize, editor_content_area: Rect) -> bool {
+        if self.macro_depth >= MACRO_DEPTH_LIMIT {
+            self.status_message = format!("Macros nested more than {} deep", MACRO_DEPTH_LIMIT);
+            return false;
+        }
+        let keys = match macros::load(register) {
+            Ok(keys) => keys,
+            Err(e) => {
+                self.status_message = e;
+                return false;
+            }
+        };
+        self.last_macro = Some(register);
+        // The keys run as commands of their own, so `.` repeats the last change they made.
+        self.change_keys.clear();
+        self.macro_depth += 1;
+        let mut should_exit = false;
+        'runs: for _ in 0..count {
+            for &key_event in &keys {
+                if self.handle_key_input(key_event, editor_content_area) {
+                    should_exit = true;
+                    break 'runs;
+                }
+            }
+        }
+        self.macro_depth -= 1;
+        self.change_keys.clear();
+        self.change_start_revision = self.buffer.revision();
+        should_exit
+    }
+
+    fn handle_key_help_mode(&mut self, key_event: KeyEvent) -> bool {
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Char('h') | KeyCode::Enter => {
+                self.application_mode = ApplicationMode::Editing;
+                self.status_message = self.default_status_message();
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn handle_key_prompt_save_mode(&mut self, key_event: KeyEvent) -> bool {
+        match key_event.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                self.application_mode = ApplicationMode::Editing;
+                self.save_and_quit()
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') => true,
+            KeyCode::Esc => {
+                self.application_mode = ApplicationMode::Editing;
+                self.status_message = self.default_status_message();
+                false
+            }
+            _ => false,
+        }
+    }
+
+    fn confirm(&mut self, question: String, confirmation: Confirmation) {
+        self.prompt_message = format!("{} (Y/N)", question);
+        self.pending_confirmation = Some(confirmation);
+        self.application_mode = ApplicationMode::Confirm;
+    }
+
+    fn handle_key_confirm_mode(&mut self, key_event: KeyEvent) -> bool {
+        let confirmed = match key_event.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => true,
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => false,
+            _ => return false,
+        };
+        self.application_mode = ApplicationMode::Editing;
+        self.prompt_message.clear();
+        match self.pending_confirmation.take() {
+            Some(confirmation) if confirmed => self.carry_out(confirmation),
+            Some(Confirmation::OpenDropped { text, .. }) => self.insert_text_at_cursor(&text, self.view_area),
+            Some(Confirmation::PlainLongLines) => self.status_message = "Long lines keep their highlighting".to_string(),
+            _ => self.status_message = "Cancelled".to_string(),
+        }
+        false
+    }
+
+    fn carry_out(&mut self, confirmation: Confirmation) {
+        let result = match confirmation {
+            Confirmation::SaveAs(path) => self.save_as(path),
+            Confirmation::Rename(path) => self.rename_file(path),
+            Confirmation::DeleteFile => self.delete_file(),
+            Confirmation::CreateDirectories(path) => match Path::new(&path).parent() {
+                Some(dir) => fs::create_dir_all(dir)
+                    .map_err(|e| format!("Error creating {}: {}", dir.display(), e))
+                    .and_then(|_| self.save_as(path)),
+                None => self.save_as(path),
+            },
+            Confirmation::OpenDropped { path, .. } => {
+                self.open_file(&path);
+                Ok(())
+            }
+            Confirmation::PlainLongLines => {
+                self.plain_long_lines = true;
+                self.status_message = "Long lines are drawn plain".to_string();
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            log::error!("{}", e);
+            self.status_message = e;
+        }
+    }
+
+    /// Text the terminal pasted in one piece (bracketed paste), which goes in as typed
+    /// text would rather than key by key.
+    fn handle_paste(&mut self, text: String) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        match self.application_mode {
+            ApplicationMode::Editing => {
+                self.end_undo_step();
+                let empty_and_unnamed = self.filename.is_none() && self.buffer.len() == 1 && self.buffer[0].is_empty();
+                if self.config.editor_behavior.open_dropped_files
+                    && empty_and_unnamed
+                    && let Some(path) = input::dropped_path(&text)
+                    && Path::new(&path).is_file()
+                {
+                    self.confirm(format!("Open {}?", path), Confirmation::OpenDropped { path, text });
+                } else {
+                    // A bracketed paste is in paste mode by itself: the text goes in as is.
+                    self.insert_text_at_cursor(&text, self.view_area);
+                }
+            }
+            ApplicationMode::Command => self.command_line.push_str(text.lines().next().unwrap_or_default()),
+            _ => {}
+        }
+    }
+
+    fn load_annotations(&mut self, path: &str) {
+        self.annotations = match annotations::Annotations::load(path) {
+            Ok(annotations) => Some(annotations),
+            Err(e) => {
+                log::warn!("could not read notes for {}: {}", path, e);
+                None
+            }
+        };
+    }
+
+    /// `:note text` on the current line, or every selected line; `:note` alone removes it.
+    fn annotate(&mut self, text: String) {
+        let (start, end) = match self.selection {
+            Some(selection) => {
+                let (first, last) = selection.ordered();
+                (first.0, last.0)
+            }
+            None => (self.cursor_y, self.cursor_y),
+        };
+        let Some(annotations) = self.annotations.as_mut() else {
+            self.status_message = "Notes are kept per file; save the buffer first".to_string();
+            return;
+        };
+        self.status_message = if text.is_empty() {
+            if !annotations.remove(self.cursor_y) {
+                self.status_message = "No note on this line".to_string();
+                return;
+            }
+            "Note removed".to_string()
+        } else if start == end {
+            annotations.set(start, end, text);
+            format!("Note added to line {}", start + 1)
+        } else {
+            annotations.set(start, end, text);
+            format!("Note added to lines {}-{}", start + 1, end + 1)
+        };
+        if let Err(e) = annotations.save() {
+            self.status_message = format!("Could not save notes: {}", e);
+        }
+    }
+
+    /// Points the buffer at `path`, picking the filetype again for the new name.
+    fn set_filename(&mut self, path: String) {
+        self.syntax = filetype::detect(&path, &self.buffer);
+        self.load_annotations(&path);
+        self.filename = Some(path);
+    }
+
+    fn save_as(&mut self, path: String) -> Result<(), String> {
+        let previous = self.filename.replace(path.clone());
+        match self.save_file() {
+            // Creating the directory saves as `path` again.
+            Ok(SaveOutcome::Confirming) => self.filename = previous,
+            Ok(_) => self.set_filename(path),
+            Err(e) => {
+                self.filename = previous;
+                return Err(format!("Error saving: {}", e));
+            }
+        }
+        Ok(())
+    }
+
+    fn rename_file(&mut self, path: String) -> Result<(), String> {
+        let Some(from) = self.filename.clone() else {
+            return Err("No file to rename (use :saveas)".to_string());
+        };
+        fs::rename(&from, &path).map_err(|e| format!("Error renaming {}: {}", from, e))?;
+        log::info!("renamed {} to {}", from, path);
+        self.status_message = format!("Renamed {} to {}", from, path);
+        self.set_filename(path);
+        Ok(())
+    }
+
+    /// Removes the file but keeps the buffer, so `:w` can still bring it back.
+    fn delete_file(&mut self) -> Result<(), String> {
+        let Some(path) = self.filename.clone() else {
+            return Err("No file to delete".to_string());
+        };
+        fs::remove_file(&path).map_err(|e| format!("Error deleting {}: {}", path, e))?;
+        log::info!("deleted {}", path);
+        self.status_message = format!("Deleted {} (:w writes it back)", path);
+        Ok(())
+    }
+
+    fn open_command_line(&mut self) {
+        self.command_line.clear();
+        self.command_prompt = ':';
+        self.application_mode = ApplicationMode::Command;
+    }
+
+    fn open_search_prompt(&mut self, prompt: char) {
+        self.command_line.clear();
+        self.command_prompt = prompt;
+        self.application_mode = ApplicationMode::Command;
+    }
+
+    /// The pattern to search for, with an empty one standing for the last search, and the
+    /// direction to remember it with: a repeat keeps that of the search it repeats.
+    fn search_pattern(&mut self, pattern: String, forward: bool) -> Option<(String, bool)> {
+        match (pattern.is_empty(), &self.search) {
+            (true, Some(previous)) => Some((previous.pattern.clone(), previous.forward)),
+            (true, None) => {
+                self.status_message = "No previous search pattern".to_string();
+                None
+            }
+            (false, _) => Some((pattern, forward)),
+        }
+    }
+
+    /// Moves to the next match of `pattern` after (or before) the cursor; an empty pattern
+    /// repeats the last search.
+    fn search_for(&mut self, pattern: String, forward: bool) {
+        let Some((pattern, direction)) = self.search_pattern(pattern, forward) else {
+            return;
+        };
+        let matcher = match self.search_matcher(&pattern) {
+            Ok(matcher) => matcher,
+            Err(e) => {
+                self.status_message = e;
+                return;
+            }
+        };
+        match search::find(&self.buffer, (self.cursor_y, self.cursor_x), &matcher, forward) {
+            Some((y, x)) => {
+                self.status_message = format!("{}{}", if forward { '/' } else { '?' }, pattern);
+                self.apply_jump(Jump::Cursor(y, x));
+                if self.view_only {
+                    // As in less, the matching line goes to the top.
+                    self.scroll_y = y;
+                    self.scroll_view(0, self.view_area);
+                }
+            }
+            None => self.status_message = format!("Pattern not found: {}", pattern),
+        }
+        self.search = Some(search::Search::new(pattern, direction));
+    }
+
+    /// How `pattern` is matched under the current `smartcase` and `regex_search` settings.
+    fn search_matcher(&self, pattern: &str) -> Result<search::Matcher, String> {
+        search::Matcher::new(pattern, self.config.editor_behavior.smartcase, self.config.editor_behavior.regex_search)
+    }
+
+    /// `n`, or `N` with `reverse`: the last search again, in its direction or the other one.
+    fn repeat_search(&mut self, reverse: bool) {
+        let forward = self.search.as_ref().is_none_or(|search| search.forward) != reverse;
+        self.search_for(String::new(), forward);
+    }
+
+    /// `d/pattern`, `y?pattern` and such: the operator runs up to the `count`th match,
+    /// exclusively.
+    fn apply_operator_to_search(&mut self, operator: Operator, pattern: String, forward: bool, count: usize) {
+        let target = self.search_pattern(pattern, forward).and_then(|(pattern, direction)| {
+            let matcher = self.search_matcher(&pattern).map_err(|e| self.status_message = e).ok()?;
+            let first = search::find(&self.buffer, (self.cursor_y, self.cursor_x), &matcher, forward);
+            let mut target = first;
+            // Searches wrap, so coming back to the first match means there are no more.
+            for _ in 1..count {
+                target = target.and_then(|at| search::find(&self.buffer, at, &matcher, forward));
+                if target == first {
+                    break;
+                }
+            }
+            if target.is_none() {
+                self.status_message = format!("Pattern not found: {}", pattern);
+            }
+            self.search = Some(search::Search::new(pattern, direction));
+            target
+        });
+        match target {
+            Some(target) => {
+                self.apply_operator(operator, (target, false), self.view_area);
+                self.finish_change();
+            }
+            None => self.change_keys.clear(),
+        }
+    }
+
+    /// `:s`: replaces matches of `pattern` on the current line, the selected lines or with
+    /// `whole` every line, and leaves the matches highlighted as a search.
+    fn substitute(&mut self, whole: bool, pattern: String, replacement: &str, global: bool) {
+        let Some((pattern, forward)) = self.search_pattern(pattern, true) else {
+            return;
+        };
+        let matcher = match self.search_matcher(&pattern) {
+            Ok(matcher) => matcher,
+            Err(e) => {
+                self.status_message = e;
+                return;
+            }
+        };
+        let rows = match self.selection {
+            _ if whole => 0..self.buffer.len(),
+            Some(selection) => {
+                let (first, last) = selection.ordered();
+                first.0..last.0 + 1
+            }
+            None => self.cursor_y..self.cursor_y + 1,
+        };
+        let mut lines: Vec<String> = Vec::with_capacity(self.buffer.len());
+        let (mut count, mut changed_lines) = (0, 0);
+        for (row, line) in self.buffer.iter().enumerate() {
+            if rows.contains(&row) && let Some((replaced, replacements)) = matcher.replace(line, replacement, global) {
+                count += replacements;
+                changed_lines += 1;
+                // A `\n` in the replacement splits the line.
+                lines.extend(replaced.split('\n').map(str::to_string));
+            } else {
+                lines.push(line.clone());
+            }
+        }
+        self.search = Some(search::Search::new(pattern.clone(), forward));
+        if count == 0 {
+            self.status_message = format!("Pattern not found: {}", pattern);
+            return;
+        }
+        self.replace_lines(lines);
+        self.status_message = format!(
+            "{} substitution{} on {} line{}",
+            count, if count == 1 { "" } else { "s" }, changed_lines, if changed_lines == 1 { "" } else { "s" }
+        );
+    }
+
+    fn clear_search_highlight(&mut self) {
+        if let Some(search) = self.search.as_mut() {
+            search.highlighted = false;
+        }
+    }
+
+    fn handle_key_command_mode(&mut self, key_event: KeyEvent) -> bool {
+        // A search typed after an operator is part of the change `.` repeats.
+        if self.pending_operator.is_some() {
+            self.change_keys.push(key_event);
+        }
+        if !matches!(key_event.code, KeyCode::Tab | KeyCode::BackTab) {
+            self.completion = None;
+        }
+        if !matches!(key_event.code, KeyCode::Up | KeyCode::Down) {
+            self.recall = None;
+        }
+        let namespace = if self.command_prompt == ':' { history::COMMANDS } else { history::SEARCHES };
+        match key_event.code {
+            KeyCode::Tab | KeyCode::BackTab if self.command_prompt == ':' => {
+                self.complete_command_line(key_event.code == KeyCode::BackTab);
+            }
+            KeyCode::Up | KeyCode::Down => {
+                let recall = self.recall.get_or_insert_with(|| history::Recall::new(&self.history, namespace, &self.command_line));
+                if let Some(line) = recall.step(&self.history, key_event.code == KeyCode::Up) {
+                    self.command_line = line;
+                }
+            }
+            KeyCode::Enter => {
+                self.application_mode = ApplicationMode::Editing;
+                let line = std::mem::take(&mut self.command_line);
+                self.remember(namespace, &line);
+                if self.command_prompt != ':' {
+                    let forward = self.command_prompt == '/';
+                    match self.pending_operator.take() {
+                        Some(operator) => {
+                            let count = self.operator_count.take().unwrap_or(1);
+                            self.apply_operator_to_search(operator, line, forward, count);
+                        }
+                        None => self.search_for(line, forward),
+                    }
+                    return false;
+                }
+                log::info!("command :{}", line);
+                return self.run_command_line(&line, 0);
+            }
+            KeyCode::Esc => self.close_command_line(),
+            KeyCode::Backspace if self.command_line.is_empty() => self.close_command_line(),
+            KeyCode::Backspace => {
+                self.command_line.pop();
+            }
+            KeyCode::Char(c) if !key_event.modifiers.contains(KeyModifiers::CONTROL) => self.command_line.push(c),
+            _ => {}
+        }
+        false
+    }
+
+    /// Completes the file name being typed after `:w` and friends: a single match fills in
+    /// outright, several fill in what they share and then Tab cycles through them.
+    fn complete_command_line(&mut self, backward: bool) {
+        let mut completion = match self.completion.take() {
+            Some(completion) => completion,
+            None => {
+                let Some(start) = command::path_argument_start(&self.command_line) else {
+                    return;
+                };
+                let candidates = complete::paths(&self.command_line[start..]);
+                let prefix = complete::common_prefix(&candidates).to_string();
+                if candidates.is_empty() {
+                    self.status_message = "No matching files".to_string();
+                    return;
+                }
+                let typed = self.command_line.len() - start;
+                self.command_line.truncate(start);
+                self.command_line.push_str(&prefix);
+                if candidates.len() == 1 {
+                    return;
+                }
+                let completion = complete::Completion::new(start, candidates);
+                if prefix.len() > typed {
+                    self.status_message = completion.summary();
+                    self.completion = Some(completion);
+                    return;
+                }
+                completion
+            }
+        };
+        let candidate = completion.step(backward).to_string();
+        self.command_line.truncate(completion.start);
+        self.command_line.push_str(&candidate);
+        self.status_message = completion.summary();
+        self.completion = Some(completion);
+    }
+
+    /// Adds `entry` to the history of a prompt, which is saved for later sessions.
+    fn remember(&mut self, namespace: &str, entry: &str) {
+        if let Err(e) = self.history.record(namespace, entry) {
+            log::warn!("error saving prompt history: {}", e);
+        }
+    }
+
+    fn close_command_line(&mut self) {
+        self.application_mode = ApplicationMode::Editing;
+        if self.pending_operator.take().is_some() {
+            self.change_keys.clear();
+        }
+    }
+
+    /// Runs a line as typed at `:`, which may name one of the user's `[commands]`.
+    fn run_command_line(&mut self, line: &str, depth: usize) -> bool {
+        // Only the command's name, since its arguments may quote the buffer.
+        let name: String = line.trim().trim_start_matches(':').chars()
+            .take_while(|c| c.is_alphanumeric() || matches!(c, '%' | '!' | '_' | '-'))
+            .collect();
+        self.remember_action(format!(":{}", name));
+        if let Some(steps) = self.config.commands.get(line.trim().trim_start_matches(':')).cloned() {
+            if depth >= COMMAND_DEPTH_LIMIT {
+                self.status_message = format!("Commands nested more than {} deep", COMMAND_DEPTH_LIMIT);
+                return false;
+            }
+            return self.run_steps(steps, depth + 1);
+        }
+        match command::parse(line) {
+            Ok(command) => self.execute_command(command),
+            Err(e) => {
+                self.status_message = e;
+                false
+            }
+        }
+    }
+
+    /// Runs a user command's steps in order. A shell command runs in the background, and
+    /// the steps after it only once it has succeeded.
+    fn run_steps(&mut self, steps: Vec<String>, depth: usize) -> bool {
+        let mut steps = steps.into_iter();
+        while let Some(step) = steps.next() {
+            if let Some(shell) = step.trim().trim_start_matches(':').strip_prefix('!') {
+                self.run_shell(shell.trim().to_string(), steps.collect(), depth);
+                return false;
+            }
+            if self.run_command_line(&step, depth) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn run_shell(&mut self, command: String, then: Vec<String>, depth: usize) {
+        let file = self.filename.clone();
+        let label = format!("!{}", command);
+        log::info!("running {}", label);
+        let id = self.tasks.spawn("shell", move |context| {
+            shell::run(&command, file.as_deref(), None, None, context).map(|finished| TaskOutput::ShellFinished { finished, then, depth })
+        });
+        self.running_tasks.push(RunningTask { id, label, done: 0, total: None, cancel_on_input: false });
+    }
+
+    /// Whether an Alt chord does anything where keys go now, which decides if the key decoder
+    /// reads Esc and a key as the chord. Vim's modes only have the ones in `AltChord`.
+    fn handles_alt_chord(&self, chord: &KeyEvent) -> bool {
+        if !self.vim_enabled {
+            return true;
+        }
+        if self.application_mode != ApplicationMode::Editing {
+            return false;
+        }
+        if self.bindings.iter().any(|(bound, _)| macros::same_key(bound, chord)) {
+            return true;
+        }
+        match chord.code {
+            KeyCode::Char(c) => AltChord::for_key(c, &self.input_mode).is_some(),
+            _ => true,
+        }
+    }
+
+    /// The command line `[keys]` binds `key` to. Plain characters are left alone where
+    /// they would be typed, and so is a key finishing a vim command.
+    fn binding_for(&self, key: KeyEvent) -> Option<String> {
+        let typing = matches!(key.code, KeyCode::Char(_)) && (key.modifiers - KeyModifiers::SHIFT).is_empty()
+            && !(self.vim_enabled && self.input_mode == InputMode::Normal);
+        let pending = self.pending_normal_key.is_some() || self.pending_operator.is_some() || self.pending_count.is_some();
+        if typing || pending {
+            return None;
+        }
+        self.bindings.iter().find(|(bound, _)| macros::same_key(bound, &key)).map(|(_, line)| line.clone())
+    }
+
+    fn execute_command(&mut self, command: command::Command) -> bool {
+        match command {
+            // As in vim, `:w path` writes a copy, unless the buffer has no file of its own yet.
+            command::Command::Write(Some(path)) if self.filename.is_some() && !self.is_current_file(&path) => {
+                if let Err(e) = self.write_copy(&path) {
+                    log::error!("error writing {}: {}", path, e);
+                    self.notify(toasts::Severity::Error, format!("Error writing {}: {}", path, e));
+                }
+                false
+            }
+            command::Command::Write(filename) => {
+                if filename.is_some() && self.filename.is_none() {
+                    self.filename = filename;
+                }
+                if let Err(e) = self.save_file() {
+                    log::error!("error saving: {}", e);
+                    self.notify(toasts::Severity::Error, format!("Error saving: {}", e));
+                }
+                false
+            }
+            command::Command::Quit { force } => {
+                if !force && self.is_dirty() {
+                    self.status_message = format!("{}No write since last change (add ! to override)", self.unsaved_warning());
+                    false
+                } else {
+                    true
+                }
+            }
+            command::Command::WriteQuit => self.save_and_quit(),
+            command::Command::Tag(name) => {
+                let result = match name.or_else(|| self.word_under_cursor()) {
+                    Some(name) => self.jump_to_tag(&name),
+                    None => Err("No tag name given".to_string()),
+                };
+                if let Err(e) = result {
+                    self.status_message = e;
+                }
+                false
+            }
+            command::Command::Retab => {
+                self.retab();
+                false
+            }
+            command::Command::GoToLine(line) => {
+                self.go_to_line(line, self.view_area);
+                false
+            }
+            command::Command::Select => {
+                self.toggle_mark();
+                false
+            }
+            command::Command::Indent(change) => {
+                if let Some(change) = change {
+                    self.change_indentation(change);
+                }
+                self.status_message = format!("Indenting with {}", self.indentation_label());
+                false
+            }
+            command::Command::Note(text) => {
+                self.annotate(text);
+                false
+            }
+            command::Command::Reload { force } => {
+                if self.is_dirty() && !force {
+                    self.status_message = "No write since last change (:e! discards it)".to_string();
+                } else {
+                    self.reload_file();
+                }
+                false
+            }
+            command::Command::Backup => {
+                self.compare_with_backup();
+                false
+            }
+            command::Command::Ignored(path) => {
+                self.status_message = self.explain_ignored(&path);
+                false
+            }
+            command::Command::Substitute { whole, pattern, replacement, global } => {
+                self.substitute(whole, pattern, &replacement, global);
+                false
+            }
+            command::Command::Timeline => {
+                self.open_timeline();
+                false
+            }
+            command::Command::RegexTester(pattern) => {
+                let pattern = pattern.or_else(|| self.search.as_ref().map(|search| search.pattern.clone())).unwrap_or_default();
+                self.regex_tester = Some(tester::RegexTester::new(pattern));
+                self.application_mode = ApplicationMode::RegexTester;
+                false
+            }
+            command::Command::Hidden { fix: false } => {
+                self.next_hidden_character();
+                false
+            }
+            command::Command::Hidden { fix: true } => {
+                self.fix_hidden_characters();
+                false
+            }
+            command::Command::Todos { project } => {
+                self.open_todos(project);
+                false
+            }
+            command::Command::Theme(Some(name)) => {
+                if let Err(e) = self.choose_theme(&name) {
+                    self.status_message = e;
+                }
+                false
+            }
+            command::Command::Theme(None) => {
+                self.preview_themes();
+                false
+            }
+            command::Command::Keymap(path) => {
+                let path = path.unwrap_or_else(|| "zepto-keys.md".to_string());
+                let mac = keymap::is_mac(&self.config.editor_behavior.keymap);
+                self.status_message = match fs::write(&path, keymap::cheat_sheet(self.vim_enabled, mac)) {
+                    Ok(()) => format!("Wrote the key list to {}", path),
+                    Err(e) => format!("Can't write {}: {}", path, e),
+                };
+                false
+            }
+            command::Command::Shell(shell) => {
+                self.run_shell(shell, Vec::new(), 0);
+                false
+            }
+            command::Command::Macro(register) => {
+                if let Err(e) = self.edit_macro(register) {
+                    self.status_message = e;
+                }
+                false
+            }
+            command::Command::Config => {
+                if let Err(e) = self.edit_config() {
+                    self.status_message = e;
+                }
+                false
+            }
+            command::Command::SaveAs(path) => {
+                if Path::new(&path).exists() && !self.is_current_file(&path) {
+                    self.confirm(format!("{} exists. Overwrite it?", path), Confirmation::SaveAs(path));
+                } else {
+                    self.carry_out(Confirmation::SaveAs(path));
+                }
+                false
+            }
+            command::Command::Rename(path) => {
+                match &self.filename {
+                    Some(from) => {
+                        let overwrite = if Path::new(&path).exists() { ", replacing the file there" } else { "" };
+                        self.confirm(format!("Rename {} to {}{}?", from, path, overwrite), Confirmation::Rename(path));
+                    }
+                    None => self.status_message = "No file to rename (use :saveas)".to_string(),
+                }
+                false
+            }
+            command::Command::DeleteFile => {
+                match &self.filename {
+                    Some(path) => self.confirm(format!("Delete {} from disk?", path), Confirmation::DeleteFile),
+                    None => self.status_message = "No file to delete".to_string(),
+                }
+                false
+            }
+            command::Command::NoHighlight => {
+                self.clear_search_highlight();
+                false
+            }
+            command::Command::Pop => {
+                if let Err(e) = self.pop_tag() {
+                    self.status_message = e;
+                }
+                false
+            }
+            command::Command::Hunk => {
+                let result = match patch::hunk_at(&self.buffer, self.cursor_y) {
+                    Some(hunk) => self.jump_to(&hunk.path, Jump::Address(tags::Address::Line(hunk.new_line_at(self.cursor_y)))),
+                    None => Err("Not in a diff hunk".to_string()),
+                };
+                if let Err(e) = result {
+                    self.status_message = e;
+                }
+                false
+            }
+            command::Command::ApplyHunk => {
+                self.status_message = match patch::hunk_at(&self.buffer, self.cursor_y) {
+                    Some(hunk) => match patch::apply(&hunk) {
+                        Ok(()) => {
+                            log::info!("applied hunk at line {} to {}", hunk.header_row + 1, hunk.path);
+                            format!("Applied hunk to {}", hunk.path)
+                        }
+                        Err(e) => e,
+                    },
+                    None => "Not in a diff hunk".to_string(),
+                };
+                false
+            }
+            command::Command::Set { args, global } => {
+                if args.is_empty() {
+                    self.open_settings();
+                    return false;
+                }
+                for arg in args {
+                    if let Err(e) = self.set_option(&arg, global) {
+                        self.status_message = e;
+                        break;
+                    }
+                }
+                false
+            }
+        }
+    }
+
+    fn set_option(&mut self, arg: &str, global: bool) -> Result<(), String> {
+        match command::parse_set_arg(arg, &self.config)? {
+            command::SetAction::Query { key, value } => {
+                self.status_message = format!("{}={}", key, value);
+            }
+            command::SetAction::Assign { key, value } => {
+                if global {
+                    self.global_config = settings::set_option(&self.global_config, &key, value)?;
+                } else {
+                    settings::set_option(&self.config, &key, value.clone())?;
+                    self.option_overrides.retain(|(k, _)| *k != key);
+                    self.option_overrides.push((key, value));
+                }
+                self.refresh_effective_config();
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes `name` the theme from now on, saving it to the config file.
+    fn choose_theme(&mut self, name: &str) -> Result<(), String> {
+        if !name.is_empty() {
+            theme::apply(&self.global_config, name)?;
+        }
+        self.global_config.main_section.theme = name.to_string();
+        self.refresh_effective_config();
+        let theme = vec![("main_section.theme".to_string(), toml::Value::String(name.to_string()))];
+        let path = config::save_options(&theme).map_err(|e| format!("Theme applied, but not saved: {}", e))?;
+        let shown = if name.is_empty() { "none" } else { name };
+        self.status_message = format!("Theme {} saved to {}", shown, path.display());
+        Ok(())
+    }
+
+    fn preview_themes(&mut self) {
+        let mut names = vec![String::new()];
+        names.extend(theme::available());
+        let index = names.iter().position(|name| *name == self.global_config.main_section.theme).unwrap_or(0);
+        self.theme_preview = Some(ThemePreview { names, index });
+        self.application_mode = ApplicationMode::Theme;
+        self.show_theme_preview();
+    }
+
+    fn show_theme_preview(&mut self) {
+        self.refresh_effective_config();
+        if let Some(preview) = &self.theme_preview {
+            let name = &preview.names[preview.index];
+            self.status_message = format!(
+                "Theme {}/{}: {}  (Tab/Shift+Tab: next/previous, Enter: keep, Esc: cancel)",
+                preview.index + 1,
+                preview.names.len(),
+                if name.is_empty() { "none" } else { name },
+            );
+        }
+    }
+
+    fn handle_key_theme_mode(&mut self, key_event: KeyEvent) -> bool {
+        let Some(preview) = self.theme_preview.as_mut() else {
+            self.application_mode = ApplicationMode::Editing;
+            return false;
+        };
+        let count = preview.names.len();
+        match key_event.code {
+            KeyCode::Tab | KeyCode::Right | KeyCode::Down | KeyCode::Char('n' | 'j') => {
+                preview.index = (preview.index + 1) % count;
+                self.show_theme_preview();
+            }
+            KeyCode::BackTab | KeyCode::Left | KeyCode::Up | KeyCode::Char('p' | 'k') => {
+                preview.index = (preview.index + count - 1) % count;
+                self.show_theme_preview();
+            }
+            KeyCode::Enter => {
+                let name = preview.names[preview.index].clone();
+                self.theme_preview = None;
+                self.application_mode = ApplicationMode::Editing;
+                if let Err(e) = self.choose_theme(&name) {
+                    self.status_message = e;
+                }
+            }
+            KeyCode::Esc => {
+                self.theme_preview = None;
+                self.application_mode = ApplicationMode::Editing;
+                self.refresh_effective_config();
+                self.status_message = self.default_status_message();
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn open_settings(&mut self) {
+        self.settings = Some(settings::SettingsView::new(&self.global_config));
+        self.application_mode = ApplicationMode::Settings;
+    }
+
+    fn handle_key_settings_mode(&mut self, key_event: KeyEvent) -> bool {
+        let Some(view) = self.settings.as_mut() else {
+            self.application_mode = ApplicationMode::Editing;
+            return false;
+        };
+
+        if let Some(text) = view.editing.as_mut() {
+            let updated = match key_event.code {
+                KeyCode::Enter => view.commit_edit(&self.global_config),
+                KeyCode::Esc => {
+                    view.editing = None;
+                    None
+                }
+                KeyCode::Backspace => {
+                    text.pop();
+                    None
+                }
+                KeyCode::Char(c) => {
+                    text.push(c);
+                    None
+                }
+                _ => None,
+            };
+            if let Some(config) = updated {
+                self.apply_config(config);
+            }
+            return false;
+        }
+
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => view.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => view.select_next(),
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                if let Some(config) = view.activate(&self.global_config) {
+                    self.apply_config(config);
+                }
+            }
+            KeyCode::Char('w') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                let changes = settings::changed_options(&config::reload_config().unwrap_or_default(), &self.global_config);
+                match config::save_options(&changes) {
+                    Ok(path) => self.status_message = format!("Settings saved to {}", path.display()),
+                    Err(e) => {
+                        log::error!("error saving settings: {}", e);
+                        self.status_message = format!("Error saving settings: {}", e);
+                    }
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.settings = None;
+                self.application_mode = ApplicationMode::Editing;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// What file lists below `root` leave out, per the config.
+    fn ignore_rules(&self, root: &Path) -> ignore::Rules {
+        let behavior = &self.config.editor_behavior;
+        ignore::Rules::new(&behavior.ignore, behavior.use_gitignore, root)
+    }
+
+    /// Why the file finder and project scans list `path` or leave it out.
+    fn explain_ignored(&self, path: &str) -> String {
+        let root = env::current_dir().unwrap_or_default();
+        let absolute = fs::canonicalize(path).unwrap_or_else(|_| root.join(path));
+        let canonical_root = fs::canonicalize(&root).unwrap_or_else(|_| root.clone());
+        match absolute.strip_prefix(&canonical_root) {
+            Ok(relative) if !relative.as_os_str().is_empty() => {
+                self.ignore_rules(&root).explain(&root, &relative.to_string_lossy().replace('\\', "/"))
+            }
+            _ => format!("{} is not below the working directory, which file lists cover", path),
+        }
+    }
+
+    fn open_finder(&mut self) {
+        let root = env::current_dir().unwrap_or_default();
+        let rules = self.ignore_rules(&root);
+        let id = self.tasks.spawn("scan files", move |context| {
+            finder::scan(&root, rules, context).map(TaskOutput::FilesListed)
+        });
+        self.running_tasks.push(RunningTask { id, label: "Scanning files".to_string(), done: 0, total: None, cancel_on_input: true });
+        self.finder = Some(finder::FileFinder::new());
+        self.application_mode = ApplicationMode::Finder;
+    }
+
+    /// Opens the TODO panel on the buffer's tags, or on the whole project's, which are
+    /// found in the background.
+    fn open_todos(&mut self, project: bool) {
+        if project {
+            let root = env::current_dir().unwrap_or_default();
+            let rules = self.ignore_rules(&root);
+            let id = self.tasks.spawn("find todos", move |context| {
+                todos::scan(&root, rules, context).map(TaskOutput::TodosFound)
+            });
+            self.running_tasks.push(RunningTask { id, label: "Finding TODOs".to_string(), done: 0, total: None, cancel_on_input: true });
+            self.todo_list = Some(todos::TodoList::new(Vec::new(), true));
+        } else {
+            let found = self.syntax.map(|syntax| todos::find(None, &self.buffer, syntax)).unwrap_or_default();
+            self.todo_list = Some(todos::TodoList::new(found, false));
+        }
+        self.application_mode = ApplicationMode::Todos;
+    }
+
+    fn handle_key_todos_mode(&mut self, key_event: KeyEvent) -> bool {
+        let Some(list) = self.todo_list.as_mut() else {
+            self.application_mode = ApplicationMode::Editing;
+            return false;
+        };
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        match key_event.code {
+            KeyCode::Up | KeyCode::Char('k') => list.select_previous(),
+            KeyCode::Char('p') if ctrl => list.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => list.select_next(),
+            KeyCode::Char('n') if ctrl => list.select_next(),
+            KeyCode::Tab => {
+                let project = !list.project;
+                self.open_todos(project);
+            }
+            KeyCode::Enter => {
+                let Some(todo) = list.selected_todo().cloned() else {
+                    return false;
+                };
+                self.todo_list = None;
+                self.application_mode = ApplicationMode::Editing;
+                let jump = Jump::Cursor(todo.line, todo.column);
+                match todo.path {
+                    Some(path) => {
+                        if let Err(e) = self.jump_to(&path, jump) {
+                            self.status_message = e;
+                        }
+                    }
+                    None => self.apply_jump(jump),
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.todo_list = None;
+                self.application_mode = ApplicationMode::Editing;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn handle_key_finder_mode(&mut self, key_event: KeyEvent) -> bool {
+        let Some(finder) = self.finder.as_mut() else {
+            self.application_mode = ApplicationMode::Editing;
+            return false;
+        };
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key_event.modifiers.contains(KeyModifiers::ALT);
+        if !(alt && matches!(key_event.code, KeyCode::Up | KeyCode::Down)) {
+            self.recall = None;
+        }
+        match key_event.code {
+            KeyCode::Up | KeyCode::Down if alt => {
+                let recall = self.recall.get_or_insert_with(|| history::Recall::new(&self.history, history::FINDER, &finder.query));
+                if let Some(query) = recall.step(&self.history, key_event.code == KeyCode::Up) {
+                    finder.set_query(query);
+                }
+            }
+            KeyCode::Up => finder.select_previous(),
+            KeyCode::Char('p') if ctrl => finder.select_previous(),
+            KeyCode::Down => finder.select_next(),
+            KeyCode::Char('n') if ctrl => finder.select_next(),
+            KeyCode::Backspace => finder.pop_char(),
+            KeyCode::Char(c) if !ctrl => finder.push_char(c),
+            KeyCode::Enter => {
+                let Some(path) = finder.selected_file().map(str::to_string) else {
+                    return false;
+                };
+                let query = std::mem::take(&mut finder.query);
+                self.remember(history::FINDER, &query);
+                self.finder = None;
+                self.application_mode = ApplicationMode::Editing;
+                if self.is_dirty() {
+                    self.status_message = format!("Save changes before opening {}", path);
+                } else {
+                    self.open_file(&path);
+                }
+            }
+            KeyCode::Esc => {
+                self.finder = None;
+                self.application_mode = ApplicationMode::Editing;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn open_diff(&mut self, view: diff::DiffView) {
+        self.status_message = format!("{} hunks", view.hunk_count());
+        self.diff = Some(view);
+        self.application_mode = ApplicationMode::Diff;
+    }
+
+    fn handle_key_diff_mode(&mut self, key_event: KeyEvent) -> bool {
+        let Some(view) = self.diff.as_mut() else {
+            self.application_mode = ApplicationMode::Editing;
+            return false;
+        };
+        let page = self.view_area.height.saturating_sub(3) as usize;
+        let last_row = view.rows.len().saturating_sub(1);
+        if view.restore_from.is_some() {
+            match key_event.code {
+                KeyCode::Char('<') => {
+                    self.status_message = "The backup is only read from".to_string();
+                    return false;
+                }
+                KeyCode::Char('w') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.finish_restoring(true);
+                    return false;
+                }
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    self.finish_restoring(true);
+                    return false;
+                }
+                KeyCode::Char('Q') => {
+                    self.finish_restoring(false);
+                    return false;
+                }
+                _ => {}
+            }
+        }
+        match key_event.code {
+            KeyCode::Char('j') | KeyCode::Down => view.cursor = (view.cursor + 1).min(last_row),
+            KeyCode::Char('k') | KeyCode::Up => view.cursor = view.cursor.saturating_sub(1),
+            KeyCode::PageDown => view.cursor = (view.cursor + page).min(last_row),
+            KeyCode::PageUp => view.cursor = view.cursor.saturating_sub(page),
+            KeyCode::Char('h') | KeyCode::Left => view.scroll_x = view.scroll_x.saturating_sub(1),
+            KeyCode::Char('l') | KeyCode::Right => view.scroll_x += 1,
+            KeyCode::Char('n') | KeyCode::Char(']') if !view.next_hunk() => {
+                self.status_message = "No more hunks".to_string();
+            }
+            KeyCode::Char('p') | KeyCode::Char('N') | KeyCode::Char('[') if !view.previous_hunk() => {
+                self.status_message = "No previous hunk".to_string();
+            }
+            KeyCode::Char('>') | KeyCode::Char('<') => {
+                let taken = if key_event.code == KeyCode::Char('>') { view.take_left() } else { view.take_right() };
+                self.status_message = if taken {
+                    format!("{} hunks", view.hunk_count())
+                } else {
+                    "No hunk under cursor".to_string()
+                };
+            }
+            KeyCode::Char('w') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                let mut saved = Vec::new();
+                for side in [&mut view.left, &mut view.right] {
+                    if !side.modified {
+                        continue;
+                    }
+                    match side.save() {
+                        Ok(()) => saved.push(side.path.clone()),
+                        Err(e) => {
+                            log::error!("error saving {}: {}", side.path, e);
+                            self.status_message = format!("Error saving {}: {}", side.path, e);
+                            return false;
+                        }
+                    }
+                }
+                self.status_message = if saved.is_empty() { "No changes to save".to_string() } else { format!("Saved {}", saved.join(", ")) };
+            }
+            KeyCode::Char('q') | KeyCode::Esc => {
+                if view.left.modified || view.right.modified {
+                    self.status_message = "Unsaved changes (Ctrl+W to save, Q to discard)".to_string();
+                } else {
+                    return true;
+                }
+            }
+            KeyCode::Char('Q') => return true,
+            _ => {}
+        }
+        false
+    }
+
+    /// Slices the on-screen window out of `line` before any styling happens, so the cost of
+    /// drawing a line is bounded by the editor width rather than by the line length.
+    /// `overlays` are `(start_col, end_col, style)` ranges patched on top of each other in order.
+    fn visible_line_spans<'a>(
+        line: &'a str,
+        scroll_x: usize,
+        width: usize,
+        overlays: &[(usize, usize, Style)],
+    ) -> Vec<Span<'a>> {
+        // `width` is in cells, so a wide glyph that would only half fit is left off.
+        let mut window_start = None;
+        let mut window_end = line.len();
+        let mut used = 0;
+        for (offset, c) in line.char_indices().skip(scroll_x) {
+            window_start.get_or_insert(offset);
+            used += display::char_width(c);
+            if used > width {
+                window_end = offset;
+                break;
+            }
+        }
+        let Some(window_start) = window_start.filter(|_| width > 0) else {
+            return Vec::new();
+        };
+        let window = &line[window_start..window_end];
+        let markers: Vec<Option<char>> = window.chars().map(display::marker).collect();
+
+        if overlays.is_empty() && markers.iter().all(Option::is_none) {
+            return vec![Span::raw(window)];
+        }
+
+        let window_offsets: Vec<usize> = window.char_indices().map(|(offset, _)| offset).collect();
+        let mut styles = vec![Style::default(); window_offsets.len()];
+        let window_cols = scroll_x..scroll_x + window_offsets.len();
+
+        for &(start, end, overlay) in overlays {
+            let from = start.max(window_cols.start);
+            let to = end.min(window_cols.end);
+            for style in styles.iter_mut().take(to.saturating_sub(scroll_x)).skip(from.saturating_sub(scroll_x)) {
+                *style = style.patch(overlay);
+            }
+        }
+
+        let mut spans = Vec::new();
+        let mut run_start = 0;
+        for col in 1..=styles.len() {
+            let marked = markers[run_start].is_some() || markers.get(col).is_some_and(Option::is_some);
+            if col == styles.len() || styles[col] != styles[run_start] || marked {
+                if let Some(marker) = markers[run_start] {
+                    spans.push(Span::styled(marker.to_string(), styles[run_start].fg(Color::Red).add_modifier(Modifier::REVERSED)));
+                } else {
+                    let byte_from = window_offsets[run_start];
+                    let byte_to = window_offsets.get(col).copied().unwrap_or(window.len());
+                    spans.push(Span::styled(&window[byte_from..byte_to], styles[run_start]));
+                }
+                run_start = col;
+            }
+        }
+        spans
+    }
+
+    /// Indexed by `TokenKind as usize`.
+    fn syntax_colors(&self) -> [Color; 7] {
+        let syntax_config = &self.config.main_section.syntax_highlighting;
+        [
+            syntax_config.keyword_color.parse::<Color>().unwrap_or(Color::Magenta),
+            syntax_config.string_color.parse::<Color>().unwrap_or(Color::Green),
+            syntax_config.comment_color.parse::<Color>().unwrap_or(Color::DarkGray),
+            syntax_config.number_color.parse::<Color>().unwrap_or(Color::Yellow),
+            syntax_config.added_color.parse::<Color>().unwrap_or(Color::Green),
+            syntax_config.removed_color.parse::<Color>().unwrap_or(Color::Red),
+            syntax_config.todo_color.parse::<Color>().unwrap_or(Color::Yellow),
+        ]
+    }
+
+    fn has_top_bar(&self) -> bool {
+        self.config.main_section.title.position == "top_bar"
+    }
+
+    fn editor_layout_index(&self) -> usize {
+        usize::from(self.has_top_bar())
+    }
+
+    /// Splits the screen into the editor, the optional top bar above it and the status and
+    /// prompt panels below it, then the regex tester when it is open.
+    fn screen_layout(&self, area: Rect) -> std::rc::Rc<[Rect]> {
+        let mut constraints = vec![Constraint::Min(1)];
+        if self.has_top_bar() {
+            constraints.insert(0, Constraint::Length(1));
+        }
+        if self.config.main_section.status_panel.enabled {
+            constraints.push(Constraint::Length(1));
+        }
+        if self.config.main_section.prompt_panel.enabled {
+            constraints.push(Constraint::Length(1));
+        }
+        if self.regex_tester.is_some() {
+            constraints.push(Constraint::Length(REGEX_TESTER_HEIGHT));
+        }
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(constraints)
+            .split(area)
+    }
+
+    /// Where the frame goes once `frame.margin` is taken off the editor's share of the
+    /// screen, and that inset by `frame.padding`: the area cursor and scroll math work in,
+    /// with the text one cell inside it on every side whether or not the frame is drawn.
+    fn editor_areas(&self, area: Rect) -> (Rect, Rect) {
+        let margin = self.config.main_section.frame.margin;
+        let padding = self.config.main_section.frame.padding;
+        let frame_area = area.inner(Margin::new(margin, margin));
+        (frame_area, frame_area.inner(Margin::new(padding, padding)))
+    }
+
+    /// Whether keys go to the buffer (or its command line) rather than an overlay or
+    /// another window.
+    fn editor_has_focus(&self) -> bool {
+        self.terminal_focused && matches!(self.application_mode, ApplicationMode::Editing | ApplicationMode::Command)
+    }
+
+    fn expand_title(&self, template: &str) -> String {
+        template
+            .replace("{filename}", self.filename.as_deref().unwrap_or(if self.view_only { "[stdin]" } else { "[No Name]" }))
+            .replace("{modified}", if self.is_dirty() { "(Modified)" } else { "" })
+            .replace("{filetype}", self.syntax.map_or("text", |syntax| syntax.name))
+            .replace("{line}", &(self.cursor_y + 1).to_string())
+            .replace("{column}", &(self.cursor_x + 1).to_string())
+            .replace("{lines}", &self.buffer.len().to_string())
+    }
+
+    fn draw_ui(&mut self, frame: &mut Frame<'_>, popups: &mut popup::Layer) {
+        let size = frame.area();
+        let line_numbers_enabled = self.config.main_section.line_numbers.enabled;
+        let line_numbers_gutter_width = self.config.main_section.line_numbers.gutter_width;
+        let focused = self.editor_has_focus();
+        let inactive_color = self.config.main_section.frame.inactive_color.parse::<Color>().unwrap_or(Color::DarkGray);
+        let line_numbers_color = if focused {
+            self.config.main_section.line_numbers.color.parse::<Color>().unwrap_or(Color::DarkGray)
+        } else {
+            inactive_color
+        };
+        let line_numbers_show_separator = self.config.main_section.line_numbers.show_separator_line;
+
+        let frame_hide = self.config.main_section.frame.hide;
+        let frame_color_str = self.config.main_section.frame.color.clone();
+        let frame_corner = self.config.main_section.frame.corner.clone();
+        let background_color_str = self.config.main_section.background_color.clone();
+
+        let status_panel_enabled = self.config.main_section.status_panel.enabled;
+        let status_panel_bg_color_str = self.config.main_section.status_panel.background_color.clone();
+        let status_panel_fg_color_str = self.config.main_section.status_panel.foreground_color.clone();
+
+        let prompt_panel_enabled = self.config.main_section.prompt_panel.enabled;
+        let prompt_panel_bg_color_str = self.config.main_section.prompt_panel.background_color.clone();
+        let prompt_panel_fg_color_str = self.config.main_section.prompt_panel.foreground_color.clone();
+
+
+        let main_layout = self.screen_layout(size);
+        let editor_index = self.editor_layout_index();
+        let (frame_area, editor_content_area) = self.editor_areas(main_layout[editor_index]);
+
+        self.ensure_cursor_in_view(editor_content_area, line_numbers_enabled, line_numbers_gutter_width);
+
+        let mut text_lines: Vec<Line> = Vec::new();
+
+        let visible_height = editor_content_area.height.saturating_sub(2) as usize;
+        let start_line_idx = self.scroll_y;
+        let end_line_idx = (self.scroll_y + visible_height).min(self.buffer.len());
+
+        let line_numbers_gutter_width_total = if line_numbers_enabled {
+            line_numbers_gutter_width + 1
+        } else {
+            0
+        };
+        let effective_editor_width = editor_content_area.width.saturating_sub(2).saturating_sub(line_numbers_gutter_width_total) as usize;
+
+        let selection = self.selection;
+        let selection_bg_color = Color::Rgb(50, 50, 100);
+
+        let syntax_colors = self.syntax_colors();
+        let syntax_enabled = self.config.main_section.syntax_highlighting.enabled && self.syntax.is_some();
+        if let Some(syntax) = self.syntax.filter(|_| syntax_enabled) {
+            self.highlighter.request(self.buffer.revision(), start_line_idx, &self.buffer[start_line_idx..end_line_idx], syntax);
+        }
+        let token_style = |kind: highlight::TokenKind| Style::default().fg(syntax_colors[kind as usize]);
+        let mut inline_highlight_budget = highlight::INLINE_HIGHLIGHT_BUDGET;
+        let max_line_length = self.max_line_length();
+        let search_color = self.config.main_section.search.highlight_color.parse::<Color>().unwrap_or(Color::Yellow);
+        // The regex tester shows its own matches in place of the search's.
+        let search_matcher = match &self.regex_tester {
+            Some(tester) => tester.matcher(self.config.editor_behavior.smartcase).ok(),
+            None => self.search.as_ref()
+                .filter(|search| search.highlighted)
+                .and_then(|search| self.search_matcher(&search.pattern).ok()),
+        };
+        let error_color = self.config.main_section.validation.error_color.parse::<Color>().unwrap_or(Color::Red);
+        let error = self.validation_error.as_ref().map(|error| (error.line, error.column));
+        let annotation_color = self.config.main_section.annotations.color.parse::<Color>().unwrap_or(Color::Yellow);
+        let underline_links = self.config.main_section.links.underline;
+        let color_swatches = self.config.main_section.color_swatches;
+        let hidden_characters = self.config.main_section.hidden_characters.highlight;
+        let hidden_color = self.config.main_section.hidden_characters.color.parse::<Color>().unwrap_or(Color::Magenta);
+        let code = self.is_code();
+        let rainbow_colors: Vec<Color> = self.config.main_section.rainbow_brackets.colors.iter().filter_map(|color| color.parse().ok()).collect();
+        let mut bracket_depth = match self.syntax.filter(|_| syntax_enabled) {
+            Some(syntax) if code && self.config.main_section.rainbow_brackets.enabled && !rainbow_colors.is_empty() => {
+                Some(self.bracket_depths.at(start_line_idx, &self.buffer[..], syntax))
+            }
+            _ => None,
+        };
+        let line_length_color = self.config.main_section.line_length.color.parse::<Color>().unwrap_or(Color::Red);
+        let breadcrumbs = if self.config.main_section.breadcrumbs.enabled {
+            let separator = self.config.main_section.breadcrumbs.separator.clone();
+            self.enclosing_scopes().join(&separator)
+        } else {
+            String::new()
+        };
+        let prose = self.prose_indicator();
+        let search_matches = self.search_match_indicator();
+        let long_lines = if max_line_length > 0 && self.config.main_section.line_length.show_count {
+            self.count_long_lines()
+        } else {
+            0
+        };
+
+        let wrap_width = self.wrap_width(editor_content_area);
+        let wrap_marker = self.config.main_section.soft_wrap.marker.clone();
+        let wrap_marker_width = display::columns_width(&wrap_marker, 0, usize::MAX);
+        let mut drawn_through = start_line_idx;
+        for i in start_line_idx..end_line_idx {
+            if text_lines.len() == visible_height {
+                break;
+            }
+            let mut spans = Vec::new();
+            let line = &self.buffer[i];
+            let rows = match wrap_width {
+                Some(width) => self.wrap_rows(i, width),
+                None => vec![wrap::Row { start: self.scroll_x, end: line.chars().count(), lead: 0 }],
+            };
+
+            if line_numbers_enabled {
+                let separator_char_width = if line_numbers_show_separator { 1 } else { 0 };
+                let trailing_space_width = 1;
+
+                let number_display_width = line_numbers_gutter_width.saturating_sub(separator_char_width).saturating_sub(trailing_space_width);
+                let number_display_width = number_display_width.max(1);
+
+                let line_num_str = format!("{:>width$}", i + 1, width = number_display_width as usize);
+                let line_num_style = match error {
+                    Some((row, _)) if row == i => Style::default().fg(error_color).add_modifier(Modifier::BOLD),
+                    _ => Style::default().fg(line_numbers_color),
+                };
+                spans.push(Span::styled(line_num_str, line_num_style));
+
+                if line_numbers_show_separator {
+                    spans.push(Span::styled("|", Style::default().fg(line_numbers_color)));
+                }
+                match self.annotations.as_ref().and_then(|annotations| annotations.at(i)) {
+                    Some(_) => spans.push(Span::styled("◆", Style::default().fg(annotation_color))),
+                    None => spans.push(Span::raw(" ")),
+                }
+            }
+
+            let plain = self.is_plain_line(line);
+            let mut overlays = Vec::new();
+            let tokens = match self.syntax.filter(|_| syntax_enabled && !plain) {
+                Some(syntax) => self.highlighter.tokens_or_highlight(i, line, syntax, &mut inline_highlight_budget),
+                None => None,
+            };
+            if let Some(tokens) = &tokens {
+                overlays.extend(tokens.iter().map(|token| (token.start, token.end, token_style(token.kind))));
+            }
+            if let Some(depth) = bracket_depth.as_mut() {
+                match (&tokens, self.syntax) {
+                    (Some(tokens), _) => {
+                        let (found, after) = highlight::brackets(line, tokens, *depth);
+                        let color = |depth: usize| rainbow_colors[depth % rainbow_colors.len()];
+                        overlays.extend(found.into_iter().map(|(col, depth)| (col, col + 1, Style::default().fg(color(depth)))));
+                        *depth = after;
+                    }
+                    // Lines drawn plain still count towards the lines below them.
+                    (None, Some(syntax)) => *depth = self.bracket_depths.at(i + 1, &self.buffer[..], syntax),
+                    (None, None) => {}
+                }
+            }
+            if !plain && color_swatches && line.len() <= highlight::MAX_HIGHLIGHT_LINE_LEN {
+                for (start, end, rgb) in swatch::hex_colors(line) {
+                    let start_col = line[..start].chars().count();
+                    let text = if swatch::is_light(rgb) { Color::Black } else { Color::White };
+                    overlays.push((start_col, start_col + end - start, Style::default().bg(Color::Rgb(rgb.0, rgb.1, rgb.2)).fg(text)));
+                }
+            }
+            if !plain && hidden_characters && line.len() <= highlight::MAX_HIGHLIGHT_LINE_LEN {
+                for suspect in confusables::find(line, code).iter().filter(|suspect| display::marker(suspect.character).is_none()) {
+                    let col = line[..suspect.start].chars().count();
+                    overlays.push((col, col + 1, Style::default().bg(hidden_color)));
+                }
+            }
+            if !plain && underline_links && line.len() <= highlight::MAX_HIGHLIGHT_LINE_LEN {
+                for (start, end, _) in links::links_in_line(line) {
+                    let start_col = line[..start].chars().count();
+                    let end_col = start_col + line[start..end].chars().count();
+                    overlays.push((start_col, end_col, Style::default().add_modifier(Modifier::UNDERLINED)));
+                }
+            }
+            // Byte length bounds the char count, so short lines never need counting.
+            if !plain && max_line_length > 0 && line.len() > max_line_length {
+                overlays.push((max_line_length, usize::MAX, Style::default().bg(line_length_color)));
+            }
+            if !plain && let Some(matcher) = &search_matcher {
+                for (start, end) in matcher.matches_in_line(line) {
+                    let start_col = line[..start].chars().count();
+                    let end_col = start_col + line[start..end].chars().count();
+                    overlays.push((start_col, end_col, Style::default().bg(search_color)));
+                }
+            }
+            if let Some((row, column)) = error && row == i {
+                overlays.push((column, column + 1, Style::default().bg(error_color)));
+            }
+            let selected = selection.filter(|_| !plain).and_then(|selection| selection.char_columns_on_row(i, line));
+            if let Some((from, to, _)) = selected {
+                overlays.push((from, to, Style::default().bg(selection_bg_color)));
+            }
+            let last_row = rows.len() - 1;
+            for (k, row) in rows.into_iter().enumerate() {
+                if k > 0 {
+                    if text_lines.len() == visible_height {
+                        break;
+                    }
+                    // Rows a wrapped line carries on over have no number.
+                    if line_numbers_enabled {
+                        spans.push(Span::raw(" ".repeat(line_numbers_gutter_width as usize)));
+                    }
+                    if row.lead >= wrap_marker_width && wrap_marker_width > 0 {
+                        spans.push(Span::raw(" ".repeat(row.lead - wrap_marker_width)));
+                        spans.push(Span::styled(wrap_marker.clone(), Style::default().fg(line_numbers_color)));
+                    } else {
+                        spans.push(Span::raw(" ".repeat(row.lead)));
+                    }
+                }
+                spans.extend(Self::visible_line_spans(line, row.start, effective_editor_width - row.lead, &overlays));
+                // A selected newline shows as a highlighted cell just past the end of the line.
+                if let Some((_, _, true)) = selected && k == last_row {
+                    let line_chars = line.chars().count();
+                    if line_chars >= row.start && row.lead + display::columns_width(line, row.start, line_chars) < effective_editor_width {
+                        spans.push(Span::styled(" ", Style::default().bg(selection_bg_color)));
+                    }
+                }
+                text_lines.push(Line::from(std::mem::take(&mut spans)));
+                if k == last_row {
+                    drawn_through = i + 1;
+                }
+            }
+        }
+        let end_of_buffer = &self.config.main_section.end_of_buffer.marker;
+        if drawn_through == self.buffer.len() && !end_of_buffer.is_empty() {
+            let filler = Line::styled(end_of_buffer.clone(), Style::default().fg(line_numbers_color));
+            text_lines.resize(visible_height, filler);
+        }
+
+        let mut editor_block = Block::default();
+
+        if !frame_hide {
+            let frame_color = if focused { frame_color_str.parse::<Color>().unwrap_or(Color::Blue) } else { inactive_color };
+            let border_style = Style::default().fg(frame_color);
+            editor_block = editor_block.borders(Borders::ALL)
+                .border_type(match frame_corner.as_str() {
+                    "rounded" => BorderType::Rounded,
+                    "thick" => BorderType::Thick,
+                    _ => BorderType::Plain,
+                })
+                .border_style(border_style);
+        }
+
+        let mut title = self.expand_title(&self.config.main_section.title.template);
+        if !breadcrumbs.is_empty() {
+            title = format!("{}- {} ", title, breadcrumbs);
+        }
+        match self.config.main_section.title.position.as_str() {
+            "hidden" => {}
+            "top_bar" => {
+                let right = self.expand_title(&self.config.main_section.title.right_template);
+                let bar_style = Style::default()
+                    .bg(status_panel_bg_color_str.parse::<Color>().unwrap_or(Color::Blue))
+                    .fg(status_panel_fg_color_str.parse::<Color>().unwrap_or(Color::White));
+                frame.render_widget(Block::default().style(bar_style), main_layout[0]);
+                frame.render_widget(Paragraph::new(title), main_layout[0]);
+                frame.render_widget(Paragraph::new(right).alignment(Alignment::Right), main_layout[0]);
+            }
+            _ => editor_block = editor_block.title(title),
+        }
+
+        let editor_bg_color = background_color_str.parse::<Color>().unwrap_or(Color::Black);
+        editor_block = editor_block.style(Style::default().bg(editor_bg_color));
+
+        let editor_paragraph = Paragraph::new(text_lines)
+            .wrap(Wrap { trim: false });
+
+        frame.render_widget(editor_block, frame_area);
+        frame.render_widget(editor_paragraph, editor_content_area.inner(Margin::new(1, 1)));
+
+        let cursor_offset_x_from_content_start: u16 = if line_numbers_enabled {
+            line_numbers_gutter_width + 1
+        } else {
+            1
+        };
+
+        let cursor_line = &self.buffer[self.cursor_y];
+        let cursor_column = cursor_line[..self.cursor_x].chars().count();
+        let (cursor_row, cursor_cell) = match wrap_width {
+            Some(width) => {
+                let rows = self.wrap_rows(self.cursor_y, width);
+                let index = wrap::locate(&rows, cursor_column);
+                let above: usize = (self.scroll_y..self.cursor_y).map(|row| self.wrap_rows(row, width).len()).sum();
+                (above + index, rows[index].lead + display::columns_width(cursor_line, rows[index].start, cursor_column))
+            }
+            None => (self.cursor_y.saturating_sub(self.scroll_y), display::columns_width(cursor_line, self.scroll_x, cursor_column)),
+        };
+        let actual_cursor_x_for_display = (cursor_cell + self.virtual_padding()) as u16;
+        let relative_cursor_y_in_view = cursor_row as u16;
+
+        frame.set_cursor_position((
+            editor_content_area.x + cursor_offset_x_from_content_start + actual_cursor_x_for_display,
+            editor_content_area.y + 1 + relative_cursor_y_in_view,
+        ));
+        if self.config.main_section.annotations.popup && self.application_mode == ApplicationMode::Editing {
+            self.draw_annotation_popup(popups, editor_content_area, editor_content_area.y + 1 + relative_cursor_y_in_view);
+        }
+
+        let mut current_layout_index = editor_index + 1;
+
+        if status_panel_enabled {
+            let status_block = Block::default()
+                .style(Style::default()
+                    .bg(status_panel_bg_color_str.parse::<Color>().unwrap_or(Color::Blue))
+                    .fg(status_panel_fg_color_str.parse::<Color>().unwrap_or(Color::White)));
+
+            let mut status_spans = vec![Span::raw(self.status_message.as_str())];
+            if let Some(progress) = self.progress_indicator() {
+                status_spans.push(Span::raw("  "));
+                status_spans.push(Span::raw(progress));
+            }
+            if let Some(age) = self.save_age_indicator() {
+                status_spans.push(Span::raw(format!("  {}", age)));
+            }
+            if let Some((register, _)) = &self.recording {
+                status_spans.push(Span::raw(format!("  recording @{}", register)));
+            }
+            if self.config.editor_behavior.paste {
+                status_spans.push(Span::raw("  PASTE"));
+            }
+            if self.sticky_selection {
+                status_spans.push(Span::raw("  MARK"));
+            }
+            if let Some(search_matches) = search_matches {
+                status_spans.push(Span::raw(format!("  {}", search_matches)));
+            }
+            if let Some(prose) = prose {
+                status_spans.push(Span::raw(format!("  {}", prose)));
+            }
+            if long_lines > 0 {
+                status_spans.push(Span::styled(
+                    format!("  {} line{} > {}", long_lines, if long_lines == 1 { "" } else { "s" }, max_line_length),
+                    Style::default().bg(line_length_color),
+                ));
+            }
+            status_spans.push(Span::raw(format!("  {}", self.indentation_label())));
+            let mut status_line = Line::from(status_spans);
+            if !focused {
+                status_line = status_line.style(Style::default().add_modifier(Modifier::DIM));
+            }
+            let status_text = Paragraph::new(status_line)
+                .block(status_block);
+            frame.render_widget(status_text, main_layout[current_layout_index]);
+            current_layout_index += 1;
+        }
+
+        if prompt_panel_enabled {
+            let prompt_block = Block::default()
+                .style(Style::default()
+                    .bg(prompt_panel_bg_color_str.parse::<Color>().unwrap_or(Color::DarkGray))
+                    .fg(prompt_panel_fg_color_str.parse::<Color>().unwrap_or(Color::White)));
+            let prompt_text = Paragraph::new(self.prompt_message.as_str())
+                .block(prompt_block);
+            frame.render_widget(prompt_text, main_layout[current_layout_index]);
+        }
+
+        if self.application_mode == ApplicationMode::Command {
+            let command_area = *main_layout.last().unwrap_or(&editor_content_area);
+            let command_area = Rect::new(command_area.x, command_area.bottom().saturating_sub(1), command_area.width, 1);
+            let command_text = format!("{}{}", self.command_prompt, self.command_line);
+            frame.render_widget(Clear, command_area);
+            frame.set_cursor_position((command_area.x + display::columns_width(&command_text, 0, usize::MAX) as u16, command_area.y));
+            let mut spans = vec![Span::raw(command_text)];
+            // An empty search repeats the last one, so show what that was.
+            if self.command_prompt != ':' && self.command_line.is_empty() && let Some(search) = &self.search {
+                spans.push(Span::styled(search.pattern.clone(), Style::default().fg(Color::DarkGray)));
+            }
+            frame.render_widget(Paragraph::new(Line::from(spans)), command_area);
+        }
+    }
+
+    fn draw_help_ui(&self, popups: &mut popup::Layer) {
+        let (title, keys) = if self.vim_enabled {
+            ("--- Help (Vim-like) ---", keymap::VIM_KEYS)
+        } else {
+            ("--- Help (Nano-like) ---", keymap::STANDARD_KEYS)
+        };
+        let mut lines = vec![Line::from(title), Line::from("")];
+        lines.extend(keys.iter().map(|line| Line::from(*line)));
+        if keymap::is_mac(&self.config.editor_behavior.keymap) {
+            lines.push(Line::from(""));
+            lines.push(Line::from("macOS:"));
+            lines.extend(keymap::mac_lines().into_iter().map(Line::from));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from("Press ESC or any key to return to editor."));
+
+        popups.show(popup::Popup::new("Zepto Help", lines).alignment(Alignment::Center).wrap(false));
+    }
+
+    fn remember_action(&mut self, action: String) {
+        if self.recent_actions.len() == RECENT_ACTIONS_LIMIT {
+            self.recent_actions.pop_front();
+        }
+        self.recent_actions.push_back(action);
+    }
+
+    /// Everything useful for diagnosing a crash. Buffer contents are only included when the
+    /// user opted in, since they may be private.
+    fn crash_report_sections(&self) -> Vec<(&'static str, String)> {
+        let mut sections = vec![
+            ("config", toml::to_string_pretty(&self.config).unwrap_or_default()),
+            ("buffer", format!(
+                "file: {}\nlines: {}\nrevision: {}\nmodified: {}\ncursor: {}:{}\nscroll: {}:{}",
+                self.filename.as_deref().unwrap_or("[No Name]"),
+                self.buffer.len(),
+                self.buffer.revision(),
+                self.is_dirty(),
+                self.cursor_y + 1,
+                self.cursor_x + 1,
+                self.scroll_y,
+                self.scroll_x,
+            )),
+            ("recent commands and keys", self.recent_actions.iter().cloned().collect::<Vec<_>>().join("\n")),
+        ];
+        if self.config.editor_behavior.crash_report_contents {
+            sections.push(("contents", self.buffer.join("\n")));
+        }
+        sections
+    }
+
+    fn draw_settings_ui(&self, frame: &mut Frame<'_>, popups: &mut popup::Layer) {
+        let Some(view) = &self.settings else {
+            return;
+        };
+        let area = popup::centered(frame.area(), 75, 75);
+        let list_height = area.height.saturating_sub(4) as usize;
+        let first = view.selected.saturating_sub(list_height.saturating_sub(1));
+
+        let mut lines: Vec<Line> = view.entries.iter().enumerate().skip(first).take(list_height).map(|(i, (key, value))| {
+            let shown = match (&view.editing, i == view.selected) {
+                (Some(text), true) => format!("{} = [{}_]", key, text),
+                _ => format!("{} = {}", key, value),
+            };
+            if i == view.selected {
+                Line::from(Span::styled(shown, Style::default().add_modifier(Modifier::REVERSED)))
+            } else {
+                Line::from(shown)
+            }
+        }).collect();
+        lines.push(Line::from(""));
+        lines.push(match &view.error {
+            Some(error) => Line::from(Span::styled(error.clone(), Style::default().fg(Color::Red))),
+            None => Line::from("Enter: toggle/edit | Ctrl+W: save to config.toml | Esc: close"),
+        });
+
+        popups.show(popup::Popup::new("Zepto Settings", lines).size(popup::Size::Percent(75, 75)));
+    }
+
+    fn draw_finder_ui(&mut self, frame: &mut Frame<'_>) {
+        let syntax_colors = self.syntax_colors();
+        let syntax_enabled = self.config.main_section.syntax_highlighting.enabled;
+        let Some(finder) = self.finder.as_mut() else {
+            return;
+        };
+        let area = popup::centered(frame.area(), 88, 75);
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(area);
+
+        let list_height = columns[0].height.saturating_sub(4) as usize;
+        let first = finder.selected.saturating_sub(list_height.saturating_sub(1));
+        let mut lines = vec![Line::from(format!("> {}_", finder.query)), Line::from("")];
+        if finder.is_scanning() {
+            lines.push(Line::from("Scanning..."));
+        }
+        for i in first..(first + list_height).min(finder.matches.len()) {
+            let file = finder.file(i).unwrap_or_default().to_string();
+            lines.push(if i == finder.selected {
+                Line::from(Span::styled(file, Style::default().add_modifier(Modifier::REVERSED)))
+            } else {
+                Line::from(file)
+            });
+        }
+        let title = format!("Open File ({}/{})", finder.matches.len().min(finder.selected + 1), finder.matches.len());
+        let list = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+
+        let preview_width = columns[1].width.saturating_sub(2) as usize;
+        let preview_height = columns[1].height.saturating_sub(2) as usize;
+        let (preview_title, preview_lines) = match finder.preview() {
+            Some(preview) if preview.binary => (preview.path.clone(), vec![Line::from("(binary file)")]),
+            Some(preview) => {
+                let token_style = |kind: highlight::TokenKind| Style::default().fg(syntax_colors[kind as usize]);
+                let lines = preview.lines.iter().take(preview_height).map(|line| {
+                    let overlays: Vec<_> = match preview.syntax.filter(|_| syntax_enabled) {
+                        Some(syntax) => syntax.highlight_line(line).iter()
+                            .map(|token| (token.start, token.end, token_style(token.kind)))
+                            .collect(),
+                        None => Vec::new(),
+                    };
+                    Line::from(Self::visible_line_spans(line, 0, preview_width, &overlays))
+                }).collect();
+                (preview.path.clone(), lines)
+            }
+            None => ("Preview".to_string(), Vec::new()),
+        };
+        let preview = Paragraph::new(preview_lines)
+            .block(Block::default().borders(Borders::ALL).title(preview_title));
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(list, columns[0]);
+        frame.render_widget(preview, columns[1]);
+    }
+
+    fn draw_todos_ui(&self, frame: &mut Frame<'_>, popups: &mut popup::Layer) {
+        let Some(list) = self.todo_list.as_ref() else {
+            return;
+        };
+        let area = popup::centered(frame.area(), 88, 75);
+        let list_height = area.height.saturating_sub(2) as usize;
+        let first = list.selected.saturating_sub(list_height.saturating_sub(1));
+        let mut lines = Vec::new();
+        if list.scanning {
+            lines.push(Line::from("Scanning..."));
+        } else if list.todos.is_empty() {
+            lines.push(Line::from("No TODO, FIXME or HACK comments"));
+        }
+        for (i, todo) in list.todos.iter().enumerate().skip(first).take(list_height) {
+            let location = match &todo.path {
+                Some(path) => format!("{}:{}: ", path, todo.line + 1),
+                None => format!("{:>5}: ", todo.line + 1),
+            };
+            let text = format!("{}{}", location, todo.text);
+            lines.push(if i == list.selected {
+                Line::from(Span::styled(text, Style::default().add_modifier(Modifier::REVERSED)))
+            } else {
+                Line::from(text)
+            });
+        }
+        let scope = if list.project { "project" } else { "buffer" };
+        let other = if list.project { "buffer" } else { "project" };
+        let title = format!("TODOs in {} ({}) - Tab: {}, Enter: go, Esc: close", scope, list.todos.len(), other);
+        popups.show(popup::Popup::new(title, lines).size(popup::Size::Percent(88, 75)));
+    }
+
+    /// The regex tester below the status panels, with the match at or after the cursor
+    /// broken down into its groups.
+    fn draw_regex_tester_ui(&mut self, frame: &mut Frame<'_>) {
+        let Some(&area) = self.screen_layout(frame.area()).last() else {
+            return;
+        };
+        let revision = self.buffer.revision();
+        let Some(tester) = self.regex_tester.as_mut() else {
+            return;
+        };
+        let active = Style::default().add_modifier(Modifier::BOLD);
+        let field = |label: &str, text: &str, is_active: bool| {
+            Line::from(vec![
+                Span::styled(label.to_string(), if is_active { active } else { Style::default() }),
+                Span::raw(text.to_string()),
+            ])
+        };
+        let mut lines = vec![
+            field("Regex:   ", &tester.pattern, !tester.editing_replacement),
+            field("Replace: ", &tester.replacement, tester.editing_replacement),
+        ];
+        let title = match tester.matcher(self.config.editor_behavior.smartcase) {
+            Err(e) => {
+                lines.push(Line::styled(e, Style::default().fg(Color::Red)));
+                "Regex".to_string()
+            }
+            Ok(matcher) => {
+                let count = tester.count(&self.buffer, revision, &matcher);
+                if let Some((row, caps)) = tester::match_from(&self.buffer, (self.cursor_y, self.cursor_x), &matcher) {
+                    lines.extend(tester::describe(&self.buffer[row], row, &caps, &tester.replacement).into_iter().map(Line::from));
+                }
+                match count {
+                    0 => "Regex: no matches".to_string(),
+                    1 => "Regex: 1 match".to_string(),
+                    count if count >= search::COUNT_LIMIT => format!("Regex: {}+ matches", count),
+                    count => format!("Regex: {} matches", count),
+                }
+            }
+        };
+        let title = format!("{} - Tab: other field, Up/Down: match, Enter: use, Esc: close", title);
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)), area);
+        let (label, text, row) = if tester.editing_replacement { ("Replace: ", &tester.replacement, 2) } else { ("Regex:   ", &tester.pattern, 1) };
+        let x = area.x + 1 + display::columns_width(&format!("{}{}", label, text), 0, usize::MAX) as u16;
+        frame.set_cursor_position((x.min(area.right().saturating_sub(2)), area.y + row));
+    }
+
+    fn draw_timeline_ui(&self, frame: &mut Frame<'_>, popups: &mut popup::Layer) {
+        let Some(list) = self.timeline.as_ref() else {
+            return;
+        };
+        let area = popup::centered(frame.area(), 75, 75);
+        let list_height = area.height.saturating_sub(2) as usize;
+        let first = list.selected.saturating_sub(list_height.saturating_sub(1));
+        let mut lines = Vec::new();
+        for (i, version) in list.versions.iter().enumerate().skip(first).take(list_height) {
+            let text = format!("{:>4} ago  {:>6} lines  {:>10}", format_age(version.age()), version.lines, format_bytes(version.bytes as u64));
+            lines.push(if i == list.selected {
+                Line::from(Span::styled(text, Style::default().add_modifier(Modifier::REVERSED)))
+            } else {
+                Line::from(text)
+            });
+        }
+        let title = format!("Saved versions ({}) - Enter: compare, r: restore, Esc: close", list.versions.len());
+        popups.show(popup::Popup::new(title, lines).size(popup::Size::Percent(75, 75)));
+    }
+
+    fn draw_diff_ui(&mut self, frame: &mut Frame<'_>) {
+        let syntax_colors = self.syntax_colors();
+        let syntax_enabled = self.config.main_section.syntax_highlighting.enabled;
+        let status_message = self.status_message.clone();
+        let Some(view) = self.diff.as_mut() else {
+            return;
+        };
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(frame.area());
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(layout[0]);
+
+        let height = panes[0].height.saturating_sub(2) as usize;
+        if view.cursor < view.scroll_y {
+            view.scroll_y = view.cursor;
+        } else if view.cursor >= view.scroll_y + height {
+            view.scroll_y = view.cursor + 1 - height;
+        }
+
+        let token_style = |kind: highlight::TokenKind| Style::default().fg(syntax_colors[kind as usize]);
+        let filler = Style::default().fg(Color::DarkGray).bg(Color::Rgb(35, 35, 35));
+        for (pane, side, is_left) in [(panes[0], &view.left, true), (panes[1], &view.right, false)] {
+            let width = pane.width.saturating_sub(2) as usize;
+            let text_width = width.saturating_sub(6);
+            let syntax = highlight::Syntax::for_path(&side.path).filter(|_| syntax_enabled);
+            let lines: Vec<Line> = view.rows.iter().enumerate().skip(view.scroll_y).take(height).map(|(row_idx, row)| {
+                let marker = if row_idx == view.cursor { ">" } else { " " };
+                let Some(line_idx) = (if is_left { row.left } else { row.right }) else {
+                    return Line::from(Span::styled(format!("{:<width$}", marker, width = width), filler));
+                };
+                let background = match row.kind {
+                    diff::RowKind::Same => None,
+                    diff::RowKind::Removed => Some(Color::Rgb(80, 30, 30)),
+                    diff::RowKind::Added => Some(Color::Rgb(30, 70, 30)),
+                    diff::RowKind::Changed => Some(Color::Rgb(70, 60, 20)),
+                };
+                let text = &side.lines[line_idx];
+                let mut overlays: Vec<_> = match syntax {
+                    Some(syntax) => syntax.highlight_line(text).iter()
+                        .map(|token| (token.start, token.end, token_style(token.kind)))
+                        .collect(),
+                    None => Vec::new(),
+                };
+                let row_style = background.map_or(Style::default(), |bg| Style::default().bg(bg));
+                overlays.push((0, usize::MAX, row_style));
+                let mut spans = vec![Span::styled(format!("{}{:>4} ", marker, line_idx + 1), Style::default().fg(Color::DarkGray))];
+                let text_spans = Self::visible_line_spans(text, view.scroll_x, text_width, &overlays);
+                let used: usize = text_spans.iter().map(|span| span.content.chars().count()).sum();
+                spans.extend(text_spans);
+                spans.push(Span::styled(" ".repeat(text_width.saturating_sub(used)), row_style));
+                Line::from(spans)
+            }).collect();
+            let name = match &view.restore_from {
+                Some(label) if is_left => label.clone(),
+                _ => side.path.clone(),
+            };
+            let title = format!("{}{}", name, if side.modified { " (Modified)" } else { "" });
+            frame.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)), pane);
+        }
+
+        let help = if view.restore_from.is_some() {
+            "n/p: next/prev hunk | >: restore hunk | q: back to the buffer | Q: drop restored hunks"
+        } else {
+            "n/p: next/prev hunk | >/<: copy hunk right/left | Ctrl+W: save | q: quit"
+        };
+        frame.render_widget(Paragraph::new(format!("{}  {}", status_message, help)).style(Style::default().add_modifier(Modifier::REVERSED)), layout[1]);
+    }
+
+    pub fn run(&mut self, mut terminal: Terminal<B>, events: &mut dyn input::EventSource) -> io::Result<Terminal<B>> {
+        let mut should_exit = false;
+        let mut redraw = true;
+        let mut last_draw: Option<Instant> = None;
+        while !should_exit {
+            let behavior = &self.config.editor_behavior;
+            let frame_interval = std::time::Duration::from_secs(1) / behavior.max_fps.max(1);
+            let idle_interval = std::time::Duration::from_millis(behavior.poll_interval_ms);
+            let low_power = behavior.low_power;
+
+            let editor_content_area = {
+                let size_of_terminal = terminal.size()?;
+                let main_layout = self.screen_layout(Rect::new(0, 0, size_of_terminal.width, size_of_terminal.height));
+                self.editor_areas(main_layout[self.editor_layout_index()]).1
+            };
+            self.view_area = editor_content_area;
+
+            if redraw && last_draw.is_none_or(|at| at.elapsed() >= frame_interval) {
+                self.draw(&mut terminal)?;
+                last_draw = Some(Instant::now());
+                redraw = false;
+            }
+
+            for event in self.tasks.poll() {
+                should_exit |= self.handle_task_event(event);
+                redraw = true;
+            }
+            self.follow_changes();
+            if self.highlighter.poll() {
+                redraw = true;
+            }
+
+            let highlight_timeout = std::time::Duration::from_millis(self.config.main_section.search.highlight_timeout_ms);
+            let mut highlight_expires_in = None;
+            if let Some(search) = self.search.as_mut()
+                && search.highlighted
+                && !highlight_timeout.is_zero()
+            {
+                match highlight_timeout.checked_sub(search.highlighted_at.elapsed()) {
+                    Some(remaining) if !remaining.is_zero() => highlight_expires_in = Some(remaining),
+                    _ => {
+                        search.highlighted = false;
+                        redraw = true;
+                    }
+                }
+            }
+
+            let toast_lifetime = std::time::Duration::from_millis(self.config.main_section.toasts.duration_ms);
+            let (toasts_went, toast_expires_in) = self.toasts.expire(toast_lifetime);
+            redraw |= toasts_went;
+
+            // Sleep until the next frame is due, the idle refresh, or in low-power mode until
+            // whatever is running or about to expire needs the screen again.
+            let busy = !self.running_tasks.is_empty() || self.highlighter.is_pending();
+            let timeout = if redraw {
+                last_draw.map_or(std::time::Duration::ZERO, |at| frame_interval.saturating_sub(at.elapsed()))
+            } else if !low_power || busy {
+                idle_interval
+            } else {
+                highlight_expires_in.into_iter().chain(toast_expires_in).min().unwrap_or(LOW_POWER_WAIT)
+            };
+            let event = events.next_event(self.key_decoder.poll_timeout(timeout))?;
+            if event.is_some() {
+                self.input_at.get_or_insert_with(Instant::now);
+            }
+            if event.is_some() || !low_power || busy {
+                redraw = true;
+            }
+            match event {
+                Some(Event::Key(key_event)) => {
+                    let chord = KeyEvent::new(key_event.code, key_event.modifiers | KeyModifiers::ALT);
+                    let alt_wanted = self.handles_alt_chord(&chord);
+                    self.key_decoder.feed(key_event, alt_wanted);
+                }
+                Some(Event::FocusLost) => {
+                    self.terminal_focused = false;
+                    if self.config.editor_behavior.save_on_focus_lost {
+                        self.save_on_focus_lost();
+                    }
+                }
+                Some(Event::FocusGained) => self.terminal_focused = true,
+                Some(Event::Paste(text)) if !self.view_only => self.handle_paste(text),
+                Some(Event::Mouse(mouse)) => self.handle_mouse(mouse, editor_content_area),
+                _ => {}
+            }
+            while !should_exit && let Some(key_event) = self.key_decoder.next_key() {
+                should_exit = self.handle_key_input(key_event, editor_content_area);
+            }
+        }
+
+        Ok(terminal)
+    }
+
+    /// Closes the open undo step before input that isn't a key, so typing on either side of
+    /// a click or a paste undoes separately, and the next step starts at the cursor.
+    fn end_undo_step(&mut self) {
+        self.follow_changes();
+        self.undo_history.seal(UNDO_LEVELS);
+        self.last_key_typed = false;
+        self.cursor_before_key = (self.cursor_y, self.cursor_x);
+    }
+
+    /// Clicks move the cursor and drags select from where the button went down, Shift
+    /// extending the selection instead. In the gutter they select whole lines.
+    fn handle_mouse(&mut self, mouse: MouseEvent, editor_content_area: Rect) {
+        if self.application_mode != ApplicationMode::Editing {
+            return;
+        }
+        let text_area = editor_content_area.inner(Margin::new(1, 1));
+        let gutter = if self.config.main_section.line_numbers.enabled { self.config.main_section.line_numbers.gutter_width } else { 0 };
+        let pressed = match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => true,
+            MouseEventKind::Drag(MouseButton::Left) => false,
+            MouseEventKind::Up(MouseButton::Left) => {
+                self.gutter_drag = None;
+                return;
+            }
+            _ => return,
+        };
+        if pressed && !text_area.contains(Position::new(mouse.column, mouse.row)) {
+            return;
+        }
+        self.end_undo_step();
+        let screen_row = mouse.row.saturating_sub(text_area.y) as usize;
+        let (row, wrapped) = match self.wrap_width(editor_content_area) {
+            Some(width) => {
+                let mut left = screen_row;
+                let mut found = (self.scroll_y, wrap::Row { start: 0, end: 0, lead: 0 });
+                for row in self.scroll_y..self.buffer.len() {
+                    let rows = self.wrap_rows(row, width);
+                    found = (row, rows[left.min(rows.len() - 1)]);
+                    if left < rows.len() {
+                        break;
+                    }
+                    left -= rows.len();
+                }
+                found
+            }
+            None => {
+                let row = (self.scroll_y + screen_row).min(self.buffer.len() - 1);
+                (row, wrap::Row { start: self.scroll_x, end: self.buffer[row].chars().count(), lead: 0 })
+            }
+        };
+        let in_gutter = mouse.column < text_area.x + gutter;
+        if pressed && in_gutter {
+            self.gutter_drag = Some(row);
+        }
+        if let Some(first) = self.gutter_drag {
+            let first = first.min(self.buffer.len() - 1);
+            self.selection = Some(selection::Selection::new((first, 0), (row, 0), selection::SelectionKind::Line));
+            self.sticky_selection = false;
+            (self.cursor_y, self.cursor_x) = (row, 0);
+            self.ensure_cursor_in_view(editor_content_area, gutter > 0, gutter);
+            return;
+        }
+        let line = &self.buffer[row];
+        // Past the end of a row a line carries on from, the cursor stays on that row.
+        let row_end = match wrapped.end.checked_sub(1).filter(|_| wrapped.end < line.chars().count()) {
+            Some(last) => line.char_indices().nth(last).map_or(line.len(), |(offset, _)| offset),
+            None => line.len(),
+        };
+        let mut cells = (mouse.column.saturating_sub(text_area.x + gutter) as usize).saturating_sub(wrapped.lead);
+        let mut column = line.char_indices().skip(wrapped.start).take(wrapped.end.saturating_sub(wrapped.start)).find(|&(_, c)| {
+            let width = display::char_width(c);
+            let past = cells < width.max(1);
+            cells = cells.saturating_sub(width);
+            past
+        }).map_or(row_end, |(offset, _)| offset);
+        if self.vim_enabled && self.input_mode == InputMode::Normal && column == line.len() {
+            column -= line[..column].chars().next_back().map_or(0, char::len_utf8);
+        }
+        self.cursor_before_key = (self.cursor_y, self.cursor_x);
+        let extend = !pressed || mouse.modifiers.contains(KeyModifiers::SHIFT);
+        if pressed && !extend {
+            self.clear_selection();
+        }
+        self.move_cursor_to((row, column), editor_content_area, extend);
+    }
+
+    /// Hands the buffer's edits since the last call to everything that tracks its lines.
+    fn follow_changes(&mut self) {
+        let changes = self.buffer.take_changes();
+        for event in &changes {
+            if !self.undoing {
+                self.undo_history.record(&event.change, self.cursor_before_key);
+            }
+            self.highlighter.apply_change(event);
+            self.bracket_depths.apply_change(event);
+            self.word_count.apply_change(event);
+            self.change_list.apply_change(event);
+            if let Some(annotations) = self.annotations.as_mut() {
+                annotations.apply_change(event);
+            }
+        }
+        if !changes.is_empty() {
+            // As in vim, an edit makes the cursor's column the wanted one again.
+            self.wanted_column = None;
+            self.gutter_drag = None;
+            self.validation_error = None;
+        }
+        if !changes.is_empty() && self.is_dirty() {
+            let now = Instant::now();
+            self.modified_at = Some(now);
+            self.unsaved_since.get_or_insert(now);
+        }
+    }
+
+    fn draw(&mut self, terminal: &mut Terminal<B>) -> io::Result<()> {
+        let written_before = metrics::bytes_written();
+        let started = Instant::now();
+        terminal.draw(|frame| {
+            let mut popups = popup::Layer::default();
+            match self.application_mode {
+                ApplicationMode::Editing | ApplicationMode::PromptSave | ApplicationMode::Confirm | ApplicationMode::Command | ApplicationMode::Theme => self.draw_ui(frame, &mut popups),
+                ApplicationMode::Help => self.draw_help_ui(&mut popups),
+                ApplicationMode::Settings => {
+                    self.draw_ui(frame, &mut popups);
+                    self.draw_settings_ui(frame, &mut popups);
+                }
+                ApplicationMode::Finder => {
+                    self.draw_ui(frame, &mut popups);
+                    self.draw_finder_ui(frame);
+                }
+                ApplicationMode::Todos => {
+                    self.draw_ui(frame, &mut popups);
+                    self.draw_todos_ui(frame, &mut popups);
+                }
+                ApplicationMode::Timeline => {
+                    self.draw_ui(frame, &mut popups);
+                    self.draw_timeline_ui(frame, &mut popups);
+                }
+                ApplicationMode::RegexTester => {
+                    self.draw_ui(frame, &mut popups);
+                    self.draw_regex_tester_ui(frame);
+                }
+                ApplicationMode::Diff => self.draw_diff_ui(frame),
+            }
+            if self.debug_overlay {
+                self.draw_debug_overlay(&mut popups);
+            }
+            self.draw_toasts(&mut popups);
+            popups.render(frame);
+        })?;
+        let written = metrics::bytes_written() - written_before;
+        self.frame_stats.record(started, written, self.input_at.take());
+        Ok(())
+    }
+
+    /// The note on the cursor line in a box just below it, or above when the line is near
+    /// the bottom of the editor.
+    fn draw_annotation_popup(&self, popups: &mut popup::Layer, editor_content_area: Rect, cursor_row: u16) {
+        let Some(note) = self.annotations.as_ref().and_then(|annotations| annotations.at(self.cursor_y)) else {
+            return;
+        };
+        let inner = editor_content_area.inner(Margin::new(1, 1));
+        let title = if note.start == note.end {
+            "Note".to_string()
+        } else {
+            format!("Note, lines {}-{}", note.start + 1, note.end + 1)
+        };
+        let color = self.config.main_section.annotations.color.parse::<Color>().unwrap_or(Color::Yellow);
+        popups.show(popup::Popup::new(title, vec![Line::from(note.text.clone())])
+            .size(popup::Size::Fit { min_width: 20, max_width: 60, max_height: (inner.height / 2).max(3) })
+            .anchor(popup::Anchor::Cell { x: None, y: cursor_row })
+            .within(inner)
+            .layer(popup::TOOLTIP)
+            .border_style(Style::default().fg(color))
+            .wrap(true));
+    }
+
+    /// Toasts stacked up from the editor's bottom right corner, the newest lowest.
+    fn draw_toasts(&self, popups: &mut popup::Layer) {
+        let settings = &self.config.main_section.toasts;
+        let mut within = self.view_area.inner(Margin::new(1, 1));
+        for toast in self.toasts.iter().rev() {
+            let color = match toast.severity {
+                toasts::Severity::Info => &settings.info_color,
+                toasts::Severity::Warning => &settings.warning_color,
+                toasts::Severity::Error => &settings.error_color,
+            };
+            let toast = popup::Popup::new(toast.severity.label(), vec![Line::from(toast.text.clone())])
+                .size(popup::Size::Fit { min_width: 20, max_width: 50, max_height: 6 })
+                .anchor(popup::Anchor::BottomRight)
+                .within(within)
+                .layer(popup::OVERLAY)
+                .border_style(Style::default().fg(color.parse::<Color>().unwrap_or(Color::Blue)))
+                .wrap(true);
+            let area = toast.area(within);
+            if area.height < 3 {
+                break;
+            }
+            within.height = area.y - within.y;
+            popups.show(toast);
+        }
+    }
+
+    /// Performance figures in the top right corner; frame figures are for the frame
+    /// before this one. Memory is the buffer's text allocations, not the whole process.
+    fn draw_debug_overlay(&self, popups: &mut popup::Layer) {
+        let stats = &self.frame_stats;
+        let text_bytes: usize = self.buffer.iter().map(|line| line.len() + 1).sum();
+        let allocated: usize = self.buffer.iter().map(|line| line.capacity() + std::mem::size_of::<String>()).sum();
+        let millis = |duration: std::time::Duration| format!("{:.1} ms", duration.as_secs_f64() * 1000.0);
+        let lines = vec![
+            Line::from(format!("frame time  {}", millis(stats.last_frame_time))),
+            Line::from(format!("latency     {}", stats.last_latency.map_or("-".to_string(), millis))),
+            Line::from(format!("frames      {} ({}/s)", stats.frames, stats.per_second())),
+            Line::from(format!("last frame  {}", format_bytes(stats.last_frame_bytes))),
+            Line::from(format!("written     {}", format_bytes(metrics::bytes_written()))),
+            Line::from(format!("buffer      {} lines, {}", self.buffer.len(), format_bytes(text_bytes as u64))),
+            Line::from(format!("memory      ~{}", format_bytes(allocated as u64))),
+        ];
+        popups.show(popup::Popup::new("Debug (F12)", lines)
+            .size(popup::Size::Fit { min_width: 34, max_width: 34, max_height: u16::MAX })
+            .anchor(popup::Anchor::TopRight)
+            .layer(popup::OVERLAY));
+    }
+}
+
+/// A headless editor for the benchmarks to drive with `TestBackend`.
+impl<B: Backend> Editor<B> {
+    /// An editor with the default config showing `lines` as the file `path`.
+    pub fn with_lines(path: &str, lines: Vec<String>) -> Self {
+        let mut editor = Editor::new_with_backend(config::Config::default());
+        editor.finish_open_file(path, lines);
+        editor.follow_changes();
+        editor
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.buffer
+    }
+
+    /// The cursor as a line and a byte offset into it.
+    pub fn cursor(&self) -> buffer::Position {
+        (self.cursor_y, self.cursor_x)
+    }
+
+    /// Moves the cursor as a click would, scrolling it into `area`.
+    pub fn set_cursor(&mut self, position: buffer::Position, area: Rect) {
+        self.move_cursor_to(position, area, false);
+    }
+
+    /// Handles a key as if it was pressed, and returns whether it quits.
+    pub fn press(&mut self, key: KeyEvent, area: Rect) -> bool {
+        let quit = self.handle_key_input(key, area);
+        self.follow_changes();
+        quit
+    }
+
+    /// Types `c` at the cursor without going through the keymap.
+    pub fn type_char(&mut self, c: char, area: Rect) {
+        self.insert_char(c, area);
+        self.follow_changes();
+    }
+
+    /// Undoes what was typed or pasted since the last key or undo, in one step.
+    pub fn undo_step(&mut self, area: Rect) {
+        self.undo_history.seal(UNDO_LEVELS);
+        self.undo(false, area);
+    }
+
+    /// Where searching forward from the cursor for `pattern` lands, as `/` would.
+    pub fn find(&self, pattern: &str) -> Option<buffer::Position> {
+        let matcher = self.search_matcher(pattern).ok()?;
+        search::find(&self.buffer, (self.cursor_y, self.cursor_x), &matcher, true)
+    }
+
+    /// Pastes `text` at the cursor, as from the clipboard.
+    pub fn paste_text(&mut self, text: &str, area: Rect) {
+        self.clipboard = text.to_string();
+        self.paste(false, area);
+        self.follow_changes();
+    }
+
+    pub fn render(&mut self, terminal: &mut Terminal<B>) -> io::Result<()> {
+        self.draw(terminal)
+    }
+}
+
+/// Runs zepto as the command line asks, until the user quits.
+pub fn run() -> io::Result<()> {
+    let args = cli::parse_args(env::args()).unwrap_or_else(|e| {
+        eprintln!("zepto: {}", e);
+        process::exit(2);
+    });
+
+    if args.log_level.is_some() || args.log_file.is_some() {
+        let level = args.log_level.unwrap_or(log::LevelFilter::Debug);
+        let path = args.log_file.clone().unwrap_or_else(logging::default_log_path);
+        if let Err(e) = logging::init(level, &path) {
+            eprintln!("zepto: could not open log file {}: {}", path.display(), e);
+        }
+    }
+    log::info!("zepto {} starting", env!("CARGO_PKG_VERSION"));
+
+    let diff_view = args.diff.as_ref().map(|(left, right)| {
+        diff::DiffView::open(left, right).unwrap_or_else(|e| {
+            eprintln!("zepto: cannot diff {} and {}: {}", left, right, e);
+            process::exit(1);
+        })
+    });
+
+    // As a pager (`MANPAGER='zepto --view'`) the text arrives on stdin and keys come from
+    // the terminal, which crossterm opens itself when stdin isn't one.
+    let piped_lines = if args.view && args.file.as_deref().is_none_or(|file| file == "-") {
+        if io::stdin().is_terminal() {
+            eprintln!("zepto: --view needs a file or text on standard input");
+            process::exit(2);
+        }
+        let mut bytes = Vec::new();
+        io::stdin().read_to_end(&mut bytes)?;
+        Some(pager::plain_lines(&String::from_utf8_lossy(&bytes)))
+    } else {
+        None
+    };
+
+    crash::install_panic_hook();
+
+    enable_raw_mode()?;
+    let mut stdout = stdout();
+    execute!(stdout, EnterAlternateScreen, EnableFocusChange, EnableBracketedPaste)?;
+
+    let config = config::load_config();
+    if config.editor_behavior.mouse {
+        execute!(stdout, EnableMouseCapture)?;
+    }
+    if keymap::is_mac(&config.editor_behavior.keymap) && let Err(e) = input::report_super_key(&mut stdout) {
+        log::warn!("could not ask the terminal to report Cmd: {}", e);
+    }
+    display::keep_logical_order(&mut stdout)?;
+
+    let backend = CrosstermBackend::new(metrics::CountingWriter(stdout));
+    let terminal = Terminal::new(backend)?;
+
+    let mut editor = Editor::new_with_backend(config);
+    editor.load_project_config(&env::current_dir().unwrap_or_default());
+    if args.view {
+        editor.start_viewing();
+    }
+    if let Some(view) = diff_view {
+        editor.open_diff(view);
+    } else if let Some(lines) = piped_lines {
+        editor.show_text(lines);
+    } else if let Some(file) = &args.file && !args.view && !Path::new(file).exists() {
+        editor.new_file(file);
+    } else if let Some(file) = &args.file {
+        editor.open_file(file);
+    }
+
+    let run_result = panic::catch_unwind(AssertUnwindSafe(|| editor.run(terminal, &mut input::TerminalEvents)));
+    let mut terminal_after_run = match run_result {
+        Ok(result) => result?,
+        Err(_) => {
+            let mut sections = vec![("panic", crash::take_panic().unwrap_or_default())];
+            sections.extend(editor.crash_report_sections());
+            match crash::write_report(&sections) {
+                Ok(path) => eprintln!("A crash report was written to {}", path.display()),
+                Err(e) => eprintln!("Could not write crash report: {}", e),
+            }
+            process::exit(101);
+        }
+    };
+
+    input::stop_reporting_super_key(terminal_after_run.backend_mut())?;
+    display::restore_bidi_layout(terminal_after_run.backend_mut())?;
+    terminal_after_run.backend_mut().execute(DisableFocusChange)?;
+    terminal_after_run.backend_mut().execute(DisableBracketedPaste)?;
+    terminal_after_run.backend_mut().execute(DisableMouseCapture)?;
+    terminal_after_run.backend_mut().execute(LeaveAlternateScreen)?;
+    terminal_after_run.show_cursor()?;
+    disable_raw_mode()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::backend::TestBackend;
+
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::testing::lines;
+
+    /// Keys that edit and move without touching files, the command line or the clipboard.
+    const EDITING_KEYS: [KeyCode; 11] = [
+        KeyCode::Left, KeyCode::Right, KeyCode::Up, KeyCode::Down, KeyCode::Home, KeyCode::End,
+        KeyCode::PageUp, KeyCode::PageDown, KeyCode::Backspace, KeyCode::Delete, KeyCode::Enter,
+    ];
+    const TYPED: [char; 6] = ['a', ' ', '\t', 'é', '😀', 'z'];
+    const VIM_KEYS: [char; 25] = [
+        'h', 'j', 'k', 'l', 'w', 'b', 'e', '0', '$', 'x', 'i', 'a', 'o', 'O', 'A', 'I', 'v', 'V',
+        'd', 'y', 'p', 'P', 'G', 'é', ' ',
+    ];
+
+    fn key(vim: bool) -> BoxedStrategy<KeyEvent> {
+        let editing = prop::sample::select(&EDITING_KEYS[..]);
+        let plain = editing.clone().prop_map(|code| KeyEvent::new(code, KeyModifiers::NONE));
+        if vim {
+            prop_oneof![
+                1 => Just(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)),
+                1 => prop::sample::select(&VIM_KEYS[..]).prop_map(|c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)),
+                2 => plain,
+            ]
+            .boxed()
+        } else {
+            prop_oneof![
+                1 => editing.prop_map(|code| KeyEvent::new(code, KeyModifiers::SHIFT)),
+                1 => prop::sample::select(&TYPED[..]).prop_map(|c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)),
+                2 => plain,
+            ]
+            .boxed()
+        }
+    }
+
+    fn cursor_stays_on_a_character(vim: bool, lines: Vec<String>, keys: Vec<KeyEvent>) -> Result<(), TestCaseError> {
+        let area = Rect::new(0, 0, 80, 24);
+        let mut config = config::Config::default();
+        config.editor_behavior.vim = vim;
+        let mut editor = Editor::<TestBackend>::new_with_backend(config);
+        editor.finish_open_file("random.txt", lines);
+        for key in keys {
+            editor.handle_key_input(key, area);
+            editor.follow_changes();
+            let line = &editor.buffer[editor.cursor_y.min(editor.buffer.len() - 1)];
+            prop_assert!(editor.cursor_y < editor.buffer.len(), "row {} after {:?}", editor.cursor_y, key);
+            prop_assert!(editor.cursor_x <= line.len(), "column {} past the end of {:?} after {:?}", editor.cursor_x, line, key);
+            prop_assert!(line.is_char_boundary(editor.cursor_x), "column {} inside a character of {:?} after {:?}", editor.cursor_x, line, key);
+        }
+        Ok(())
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(300))]
+
+        #[test]
+        fn cursor_stays_on_a_character_when_editing(lines in lines(40), keys in prop::collection::vec(key(false), 80)) {
+            cursor_stays_on_a_character(false, lines, keys)?;
+        }
+
+        #[test]
+        fn cursor_stays_on_a_character_in_vim_mode(lines in lines(40), keys in prop::collection::vec(key(true), 80)) {
+            cursor_stays_on_a_character(true, lines, keys)?;
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(200))]
+
+        #[test]
+        fn undo_goes_back_to_the_opened_text_and_redo_forward_again(lines in lines(20), keys in prop::collection::vec(key(false), 60)) {
+            let area = Rect::new(0, 0, 80, 24);
+            let undo = KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL);
+            let redo = KeyEvent::new(KeyCode::Char('y'), KeyModifiers::CONTROL);
+            let mut editor = Editor::<TestBackend>::new_with_backend(config::Config::default());
+            editor.finish_open_file("random.txt", lines);
+            let opened = editor.buffer.join("\n");
+            for key in keys {
+                editor.handle_key_input(key, area);
+                editor.follow_changes();
+            }
+            let edited = editor.buffer.join("\n");
+            while editor.undo_history.can_undo() {
+                editor.handle_key_input(undo, area);
+            }
+            prop_assert_eq!(editor.buffer.join("\n"), opened);
+            while editor.undo_history.can_redo() {
+                editor.handle_key_input(redo, area);
+            }
+            prop_assert_eq!(editor.buffer.join("\n"), edited);
+        }
+    }
+
+    #[test]
+    fn undoing_back_to_the_saved_text_leaves_the_buffer_unmodified() {
+        let area = Rect::new(0, 0, 80, 24);
+        let undo = KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL);
+        let redo = KeyEvent::new(KeyCode::Char('y'), KeyModifiers::CONTROL);
+        let mut editor = Editor::<TestBackend>::new_with_backend(config::Config::default());
+        editor.finish_open_file("saved.txt", vec!["saved".to_string()]);
+        assert!(!editor.is_dirty());
+        editor.handle_key_input(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE), area);
+        editor.follow_changes();
+        assert!(editor.is_dirty());
+        editor.handle_key_input(undo, area);
+        assert!(!editor.is_dirty());
+        editor.handle_key_input(redo, area);
+        assert!(editor.is_dirty());
+        editor.mark_saved();
+        editor.handle_key_input(undo, area);
+        assert!(editor.is_dirty());
+        editor.handle_key_input(redo, area);
+        assert!(!editor.is_dirty());
+    }
+
+    #[test]
+    fn a_click_between_typed_words_leaves_two_undo_steps() {
+        let area = Rect::new(0, 0, 80, 24);
+        let undo = KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL);
+        let mut editor = Editor::<TestBackend>::new_with_backend(config::Config::default());
+        editor.finish_open_file("click.txt", vec![String::new(), String::new()]);
+        let type_word = |editor: &mut Editor<TestBackend>, word: &str| {
+            for c in word.chars() {
+                editor.handle_key_input(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE), area);
+            }
+        };
+        type_word(&mut editor, "foo");
+        editor.handle_mouse(MouseEvent { kind: MouseEventKind::Down(MouseButton::Left), column: 1, row: 2, modifiers: KeyModifiers::NONE }, area);
+        assert_eq!(editor.cursor_y, 1);
+        type_word(&mut editor, "bar");
+        editor.handle_key_input(undo, area);
+        assert_eq!(editor.buffer.join("\n"), "foo\n");
+        assert_eq!(editor.cursor_y, 1);
+        editor.handle_key_input(undo, area);
+        assert_eq!(editor.buffer.join("\n"), "\n");
+    }
+
+    #[test]
+    fn operators_take_gg_g_and_counts() {
+        let area = Rect::new(0, 0, 80, 24);
+        let lines = ["one two three four", "second", "third", "fourth", "fifth"];
+        // Keys typed on line 3 ("third"), with what is left and what was taken.
+        let cases = [
+            ("dgg", "fourth\nfifth", "one two three four\nsecond\nthird\n"),
+            ("dG", "one two three four\nsecond", "third\nfourth\nfifth\n"),
+            ("ygg", "one two three four\nsecond\nthird\nfourth\nfifth", "one two three four\nsecond\nthird\n"),
+            ("yG", "one two three four\nsecond\nthird\nfourth\nfifth", "third\nfourth\nfifth\n"),
+            ("d4G", "one two three four\nsecond\nfifth", "third\nfourth\n"),
+            ("2dd", "one two three four\nsecond\nfifth", "third\nfourth\n"),
+            ("d2k", "fourth\nfifth", "one two three four\nsecond\nthird\n"),
+            ("kkk3dw", "four\nsecond\nthird\nfourth\nfifth", "one two three "),
+            ("kkk2d1w", "three four\nsecond\nthird\nfourth\nfifth", "one two "),
+            ("kkk2y/t\r", "one two three four\nsecond\nthird\nfourth\nfifth", "one two "),
+        ];
+        for (keys, left, taken) in cases {
+            let mut config = config::Config::default();
+            config.editor_behavior.vim = true;
+            let mut editor = Editor::<TestBackend>::new_with_backend(config);
+            editor.finish_open_file("counts.txt", lines.map(String::from).to_vec());
+            editor.go_to_line(3, area);
+            for c in keys.chars() {
+                let code = if c == '\r' { KeyCode::Enter } else { KeyCode::Char(c) };
+                editor.handle_key_input(KeyEvent::new(code, KeyModifiers::NONE), area);
+            }
+            assert_eq!(editor.buffer.join("\n"), left, "after {}", keys);
+            assert_eq!(editor.clipboard, taken, "after {}", keys);
+        }
+    }
+
+    #[test]
+    fn saving_into_a_missing_directory_asks_before_reporting_anything() {
+        let dir = env::temp_dir().join(format!("zepto-missing-dir-{}", process::id()));
+        let path = dir.join("new.txt").to_string_lossy().into_owned();
+        let mut config = config::Config::default();
+        config.editor_behavior.backup = false;
+        config.editor_behavior.timeline_size = 0;
+        let mut editor = Editor::<TestBackend>::new_with_backend(config);
+        editor.finish_open_file(&path, vec!["text".to_string()]);
+        assert!(!editor.save_and_quit(), "quit before the directory was made");
+        assert!(matches!(editor.pending_confirmation, Some(Confirmation::CreateDirectories(_))));
+        assert!(editor.toasts.iter().all(|toast| toast.severity != toasts::Severity::Error));
+        editor.handle_key_confirm_mode(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE));
+        let written = fs::read_to_string(&path);
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(written.ok().as_deref(), Some("text"));
+    }
+
+    #[test]
+    fn typing_in_the_buffer_cancels_a_scan_whose_panel_was_closed() {
+        let area = Rect::new(0, 0, 80, 24);
+        let mut editor = Editor::<TestBackend>::new_with_backend(config::Config::default());
+        editor.finish_open_file("scan.txt", vec![String::new()]);
+        editor.open_finder();
+        editor.handle_key_input(KeyEvent::new(KeyCode::Char('m'), KeyModifiers::NONE), area);
+        assert_eq!(editor.finder.as_ref().map(|finder| finder.query.as_str()), Some("m"));
+        // Finished tasks stay listed until polled, so only a cancel removes this one.
+        assert_eq!(editor.running_tasks.len(), 1);
+        editor.finder = None;
+        editor.application_mode = ApplicationMode::Editing;
+        editor.handle_key_input(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE), area);
+        assert!(editor.running_tasks.is_empty());
+    }
+
+    #[test]
+    fn a_panicking_task_leaves_the_editor_usable() {
+        let area = Rect::new(0, 0, 80, 24);
+        crash::install_panic_hook();
+        let mut editor = Editor::<TestBackend>::new_with_backend(config::Config::default());
+        editor.finish_open_file("panic.txt", vec![String::new()]);
+        let id = editor.tasks.spawn("panic", |_| -> Result<TaskOutput, String> { panic!("boom") });
+        editor.running_tasks.push(RunningTask { id, label: "Panicking".to_string(), done: 0, total: None, cancel_on_input: false });
+        while !editor.running_tasks.is_empty() {
+            for event in editor.tasks.poll() {
+                editor.handle_task_event(event);
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert!(crash::take_panic().is_none(), "a task's panic was taken for a crash");
+        editor.handle_key_input(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE), area);
+        editor.follow_changes();
+        assert_eq!(editor.buffer[0], "a");
+    }
+
+    #[test]
+    fn dragging_from_the_gutter_after_lines_went_away_does_not_panic() {
+        let area = Rect::new(0, 0, 80, 24);
+        let mut config = config::Config::default();
+        config.main_section.line_numbers.enabled = true;
+        let mut editor = Editor::<TestBackend>::new_with_backend(config);
+        editor.finish_open_file("gutter.txt", vec!["one".to_string(), "two".to_string()]);
+        let mouse = |kind, row| MouseEvent { kind, column: 1, row, modifiers: KeyModifiers::NONE };
+        editor.handle_mouse(mouse(MouseEventKind::Down(MouseButton::Left), 2), area);
+        editor.handle_key_input(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE), area);
+        editor.follow_changes();
+        assert_eq!(editor.buffer.len(), 1);
+        editor.handle_mouse(mouse(MouseEventKind::Drag(MouseButton::Left), 1), area);
+        editor.handle_key_input(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE), area);
+        editor.follow_changes();
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(100))]
+
+        #[test]
+        fn indenting_selected_lines_undoes_in_one_step(lines in lines(20), selected in 1..=4usize, indents in prop::collection::vec(any::<bool>(), 1..=3)) {
+            let area = Rect::new(0, 0, 80, 24);
+            let undo = KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL);
+            let mut editor = Editor::<TestBackend>::new_with_backend(config::Config::default());
+            editor.finish_open_file("random.txt", lines);
+            for _ in 0..selected {
+                editor.handle_key_input(KeyEvent::new(KeyCode::Down, KeyModifiers::SHIFT), area);
+            }
+            let mut before = Vec::new();
+            for indent in indents {
+                before.push(editor.buffer.join("\n"));
+                let key = if indent { KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE) } else { KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT) };
+                editor.handle_key_input(key, area);
+                editor.follow_changes();
+                // An outdent with nothing to take off changes nothing and leaves no step.
+                if before.last() == Some(&editor.buffer.join("\n")) {
+                    before.pop();
+                }
+            }
+            while let Some(text) = before.pop() {
+                editor.handle_key_input(undo, area);
+                prop_assert_eq!(editor.buffer.join("\n"), text);
+            }
+        }
+    }
+}
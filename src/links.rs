@@ -0,0 +1,108 @@
+use std::path::{Path, PathBuf};
+
+use crate::expand::expand_path;
+
+/// Schemes recognized as the start of a URL.
+const SCHEMES: &[&str] = &["https://", "http://", "ftp://", "file://"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Link {
+    Url(String),
+    /// A `path:line` reference such as compilers and grep print; the line is 1-based.
+    File { path: String, line: Option<usize> },
+}
+
+/// Characters that can be part of a file name in a `path:line` reference.
+fn is_path_char(c: char) -> bool {
+    c.is_alphanumeric() || "._-/~+@\\".contains(c)
+}
+
+/// Characters that end a URL; trailing punctuation is trimmed separately.
+fn ends_url(c: char) -> bool {
+    c.is_whitespace() || "<>\"'`|{}^".contains(c)
+}
+
+/// The URLs and `path:line` references in `line`, as `(start, end, link)` with byte
+/// offsets, in order.
+pub fn links_in_line(line: &str) -> Vec<(usize, usize, Link)> {
+    let mut links = Vec::new();
+    let mut at = 0;
+    while at < line.len() {
+        let rest = &line[at..];
+        let next = rest.chars().next().unwrap_or_default();
+        if let Some(len) = url_len(rest) && (at == 0 || !line[..at].ends_with(char::is_alphanumeric)) {
+            links.push((at, at + len, Link::Url(rest[..len].to_string())));
+            at += len;
+        } else if is_path_char(next) && (at == 0 || !line[..at].ends_with(is_path_char)) {
+            let (len, link) = file_reference(rest);
+            if let Some(link) = link {
+                links.push((at, at + len, link));
+            }
+            at += len.max(next.len_utf8());
+        } else {
+            at += next.len_utf8();
+        }
+    }
+    links
+}
+
+/// How long the URL at the start of `text` is, if one starts there. Punctuation that
+/// usually closes the sentence around a URL is left out, and so is a `)` with no `(` to
+/// match inside the URL.
+fn url_len(text: &str) -> Option<usize> {
+    let scheme = SCHEMES.iter().find(|scheme| {
+        text.get(..scheme.len()).is_some_and(|start| start.eq_ignore_ascii_case(scheme))
+    })?;
+    let mut url = &text[..text.find(ends_url).unwrap_or(text.len())];
+    loop {
+        let trimmed = url.trim_end_matches(['.', ',', ';', ':', '!', '?', ']']);
+        let trimmed = match trimmed.strip_suffix(')') {
+            Some(inner) if inner.matches('(').count() < trimmed.matches(')').count() => inner,
+            _ => trimmed,
+        };
+        if trimmed.len() == url.len() {
+            break;
+        }
+        url = trimmed;
+    }
+    (url.len() > scheme.len()).then_some(url.len())
+}
+
+/// Reads a run of path characters at the start of `text` and, when it is followed by
+/// `:line`, the reference it makes. Returns how far it read either way.
+fn file_reference(text: &str) -> (usize, Option<Link>) {
+    let path_len = text.find(|c: char| !is_path_char(c)).unwrap_or(text.len());
+    let path = text[..path_len].trim_end_matches('.');
+    let after = &text[path.len()..];
+    let digits = after.strip_prefix(':').map_or(0, |rest| rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len()));
+    let looks_like_file = (path.contains('/') || path.contains('.')) && path.contains(char::is_alphabetic);
+    if digits == 0 || !looks_like_file {
+        return (path_len, None);
+    }
+    let line = after[1..=digits].parse().ok();
+    (path.len() + 1 + digits, Some(Link::File { path: path.to_string(), line }))
+}
+
+/// The link under byte column `col` of `line`. Outside of any URL or reference, a path
+/// under the cursor still counts, so `gf` works on a bare file name as it does in vim.
+pub fn link_at(line: &str, col: usize) -> Option<Link> {
+    let found = links_in_line(line).into_iter().find(|(start, end, _)| (*start..*end).contains(&col));
+    if let Some((_, _, link)) = found {
+        return Some(link);
+    }
+    let start = line[..col.min(line.len())].rfind(|c: char| !is_path_char(c)).map_or(0, |i| i + 1);
+    let end = line[start..].find(|c: char| !is_path_char(c)).map_or(line.len(), |i| start + i);
+    let path = line[start..end].trim_end_matches('.');
+    (!path.is_empty() && (col < end || col == line.len())).then(|| Link::File { path: path.to_string(), line: None })
+}
+
+/// Finds the file a reference names: as given (after `~` and `$VAR` expansion), then
+/// relative to `base`, the directory of the file being edited.
+pub fn resolve(path: &str, base: Option<&Path>) -> Option<PathBuf> {
+    let expanded = PathBuf::from(expand_path(path).ok()?);
+    if expanded.is_file() {
+        return Some(expanded);
+    }
+    let beside = base?.join(&expanded);
+    beside.is_file().then_some(beside)
+}
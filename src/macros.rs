@@ -0,0 +1,185 @@
+use std::{fs, io, path::PathBuf};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::paths;
+
+/// Written at the top of a macro file opened with `:macro`.
+const HEADER: &str = "# Keys of the macro, saved back into its register when this file is saved.
+# Plain characters stand for themselves; spaces and line breaks are ignored, so type a space
+# as <Space>. Others: <Esc> <CR> <Tab> <BS> <Del> <Up> <Down> <Left> <Right> <Home> <End>
+# <PageUp> <PageDown> <F1>..<F12>, <lt> for <, and <C-x> <A-x> <S-x> for Ctrl, Alt and Shift.
+";
+
+fn macros_dir() -> PathBuf {
+    paths::state_dir().join("macros")
+}
+
+/// Registers are the lowercase letters and the digits.
+pub fn is_register(c: char) -> bool {
+    c.is_ascii_lowercase() || c.is_ascii_digit()
+}
+
+/// The file a register's keys are kept in, so macros outlast the session.
+pub fn path(register: char) -> PathBuf {
+    macros_dir().join(format!("{}.keys", register))
+}
+
+/// The register whose file `path` is, if it is one.
+pub fn register_of(path: &str) -> Option<char> {
+    let path = fs::canonicalize(path).ok()?;
+    let dir = fs::canonicalize(macros_dir()).ok()?;
+    if path.parent()? != dir || path.extension()? != "keys" {
+        return None;
+    }
+    let mut stem = path.file_stem()?.to_str()?.chars();
+    stem.next().filter(|c| is_register(*c) && stem.next().is_none())
+}
+
+pub fn save(register: char, keys: &[KeyEvent]) -> io::Result<PathBuf> {
+    let path = path(register);
+    fs::create_dir_all(macros_dir())?;
+    fs::write(&path, format!("{}{}\n", HEADER, names(keys)))?;
+    Ok(path)
+}
+
+pub fn load(register: char) -> Result<Vec<KeyEvent>, String> {
+    match fs::read_to_string(path(register)) {
+        Ok(text) => parse(&text).map_err(|e| format!("macro {}: {}", register, e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Err(format!("register {} is empty", register)),
+        Err(e) => Err(format!("macro {}: {}", register, e)),
+    }
+}
+
+const NAMED: &[(KeyCode, &str)] = &[
+    (KeyCode::Esc, "Esc"),
+    (KeyCode::Enter, "CR"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::BackTab, "S-Tab"),
+    (KeyCode::Backspace, "BS"),
+    (KeyCode::Delete, "Del"),
+    (KeyCode::Insert, "Insert"),
+    (KeyCode::Up, "Up"),
+    (KeyCode::Down, "Down"),
+    (KeyCode::Left, "Left"),
+    (KeyCode::Right, "Right"),
+    (KeyCode::Home, "Home"),
+    (KeyCode::End, "End"),
+    (KeyCode::PageUp, "PageUp"),
+    (KeyCode::PageDown, "PageDown"),
+    (KeyCode::Char(' '), "Space"),
+    (KeyCode::Char('<'), "lt"),
+    (KeyCode::Null, "Nop"),
+];
+
+/// Whether `a` and `b` are the same key. Shift doesn't count for characters, whose case
+/// says it already.
+pub fn same_key(a: &KeyEvent, b: &KeyEvent) -> bool {
+    let ignored = if matches!(a.code, KeyCode::Char(_)) { KeyModifiers::SHIFT } else { KeyModifiers::NONE };
+    a.code == b.code && a.modifiers - ignored == b.modifiers - ignored
+}
+
+/// The name of one key, e.g. `x`, `<CR>` or `<C-w>`.
+pub fn name(key: &KeyEvent) -> String {
+    let mut prefix = String::new();
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        prefix.push_str("C-");
+    }
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        prefix.push_str("A-");
+    }
+    // Shift is already in the character for letters and symbols.
+    if key.modifiers.contains(KeyModifiers::SHIFT) && !matches!(key.code, KeyCode::Char(_) | KeyCode::BackTab) {
+        prefix.push_str("S-");
+    }
+    let base = match key.code {
+        KeyCode::F(n) => format!("F{}", n),
+        code => match NAMED.iter().find(|(named, _)| *named == code) {
+            Some((_, name)) => name.to_string(),
+            None => match code {
+                KeyCode::Char(c) if prefix.is_empty() && !c.is_whitespace() => return c.to_string(),
+                KeyCode::Char(c) => c.to_string(),
+                // Media and other keys nothing is bound to.
+                _ => "Nop".to_string(),
+            },
+        },
+    };
+    format!("<{}{}>", prefix, base)
+}
+
+/// Keys as text, a line ending after each `<Esc>` and `<CR>` to keep it readable. A `#`
+/// that would start a line is written `<#>` so it isn't read as a comment.
+pub fn names(keys: &[KeyEvent]) -> String {
+    let mut text = String::new();
+    let mut line_start = true;
+    for key in keys {
+        let name = name(key);
+        text.push_str(if line_start && name == "#" { "<#>" } else { &name });
+        line_start = name == "<Esc>" || name == "<CR>";
+        if line_start {
+            text.push('\n');
+        }
+    }
+    text
+}
+
+/// Reads keys written as [`names`] writes them. Lines starting with `#` are comments.
+pub fn parse(text: &str) -> Result<Vec<KeyEvent>, String> {
+    let mut keys = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+        let mut rest = line;
+        while let Some(c) = rest.chars().next() {
+            if c.is_whitespace() {
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+            let bracketed = (c == '<').then(|| rest[1..].find('>').map(|end| &rest[1..end + 1])).flatten();
+            match bracketed {
+                Some(inside) if !inside.is_empty() => {
+                    keys.push(parse_name(inside).map_err(|e| format!("line {}: {}", number + 1, e))?);
+                    rest = &rest[inside.len() + 2..];
+                }
+                _ => {
+                    // Terminals report capitals with Shift held.
+                    let modifiers = if c.is_uppercase() { KeyModifiers::SHIFT } else { KeyModifiers::NONE };
+                    keys.push(KeyEvent::new(KeyCode::Char(c), modifiers));
+                    rest = &rest[c.len_utf8()..];
+                }
+            }
+        }
+    }
+    Ok(keys)
+}
+
+/// The key for what is inside `<...>`.
+fn parse_name(inside: &str) -> Result<KeyEvent, String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut base = inside;
+    while base.len() > 2 && let Some((modifier, rest)) = base.split_once('-') {
+        modifiers |= match modifier {
+            "C" | "c" => KeyModifiers::CONTROL,
+            "A" | "a" | "M" | "m" => KeyModifiers::ALT,
+            "S" | "s" if rest != "Tab" => KeyModifiers::SHIFT,
+            "S" | "s" => break,
+            _ => return Err(format!("unknown modifier in <{}>", inside)),
+        };
+        base = rest;
+    }
+    let code = if let Some((code, _)) = NAMED.iter().find(|(_, name)| name.eq_ignore_ascii_case(base)) {
+        *code
+    } else if let Some(n) = base.strip_prefix(['F', 'f']).and_then(|n| n.parse().ok()).filter(|n| (1..=12).contains(n)) {
+        KeyCode::F(n)
+    } else if base.eq_ignore_ascii_case("Enter") || base.eq_ignore_ascii_case("Return") {
+        KeyCode::Enter
+    } else {
+        let mut chars = base.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => return Err(format!("unknown key <{}>", inside)),
+        }
+    };
+    Ok(KeyEvent::new(code, modifiers))
+}
@@ -0,0 +1,61 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+/// Bytes sent to the terminal since startup.
+static BYTES_WRITTEN: AtomicU64 = AtomicU64::new(0);
+
+pub fn bytes_written() -> u64 {
+    BYTES_WRITTEN.load(Ordering::Relaxed)
+}
+
+/// Passes output through to the terminal, counting it on the way, so the cost of a
+/// redraw over a slow link can be seen in the debug overlay.
+pub struct CountingWriter<W>(pub W);
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.0.write(buf)?;
+        BYTES_WRITTEN.fetch_add(written as u64, Ordering::Relaxed);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// How redraws have been going, for the debug overlay.
+#[derive(Default)]
+pub struct FrameStats {
+    pub frames: u64,
+    pub last_frame_bytes: u64,
+    pub last_frame_time: Duration,
+    /// From the last input event arriving to the end of the frame that showed it.
+    pub last_latency: Option<Duration>,
+    /// When each frame in the last second finished.
+    recent: VecDeque<Instant>,
+}
+
+impl FrameStats {
+    pub fn record(&mut self, started: Instant, bytes: u64, input_at: Option<Instant>) {
+        let now = Instant::now();
+        self.frames += 1;
+        self.last_frame_bytes = bytes;
+        self.last_frame_time = now - started;
+        if let Some(input_at) = input_at {
+            self.last_latency = Some(now - input_at);
+        }
+        self.recent.push_back(now);
+        while self.recent.front().is_some_and(|&at| now - at > Duration::from_secs(1)) {
+            self.recent.pop_front();
+        }
+    }
+
+    pub fn per_second(&self) -> usize {
+        self.recent.len()
+    }
+}
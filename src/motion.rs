@@ -0,0 +1,274 @@
+use crate::buffer::Position;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CharClass {
+    Blank,
+    Word,
+    Punctuation,
+}
+
+/// Vim's classes: a small word is a run of letters, digits and underscores or a run of
+/// other non-blank characters; a big WORD is any run of non-blank characters.
+fn class_of(c: char, big: bool) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Blank
+    } else if big || c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
+
+/// Steps through the buffer one char at a time, with the end of each line standing in
+/// for the newline so motions naturally cross lines. `col` counts chars; positions going
+/// in and out are byte columns like everywhere else.
+struct Walker<'a> {
+    lines: &'a [String],
+    row: usize,
+    col: usize,
+    chars: Vec<char>,
+}
+
+impl<'a> Walker<'a> {
+    fn new(lines: &'a [String], (row, col): Position) -> Self {
+        let row = row.min(lines.len().saturating_sub(1));
+        let line = lines.get(row).map_or("", String::as_str);
+        let chars: Vec<char> = line.chars().collect();
+        let col = line[..line.floor_char_boundary(col)].chars().count();
+        Walker { lines, row, col, chars }
+    }
+
+    fn position(&self) -> Position {
+        (self.row, self.chars[..self.col].iter().map(|c| c.len_utf8()).sum())
+    }
+
+    fn char(&self) -> char {
+        self.chars.get(self.col).copied().unwrap_or('\n')
+    }
+
+    fn class(&self, big: bool) -> CharClass {
+        class_of(self.char(), big)
+    }
+
+    /// Empty lines count as a word of their own, as in vim, except the one the motion
+    /// started on.
+    fn stops_at_empty_line(&self, from: Position) -> bool {
+        self.chars.is_empty() && self.position() != from
+    }
+
+    fn load_row(&mut self, row: usize) {
+        self.row = row;
+        self.chars = self.lines[row].chars().collect();
+    }
+
+    fn forward(&mut self) -> bool {
+        if self.col < self.chars.len() {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.load_row(self.row + 1);
+            self.col = 0;
+        } else {
+            return false;
+        }
+        true
+    }
+
+    fn backward(&mut self) -> bool {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.load_row(self.row - 1);
+            self.col = self.chars.len();
+        } else {
+            return false;
+        }
+        true
+    }
+}
+
+/// `w` / `W`: start of the next word.
+pub fn word_forward(lines: &[String], from: Position, big: bool) -> Position {
+    let mut walker = Walker::new(lines, from);
+    let start = walker.class(big);
+    if start != CharClass::Blank {
+        while walker.class(big) == start && walker.forward() {}
+    }
+    while walker.class(big) == CharClass::Blank && !walker.stops_at_empty_line(from) && walker.forward() {}
+    walker.position()
+}
+
+/// `e` / `E`: end of the current or next word.
+pub fn word_end_forward(lines: &[String], from: Position, big: bool) -> Position {
+    let mut walker = Walker::new(lines, from);
+    walker.forward();
+    while walker.class(big) == CharClass::Blank && walker.forward() {}
+    let class = walker.class(big);
+    loop {
+        let before = walker.position();
+        if !walker.forward() || walker.class(big) != class {
+            return before;
+        }
+    }
+}
+
+/// Last character of the word under the cursor, which `cw` changes up to.
+pub fn word_end_at(lines: &[String], from: Position, big: bool) -> Position {
+    let mut walker = Walker::new(lines, from);
+    let class = walker.class(big);
+    loop {
+        let before = walker.position();
+        if !walker.forward() || walker.class(big) != class {
+            return before;
+        }
+    }
+}
+
+/// `b` / `B`: start of the current or previous word.
+pub fn word_backward(lines: &[String], from: Position, big: bool) -> Position {
+    let mut walker = Walker::new(lines, from);
+    if !walker.backward() {
+        return walker.position();
+    }
+    while walker.class(big) == CharClass::Blank && !walker.stops_at_empty_line(from) && walker.backward() {}
+    let class = walker.class(big);
+    if class == CharClass::Blank {
+        return walker.position();
+    }
+    loop {
+        let before = walker.position();
+        if !walker.backward() || walker.class(big) != class {
+            return before;
+        }
+    }
+}
+
+/// `ge` / `gE`: end of the previous word.
+pub fn word_end_backward(lines: &[String], from: Position, big: bool) -> Position {
+    let mut walker = Walker::new(lines, from);
+    let start = walker.class(big);
+    if start != CharClass::Blank {
+        while walker.class(big) == start && walker.backward() {}
+    }
+    while walker.class(big) == CharClass::Blank && !walker.stops_at_empty_line(from) && walker.backward() {}
+    walker.position()
+}
+
+/// Lines holding nothing but indentation separate paragraphs just like empty ones.
+fn is_blank(lines: &[String], row: usize) -> bool {
+    lines.get(row).is_none_or(|line| line.trim().is_empty())
+}
+
+/// `}`: the blank line after the current paragraph, or the end of the buffer.
+pub fn paragraph_forward(lines: &[String], (row, _): Position) -> Position {
+    let last = lines.len().saturating_sub(1);
+    let mut row = row.min(last);
+    while row < last && is_blank(lines, row) {
+        row += 1;
+    }
+    while row < last && !is_blank(lines, row) {
+        row += 1;
+    }
+    if is_blank(lines, row) { (row, 0) } else { (row, lines[row].len()) }
+}
+
+/// `{`: the blank line before the current paragraph, or the start of the buffer.
+pub fn paragraph_backward(lines: &[String], (row, _): Position) -> Position {
+    let mut row = row.min(lines.len().saturating_sub(1));
+    while row > 0 && is_blank(lines, row) {
+        row -= 1;
+    }
+    while row > 0 && !is_blank(lines, row) {
+        row -= 1;
+    }
+    (row, 0)
+}
+
+/// `)`: start of the next sentence. A sentence ends at `.`, `!` or `?`, optionally followed
+/// by closing brackets or quotes, and then whitespace; blank lines end sentences too.
+pub fn sentence_forward(lines: &[String], from: Position) -> Position {
+    let mut walker = Walker::new(lines, from);
+    if is_blank(lines, walker.row) {
+        while is_blank(lines, walker.row) && walker.forward() {}
+        while walker.class(false) == CharClass::Blank && walker.forward() {}
+        return walker.position();
+    }
+    loop {
+        let c = walker.char();
+        if !walker.forward() {
+            return walker.position();
+        }
+        if walker.col == 0 && is_blank(lines, walker.row) {
+            return walker.position();
+        }
+        if !matches!(c, '.' | '!' | '?') {
+            continue;
+        }
+        while matches!(walker.char(), ')' | ']' | '"' | '\'') {
+            if !walker.forward() {
+                return walker.position();
+            }
+        }
+        if walker.char().is_whitespace() {
+            while walker.class(false) == CharClass::Blank && walker.forward() {
+                if walker.col == 0 && is_blank(lines, walker.row) {
+                    return walker.position();
+                }
+            }
+            return walker.position();
+        }
+    }
+}
+
+/// `(`: start of the current sentence, or of the previous one when already at a start.
+/// Sentence starts are found by scanning forward from a paragraph boundary safely before
+/// `from`, since sentence ends cannot be recognised reliably walking backwards.
+pub fn sentence_backward(lines: &[String], from: Position) -> Position {
+    let anchor = paragraph_backward(lines, paragraph_backward(lines, from));
+    let mut start = anchor;
+    let mut best = anchor;
+    while start < from {
+        best = start;
+        let next = sentence_forward(lines, start);
+        if next <= start {
+            break;
+        }
+        start = next;
+    }
+    best
+}
+
+/// An `f`, `t`, `F` or `T` search, kept so `;` and `,` can repeat it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CharFind {
+    pub target: char,
+    pub forward: bool,
+    /// `t` / `T`: stop next to the target instead of on it.
+    pub till: bool,
+}
+
+impl CharFind {
+    /// The same search in the other direction, for `,`.
+    pub fn reversed(self) -> Self {
+        CharFind { forward: !self.forward, ..self }
+    }
+}
+
+/// `f`, `t`, `F` and `T` never leave the line. A repeated till skips a target right next
+/// to the cursor, otherwise `;` after `t` would stay put.
+pub fn find_char(lines: &[String], (row, col): Position, find: CharFind, repeat: bool) -> Option<Position> {
+    let line = lines.get(row)?;
+    let chars: Vec<char> = line.chars().collect();
+    let col = line[..line.floor_char_boundary(col)].chars().count();
+    let skip = usize::from(find.till && repeat);
+    let found = if find.forward {
+        (col + 1 + skip..chars.len()).find(|&i| chars[i] == find.target)?
+    } else {
+        (0..col.saturating_sub(skip)).rev().find(|&i| chars[i] == find.target)?
+    };
+    let col = match (find.till, find.forward) {
+        (false, _) => found,
+        (true, true) => found - 1,
+        (true, false) => found + 1,
+    };
+    Some((row, chars[..col].iter().map(|c| c.len_utf8()).sum()))
+}
@@ -0,0 +1,29 @@
+/// Text piped to `--view`, without the formatting `man` and friends add for terminal
+/// pagers: `X\bX` overstrikes for bold, `_\bX` for underline, and ANSI escape sequences.
+pub fn plain_lines(text: &str) -> Vec<String> {
+    text.lines().map(strip_formatting).collect()
+}
+
+fn strip_formatting(line: &str) -> String {
+    let mut plain = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            // The character after a backspace is printed over the one before it.
+            '\u{8}' => {
+                plain.pop();
+            }
+            '\u{1b}' if chars.peek() == Some(&'[') => {
+                chars.next();
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            '\r' => {}
+            _ => plain.push(c),
+        }
+    }
+    plain
+}
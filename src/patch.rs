@@ -0,0 +1,89 @@
+use std::{fs, path::Path};
+
+/// One `@@ -a,b +c,d @@` section of a unified diff.
+#[derive(Debug)]
+pub struct Hunk {
+    /// Target file from the nearest `+++` header, with any `b/` prefix removed.
+    pub path: String,
+    pub old_start: usize,
+    pub new_start: usize,
+    /// Body lines, each still carrying its ` `, `-` or `+` marker.
+    pub lines: Vec<String>,
+    /// Buffer row of the `@@` header.
+    pub header_row: usize,
+}
+
+impl Hunk {
+    fn old_lines(&self) -> Vec<&str> {
+        self.lines.iter().filter(|line| !line.starts_with('+')).map(|line| line.get(1..).unwrap_or("")).collect()
+    }
+
+    fn new_lines(&self) -> Vec<&str> {
+        self.lines.iter().filter(|line| !line.starts_with('-')).map(|line| line.get(1..).unwrap_or("")).collect()
+    }
+
+    /// The 1-based line in the new file that buffer row `row` corresponds to.
+    pub fn new_line_at(&self, row: usize) -> usize {
+        let body_rows = row.saturating_sub(self.header_row + 1);
+        let advanced = self.lines.iter().take(body_rows).filter(|line| !line.starts_with('-')).count();
+        self.new_start.max(1) + advanced
+    }
+}
+
+fn parse_range(range: &str) -> Option<usize> {
+    range.split(',').next()?.parse().ok()
+}
+
+fn is_header(line: &str) -> bool {
+    line.starts_with("@@") || line.starts_with("diff ") || line.starts_with("--- ") || line.starts_with("+++ ")
+}
+
+/// Finds the hunk containing `row` in a buffer holding a unified diff.
+pub fn hunk_at(lines: &[String], row: usize) -> Option<Hunk> {
+    let header_row = (0..=row.min(lines.len().checked_sub(1)?)).rev().find(|&i| lines[i].starts_with("@@"))?;
+    if lines[header_row + 1..=row.max(header_row)].iter().any(|line| is_header(line) && !line.starts_with("@@")) {
+        return None;
+    }
+    let mut ranges = lines[header_row].split_whitespace().skip(1);
+    let old_start = parse_range(ranges.next()?.strip_prefix('-')?)?;
+    let new_start = parse_range(ranges.next()?.strip_prefix('+')?)?;
+
+    let path = lines[..header_row].iter().rev()
+        .find_map(|line| line.strip_prefix("+++ "))
+        .map(|path| path.split('\t').next().unwrap_or(path).trim())?;
+    let path = path.strip_prefix("b/").unwrap_or(path).to_string();
+
+    let body = lines[header_row + 1..].iter()
+        .take_while(|line| !is_header(line))
+        .filter(|line| !line.starts_with('\\'))
+        .cloned()
+        .collect::<Vec<_>>();
+    if row > header_row + body.len() {
+        return None;
+    }
+    Some(Hunk { path, old_start, new_start, lines: body, header_row })
+}
+
+/// Applies `hunk` to its target file, looking for the old text nearest to where the header
+/// says it should be so hunks still apply after earlier ones shifted the file.
+pub fn apply(hunk: &Hunk) -> Result<(), String> {
+    let path = Path::new(&hunk.path);
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", hunk.path, e))?;
+    let mut file_lines: Vec<&str> = content.lines().collect();
+    let old = hunk.old_lines();
+    let new = hunk.new_lines();
+
+    let expected = hunk.old_start.saturating_sub(1);
+    let matches_at = |at: usize| file_lines.get(at..at + old.len()).is_some_and(|window| window == old.as_slice());
+    let at = (0..=file_lines.len())
+        .filter(|&at| matches_at(at))
+        .min_by_key(|&at| at.abs_diff(expected))
+        .ok_or_else(|| format!("Hunk does not apply to {}", hunk.path))?;
+
+    file_lines.splice(at..at + old.len(), new);
+    let mut updated = file_lines.join("\n");
+    if content.ends_with('\n') {
+        updated.push('\n');
+    }
+    fs::write(path, updated).map_err(|e| format!("{}: {}", hunk.path, e))
+}
@@ -0,0 +1,31 @@
+use std::{env, path::PathBuf};
+
+/// `$ZEPTO_*` when set, otherwise a `zepto` directory under the platform's `base`. With
+/// neither, paths end up relative to the working directory.
+fn dir(variable: &str, base: Option<PathBuf>) -> PathBuf {
+    match env::var_os(variable).filter(|value| !value.is_empty()) {
+        Some(value) => PathBuf::from(value),
+        None => base.map(|base| base.join("zepto")).unwrap_or_default(),
+    }
+}
+
+/// Settings the user edits: `config.toml`. `$ZEPTO_CONFIG_DIR` overrides
+/// `$XDG_CONFIG_HOME/zepto`.
+pub fn config_dir() -> PathBuf {
+    dir("ZEPTO_CONFIG_DIR", dirs::config_dir())
+}
+
+/// Files zepto writes for itself, such as logs and crash reports. `$ZEPTO_STATE_DIR`
+/// overrides `$XDG_STATE_HOME/zepto`; platforms without a state directory use the local
+/// data directory.
+pub fn state_dir() -> PathBuf {
+    dir("ZEPTO_STATE_DIR", dirs::state_dir().or_else(dirs::data_local_dir))
+}
+
+pub fn config_file() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+pub fn log_file() -> PathBuf {
+    state_dir().join("zepto.log")
+}
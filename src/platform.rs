@@ -0,0 +1,103 @@
+use std::{
+    io,
+    process::{Command, Stdio},
+    thread,
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+pub const WINDOWS: bool = cfg!(windows);
+
+/// Unix terminals send Alt chords as Esc followed by the key; Windows consoles report the
+/// Alt modifier itself, so an Esc there is always just Esc.
+pub const ALT_SENDS_ESCAPE: bool = !WINDOWS;
+
+pub fn is_separator(c: char) -> bool {
+    c == '/' || (WINDOWS && c == '\\')
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    /// What new files get: CRLF on Windows, LF elsewhere.
+    pub fn native() -> Self {
+        if WINDOWS { LineEnding::CrLf } else { LineEnding::Lf }
+    }
+
+    /// The ending of the first line of `text`, or the native one when it has no newline.
+    pub fn detect(text: &str) -> Self {
+        match text.find('\n') {
+            Some(at) if text[..at].ends_with('\r') => LineEnding::CrLf,
+            Some(_) => LineEnding::Lf,
+            None => Self::native(),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Device names Windows reserves in every directory, whatever the extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Why the last component of `path` can't name a file on Windows, if it can't. Checked
+/// on any platform so the rules don't depend on where they run.
+pub fn windows_name_problem(path: &str) -> Option<String> {
+    let name = path.rsplit(['/', '\\']).next().filter(|name| !name.is_empty())?;
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    if RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)) {
+        return Some(format!("{} is a reserved device name on Windows", stem));
+    }
+    if let Some(c) = name.chars().find(|&c| "<>:\"|?*".contains(c) || c.is_control()) {
+        return Some(format!("{:?} is not allowed in Windows file names", c));
+    }
+    name.ends_with(['.', ' ']).then(|| "Windows file names cannot end with a dot or a space".to_string())
+}
+
+/// Hands `url` to `browser` (a program and its leading arguments), or to the desktop's
+/// default handler when that is empty. Returns once the program has started.
+pub fn open_url(url: &str, browser: &str) -> io::Result<()> {
+    let mut words = browser.split_whitespace();
+    let mut command = match words.next() {
+        Some(program) => {
+            let mut command = Command::new(program);
+            command.args(words);
+            command
+        }
+        // Not `cmd /C start`, which would run whatever follows a `&` in the URL.
+        None if WINDOWS => {
+            let mut command = Command::new("rundll32");
+            command.arg("url.dll,FileProtocolHandler");
+            command
+        }
+        None if cfg!(target_os = "macos") => Command::new("open"),
+        None => Command::new("xdg-open"),
+    };
+    let mut child = command.arg(url).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).spawn()?;
+    // Reaped in the background so a browser that stays open doesn't hold the editor up.
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Windows consoles report AltGr as Ctrl+Alt, so on keyboards that type `@` or `{` with
+/// AltGr those would look like Ctrl chords. A symbol with both modifiers is taken as text.
+pub fn normalize_key(key: KeyEvent) -> KeyEvent {
+    let altgr = KeyModifiers::CONTROL | KeyModifiers::ALT;
+    match key.code {
+        KeyCode::Char(c) if WINDOWS && key.modifiers.contains(altgr) && !c.is_ascii_alphanumeric() => {
+            KeyEvent { modifiers: key.modifiers - altgr, ..key }
+        }
+        _ => key,
+    }
+}
@@ -0,0 +1,169 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+/// Panels the user opened, such as help and settings.
+pub const PANEL: u8 = 0;
+/// Boxes next to the cursor, such as the note on its line.
+pub const TOOLTIP: u8 = 1;
+/// What goes over everything else: notifications and the debug figures.
+pub const OVERLAY: u8 = 2;
+
+/// How big a popup is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Size {
+    /// This many percent of the area's width and height.
+    Percent(u16, u16),
+    /// Just big enough for the content, borders included, wrapping lines longer than
+    /// `max_width` allows.
+    Fit { min_width: u16, max_width: u16, max_height: u16 },
+}
+
+/// Where a popup goes in its area.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Anchor {
+    Center,
+    TopRight,
+    BottomRight,
+    /// Just below the cell at `(x, y)`, or just above it when there isn't room below.
+    /// Without `x` the popup keeps to the right edge of the area.
+    Cell { x: Option<u16>, y: u16 },
+}
+
+/// A bordered box of text floating over the editor.
+pub struct Popup {
+    title: String,
+    lines: Vec<Line<'static>>,
+    size: Size,
+    anchor: Anchor,
+    within: Option<Rect>,
+    layer: u8,
+    border_style: Style,
+    wrap: Option<Wrap>,
+    alignment: Alignment,
+}
+
+impl Popup {
+    /// A panel centered on the screen, half its width and height.
+    pub fn new(title: impl Into<String>, lines: Vec<Line<'static>>) -> Self {
+        Popup {
+            title: title.into(),
+            lines,
+            size: Size::Percent(50, 50),
+            anchor: Anchor::Center,
+            within: None,
+            layer: PANEL,
+            border_style: Style::default(),
+            wrap: None,
+            alignment: Alignment::Left,
+        }
+    }
+
+    pub fn size(mut self, size: Size) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn anchor(mut self, anchor: Anchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Keeps the popup inside `area` rather than the whole screen.
+    pub fn within(mut self, area: Rect) -> Self {
+        self.within = Some(area);
+        self
+    }
+
+    pub fn layer(mut self, layer: u8) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    pub fn border_style(mut self, style: Style) -> Self {
+        self.border_style = style;
+        self
+    }
+
+    pub fn wrap(mut self, trim: bool) -> Self {
+        self.wrap = Some(Wrap { trim });
+        self
+    }
+
+    pub fn alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Where the popup lands on a `screen` of this size.
+    pub fn area(&self, screen: Rect) -> Rect {
+        let within = self.within.unwrap_or(screen).intersection(screen);
+        let (width, height) = match self.size {
+            Size::Percent(width, height) => (within.width * width.min(100) / 100, within.height * height.min(100) / 100),
+            Size::Fit { min_width, max_width, max_height } => {
+                let widest = self.lines.iter().map(Line::width).max().unwrap_or(0).max(self.title.chars().count());
+                let width = (widest as u16).saturating_add(2).clamp(min_width, max_width.max(min_width)).min(within.width);
+                let text_width = width.saturating_sub(2).max(1) as usize;
+                let rows: usize = self.lines.iter().map(|line| match self.wrap {
+                    Some(_) => line.width().div_ceil(text_width).max(1),
+                    None => 1,
+                }).sum();
+                (width, (rows as u16).saturating_add(2).min(max_height).min(within.height))
+            }
+        };
+        place(self.anchor, width, height, within)
+    }
+
+    fn render(self, frame: &mut Frame<'_>) {
+        let area = self.area(frame.area());
+        let block = Block::default().borders(Borders::ALL).border_style(self.border_style).title(self.title);
+        let mut paragraph = Paragraph::new(self.lines).block(block).alignment(self.alignment);
+        if let Some(wrap) = self.wrap {
+            paragraph = paragraph.wrap(wrap);
+        }
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+    }
+}
+
+/// A box `width` by `height` put at `anchor` in `within`, which it must fit in.
+fn place(anchor: Anchor, width: u16, height: u16, within: Rect) -> Rect {
+    let (x, y) = match anchor {
+        Anchor::Center => (within.x + (within.width - width) / 2, within.y + (within.height - height) / 2),
+        Anchor::TopRight => (within.right() - width, within.y),
+        Anchor::BottomRight => (within.right() - width, within.bottom() - height),
+        Anchor::Cell { x, y } => {
+            let x = x.map_or(within.right() - width, |x| x.min(within.right() - width).max(within.x));
+            let y = if y + 1 + height <= within.bottom() { y + 1 } else { y.saturating_sub(height).max(within.y) };
+            (x, y)
+        }
+    };
+    Rect::new(x, y, width, height)
+}
+
+/// The middle of `screen`, `width` and `height` percent of it, for panels that draw
+/// themselves rather than as one box of text.
+pub fn centered(screen: Rect, width: u16, height: u16) -> Rect {
+    place(Anchor::Center, screen.width * width.min(100) / 100, screen.height * height.min(100) / 100, screen)
+}
+
+/// The popups to draw over a frame, lowest layer first and in the order they were shown
+/// within a layer.
+#[derive(Default)]
+pub struct Layer {
+    popups: Vec<Popup>,
+}
+
+impl Layer {
+    pub fn show(&mut self, popup: Popup) {
+        self.popups.push(popup);
+    }
+
+    pub fn render(mut self, frame: &mut Frame<'_>) {
+        self.popups.sort_by_key(|popup| popup.layer);
+        for popup in self.popups {
+            popup.render(frame);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use toml::Table;
+
+use crate::{config::Config, settings};
+
+/// The project settings file, looked for in the file's directory and every one above it.
+pub const PROJECT_FILE: &str = ".zepto.toml";
+
+/// Options a project file may not set, since they name programs to run and a cloned
+/// repository shouldn't get to pick those. A table's name covers everything in it.
+/// Themes may not set them either, as a project file can pick the theme.
+const UNTRUSTED_KEYS: &[&str] = &["main_section.links.browser", "commands", "keys", "hooks"];
+
+pub fn is_untrusted(key: &str) -> bool {
+    UNTRUSTED_KEYS.iter().any(|untrusted| key.strip_prefix(untrusted).is_some_and(|rest| rest.is_empty() || rest.starts_with('.')))
+}
+
+/// Settings from a project's `.zepto.toml`, laid out like `config.toml`.
+pub struct ProjectConfig {
+    pub path: PathBuf,
+    table: Table,
+}
+
+/// The nearest `.zepto.toml` in `dir` or above it.
+pub fn find(dir: &Path) -> Option<PathBuf> {
+    let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    dir.ancestors().map(|ancestor| ancestor.join(PROJECT_FILE)).find(|candidate| candidate.is_file())
+}
+
+/// Reads the project file that applies to files in `dir`, if there is one.
+pub fn load(dir: &Path) -> Result<Option<ProjectConfig>, String> {
+    let Some(path) = find(dir) else {
+        return Ok(None);
+    };
+    let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let table = toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(Some(ProjectConfig { path, table }))
+}
+
+impl ProjectConfig {
+    /// `config` with the project's options in place of its own. Options that can't be
+    /// applied are left out, and the first of them is reported.
+    pub fn apply(&self, config: &Config) -> (Config, Option<String>) {
+        let mut merged = config.clone();
+        let mut problem = None;
+        for (key, value) in settings::flatten_table(&self.table) {
+            let applied = if is_untrusted(&key) {
+                Err(format!("{} can only be set in your own config", key))
+            } else {
+                settings::set_option(&merged, &key, value)
+            };
+            match applied {
+                Ok(updated) => merged = updated,
+                Err(e) => {
+                    problem.get_or_insert_with(|| format!("{}: {}", self.path.display(), e));
+                }
+            }
+        }
+        (merged, problem)
+    }
+}
@@ -0,0 +1,512 @@
+use std::cell::Cell;
+
+/// A backtracking regular expression matcher for searching and replacing within a line.
+/// It covers the everyday syntax: `.`, `[...]` classes, `\d \w \s` and their capitals,
+/// `^ $ \b \B`, groups `(...)` and `(?:...)`, `|`, and the quantifiers `* + ? {m,n}`
+/// with a trailing `?` for the lazy kind.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regex {
+    node: Node,
+    groups: usize,
+}
+
+/// Byte ranges of the whole match and of each group, which is `None` when it took no part.
+pub type Captures = Vec<Option<(usize, usize)>>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    WordBoundary(bool),
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat { node: Box<Node>, min: usize, max: Option<usize>, greedy: bool },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Class {
+    items: Vec<ClassItem>,
+    negated: bool,
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl ClassItem {
+    fn matches(&self, c: char, ignore_case: bool) -> bool {
+        match *self {
+            ClassItem::Range(low, high) if ignore_case => {
+                c.to_lowercase().chain(c.to_uppercase()).any(|variant| (low..=high).contains(&variant))
+            }
+            ClassItem::Range(low, high) => (low..=high).contains(&c),
+            ClassItem::Digit(wanted) => c.is_ascii_digit() == wanted,
+            ClassItem::Word(wanted) => is_word(c) == wanted,
+            ClassItem::Space(wanted) => c.is_whitespace() == wanted,
+        }
+    }
+}
+
+impl Class {
+    fn matches(&self, c: char, ignore_case: bool) -> bool {
+        self.items.iter().any(|item| item.matches(c, ignore_case)) != self.negated
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    groups: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn alternation(&mut self) -> Result<Node, String> {
+        let mut options = vec![self.concatenation()?];
+        while self.eat('|') {
+            options.push(self.concatenation()?);
+        }
+        Ok(if options.len() == 1 { options.remove(0) } else { Node::Alternate(options) })
+    }
+
+    fn concatenation(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.quantified(atom)?);
+        }
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.remove(0),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        let c = self.peek().ok_or("unexpected end of pattern")?;
+        self.pos += 1;
+        Ok(match c {
+            '(' => {
+                let index = if self.chars[self.pos..].starts_with(&['?', ':']) {
+                    self.pos += 2;
+                    None
+                } else {
+                    self.groups += 1;
+                    Some(self.groups)
+                };
+                let inner = self.alternation()?;
+                if !self.eat(')') {
+                    return Err("unclosed (".to_string());
+                }
+                Node::Group(Box::new(inner), index)
+            }
+            '[' => Node::Class(self.class()?),
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '*' | '+' | '?' => return Err(format!("nothing before {} to repeat", c)),
+            '\\' => {
+                let escaped = self.peek().ok_or("pattern ends with \\")?;
+                self.pos += 1;
+                match escaped {
+                    'b' => Node::WordBoundary(true),
+                    'B' => Node::WordBoundary(false),
+                    _ => match class_escape(escaped) {
+                        Some(item) => Node::Class(Class { items: vec![item], negated: false }),
+                        None => Node::Char(literal_escape(escaped)),
+                    },
+                }
+            }
+            c => Node::Char(c),
+        })
+    }
+
+    fn class(&mut self) -> Result<Class, String> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.peek().ok_or("unclosed [")?;
+            self.pos += 1;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+            let low = if c == '\\' {
+                let escaped = self.peek().ok_or("unclosed [")?;
+                self.pos += 1;
+                if let Some(item) = class_escape(escaped) {
+                    items.push(item);
+                    continue;
+                }
+                literal_escape(escaped)
+            } else {
+                c
+            };
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|next| *next != ']') {
+                self.pos += 1;
+                let mut high = self.peek().ok_or("unclosed [")?;
+                self.pos += 1;
+                if high == '\\' {
+                    high = literal_escape(self.peek().ok_or("unclosed [")?);
+                    self.pos += 1;
+                }
+                if high < low {
+                    return Err(format!("range {}-{} is backwards", low, high));
+                }
+                items.push(ClassItem::Range(low, high));
+            } else {
+                items.push(ClassItem::Range(low, low));
+            }
+        }
+        Ok(Class { items, negated })
+    }
+
+    fn quantified(&mut self, atom: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('{') => match self.bounds() {
+                Some(bounds) => bounds,
+                None => return Ok(atom),
+            },
+            Some(c @ ('*' | '+' | '?')) => {
+                self.pos += 1;
+                (usize::from(c == '+'), (c == '?').then_some(1))
+            }
+            _ => return Ok(atom),
+        };
+        if matches!(atom, Node::Start | Node::End | Node::WordBoundary(_)) {
+            return Err("an anchor can't be repeated".to_string());
+        }
+        let greedy = !self.eat('?');
+        Ok(Node::Repeat { node: Box::new(atom), min, max, greedy })
+    }
+
+    /// Reads `{m}`, `{m,}` or `{m,n}`; anything else leaves the `{` to be taken literally.
+    fn bounds(&mut self) -> Option<(usize, Option<usize>)> {
+        let close = self.chars[self.pos..].iter().position(|c| *c == '}')? + self.pos;
+        let inside: String = self.chars[self.pos + 1..close].iter().collect();
+        let (min, max) = match inside.split_once(',') {
+            Some((min, "")) => (min.parse().ok()?, None),
+            Some((min, max)) => (min.parse().ok()?, Some(max.parse().ok()?)),
+            None => {
+                let count = inside.parse().ok()?;
+                (count, Some(count))
+            }
+        };
+        if max.is_some_and(|max| max < min) {
+            return None;
+        }
+        self.pos = close + 1;
+        Some((min, max))
+    }
+}
+
+fn class_escape(c: char) -> Option<ClassItem> {
+    Some(match c {
+        'd' => ClassItem::Digit(true),
+        'D' => ClassItem::Digit(false),
+        'w' => ClassItem::Word(true),
+        'W' => ClassItem::Word(false),
+        's' => ClassItem::Space(true),
+        'S' => ClassItem::Space(false),
+        _ => return None,
+    })
+}
+
+fn literal_escape(c: char) -> char {
+    match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        c => c,
+    }
+}
+
+/// One attempt at matching, over the characters of a line.
+struct Matcher<'a> {
+    text: &'a [char],
+    ignore_case: bool,
+    /// Steps taken on this line, and how many repetitions of a group are nested right now.
+    /// Either running past its limit gives up on the rest of the line rather than hanging
+    /// or overflowing the stack.
+    steps: Cell<usize>,
+    depth: Cell<usize>,
+}
+
+const STEP_LIMIT: usize = 1_000_000;
+const DEPTH_LIMIT: usize = 1_000;
+
+type Continuation<'k> = dyn FnMut(usize, &mut Vec<Option<(usize, usize)>>) -> bool + 'k;
+
+impl Matcher<'_> {
+    fn same(&self, a: char, b: char) -> bool {
+        a == b || (self.ignore_case && a.to_lowercase().eq(b.to_lowercase()))
+    }
+
+    /// Whether `node` matches only single characters, so repeating it needs no backtracking
+    /// into it.
+    fn single(&self, node: &Node, pos: usize) -> Option<bool> {
+        let c = self.text.get(pos);
+        match node {
+            Node::Char(wanted) => Some(c.is_some_and(|c| self.same(*c, *wanted))),
+            Node::Any => Some(c.is_some()),
+            Node::Class(class) => Some(c.is_some_and(|c| class.matches(*c, self.ignore_case))),
+            _ => None,
+        }
+    }
+
+    fn gave_up(&self) -> bool {
+        self.steps.get() > STEP_LIMIT || self.depth.get() > DEPTH_LIMIT
+    }
+
+    fn at(&self, node: &Node, pos: usize, caps: &mut Vec<Option<(usize, usize)>>, next: &mut Continuation) -> bool {
+        self.steps.set(self.steps.get() + 1);
+        if self.gave_up() {
+            return false;
+        }
+        match node {
+            Node::Empty => next(pos, caps),
+            Node::Char(_) | Node::Any | Node::Class(_) => self.single(node, pos) == Some(true) && next(pos + 1, caps),
+            Node::Start => pos == 0 && next(pos, caps),
+            Node::End => pos == self.text.len() && next(pos, caps),
+            Node::WordBoundary(wanted) => {
+                let before = pos > 0 && is_word(self.text[pos - 1]);
+                let after = self.text.get(pos).is_some_and(|c| is_word(*c));
+                (before != after) == *wanted && next(pos, caps)
+            }
+            Node::Group(inner, None) => self.at(inner, pos, caps, next),
+            Node::Group(inner, Some(index)) => {
+                let index = *index;
+                self.at(inner, pos, caps, &mut |end, caps| {
+                    let before = caps[index];
+                    caps[index] = Some((pos, end));
+                    next(end, caps) || {
+                        caps[index] = before;
+                        false
+                    }
+                })
+            }
+            Node::Concat(nodes) => self.sequence(nodes, pos, caps, next),
+            Node::Alternate(options) => options.iter().any(|option| self.at(option, pos, caps, next)),
+            Node::Repeat { node, min, max, greedy } => {
+                if self.single(node, pos).is_some() {
+                    self.repeat_single(node, *min, *max, *greedy, pos, caps, next)
+                } else {
+                    self.repeat(node, *min, *max, *greedy, pos, 0, caps, next)
+                }
+            }
+        }
+    }
+
+    fn sequence(&self, nodes: &[Node], pos: usize, caps: &mut Vec<Option<(usize, usize)>>, next: &mut Continuation) -> bool {
+        match nodes.split_first() {
+            None => next(pos, caps),
+            Some((first, rest)) => self.at(first, pos, caps, &mut |pos, caps| self.sequence(rest, pos, caps, next)),
+        }
+    }
+
+    /// Repeats a single-character node without recursing once per character, so `.*` on a
+    /// long line can't run out of stack.
+    #[allow(clippy::too_many_arguments)]
+    fn repeat_single(&self, node: &Node, min: usize, max: Option<usize>, greedy: bool, pos: usize, caps: &mut Vec<Option<(usize, usize)>>, next: &mut Continuation) -> bool {
+        let limit = max.unwrap_or(usize::MAX);
+        let mut count = 0;
+        while count < limit && self.single(node, pos + count) == Some(true) {
+            count += 1;
+        }
+        if count < min {
+            return false;
+        }
+        if greedy {
+            (min..=count).rev().any(|taken| next(pos + taken, caps))
+        } else {
+            (min..=count).any(|taken| next(pos + taken, caps))
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn repeat(&self, node: &Node, min: usize, max: Option<usize>, greedy: bool, pos: usize, count: usize, caps: &mut Vec<Option<(usize, usize)>>, next: &mut Continuation) -> bool {
+        let can_repeat = max.is_none_or(|max| count < max);
+        // Stopping after an empty repetition keeps `(a*)*` from looping forever.
+        let again = |caps: &mut Vec<Option<(usize, usize)>>, next: &mut Continuation| {
+            can_repeat && self.at(node, pos, caps, &mut |end, caps| {
+                if end == pos && count >= min {
+                    return false;
+                }
+                self.depth.set(self.depth.get() + 1);
+                let matched = self.repeat(node, min, max, greedy, end, count + 1, caps, next);
+                self.depth.set(self.depth.get() - 1);
+                matched
+            })
+        };
+        let enough = count >= min;
+        match greedy {
+            true => again(caps, next) || (enough && next(pos, caps)),
+            false if enough && next(pos, caps) => true,
+            false => again(caps, next),
+        }
+    }
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Regex, String> {
+        let mut parser = Parser { chars: pattern.chars().collect(), pos: 0, groups: 0 };
+        let node = parser.alternation()?;
+        if parser.pos < parser.chars.len() {
+            return Err("unmatched )".to_string());
+        }
+        Ok(Regex { node, groups: parser.groups })
+    }
+
+    /// Every non-overlapping match in `line`, in order, with its groups.
+    pub fn captures_all(&self, line: &str, ignore_case: bool) -> Vec<Captures> {
+        let text: Vec<char> = line.chars().collect();
+        let offsets: Vec<usize> = line.char_indices().map(|(offset, _)| offset).chain([line.len()]).collect();
+        let matcher = Matcher { text: &text, ignore_case, steps: Cell::new(0), depth: Cell::new(0) };
+        let mut found = Vec::new();
+        let mut start = 0;
+        while start <= text.len() {
+            let mut caps = vec![None; self.groups + 1];
+            let mut end = None;
+            // Groups are only rolled back on failure, so they stay set once this succeeds.
+            matcher.at(&self.node, start, &mut caps, &mut |pos, _| {
+                end = Some(pos);
+                true
+            });
+            if matcher.gave_up() {
+                log::warn!("regex gave up on a line of {} characters", text.len());
+                break;
+            }
+            let Some(end) = end else {
+                start += 1;
+                continue;
+            };
+            caps[0] = Some((start, end));
+            found.push(caps.iter().map(|cap| cap.map(|(from, to)| (offsets[from], offsets[to]))).collect());
+            start = if end > start { end } else { end + 1 };
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found<'a>(pattern: &str, line: &'a str) -> Vec<&'a str> {
+        let regex = Regex::new(pattern).unwrap();
+        regex.captures_all(line, false).iter().map(|caps| caps[0].map_or("", |(start, end)| &line[start..end])).collect()
+    }
+
+    fn groups<'a>(pattern: &str, line: &'a str) -> Vec<Option<&'a str>> {
+        let regex = Regex::new(pattern).unwrap();
+        let all = regex.captures_all(line, false);
+        all[0].iter().map(|cap| cap.map(|(start, end)| &line[start..end])).collect()
+    }
+
+    #[test]
+    fn classes_and_escapes() {
+        assert_eq!(found("[a-c]+", "abcd cab"), ["abc", "cab"]);
+        assert_eq!(found("[^a-c ]+", "abcd cab"), ["d"]);
+        assert_eq!(found("[]x]", "a]x"), ["]", "x"]);
+        assert_eq!(found("[a-]", "b-a"), ["-", "a"]);
+        assert_eq!(found(r"\d+", "a12 b345"), ["12", "345"]);
+        assert_eq!(found(r"\D+", "a12b"), ["a", "b"]);
+        assert_eq!(found(r"\w+", "foo_1 bär"), ["foo_1", "bär"]);
+        assert_eq!(found(r"\s+", "a \tb"), [" \t"]);
+        assert_eq!(found(r"[\d.]+", "v1.25!"), ["1.25"]);
+        assert_eq!(found(r"\.\*", "a.*b"), [".*"]);
+        assert_eq!(found(r"\t", "a\tb"), ["\t"]);
+        assert_eq!(found("a.c", "abc aéc a\u{1F600}c"), ["abc", "aéc", "a\u{1F600}c"]);
+        assert!(Regex::new("[z-a]").is_err());
+        assert!(Regex::new("[abc").is_err());
+        assert!(Regex::new("abc\\").is_err());
+    }
+
+    #[test]
+    fn anchors() {
+        assert_eq!(found("^a", "aaa"), ["a"]);
+        assert_eq!(found("a$", "aaa"), ["a"]);
+        assert_eq!(found("^$", ""), [""]);
+        assert_eq!(found(r"\bcat\b", "cat concat cats cat"), ["cat", "cat"]);
+        assert_eq!(found(r"\Bcat", "cat concat"), ["cat"]);
+        assert!(Regex::new("^*").is_err());
+    }
+
+    #[test]
+    fn groups_and_alternation() {
+        assert_eq!(found("cat|dog", "dog cat cow"), ["dog", "cat"]);
+        assert_eq!(found("gr(a|e)y", "gray grey gruy"), ["gray", "grey"]);
+        assert_eq!(groups(r"(\w+)@(\w+)", "me@host"), [Some("me@host"), Some("me"), Some("host")]);
+        assert_eq!(groups("(?:a)(b)", "ab"), [Some("ab"), Some("b")]);
+        assert_eq!(groups("(a)|(b)", "b"), [Some("b"), None, Some("b")]);
+        assert_eq!(groups("(a)*", "aaa"), [Some("aaa"), Some("a")]);
+        assert_eq!(found("a|", "ba"), ["", "a", ""]);
+        assert!(Regex::new("(a").is_err());
+        assert!(Regex::new("a)").is_err());
+    }
+
+    #[test]
+    fn greedy_and_lazy_quantifiers() {
+        assert_eq!(found("<.*>", "<a><b>"), ["<a><b>"]);
+        assert_eq!(found("<.*?>", "<a><b>"), ["<a>", "<b>"]);
+        assert_eq!(found("a+?", "aaa"), ["a", "a", "a"]);
+        assert_eq!(found("ab?", "a ab"), ["a", "ab"]);
+        assert_eq!(found("ab??", "ab"), ["a"]);
+        assert_eq!(found("a{2}", "aaaaa"), ["aa", "aa"]);
+        assert_eq!(found("a{2,}", "a aaaaa"), ["aaaaa"]);
+        assert_eq!(found("a{1,2}", "aaa"), ["aa", "a"]);
+        assert_eq!(found("a{2,3}?", "aaaa"), ["aa", "aa"]);
+        assert_eq!(found("(ab)+", "ababa"), ["abab"]);
+        assert_eq!(found("(ab)+?", "abab"), ["ab", "ab"]);
+        assert_eq!(found("a{x}", "a{x}"), ["a{x}"]);
+        assert!(Regex::new("*a").is_err());
+    }
+
+    #[test]
+    fn ignore_case_covers_literals_and_ranges() {
+        let regex = Regex::new("[a-c]x").unwrap();
+        assert_eq!(regex.captures_all("BX", true).len(), 1);
+        assert!(regex.captures_all("BX", false).is_empty());
+        assert_eq!(Regex::new("é").unwrap().captures_all("É", true).len(), 1);
+    }
+
+    #[test]
+    fn the_step_and_depth_limits_give_up_instead_of_hanging() {
+        let line = "a".repeat(40);
+        assert!(found("(a|a)*b", &line).is_empty());
+        // Searches run on the main thread, whose stack is bigger than a test thread's.
+        let deep = std::thread::Builder::new().stack_size(8 << 20).spawn(|| {
+            let line = "ab".repeat(DEPTH_LIMIT + 10);
+            found("(ab)*c", &line).is_empty()
+        });
+        assert!(deep.unwrap().join().unwrap());
+    }
+}
@@ -0,0 +1,305 @@
+use std::time::Instant;
+
+use crate::{buffer::Position, regex::{Captures, Regex}};
+
+/// The last search. The pattern outlives its highlights, which `:noh`, Esc Esc or the
+/// highlight timeout turn off without forgetting what was searched for.
+pub struct Search {
+    pub pattern: String,
+    /// Whether it was a `/` search rather than a `?` one, which `n` and `N` go by.
+    pub forward: bool,
+    pub highlighted: bool,
+    pub highlighted_at: Instant,
+}
+
+impl Search {
+    pub fn new(pattern: String, forward: bool) -> Self {
+        Search { pattern, forward, highlighted: true, highlighted_at: Instant::now() }
+    }
+
+    /// The last search of an earlier session, ready to repeat but not highlighted.
+    pub fn remembered(pattern: String) -> Self {
+        Search { highlighted: false, ..Search::new(pattern, true) }
+    }
+}
+
+/// What a search pattern finds: the text itself, or with regex search on, what the
+/// regular expression matches.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matcher {
+    pattern: String,
+    ignore_case: bool,
+    regex: Option<Regex>,
+}
+
+impl Matcher {
+    /// With `smartcase` the search ignores case unless the pattern has a capital letter;
+    /// in a regex, escapes such as `\W` don't count.
+    pub fn new(pattern: &str, smartcase: bool, regex: bool) -> Result<Matcher, String> {
+        let mut escaped = false;
+        let has_capital = pattern.chars().any(|c| {
+            let capital = c.is_uppercase() && !(regex && escaped);
+            escaped = regex && c == '\\' && !escaped;
+            capital
+        });
+        Ok(Matcher {
+            pattern: pattern.to_string(),
+            ignore_case: smartcase && !has_capital,
+            regex: if regex { Some(Regex::new(pattern).map_err(|e| format!("Invalid regex {}: {}", pattern, e))?) } else { None },
+        })
+    }
+
+    /// Byte ranges of every non-overlapping match in `line`.
+    pub fn matches_in_line(&self, line: &str) -> Vec<(usize, usize)> {
+        match &self.regex {
+            Some(regex) => regex.captures_all(line, self.ignore_case).into_iter().filter_map(|caps| caps[0]).collect(),
+            None => matches_in_line(line, &self.pattern, self.ignore_case),
+        }
+    }
+
+    /// Every match in `line` with its groups; without regex search there are none.
+    pub fn captures_in_line(&self, line: &str) -> Vec<Captures> {
+        match &self.regex {
+            Some(regex) => regex.captures_all(line, self.ignore_case),
+            None => matches_in_line(line, &self.pattern, self.ignore_case).into_iter().map(|range| vec![Some(range)]).collect(),
+        }
+    }
+
+    /// `line` with the first match, or with `global` every match, replaced, and how many
+    /// were; `None` when nothing matched. With regex search on, the replacement can refer
+    /// to groups (see [`expand_replacement`]).
+    pub fn replace(&self, line: &str, replacement: &str, global: bool) -> Option<(String, usize)> {
+        let found = self.captures_in_line(line);
+        let found = if global { &found[..] } else { &found[..found.len().min(1)] };
+        if found.is_empty() {
+            return None;
+        }
+        let mut replaced = String::with_capacity(line.len());
+        let mut copied = 0;
+        for caps in found {
+            let Some((start, end)) = caps[0] else {
+                continue;
+            };
+            replaced.push_str(&line[copied..start]);
+            match self.regex {
+                Some(_) => replaced.push_str(&expand_replacement(replacement, line, caps)),
+                None => replaced.push_str(replacement),
+            }
+            copied = end;
+        }
+        replaced.push_str(&line[copied..]);
+        Some((replaced, found.len()))
+    }
+}
+
+/// The text that replaces a regex match: `$1` or `\1` is the first group (`$0`, `\0` or
+/// `&` the whole match, `${12}` for two digits), `\u`/`\l` change the case of the next
+/// character, `\U`/`\L` of everything up to `\E`, and `\n`/`\t` are a line break and a tab.
+pub fn expand_replacement(template: &str, line: &str, caps: &Captures) -> String {
+    let group = |index: usize| caps.get(index).copied().flatten().map_or("", |(start, end)| &line[start..end]);
+    let mut expanded = Expanded::default();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek().copied()) {
+            ('$', Some('{')) => {
+                let digits: String = chars.clone().skip(1).take_while(|c| *c != '}').collect();
+                match digits.parse::<usize>() {
+                    Ok(index) if chars.clone().nth(digits.len() + 1) == Some('}') => {
+                        chars.nth(digits.len() + 1);
+                        expanded.push_str(group(index));
+                    }
+                    _ => expanded.push('$'),
+                }
+            }
+            ('$' | '\\', Some(digit @ '0'..='9')) => {
+                chars.next();
+                expanded.push_str(group(digit as usize - '0' as usize));
+            }
+            ('$', Some('$')) => {
+                chars.next();
+                expanded.push('$');
+            }
+            ('&', _) => expanded.push_str(group(0)),
+            ('\\', Some(escaped)) => {
+                chars.next();
+                match escaped {
+                    'u' => expanded.next_case = Some(true),
+                    'l' => expanded.next_case = Some(false),
+                    'U' => expanded.span_case = Some(true),
+                    'L' => expanded.span_case = Some(false),
+                    'E' => expanded.span_case = None,
+                    'n' => expanded.text.push('\n'),
+                    't' => expanded.text.push('\t'),
+                    other => expanded.push(other),
+                }
+            }
+            (c, _) => expanded.push(c),
+        }
+    }
+    expanded.text
+}
+
+/// Replacement text being built, with the case changes in force: `Some(true)` for upper.
+#[derive(Default)]
+struct Expanded {
+    text: String,
+    next_case: Option<bool>,
+    span_case: Option<bool>,
+}
+
+impl Expanded {
+    fn push(&mut self, c: char) {
+        match self.next_case.take().or(self.span_case) {
+            Some(true) => self.text.extend(c.to_uppercase()),
+            Some(false) => self.text.extend(c.to_lowercase()),
+            None => self.text.push(c),
+        }
+    }
+
+    fn push_str(&mut self, text: &str) {
+        text.chars().for_each(|c| self.push(c));
+    }
+}
+
+/// Byte ranges of every non-overlapping occurrence of `pattern` in `line`.
+fn matches_in_line(line: &str, pattern: &str, ignore_case: bool) -> Vec<(usize, usize)> {
+    if pattern.is_empty() {
+        return Vec::new();
+    }
+    if !ignore_case {
+        return line.match_indices(pattern).map(|(start, text)| (start, start + text.len())).collect();
+    }
+    if line.is_ascii() && pattern.is_ascii() {
+        // Lowercasing ASCII keeps every byte where it was.
+        let pattern = pattern.to_ascii_lowercase();
+        return line.to_ascii_lowercase().match_indices(&pattern).map(|(start, text)| (start, start + text.len())).collect();
+    }
+    let mut found = Vec::new();
+    let mut from = 0;
+    while let Some(c) = line[from..].chars().next() {
+        match caseless_match_length(&line[from..], pattern) {
+            Some(length) => {
+                found.push((from, from + length));
+                from += length;
+            }
+            None => from += c.len_utf8(),
+        }
+    }
+    found
+}
+
+/// The byte length of the match of `pattern` at the start of `text`, ignoring case.
+fn caseless_match_length(text: &str, pattern: &str) -> Option<usize> {
+    let mut chars = text.char_indices();
+    let mut length = 0;
+    for wanted in pattern.chars() {
+        let (start, c) = chars.next()?;
+        if !c.to_lowercase().eq(wanted.to_lowercase()) {
+            return None;
+        }
+        length = start + c.len_utf8();
+    }
+    Some(length)
+}
+
+/// The next match strictly after (or before) `from`, wrapping around the end of the buffer.
+pub fn find(lines: &[String], from: Position, matcher: &Matcher, forward: bool) -> Option<Position> {
+    let (row, col) = from;
+    if lines.is_empty() || matcher.pattern.is_empty() {
+        return None;
+    }
+    let count = lines.len();
+    // The starting line comes round again last, for matches on the other side of the cursor.
+    for step in 0..=count {
+        let index = if forward { (row + step) % count } else { (row + count - step % count) % count };
+        let candidates = matcher.matches_in_line(&lines[index]);
+        let found = if forward {
+            candidates.into_iter().map(|(start, _)| start).find(|&start| step > 0 || start > col)
+        } else {
+            candidates.into_iter().map(|(start, _)| start).rev().find(|&start| step > 0 || start < col)
+        };
+        if let Some(start) = found {
+            return Some((index, start));
+        }
+    }
+    None
+}
+
+/// How many matches the status bar counts before it settles for "1000+".
+pub const COUNT_LIMIT: usize = 1000;
+
+/// Where the first `limit` matches start, in buffer order.
+pub fn positions(lines: &[String], matcher: &Matcher, limit: usize) -> Vec<Position> {
+    let mut positions = Vec::new();
+    for (row, line) in lines.iter().enumerate() {
+        for (start, _) in matcher.matches_in_line(line) {
+            if positions.len() == limit {
+                return positions;
+            }
+            positions.push((row, start));
+        }
+    }
+    positions
+}
+
+/// "match 3 of 27" with the cursor on a match, "27 matches" elsewhere. With `limit`
+/// positions there may be more, so the total becomes "1000+".
+pub fn describe_matches(positions: &[Position], cursor: Position, limit: usize) -> String {
+    let total = if positions.len() >= limit { format!("{}+", limit) } else { positions.len().to_string() };
+    match positions.binary_search(&cursor) {
+        Ok(index) => format!("match {} of {}", index + 1, total),
+        Err(_) if positions.len() == 1 => "1 match".to_string(),
+        Err(_) => format!("{} matches", total),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(template: &str, pattern: &str, line: &str) -> String {
+        let caps = &Regex::new(pattern).unwrap().captures_all(line, false)[0];
+        expand_replacement(template, line, caps)
+    }
+
+    #[test]
+    fn smartcase_ignores_case_until_the_pattern_has_a_capital() {
+        let line = "Foo foo FOO";
+        assert_eq!(Matcher::new("foo", true, false).unwrap().matches_in_line(line).len(), 3);
+        assert_eq!(Matcher::new("Foo", true, false).unwrap().matches_in_line(line), [(0, 3)]);
+        assert_eq!(Matcher::new("foo", false, false).unwrap().matches_in_line(line), [(4, 7)]);
+        assert_eq!(Matcher::new("é", true, false).unwrap().matches_in_line("É é"), [(0, 2), (3, 5)]);
+        assert_eq!(Matcher::new(r"fo\W", true, true).unwrap().matches_in_line("FO! fo!").len(), 2);
+        assert_eq!(Matcher::new(r"F\w+", true, true).unwrap().matches_in_line(line), [(0, 3), (8, 11)]);
+        assert!(Matcher::new("(", true, true).is_err());
+    }
+
+    #[test]
+    fn replace_first_or_every_match() {
+        let matcher = Matcher::new("o", false, false).unwrap();
+        assert_eq!(matcher.replace("foo", "0", false), Some(("f0o".to_string(), 1)));
+        assert_eq!(matcher.replace("foo", "$0", true), Some(("f$0$0".to_string(), 2)));
+        assert_eq!(matcher.replace("bar", "0", true), None);
+        let matcher = Matcher::new(r"(\w+)=(\w+)", false, true).unwrap();
+        assert_eq!(matcher.replace("a=1 b=2", "$2=$1", true), Some(("1=a 2=b".to_string(), 2)));
+    }
+
+    #[test]
+    fn groups_expand_by_dollar_and_backslash() {
+        assert_eq!(expand("$2-$1", r"(\w+) (\w+)", "hello world"), "world-hello");
+        assert_eq!(expand(r"\2-\1", r"(\w+) (\w+)", "hello world"), "world-hello");
+        assert_eq!(expand("[$0|&|\\0]", "l+", "hello"), "[ll|ll|ll]");
+        assert_eq!(expand("${1}0", "(a)", "a"), "a0");
+        assert_eq!(expand("$3.$${x", "(a)", "a"), ".${x");
+        assert_eq!(expand(r"a\nb\tc\&", "x", "x"), "a\nb\tc&");
+    }
+
+    #[test]
+    fn case_changes_apply_to_the_next_character_or_up_to_e() {
+        assert_eq!(expand(r"\u$1", r"(\w+)", "hello"), "Hello");
+        assert_eq!(expand(r"\l$1", r"(\w+)", "HELLO"), "hELLO");
+        assert_eq!(expand(r"\U$1\E!$1", r"(\w+)", "hello"), "HELLO!hello");
+        assert_eq!(expand(r"\L$1 x\E X", r"(\w+)", "HeLLo"), "hello x X");
+        assert_eq!(expand(r"\U\l$1", r"(\w+)", "hello"), "hELLO");
+    }
+}
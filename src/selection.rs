@@ -0,0 +1,216 @@
+use crate::buffer::{Buffer, Position};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelectionKind {
+    Char,
+    /// Whole lines, newline included.
+    Line,
+    /// The rectangle between anchor and head columns on every row in between.
+    Block,
+}
+
+/// Selected text from `anchor`, where selecting started, to `head`, which follows the
+/// cursor. Either end may come first in the document.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Selection {
+    pub anchor: Position,
+    pub head: Position,
+    pub kind: SelectionKind,
+}
+
+impl Selection {
+    pub fn new(anchor: Position, head: Position, kind: SelectionKind) -> Self {
+        Selection { anchor, head, kind }
+    }
+
+    /// The ends in document order.
+    pub fn ordered(&self) -> (Position, Position) {
+        if self.anchor <= self.head { (self.anchor, self.head) } else { (self.head, self.anchor) }
+    }
+
+    fn rows(&self) -> (usize, usize) {
+        (self.anchor.0.min(self.head.0), self.anchor.0.max(self.head.0))
+    }
+
+    /// Byte columns selected on `row`, end exclusive; `usize::MAX` runs through the newline.
+    pub fn columns_on_row(&self, row: usize) -> Option<(usize, usize)> {
+        let (first, last) = self.rows();
+        if row < first || row > last {
+            return None;
+        }
+        match self.kind {
+            SelectionKind::Char => {
+                let ((start_row, start_col), (end_row, end_col)) = self.ordered();
+                let from = if row == start_row { start_col } else { 0 };
+                let to = if row == end_row { end_col } else { usize::MAX };
+                Some((from, to))
+            }
+            SelectionKind::Line => Some((0, usize::MAX)),
+            SelectionKind::Block => {
+                let (left, right) = (self.anchor.1.min(self.head.1), self.anchor.1.max(self.head.1));
+                Some((left, right))
+            }
+        }
+    }
+
+    /// Char columns selected on `row` holding `line`, end exclusive, and whether the
+    /// newline ending the row is selected too, for drawing.
+    pub fn char_columns_on_row(&self, row: usize, line: &str) -> Option<(usize, usize, bool)> {
+        let (from, to) = self.columns_on_row(row)?;
+        let char_column = |byte: usize| line.char_indices().take_while(|&(offset, _)| offset < byte).count();
+        Some((char_column(from), char_column(to), to == usize::MAX))
+    }
+
+    /// The span a character or line selection covers, end exclusive. Linewise spans take
+    /// the newline after the last line, or before the first when there is no line after.
+    fn span(&self, lines: &[String]) -> (Position, Position) {
+        match self.kind {
+            SelectionKind::Line => {
+                let (first, last) = self.rows();
+                if last + 1 < lines.len() {
+                    ((first, 0), (last + 1, 0))
+                } else if first > 0 {
+                    ((first - 1, lines[first - 1].len()), (last, lines[last].len()))
+                } else {
+                    ((first, 0), (last, lines[last].len()))
+                }
+            }
+            _ => self.ordered(),
+        }
+    }
+
+    /// Block columns clamped to `line`, so short lines contribute what they have. The
+    /// columns are bytes on the anchor and head rows, so on other rows they can fall inside
+    /// a character and are moved back to its start.
+    fn block_columns(&self, line: &str) -> (usize, usize) {
+        let (left, right) = (self.anchor.1.min(self.head.1), self.anchor.1.max(self.head.1));
+        (line.floor_char_boundary(left), line.floor_char_boundary(right))
+    }
+
+    pub fn text(&self, lines: &[String]) -> String {
+        match self.kind {
+            SelectionKind::Char => {
+                let ((start_row, start_col), (end_row, end_col)) = self.ordered();
+                if start_row == end_row {
+                    return lines[start_row][start_col..end_col].to_string();
+                }
+                let mut text = lines[start_row][start_col..].to_string();
+                for line in &lines[start_row + 1..end_row] {
+                    text.push('\n');
+                    text.push_str(line);
+                }
+                text.push('\n');
+                text.push_str(&lines[end_row][..end_col]);
+                text
+            }
+            SelectionKind::Line => {
+                let (first, last) = self.rows();
+                lines[first..=last].iter().map(|line| format!("{}\n", line)).collect()
+            }
+            SelectionKind::Block => {
+                let (first, last) = self.rows();
+                lines[first..=last].iter()
+                    .map(|line| {
+                        let (from, to) = self.block_columns(line);
+                        &line[from..to]
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        }
+    }
+
+    /// Removes the selected text from `buffer` and returns where the cursor belongs after.
+    pub fn delete(&self, buffer: &mut Buffer) -> Position {
+        match self.kind {
+            SelectionKind::Block => {
+                let (first, last) = self.rows();
+                for row in first..=last {
+                    let (from, to) = self.block_columns(&buffer[row]);
+                    buffer.delete((row, from), (row, to));
+                }
+                (first, buffer[first].floor_char_boundary(self.anchor.1.min(self.head.1)))
+            }
+            SelectionKind::Line => {
+                let (start, end) = self.span(buffer);
+                buffer.delete(start, end);
+                (self.rows().0.min(buffer.len() - 1), 0)
+            }
+            SelectionKind::Char => {
+                let (start, end) = self.span(buffer);
+                buffer.delete(start, end);
+                start
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{
+        buffer::Change,
+        testing::{lines, spot},
+        undo::History,
+    };
+
+    const KINDS: [SelectionKind; 3] = [SelectionKind::Char, SelectionKind::Line, SelectionKind::Block];
+
+    fn kind() -> impl Strategy<Value = SelectionKind> {
+        prop::sample::select(&KINDS[..])
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2000))]
+
+        #[test]
+        fn swapping_anchor_and_head_selects_the_same_text(lines in lines(6), anchor in spot(), head in spot(), kind in kind()) {
+            let (anchor, head) = (anchor.in_lines(&lines), head.in_lines(&lines));
+            let selection = Selection::new(anchor, head, kind);
+            let swapped = Selection::new(head, anchor, kind);
+            prop_assert_eq!(selection.ordered(), swapped.ordered());
+            prop_assert!(selection.ordered().0 <= selection.ordered().1);
+            prop_assert_eq!(selection.text(&lines), swapped.text(&lines));
+            for row in 0..lines.len() {
+                prop_assert_eq!(selection.columns_on_row(row), swapped.columns_on_row(row));
+            }
+        }
+
+        #[test]
+        fn deleting_a_selection_leaves_the_cursor_on_a_character(lines in lines(6), anchor in spot(), head in spot(), kind in kind()) {
+            let mut buffer = Buffer::new();
+            buffer.reset(lines);
+            let selection = Selection::new(anchor.in_lines(&buffer), head.in_lines(&buffer), kind);
+            let (row, col) = selection.delete(&mut buffer);
+            prop_assert!(row < buffer.len());
+            prop_assert!(buffer[row].is_char_boundary(col), "column {} in {:?}", col, buffer[row]);
+        }
+
+        #[test]
+        fn undoing_a_deleted_selection_restores_the_text_byte_for_byte(lines in lines(6), anchor in spot(), head in spot(), kind in kind()) {
+            let mut buffer = Buffer::new();
+            buffer.reset(lines);
+            buffer.take_changes();
+            let mut history = History::default();
+            let original = buffer.join("\n");
+            let selection = Selection::new(anchor.in_lines(&buffer), head.in_lines(&buffer), kind);
+            let selected = selection.text(&buffer);
+            selection.delete(&mut buffer);
+            let changes = buffer.take_changes();
+            if selection.kind == SelectionKind::Char {
+                prop_assert_eq!(changes.iter().map(|event| match &event.change {
+                    Change::Deleted { text, .. } => text.len(),
+                    _ => 0,
+                }).sum::<usize>(), selected.len());
+            }
+            for event in &changes {
+                history.record(&event.change, (0, 0));
+            }
+            history.seal(usize::MAX);
+            history.undo(&mut buffer);
+            prop_assert_eq!(buffer.join("\n"), original);
+        }
+    }
+}
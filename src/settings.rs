@@ -0,0 +1,145 @@
+use toml::{Table, Value};
+
+use crate::config::Config;
+
+/// A flattened view of every config option (`main_section.frame.corner = "rounded"`),
+/// built from the serialized config so new options show up without touching this file.
+pub struct SettingsView {
+    pub entries: Vec<(String, Value)>,
+    pub selected: usize,
+    pub editing: Option<String>,
+    pub error: Option<String>,
+}
+
+impl SettingsView {
+    pub fn new(config: &Config) -> Self {
+        SettingsView {
+            entries: flatten(config),
+            selected: 0,
+            editing: None,
+            error: None,
+        }
+    }
+
+    pub fn refresh(&mut self, config: &Config) {
+        self.entries = flatten(config);
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn selected_entry(&self) -> Option<&(String, Value)> {
+        self.entries.get(self.selected)
+    }
+
+    /// Toggles a boolean in place, or opens the inline editor for anything else.
+    pub fn activate(&mut self, config: &Config) -> Option<Config> {
+        let (key, value) = self.selected_entry()?.clone();
+        match value {
+            Value::Boolean(b) => self.apply(config, &key, Value::Boolean(!b)),
+            Value::String(s) => {
+                self.editing = Some(s);
+                None
+            }
+            other => {
+                self.editing = Some(other.to_string());
+                None
+            }
+        }
+    }
+
+    /// Parses the edit field as the same type as the current value and applies it.
+    pub fn commit_edit(&mut self, config: &Config) -> Option<Config> {
+        let text = self.editing.take()?;
+        let (key, current) = self.selected_entry()?.clone();
+        let value = match parse_like(&current, &text) {
+            Ok(value) => value,
+            Err(e) => {
+                self.error = Some(e);
+                return None;
+            }
+        };
+        self.apply(config, &key, value)
+    }
+
+    fn apply(&mut self, config: &Config, key: &str, value: Value) -> Option<Config> {
+        match set_option(config, key, value) {
+            Ok(updated) => {
+                self.error = None;
+                self.refresh(&updated);
+                Some(updated)
+            }
+            Err(e) => {
+                self.error = Some(e);
+                None
+            }
+        }
+    }
+}
+
+pub fn flatten(config: &Config) -> Vec<(String, Value)> {
+    match Value::try_from(config) {
+        Ok(Value::Table(table)) => flatten_table(&table),
+        _ => Vec::new(),
+    }
+}
+
+/// The options set differently in `config` than in `saved`, for writing back only those.
+pub fn changed_options(saved: &Config, config: &Config) -> Vec<(String, Value)> {
+    let saved = flatten(saved);
+    flatten(config).into_iter().filter(|entry| !saved.contains(entry)).collect()
+}
+
+/// Every value in `table` under its dotted key.
+pub fn flatten_table(table: &Table) -> Vec<(String, Value)> {
+    let mut entries = Vec::new();
+    flatten_into(table, "", &mut entries);
+    entries
+}
+
+fn flatten_into(table: &Table, prefix: &str, entries: &mut Vec<(String, Value)>) {
+    for (key, value) in table {
+        let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match value {
+            Value::Table(inner) => flatten_into(inner, &path, entries),
+            other => entries.push((path, other.clone())),
+        }
+    }
+}
+
+/// Returns a copy of `config` with the dotted option `key` replaced by `value`, going
+/// through serde so the result is validated exactly like a config file would be.
+pub fn set_option(config: &Config, key: &str, value: Value) -> Result<Config, String> {
+    let mut root = Value::try_from(config).map_err(|e| e.to_string())?;
+    let mut slot = &mut root;
+    for part in key.split('.') {
+        slot = slot.get_mut(part).ok_or_else(|| format!("unknown option '{}'", key))?;
+    }
+    *slot = value;
+    root.try_into().map_err(|e: toml::de::Error| e.to_string())
+}
+
+pub fn parse_like(current: &Value, text: &str) -> Result<Value, String> {
+    match current {
+        Value::Boolean(_) => match text {
+            "true" | "on" | "yes" => Ok(Value::Boolean(true)),
+            "false" | "off" | "no" => Ok(Value::Boolean(false)),
+            _ => Err(format!("'{}' is not a boolean", text)),
+        },
+        Value::Integer(_) => text.parse::<i64>()
+            .map(Value::Integer)
+            .map_err(|_| format!("'{}' is not a number", text)),
+        Value::Float(_) => text.parse::<f64>()
+            .map(Value::Float)
+            .map_err(|_| format!("'{}' is not a number", text)),
+        _ => Ok(Value::String(text.to_string())),
+    }
+}
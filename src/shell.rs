@@ -0,0 +1,87 @@
+use std::{
+    io::{Read, Write},
+    path::{self, Path},
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use crate::{platform, tasks::TaskContext};
+
+/// How a shell command went: whether it exited with success, its exit code if it had one,
+/// and what it wrote to stdout and stderr.
+#[derive(Debug)]
+pub struct Finished {
+    pub command: String,
+    pub success: bool,
+    pub code: Option<i32>,
+    pub output: String,
+}
+
+impl Finished {
+    /// One line for the status bar: the last thing the command said, and on failure its
+    /// exit code.
+    pub fn summary(&self) -> String {
+        let last = self.output.lines().rev().map(str::trim).find(|line| !line.is_empty());
+        match (self.success, last) {
+            (true, Some(last)) => format!("!{}: {}", self.command, last),
+            (true, None) => format!("!{} finished", self.command),
+            (false, last) => {
+                let code = self.code.map_or("killed".to_string(), |code| format!("exit {}", code));
+                format!("!{} failed ({}){}", self.command, code, last.map(|last| format!(": {}", last)).unwrap_or_default())
+            }
+        }
+    }
+}
+
+/// Runs `command` through the platform's shell in `dir`, or zepto's own directory, with
+/// `ZEPTO_FILE` set to the file being edited and `input` on stdin. The file's path is made
+/// absolute, since `dir` need not be where it was relative to. Cancelling the task
+/// kills the command.
+pub fn run<T>(command: &str, file: Option<&str>, dir: Option<&Path>, input: Option<String>, context: &TaskContext<T>) -> Result<Finished, String> {
+    let mut shell = if platform::WINDOWS {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    };
+    if let Some(file) = file {
+        shell.env("ZEPTO_FILE", path::absolute(file).unwrap_or_else(|_| file.into()));
+    }
+    if let Some(dir) = dir.filter(|dir| !dir.as_os_str().is_empty()) {
+        shell.current_dir(dir);
+    }
+    let stdin = if input.is_some() { Stdio::piped() } else { Stdio::null() };
+    let mut child = shell.stdin(stdin).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()
+        .map_err(|e| format!("can't run {}: {}", command, e))?;
+    if let (Some(mut pipe), Some(input)) = (child.stdin.take(), input) {
+        // A command that doesn't read it all just closes the pipe, which is no error here.
+        thread::spawn(move || pipe.write_all(input.as_bytes()));
+    }
+    // Read on threads of their own so a command filling one pipe doesn't stall on the other.
+    let readers = [child.stdout.take().map(read_all), child.stderr.take().map(read_all)];
+    let status = loop {
+        if context.is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err("cancelled".to_string());
+        }
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => break status,
+            None => thread::sleep(Duration::from_millis(20)),
+        }
+    };
+    let output = readers.into_iter().flatten().filter_map(|reader| reader.join().ok()).collect::<Vec<_>>().concat();
+    Ok(Finished { command: command.to_string(), success: status.success(), code: status.code(), output })
+}
+
+fn read_all(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = pipe.read_to_end(&mut bytes);
+        String::from_utf8_lossy(&bytes).into_owned()
+    })
+}
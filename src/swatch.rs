@@ -0,0 +1,34 @@
+/// Hex color literals in `line`: `#rgb`, `#rrggbb` and `#rrggbbaa`, as byte ranges with
+/// the color they name. Alpha is ignored, since the terminal can't blend.
+pub fn hex_colors(line: &str) -> Vec<(usize, usize, (u8, u8, u8))> {
+    let bytes = line.as_bytes();
+    let mut colors = Vec::new();
+    for (at, _) in line.match_indices('#') {
+        if at > 0 && (bytes[at - 1].is_ascii_alphanumeric() || bytes[at - 1] == b'&') {
+            continue;
+        }
+        let digits = bytes[at + 1..].iter().take_while(|b| b.is_ascii_hexdigit()).count();
+        let followed_by_word = bytes.get(at + 1 + digits).is_some_and(|b| b.is_ascii_alphanumeric() || *b == b'_');
+        if followed_by_word {
+            continue;
+        }
+        let hex = &line[at + 1..at + 1 + digits];
+        let channel = |i: usize, width: usize| {
+            let value = u8::from_str_radix(&hex[i * width..(i + 1) * width], 16).unwrap_or(0);
+            if width == 1 { value * 17 } else { value }
+        };
+        let rgb = match digits {
+            3 => (channel(0, 1), channel(1, 1), channel(2, 1)),
+            6 | 8 => (channel(0, 2), channel(1, 2), channel(2, 2)),
+            _ => continue,
+        };
+        colors.push((at, at + 1 + digits, rgb));
+    }
+    colors
+}
+
+/// Whether dark text reads better than light text on `rgb`.
+pub fn is_light((r, g, b): (u8, u8, u8)) -> bool {
+    // Rec. 601 luma, close enough for picking a text color.
+    r as u32 * 299 + g as u32 * 587 + b as u32 * 114 > 128_000
+}
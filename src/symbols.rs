@@ -0,0 +1,104 @@
+use crate::highlight::{Scopes, Syntax};
+
+/// How far above the cursor to look for enclosing scopes, so breadcrumbs stay cheap in
+/// very long files.
+const MAX_SCAN_LINES: usize = 5_000;
+
+/// Words that may precede a definition keyword without changing what it defines.
+const MODIFIERS: &[&str] = &[
+    "pub", "pub(crate)", "pub(super)", "async", "unsafe", "const", "extern", "export", "default",
+    "static", "abstract",
+];
+
+/// Returns the scopes enclosing `row`, outermost first, e.g. `["impl Editor", "fn draw_ui"]`.
+pub fn enclosing_scopes(lines: &[String], row: usize, syntax: &Syntax) -> Vec<String> {
+    let above = lines.iter().enumerate()
+        .take(row.saturating_add(1).min(lines.len()))
+        .rev()
+        .take(MAX_SCAN_LINES);
+    let mut scopes = Vec::new();
+
+    match syntax.scopes {
+        Scopes::Definitions(keywords) => {
+            let mut threshold = usize::MAX;
+            for (i, line) in above {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let indent = line.len() - line.trim_start().len();
+                if indent >= threshold && i != row {
+                    continue;
+                }
+                if let Some(scope) = definition(line.trim_start(), keywords) {
+                    scopes.push(scope);
+                }
+                threshold = indent;
+                if threshold == 0 {
+                    break;
+                }
+            }
+        }
+        Scopes::Headings => {
+            let mut level_limit = usize::MAX;
+            for (_, line) in above {
+                let level = line.chars().take_while(|c| *c == '#').count();
+                if level == 0 || level >= level_limit || !line[level..].starts_with(' ') {
+                    continue;
+                }
+                scopes.push(line[level..].trim().to_string());
+                level_limit = level;
+                if level == 1 {
+                    break;
+                }
+            }
+        }
+        Scopes::Sections => {
+            if let Some((_, line)) = above.into_iter().find(|(_, line)| line.trim_start().starts_with('[')) {
+                scopes.push(line.trim().to_string());
+            }
+        }
+    }
+
+    scopes.reverse();
+    scopes
+}
+
+fn definition(line: &str, keywords: &[&str]) -> Option<String> {
+    let mut rest = line;
+    while let Some(modifier) = MODIFIERS.iter().find(|m| rest.starts_with(&format!("{} ", m))) {
+        rest = rest[modifier.len()..].trim_start();
+    }
+    let keyword = keywords.iter().find(|k| {
+        rest.strip_prefix(**k).is_some_and(|after| after.starts_with([' ', '<']))
+    })?;
+    let mut rest = &rest[keyword.len()..];
+    if rest.starts_with('<') {
+        rest = skip_generics(rest);
+    }
+    let rest = rest.trim_start();
+    let name = if *keyword == "impl" {
+        // `impl Trait for Type` reads best whole; drop only the body and where clause.
+        rest.split(['{', ';']).next().unwrap_or(rest).split(" where").next().unwrap_or(rest).trim()
+    } else {
+        let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$')).unwrap_or(rest.len());
+        &rest[..end]
+    };
+    (!name.is_empty()).then(|| format!("{} {}", keyword, name))
+}
+
+fn skip_generics(text: &str) -> &str {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return &text[i + 1..];
+                }
+            }
+            _ => {}
+        }
+    }
+    ""
+}
@@ -0,0 +1,112 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Address {
+    /// 1-based, as written by ctags `-n`.
+    Line(usize),
+    /// A `/^...$/` search pattern with the slashes and escapes removed.
+    Pattern(String),
+}
+
+impl Address {
+    fn parse(field: &str) -> Option<Address> {
+        let field = field.split(";\"").next()?.trim_end_matches(';');
+        if let Ok(line) = field.parse() {
+            return Some(Address::Line(line));
+        }
+        let delimiter = field.chars().next().filter(|c| *c == '/' || *c == '?')?;
+        let body = field[1..].strip_suffix(delimiter)?;
+        let mut pattern = String::with_capacity(body.len());
+        let mut chars = body.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => pattern.extend(chars.next()),
+                other => pattern.push(other),
+            }
+        }
+        Some(Address::Pattern(pattern))
+    }
+
+    /// Returns the 0-based line the address points at in `lines`.
+    pub fn resolve(&self, lines: &[String]) -> Option<usize> {
+        match self {
+            Address::Line(line) => Some(line.saturating_sub(1).min(lines.len().saturating_sub(1))),
+            Address::Pattern(pattern) => {
+                let (anchored_start, rest) = match pattern.strip_prefix('^') {
+                    Some(rest) => (true, rest),
+                    None => (false, pattern.as_str()),
+                };
+                let (anchored_end, needle) = match rest.strip_suffix('$') {
+                    Some(needle) => (true, needle),
+                    None => (false, rest),
+                };
+                lines.iter().position(|line| match (anchored_start, anchored_end) {
+                    (true, true) => line == needle,
+                    (true, false) => line.starts_with(needle),
+                    (false, true) => line.ends_with(needle),
+                    (false, false) => line.contains(needle),
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Tag {
+    pub name: String,
+    pub file: PathBuf,
+    pub address: Address,
+}
+
+/// A parsed ctags `tags` file, sorted by name so lookups are a binary search.
+pub struct TagFile {
+    pub path: PathBuf,
+    modified: Option<SystemTime>,
+    tags: Vec<Tag>,
+}
+
+impl TagFile {
+    pub fn load(path: &Path) -> io::Result<TagFile> {
+        let content = fs::read_to_string(path)?;
+        let base = path.parent().unwrap_or(Path::new("."));
+        let mut tags: Vec<Tag> = content.lines()
+            .filter(|line| !line.starts_with("!_TAG_"))
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                let name = fields.next()?;
+                let file = fields.next()?;
+                let address = Address::parse(fields.next()?)?;
+                Some(Tag { name: name.to_string(), file: base.join(file), address })
+            })
+            .collect();
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+        log::info!("loaded {} tags from {}", tags.len(), path.display());
+        Ok(TagFile { path: path.to_path_buf(), modified: modified_time(path), tags })
+    }
+
+    /// True when the file on disk was regenerated since it was loaded.
+    pub fn is_stale(&self) -> bool {
+        modified_time(&self.path) != self.modified
+    }
+
+    pub fn lookup(&self, name: &str) -> &[Tag] {
+        let start = self.tags.partition_point(|tag| tag.name.as_str() < name);
+        let end = start + self.tags[start..].partition_point(|tag| tag.name == name);
+        &self.tags[start..end]
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Looks for a `tags` file in `start` and each of its parents, like vim's `tags=./tags;`.
+pub fn find_tags_file(start: &Path) -> Option<PathBuf> {
+    start.ancestors()
+        .map(|dir| dir.join("tags"))
+        .find(|candidate| candidate.is_file())
+}
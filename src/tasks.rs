@@ -0,0 +1,111 @@
+use std::{
+    collections::HashMap,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TaskId(u64);
+
+pub enum TaskEvent<T> {
+    Progress { id: TaskId, done: u64, total: Option<u64> },
+    Finished { id: TaskId, result: Result<T, String> },
+}
+
+/// Shared flag a task polls between units of work; once set the task should give up early.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Handed to a running task so it can report how far along it is and notice cancellation.
+pub struct TaskContext<T> {
+    id: TaskId,
+    sender: Sender<TaskEvent<T>>,
+    token: CancellationToken,
+}
+
+impl<T> TaskContext<T> {
+    pub fn progress(&self, done: u64, total: Option<u64>) {
+        let _ = self.sender.send(TaskEvent::Progress { id: self.id, done, total });
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Convenience for `?`-style early returns inside task bodies.
+    pub fn check_cancelled(&self) -> Result<(), String> {
+        if self.is_cancelled() { Err("cancelled".to_string()) } else { Ok(()) }
+    }
+}
+
+/// Runs long jobs (file loading, grep, external tools) on their own threads and reports back
+/// over a channel that the editor loop drains between frames, so the UI never blocks on them.
+pub struct TaskRunner<T> {
+    sender: Sender<TaskEvent<T>>,
+    receiver: Receiver<TaskEvent<T>>,
+    tokens: HashMap<TaskId, CancellationToken>,
+    next_id: u64,
+}
+
+impl<T: Send + 'static> TaskRunner<T> {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        TaskRunner { sender, receiver, tokens: HashMap::new(), next_id: 0 }
+    }
+
+    pub fn spawn<F>(&mut self, name: &str, work: F) -> TaskId
+    where
+        F: FnOnce(&TaskContext<T>) -> Result<T, String> + Send + 'static,
+    {
+        let id = TaskId(self.next_id);
+        self.next_id += 1;
+
+        let token = CancellationToken::default();
+        self.tokens.insert(id, token.clone());
+
+        let sender = self.sender.clone();
+        let spawned = thread::Builder::new()
+            .name(format!("zepto-{}", name))
+            .spawn(move || {
+                let context = TaskContext { id, sender, token };
+                let result = panic::catch_unwind(AssertUnwindSafe(|| work(&context)))
+                    .unwrap_or_else(|_| Err("task panicked".to_string()));
+                let _ = context.sender.send(TaskEvent::Finished { id, result });
+            });
+        if let Err(e) = spawned {
+            let _ = self.sender.send(TaskEvent::Finished { id, result: Err(e.to_string()) });
+        }
+        id
+    }
+
+    pub fn cancel(&mut self, id: TaskId) {
+        if let Some(token) = self.tokens.remove(&id) {
+            token.cancel();
+        }
+    }
+
+    pub fn poll(&mut self) -> Vec<TaskEvent<T>> {
+        let events: Vec<_> = self.receiver.try_iter().collect();
+        for event in &events {
+            if let TaskEvent::Finished { id, .. } = event {
+                self.tokens.remove(id);
+            }
+        }
+        events
+    }
+}
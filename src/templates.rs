@@ -0,0 +1,104 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{buffer::Position, paths};
+
+/// Where templates live: one file each, named after the filetype or extension it is for,
+/// such as `rust.rs`, `shell` or `html.html`.
+pub fn templates_dir() -> PathBuf {
+    paths::config_dir().join("templates")
+}
+
+/// The template for a new file at `path` of `filetype`: one named after the filetype, or
+/// failing that after the file's extension.
+pub fn find(path: &str, filetype: Option<&str>) -> Option<PathBuf> {
+    let extension = Path::new(path).extension().and_then(|extension| extension.to_str());
+    let mut templates: Vec<PathBuf> = fs::read_dir(templates_dir()).ok()?.flatten().map(|entry| entry.path()).collect();
+    templates.sort();
+    [filetype, extension].into_iter().flatten().find_map(|name| {
+        templates.iter().find(|template| template.is_file() && template.file_stem().is_some_and(|stem| stem == name)).cloned()
+    })
+}
+
+/// What the placeholders in a template stand for.
+pub struct Placeholders {
+    /// `{{filename}}`: the new file's name without its directory.
+    pub filename: String,
+    /// `{{name}}`: the file name without its extension.
+    pub name: String,
+    /// `{{date}}`: today, as `2024-05-31`.
+    pub date: String,
+    /// `{{year}}`: this year.
+    pub year: String,
+    /// `{{author}}`: the `author` option, or the login name.
+    pub author: String,
+}
+
+impl Placeholders {
+    pub fn new(path: &str, author: &str) -> Self {
+        let path = Path::new(path);
+        let text = |part: Option<&std::ffi::OsStr>| part.map(|part| part.to_string_lossy().into_owned()).unwrap_or_default();
+        let days = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / 86_400;
+        let (year, month, day) = civil_date(days as i64);
+        let author = match author {
+            "" => env::var("USER").or_else(|_| env::var("USERNAME")).unwrap_or_default(),
+            author => author.to_string(),
+        };
+        Placeholders {
+            filename: text(path.file_name()),
+            name: text(path.file_stem()),
+            date: format!("{:04}-{:02}-{:02}", year, month, day),
+            year: year.to_string(),
+            author,
+        }
+    }
+}
+
+/// The template's lines with its placeholders filled in, and where `{{cursor}}` was, if
+/// it has one. Unknown placeholders are left as they are.
+pub fn fill(template: &str, placeholders: &Placeholders) -> (Vec<String>, Option<Position>) {
+    let mut cursor = None;
+    let lines = template.lines().enumerate().map(|(row, line)| {
+        let mut filled = String::new();
+        let mut rest = line;
+        while let Some(start) = rest.find("{{")
+            && let Some(end) = rest[start..].find("}}").map(|end| start + end)
+        {
+            filled.push_str(&rest[..start]);
+            let value = match rest[start + 2..end].trim() {
+                "filename" => &placeholders.filename,
+                "name" => &placeholders.name,
+                "date" => &placeholders.date,
+                "year" => &placeholders.year,
+                "author" => &placeholders.author,
+                "cursor" => {
+                    cursor.get_or_insert((row, filled.len()));
+                    ""
+                }
+                _ => &rest[start..end + 2],
+            };
+            filled.push_str(value);
+            rest = &rest[end + 2..];
+        }
+        filled.push_str(rest);
+        filled
+    }).collect();
+    (lines, cursor)
+}
+
+/// The year, month and day `days` after 1970-01-01, in the proleptic Gregorian calendar.
+fn civil_date(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,75 @@
+use crate::{
+    buffer::Position,
+    regex::Captures,
+    search::{self, Matcher},
+};
+
+/// The `:regex` panel: a pattern and a replacement being tried out on the buffer.
+pub struct RegexTester {
+    pub pattern: String,
+    pub replacement: String,
+    /// Typing goes to the replacement rather than the pattern.
+    pub editing_replacement: bool,
+    /// `(revision, matcher, count)` from the last count, redone only after a change.
+    counted: Option<(u64, Matcher, usize)>,
+}
+
+impl RegexTester {
+    pub fn new(pattern: String) -> Self {
+        RegexTester { pattern, replacement: String::new(), editing_replacement: false, counted: None }
+    }
+
+    /// How many matches `matcher` has in `lines`, up to [`search::COUNT_LIMIT`].
+    pub fn count(&mut self, lines: &[String], revision: u64, matcher: &Matcher) -> usize {
+        match &self.counted {
+            Some((counted_revision, counted_matcher, count)) if *counted_revision == revision && counted_matcher == matcher => *count,
+            _ => {
+                let count = search::positions(lines, matcher, search::COUNT_LIMIT).len();
+                self.counted = Some((revision, matcher.clone(), count));
+                count
+            }
+        }
+    }
+
+    pub fn field(&mut self) -> &mut String {
+        if self.editing_replacement { &mut self.replacement } else { &mut self.pattern }
+    }
+
+    /// The pattern as a regex, whatever `regex_search` says.
+    pub fn matcher(&self, smartcase: bool) -> Result<Matcher, String> {
+        Matcher::new(&self.pattern, smartcase, true)
+    }
+
+    /// The `:%s` command that makes the replacement for real.
+    pub fn substitute_command(&self) -> String {
+        format!("%s/{}/{}/g", self.pattern.replace('/', "\\/"), self.replacement.replace('/', "\\/"))
+    }
+}
+
+/// The first match at or after `from`, wrapping around the end of the buffer, with the
+/// row it is on.
+pub fn match_from(lines: &[String], from: Position, matcher: &Matcher) -> Option<(usize, Captures)> {
+    let (row, col) = from;
+    (0..=lines.len()).find_map(|step| {
+        let index = (row + step) % lines.len().max(1);
+        let line = lines.get(index)?;
+        matcher.captures_in_line(line).into_iter()
+            .find(|caps| step > 0 || caps[0].is_some_and(|(start, _)| start >= col))
+            .map(|caps| (index, caps))
+    })
+}
+
+/// The panel's report on one match: where it is, what it and each group matched, and what
+/// the replacement would turn it into.
+pub fn describe(line: &str, row: usize, caps: &Captures, replacement: &str) -> Vec<String> {
+    let text = |range: Option<(usize, usize)>| range.map_or("(no match)".to_string(), |(start, end)| format!("\"{}\"", &line[start..end]));
+    let mut lines = vec![format!("Line {}: {}", row + 1, text(caps[0]))];
+    let groups: Vec<String> = caps.iter().enumerate().skip(1).map(|(index, range)| format!("${} {}", index, text(*range))).collect();
+    if !groups.is_empty() {
+        lines.push(groups.join("  "));
+    }
+    if !replacement.is_empty() {
+        lines.push(format!("Becomes \"{}\"", search::expand_replacement(replacement, line, caps)));
+    }
+    lines
+}
@@ -0,0 +1,71 @@
+//! Strategies for the property tests: short lines of mixed-width text, and edits that
+//! land anywhere in whatever text they are made to.
+
+use proptest::{prelude::*, sample::Index};
+
+use crate::{
+    buffer::{Buffer, Position},
+    undo::History,
+};
+
+const CHARS: [char; 7] = ['a', 'b', ' ', '\t', '\n', 'é', '😀'];
+
+/// Up to `max` characters, multi-byte ones and newlines included.
+pub fn text(max: usize) -> impl Strategy<Value = String> {
+    prop::collection::vec(prop::sample::select(&CHARS[..]), 0..=max).prop_map(String::from_iter)
+}
+
+pub fn lines(max: usize) -> impl Strategy<Value = Vec<String>> {
+    prop::collection::vec(text(8).prop_map(|line| line.replace('\n', "")), 1..=max)
+}
+
+/// A place in text that isn't known yet, which `in_lines` pins to a character boundary,
+/// where the cursor can be.
+#[derive(Debug, Clone, Copy)]
+pub struct Spot(Index, Index);
+
+impl Spot {
+    pub fn in_lines(self, lines: &[String]) -> Position {
+        let row = self.0.index(lines.len());
+        (row, lines[row].floor_char_boundary(self.1.index(lines[row].len() + 1)))
+    }
+}
+
+pub fn spot() -> impl Strategy<Value = Spot> {
+    (any::<Index>(), any::<Index>()).prop_map(|(row, col)| Spot(row, col))
+}
+
+#[derive(Debug, Clone)]
+pub enum Edit {
+    Insert(Spot, String),
+    Delete(Spot, Spot),
+}
+
+impl Edit {
+    pub fn apply(&self, buffer: &mut Buffer) {
+        match self {
+            Edit::Insert(at, text) => {
+                buffer.insert(at.in_lines(buffer), text);
+            }
+            Edit::Delete(a, b) => {
+                let (a, b) = (a.in_lines(buffer), b.in_lines(buffer));
+                buffer.delete(a.min(b), a.max(b));
+            }
+        }
+    }
+}
+
+pub fn edit() -> impl Strategy<Value = Edit> {
+    prop_oneof![
+        (spot(), text(6)).prop_map(|(at, text)| Edit::Insert(at, text)),
+        (spot(), spot()).prop_map(|(a, b)| Edit::Delete(a, b)),
+    ]
+}
+
+/// Files the buffer's pending changes as one undo step, as the editor does after a key.
+pub fn record(history: &mut History, buffer: &mut Buffer) {
+    for event in buffer.take_changes() {
+        history.record(&event.change, (0, 0));
+    }
+    history.seal(usize::MAX);
+}
@@ -0,0 +1,88 @@
+use std::{fs, path::PathBuf};
+
+use toml::Table;
+
+use crate::{config::Config, paths, project, settings};
+
+/// Themes that come with zepto. A file of the same name in the themes directory
+/// replaces one of these.
+const BUILT_IN: &[(&str, &str)] = &[
+    ("dusk", include_str!("../themes/dusk.toml")),
+    ("gruvbox", include_str!("../themes/gruvbox.toml")),
+    ("nord", include_str!("../themes/nord.toml")),
+    ("solarized-dark", include_str!("../themes/solarized-dark.toml")),
+];
+
+/// Where installed themes live: one `name.toml` each, laid out like the config file.
+pub fn themes_dir() -> PathBuf {
+    paths::config_dir().join("themes")
+}
+
+/// Every theme that can be chosen, built-in ones first, then installed ones by name.
+pub fn available() -> Vec<String> {
+    let mut names: Vec<String> = BUILT_IN.iter().map(|(name, _)| name.to_string()).collect();
+    let mut installed: Vec<String> = fs::read_dir(themes_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "toml" {
+                return None;
+            }
+            path.file_stem()?.to_str().map(str::to_string)
+        })
+        .filter(|name| !names.contains(name))
+        .collect();
+    installed.sort();
+    names.append(&mut installed);
+    names
+}
+
+/// Whether `name` can only mean a file directly in the themes directory, so a theme
+/// picked by a project file can't read some other file on disk.
+fn is_plain_name(name: &str) -> bool {
+    !name.contains(['/', '\\', ':']) && !name.contains("..")
+}
+
+fn source(name: &str) -> Result<String, String> {
+    if !is_plain_name(name) {
+        return Err(format!("{:?} is not a theme name", name));
+    }
+    let path = themes_dir().join(format!("{}.toml", name));
+    match fs::read_to_string(&path) {
+        Ok(text) => Ok(text),
+        Err(_) => BUILT_IN.iter()
+            .find(|(built_in, _)| *built_in == name)
+            .map(|(_, text)| text.to_string())
+            .ok_or_else(|| format!("no theme named {}", name)),
+    }
+}
+
+/// `config` with the options theme `name` sets put in place of its own. Themes hold
+/// colors in practice, but any option works, as the theme is applied like `:set`, except
+/// the ones a project file may not set.
+pub fn apply(config: &Config, name: &str) -> Result<Config, String> {
+    let table: Table = toml::from_str(&source(name)?).map_err(|e| format!("theme {}: {}", name, e))?;
+    let mut themed = config.clone();
+    for (key, value) in settings::flatten_table(&table) {
+        if project::is_untrusted(&key) {
+            return Err(format!("theme {}: {} can only be set in your own config", name, key));
+        }
+        themed = settings::set_option(&themed, &key, value).map_err(|e| format!("theme {}: {}", name, e))?;
+    }
+    Ok(themed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn theme_names_cannot_leave_the_themes_directory() {
+        for name in ["../secrets", "/etc/passwd", "a/b", "..\\x", "C:x", ".."] {
+            assert!(source(name).is_err(), "{}", name);
+        }
+        assert!(source("dusk").is_ok());
+    }
+}
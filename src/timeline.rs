@@ -0,0 +1,134 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{backup, paths};
+
+/// One save of a file. The content is stored once per distinct text, under its hash.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Version {
+    /// Seconds since the Unix epoch.
+    pub saved: u64,
+    pub hash: String,
+    pub lines: usize,
+    pub bytes: usize,
+}
+
+impl Version {
+    pub fn age(&self) -> Duration {
+        SystemTime::now().duration_since(UNIX_EPOCH + Duration::from_secs(self.saved)).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Index {
+    #[serde(default)]
+    version: Vec<Version>,
+}
+
+fn history_dir() -> PathBuf {
+    paths::state_dir().join("history")
+}
+
+/// Where the contents with `hash` are kept.
+pub fn object_path(hash: &str) -> PathBuf {
+    history_dir().join("objects").join(hash)
+}
+
+fn index_path(path: &str) -> PathBuf {
+    let name = backup::backup_path(path).file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    history_dir().join(format!("{}.toml", name.to_string_lossy()))
+}
+
+/// FNV-1a, which unlike the standard hasher stays the same from one build to the next.
+fn hash(content: &str) -> String {
+    let hash = content.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3));
+    format!("{:016x}-{}", hash, content.len())
+}
+
+fn read_index(path: &PathBuf) -> io::Result<Index> {
+    match fs::read_to_string(path) {
+        Ok(text) => toml::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Index::default()),
+        Err(e) => Err(e),
+    }
+}
+
+/// The saved versions of `path`, newest first.
+pub fn versions(path: &str) -> io::Result<Vec<Version>> {
+    let mut versions = read_index(&index_path(path))?.version;
+    versions.reverse();
+    Ok(versions)
+}
+
+/// Adds `content`, just saved to `path`, to its timeline and forgets all but the newest
+/// `keep` versions. Saving the same text twice in a row records it once.
+pub fn record(path: &str, content: &str, keep: usize) -> io::Result<()> {
+    let index_path = index_path(path);
+    let mut index = read_index(&index_path)?;
+    let hash = hash(content);
+    if index.version.last().is_some_and(|last| last.hash == hash) {
+        return Ok(());
+    }
+    let object = object_path(&hash);
+    if let Some(dir) = object.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    if !object.exists() {
+        fs::write(&object, content)?;
+    }
+    index.version.push(Version {
+        saved: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+        hash,
+        lines: content.lines().count(),
+        bytes: content.len(),
+    });
+    let dropped: Vec<Version> = index.version.drain(..index.version.len().saturating_sub(keep.max(1))).collect();
+    fs::write(&index_path, toml::to_string(&index).map_err(|e| io::Error::other(e.to_string()))?)?;
+    for version in dropped {
+        if !is_referenced(&version.hash) {
+            let _ = fs::remove_file(object_path(&version.hash));
+        }
+    }
+    Ok(())
+}
+
+/// Whether any file's timeline still holds the contents with `hash`.
+fn is_referenced(hash: &str) -> bool {
+    let Ok(entries) = fs::read_dir(history_dir()) else {
+        return false;
+    };
+    entries.flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|extension| extension == "toml"))
+        .any(|entry| read_index(&entry.path()).is_ok_and(|index| index.version.iter().any(|version| version.hash == hash)))
+}
+
+/// The timeline panel: the saved versions of the current file.
+pub struct Timeline {
+    pub versions: Vec<Version>,
+    pub selected: usize,
+}
+
+impl Timeline {
+    pub fn new(versions: Vec<Version>) -> Self {
+        Timeline { versions, selected: 0 }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.versions.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn selected_version(&self) -> Option<&Version> {
+        self.versions.get(self.selected)
+    }
+}
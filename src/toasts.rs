@@ -0,0 +1,62 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Info => "Info",
+            Severity::Warning => "Warning",
+            Severity::Error => "Error",
+        }
+    }
+}
+
+pub struct Toast {
+    pub severity: Severity,
+    pub text: String,
+    shown_at: Instant,
+}
+
+/// Notifications on screen, oldest first, each until it has been up for a while.
+#[derive(Default)]
+pub struct Toasts {
+    shown: VecDeque<Toast>,
+}
+
+impl Toasts {
+    /// Shows `text`, dropping the oldest toasts beyond `limit`. The same text shown again
+    /// moves to the end and starts over rather than stacking up.
+    pub fn push(&mut self, severity: Severity, text: String, limit: usize) {
+        self.shown.retain(|toast| toast.text != text);
+        self.shown.push_back(Toast { severity, text, shown_at: Instant::now() });
+        while self.shown.len() > limit.max(1) {
+            self.shown.pop_front();
+        }
+    }
+
+    /// Drops the toasts up for `lifetime` or longer. Says whether any went, and how long
+    /// until the next one goes.
+    pub fn expire(&mut self, lifetime: Duration) -> (bool, Option<Duration>) {
+        let before = self.shown.len();
+        self.shown.retain(|toast| toast.shown_at.elapsed() < lifetime);
+        let next = self.shown.iter().map(|toast| lifetime.saturating_sub(toast.shown_at.elapsed())).min();
+        (self.shown.len() != before, next)
+    }
+
+    pub fn clear(&mut self) {
+        self.shown.clear();
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Toast> {
+        self.shown.iter()
+    }
+}
@@ -0,0 +1,102 @@
+use std::{fs, path::Path};
+
+use crate::{
+    finder,
+    highlight::{Syntax, TokenKind, MAX_HIGHLIGHT_LINE_LEN, TODO_TAGS},
+    ignore,
+    tasks::TaskContext,
+};
+
+/// Files bigger than this are skipped by the project scan; they are rarely hand-written.
+const MAX_SCAN_BYTES: u64 = 1 << 20;
+
+/// A TODO, FIXME or HACK tag in a comment.
+#[derive(Debug, Clone)]
+pub struct Todo {
+    /// Relative to the working directory; `None` for the buffer being edited.
+    pub path: Option<String>,
+    pub line: usize,
+    /// Byte column of the tag.
+    pub column: usize,
+    /// The tag and the rest of the comment after it.
+    pub text: String,
+}
+
+/// The tags found in `lines`, in order. Only tags inside comments count, so a `TODO` in a
+/// string or an identifier is left out.
+pub fn find(path: Option<&str>, lines: &[String], syntax: &Syntax) -> Vec<Todo> {
+    let mut todos = Vec::new();
+    for (line_idx, line) in lines.iter().enumerate() {
+        if line.len() > MAX_HIGHLIGHT_LINE_LEN || !TODO_TAGS.iter().any(|tag| line.contains(tag)) {
+            continue;
+        }
+        for token in syntax.highlight_line(line).iter().filter(|token| token.kind == TokenKind::Todo) {
+            let column = line.char_indices().nth(token.start).map_or(line.len(), |(at, _)| at);
+            todos.push(Todo {
+                path: path.map(str::to_string),
+                line: line_idx,
+                column,
+                text: line[column..].trim_end().to_string(),
+            });
+        }
+    }
+    todos
+}
+
+/// Every tag in the files the file finder would list below `root`.
+pub fn scan<T>(root: &Path, rules: ignore::Rules, context: &TaskContext<T>) -> Result<Vec<Todo>, String> {
+    let files = finder::scan(root, rules, context)?;
+    let mut todos = Vec::new();
+    for (done, file) in files.iter().enumerate() {
+        context.check_cancelled()?;
+        context.progress(done as u64, Some(files.len() as u64));
+        let Some(syntax) = Syntax::for_path(file) else {
+            continue;
+        };
+        let path = root.join(file);
+        if !fs::metadata(&path).is_ok_and(|metadata| metadata.len() <= MAX_SCAN_BYTES) {
+            continue;
+        }
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let lines: Vec<String> = text.lines().map(str::to_string).collect();
+        todos.extend(find(Some(file), &lines, syntax));
+    }
+    Ok(todos)
+}
+
+/// The TODO panel: tags in the current buffer, or in the whole project.
+pub struct TodoList {
+    pub todos: Vec<Todo>,
+    pub selected: usize,
+    pub project: bool,
+    /// The project scan has not reported back yet.
+    pub scanning: bool,
+}
+
+impl TodoList {
+    pub fn new(todos: Vec<Todo>, project: bool) -> Self {
+        TodoList { todos, selected: 0, project, scanning: project }
+    }
+
+    pub fn set_todos(&mut self, todos: Vec<Todo>) {
+        self.todos = todos;
+        self.selected = 0;
+        self.scanning = false;
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.todos.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn selected_todo(&self) -> Option<&Todo> {
+        self.todos.get(self.selected)
+    }
+}
@@ -0,0 +1,133 @@
+use crate::buffer::{Buffer, Change, Position};
+
+/// Edits that undo and redo together, with where the cursor was before them.
+struct Step {
+    /// Unique within the history, and higher for later steps.
+    id: u64,
+    changes: Vec<Change>,
+    cursor: Position,
+}
+
+/// The buffer's undo and redo stacks, built from the changes it records. Everything one
+/// key does is one step, so a reload or a block indent goes back in one go. It also
+/// remembers which step the file was saved after, so undoing back to it reads as clean.
+pub struct History {
+    done: Vec<Step>,
+    undone: Vec<Step>,
+    /// The step edits go into until `seal` closes it.
+    open: Option<Step>,
+    next_id: u64,
+    /// The id of the last step done when the file was saved, 0 for none, or `None` when
+    /// the saved text can no longer be reached by undo and redo.
+    saved: Option<u64>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History { done: Vec::new(), undone: Vec::new(), open: None, next_id: 1, saved: Some(0) }
+    }
+}
+
+impl History {
+    /// Adds `change` to the open step, starting one at `cursor` if there is none. A new
+    /// edit forgets what was undone, and a reset of the whole buffer forgets everything,
+    /// the saved point included.
+    pub fn record(&mut self, change: &Change, cursor: Position) {
+        if *change == Change::Reset {
+            *self = History { saved: None, ..History::default() };
+            return;
+        }
+        self.undone.clear();
+        let id = self.next_id;
+        let step = self.open.get_or_insert_with(|| Step { id, changes: Vec::new(), cursor });
+        if step.id == id {
+            self.next_id += 1;
+        }
+        step.changes.push(change.clone());
+    }
+
+    /// Ends the open step, dropping the oldest beyond `limit`.
+    pub fn seal(&mut self, limit: usize) {
+        if let Some(step) = self.open.take() {
+            self.done.push(step);
+        }
+        if self.done.len() > limit {
+            self.done.drain(..self.done.len() - limit);
+            if self.saved.is_some_and(|saved| saved < self.done[0].id) {
+                self.saved = None;
+            }
+        }
+    }
+
+    fn current(&self) -> u64 {
+        self.done.last().map_or(0, |step| step.id)
+    }
+
+    /// Notes that the buffer as it stands is what's on disk. Ends the open step, so
+    /// undo stops at the saved text.
+    pub fn mark_saved(&mut self) {
+        if let Some(step) = self.open.take() {
+            self.done.push(step);
+        }
+        self.saved = Some(self.current());
+    }
+
+    /// Whether the buffer differs from when `mark_saved` was last called.
+    pub fn is_modified(&self) -> bool {
+        self.open.is_some() || self.saved != Some(self.current())
+    }
+
+    #[cfg(test)]
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty() || self.open.is_some()
+    }
+
+    #[cfg(test)]
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Reverts the last step and returns where the cursor was before it.
+    pub fn undo(&mut self, buffer: &mut Buffer) -> Option<Position> {
+        let step = self.done.pop()?;
+        for change in step.changes.iter().rev() {
+            match change {
+                Change::Inserted { at, text } => {
+                    buffer.delete(*at, end_of(*at, text));
+                }
+                Change::Deleted { start, text, .. } => {
+                    buffer.insert(*start, text);
+                }
+                Change::Reset => {}
+            }
+        }
+        let cursor = step.cursor;
+        self.undone.push(step);
+        Some(cursor)
+    }
+
+    /// Makes the last undone step again and returns where it left the cursor.
+    pub fn redo(&mut self, buffer: &mut Buffer) -> Option<Position> {
+        let step = self.undone.pop()?;
+        let mut cursor = step.cursor;
+        for change in &step.changes {
+            cursor = match change {
+                Change::Inserted { at, text } => buffer.insert(*at, text),
+                Change::Deleted { start, end, .. } => {
+                    buffer.delete(*start, *end);
+                    *start
+                }
+                Change::Reset => cursor,
+            };
+        }
+        self.done.push(step);
+        Some(cursor)
+    }
+}
+
+/// Where `text` inserted at `at` ends.
+fn end_of(at: Position, text: &str) -> Position {
+    let rows = text.matches('\n').count();
+    let last = text.rsplit('\n').next().unwrap_or_default().len();
+    if rows == 0 { (at.0, at.1 + last) } else { (at.0 + rows, last) }
+}
@@ -0,0 +1,443 @@
+use std::fmt;
+
+/// The first thing wrong with a data file, 0-based like buffer positions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line + 1, self.column + 1, self.message)
+    }
+}
+
+/// Checks `lines` as the data format named by `filetype`. Filetypes without a checker are
+/// always fine.
+pub fn validate(filetype: &str, lines: &[String]) -> Result<(), SyntaxError> {
+    match filetype {
+        "json" => Json::new(&lines.join("\n")).document(),
+        "toml" => toml_document(&lines.join("\n")),
+        "yaml" => yaml_document(lines),
+        _ => Ok(()),
+    }
+}
+
+/// Line and char column of byte `offset` in `text`.
+fn position_of(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..text.floor_char_boundary(offset)];
+    let line_start = before.rfind('\n').map_or(0, |at| at + 1);
+    (before.matches('\n').count(), before[line_start..].chars().count())
+}
+
+fn error_at(text: &str, offset: usize, message: impl Into<String>) -> SyntaxError {
+    let (line, column) = position_of(text, offset);
+    SyntaxError { line, column, message: message.into() }
+}
+
+fn toml_document(text: &str) -> Result<(), SyntaxError> {
+    text.parse::<toml::Table>().map(|_| ()).map_err(|e| {
+        let offset = e.span().map_or(0, |span| span.start);
+        error_at(text, offset, e.message().trim_end())
+    })
+}
+
+#[derive(Clone, Copy)]
+enum Container {
+    Object,
+    Array,
+}
+
+/// A JSON checker that keeps its own stack, so deep nesting can't overflow ours.
+struct Json<'a> {
+    text: &'a str,
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Json<'a> {
+    fn new(text: &'a str) -> Self {
+        Json { text, bytes: text.as_bytes(), at: 0 }
+    }
+
+    fn error(&self, message: &str) -> SyntaxError {
+        let message = if self.at >= self.bytes.len() { "unexpected end of file" } else { message };
+        error_at(self.text, self.at, message)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.at).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.at += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8, message: &str) -> Result<(), SyntaxError> {
+        self.skip_whitespace();
+        if self.peek() != Some(byte) {
+            return Err(self.error(message));
+        }
+        self.at += 1;
+        Ok(())
+    }
+
+    fn document(mut self) -> Result<(), SyntaxError> {
+        let mut stack = Vec::new();
+        'value: loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b'{') => {
+                    self.at += 1;
+                    self.skip_whitespace();
+                    if self.peek() == Some(b'}') {
+                        self.at += 1;
+                    } else {
+                        stack.push(Container::Object);
+                        self.key()?;
+                        continue 'value;
+                    }
+                }
+                Some(b'[') => {
+                    self.at += 1;
+                    self.skip_whitespace();
+                    if self.peek() == Some(b']') {
+                        self.at += 1;
+                    } else {
+                        stack.push(Container::Array);
+                        continue 'value;
+                    }
+                }
+                Some(b'"') => self.string()?,
+                Some(b't') => self.literal("true")?,
+                Some(b'f') => self.literal("false")?,
+                Some(b'n') => self.literal("null")?,
+                Some(b'-' | b'0'..=b'9') => self.number()?,
+                _ => return Err(self.error("expected a value")),
+            }
+            // A value just ended; close whatever containers end with it.
+            loop {
+                self.skip_whitespace();
+                let Some(&container) = stack.last() else {
+                    break 'value;
+                };
+                let (close, message) = match container {
+                    Container::Object => (b'}', "expected ',' or '}'"),
+                    Container::Array => (b']', "expected ',' or ']'"),
+                };
+                match self.peek() {
+                    Some(b',') => {
+                        self.at += 1;
+                        if let Container::Object = container {
+                            self.key()?;
+                        }
+                        continue 'value;
+                    }
+                    Some(byte) if byte == close => {
+                        self.at += 1;
+                        stack.pop();
+                    }
+                    _ => return Err(self.error(message)),
+                }
+            }
+        }
+        if self.at < self.bytes.len() {
+            return Err(self.error("unexpected characters after the document"));
+        }
+        Ok(())
+    }
+
+    /// An object key and the colon after it.
+    fn key(&mut self) -> Result<(), SyntaxError> {
+        self.skip_whitespace();
+        if self.peek() != Some(b'"') {
+            return Err(self.error("expected a string key"));
+        }
+        self.string()?;
+        self.expect(b':', "expected ':'")
+    }
+
+    fn string(&mut self) -> Result<(), SyntaxError> {
+        let start = self.at;
+        self.at += 1;
+        loop {
+            match self.peek() {
+                None => return Err(error_at(self.text, start, "unterminated string")),
+                Some(b'"') => {
+                    self.at += 1;
+                    return Ok(());
+                }
+                Some(b'\\') => {
+                    self.at += 1;
+                    match self.peek() {
+                        Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => self.at += 1,
+                        Some(b'u') => {
+                            self.at += 1;
+                            for _ in 0..4 {
+                                if !self.peek().is_some_and(|byte| byte.is_ascii_hexdigit()) {
+                                    return Err(self.error("expected four hex digits after \\u"));
+                                }
+                                self.at += 1;
+                            }
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    }
+                }
+                Some(0..0x20) => return Err(self.error("control character in string")),
+                Some(_) => self.at += 1,
+            }
+        }
+    }
+
+    fn literal(&mut self, word: &str) -> Result<(), SyntaxError> {
+        if !self.bytes[self.at..].starts_with(word.as_bytes()) {
+            return Err(self.error("expected a value"));
+        }
+        self.at += word.len();
+        Ok(())
+    }
+
+    fn digits(&mut self) -> Result<(), SyntaxError> {
+        if !self.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+            return Err(self.error("expected a digit"));
+        }
+        while self.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+            self.at += 1;
+        }
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<(), SyntaxError> {
+        if self.peek() == Some(b'-') {
+            self.at += 1;
+        }
+        if self.peek() == Some(b'0') {
+            self.at += 1;
+        } else {
+            self.digits()?;
+        }
+        if self.peek() == Some(b'.') {
+            self.at += 1;
+            self.digits()?;
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            self.at += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.at += 1;
+            }
+            self.digits()?;
+        }
+        Ok(())
+    }
+}
+
+/// Where the YAML check is inside a quoted scalar, which may run over several lines.
+enum Quote {
+    Single,
+    Double,
+}
+
+/// A structural YAML check rather than a parser: it catches tab indentation, unbalanced
+/// flow brackets and unterminated quotes, which covers most hand-editing slips.
+fn yaml_document(lines: &[String]) -> Result<(), SyntaxError> {
+    let error = |line: usize, column: usize, message: &str| SyntaxError { line, column, message: message.to_string() };
+    let mut brackets: Vec<(char, usize, usize)> = Vec::new();
+    let mut quote: Option<(Quote, usize, usize)> = None;
+    // Lines indented deeper than this belong to a `|` or `>` block scalar.
+    let mut block_scalar_indent: Option<usize> = None;
+
+    for (row, line) in lines.iter().enumerate() {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        let content = &line[indent..];
+        if let Some(block_indent) = block_scalar_indent {
+            if content.is_empty() || indent > block_indent {
+                continue;
+            }
+            block_scalar_indent = None;
+        }
+        if quote.is_none() && content.trim().is_empty() {
+            continue;
+        }
+        if quote.is_none() && content.starts_with('\t') {
+            return Err(error(row, indent, "tabs are not allowed in indentation"));
+        }
+
+        let mut previous = ' ';
+        let mut chars = line.chars().enumerate().skip(if quote.is_some() { 0 } else { indent }).peekable();
+        while let Some((column, c)) = chars.next() {
+            match &quote {
+                Some((Quote::Double, ..)) => match c {
+                    '\\' => {
+                        chars.next();
+                    }
+                    '"' => quote = None,
+                    _ => {}
+                },
+                Some((Quote::Single, ..)) => {
+                    if c == '\'' {
+                        // A doubled quote is an escaped one.
+                        if chars.peek().is_some_and(|&(_, next)| next == '\'') {
+                            chars.next();
+                        } else {
+                            quote = None;
+                        }
+                    }
+                }
+                None => {
+                    let token_start = previous.is_whitespace() || matches!(previous, '[' | '{' | ',');
+                    match c {
+                        '#' if previous.is_whitespace() => break,
+                        '"' if token_start => quote = Some((Quote::Double, row, column)),
+                        '\'' if token_start => quote = Some((Quote::Single, row, column)),
+                        '[' | '{' if token_start || !brackets.is_empty() => brackets.push((c, row, column)),
+                        ']' | '}' if !brackets.is_empty() => {
+                            let open = if c == ']' { '[' } else { '{' };
+                            match brackets.pop() {
+                                Some((opened, ..)) if opened == open => {}
+                                _ => return Err(error(row, column, &format!("unexpected '{}'", c))),
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            previous = c;
+        }
+
+        let value = content.split(" #").next().unwrap_or("").trim_end();
+        let block_indicator = value.rsplit(' ').next().unwrap_or("");
+        if quote.is_none()
+            && brackets.is_empty()
+            && block_indicator.starts_with(['|', '>'])
+            && block_indicator[1..].chars().all(|c| matches!(c, '+' | '-' | '1'..='9'))
+        {
+            block_scalar_indent = Some(indent);
+        }
+    }
+
+    // Whichever was left open first is the error; the other may only follow from it.
+    let unclosed_bracket = brackets.first().map(|&(open, row, column)| error(row, column, &format!("unclosed '{}'", open)));
+    let unterminated_quote = quote.map(|(quote, row, column)| {
+        let message = match quote {
+            Quote::Single => "unterminated single-quoted string",
+            Quote::Double => "unterminated double-quoted string",
+        };
+        error(row, column, message)
+    });
+    match [unclosed_bracket, unterminated_quote].into_iter().flatten().min_by_key(|error| (error.line, error.column)) {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(filetype: &str, text: &str) -> Result<(), (usize, usize, String)> {
+        let lines: Vec<String> = text.split('\n').map(str::to_string).collect();
+        validate(filetype, &lines).map_err(|error| (error.line, error.column, error.message))
+    }
+
+    fn error(line: usize, column: usize, message: &str) -> Result<(), (usize, usize, String)> {
+        Err((line, column, message.to_string()))
+    }
+
+    #[test]
+    fn valid_json_passes() {
+        let document = "{\n  \"a\": [1, -2.5e+3, 0, true, false, null],\n  \"b\": {\"c\": \"\\u00e9\\n\\\"\"},\n  \"d\": {}, \"e\": []\n}";
+        assert_eq!(check("json", document), Ok(()));
+        assert_eq!(check("json", "  \"just a string\"  "), Ok(()));
+    }
+
+    #[test]
+    fn json_errors_say_what_and_where() {
+        assert_eq!(check("json", "{\"a\": "), error(0, 6, "unexpected end of file"));
+        assert_eq!(check("json", "[1, ]"), error(0, 4, "expected a value"));
+        assert_eq!(check("json", "{\n  \"a\": tru\n}"), error(1, 7, "expected a value"));
+        assert_eq!(check("json", "{\"a\": 1 \"b\": 2}"), error(0, 8, "expected ',' or '}'"));
+        assert_eq!(check("json", "[\"é\" x]"), error(0, 5, "expected ',' or ']'"));
+        assert_eq!(check("json", "{1: 2}"), error(0, 1, "expected a string key"));
+        assert_eq!(check("json", "{\"a\" 1}"), error(0, 5, "expected ':'"));
+        assert_eq!(check("json", "[\n\"abc"), error(1, 0, "unterminated string"));
+        assert_eq!(check("json", "\"\\u12g4\""), error(0, 5, "expected four hex digits after \\u"));
+        assert_eq!(check("json", "\"\\x\""), error(0, 2, "invalid escape"));
+        assert_eq!(check("json", "\"a\tb\""), error(0, 2, "control character in string"));
+        assert_eq!(check("json", "[1.e5]"), error(0, 3, "expected a digit"));
+        assert_eq!(check("json", "{} x"), error(0, 3, "unexpected characters after the document"));
+    }
+
+    #[test]
+    fn deep_json_nesting_does_not_overflow() {
+        let depth = 200_000;
+        let nested = format!("{}1{}", "[{\"a\":".repeat(depth), "}]".repeat(depth));
+        assert_eq!(check("json", &nested), Ok(()));
+        assert_eq!(check("json", &"[".repeat(depth)), error(0, depth, "unexpected end of file"));
+    }
+
+    #[test]
+    fn toml_errors_point_at_the_span() {
+        assert_eq!(check("toml", "[table]\na = 1\nb = \"x\""), Ok(()));
+        let (line, column, message) = check("toml", "a = 1\nb = [1, 2").unwrap_err();
+        assert_eq!((line, column), (1, 9));
+        assert!(!message.is_empty() && !message.ends_with('\n'));
+        let (line, column, message) = check("toml", "a = 1\na = 2").unwrap_err();
+        assert_eq!((line, column), (1, 0));
+        assert!(message.contains("duplicate"));
+    }
+
+    #[test]
+    fn valid_yaml_passes() {
+        let document = [
+            "# comment with an ' apostrophe",
+            "list: [1, [2, 3], {a: b}]",
+            "text: it's fine",
+            "url: http://x/#anchor",
+            "quoted: \"a # not a comment\"",
+        ];
+        assert_eq!(check("yaml", &document.join("\n")), Ok(()));
+    }
+
+    #[test]
+    fn yaml_block_scalars_are_not_checked() {
+        let document = [
+            "script: |",
+            "  echo \"unbalanced [ and '",
+            "  \tindented with a tab",
+            "",
+            "  still the block",
+            "folded: >-",
+            "    'also open",
+            "after: [ok]",
+        ];
+        assert_eq!(check("yaml", &document.join("\n")), Ok(()));
+        assert_eq!(check("yaml", "a: |\n  text\nb: [1"), error(2, 3, "unclosed '['"));
+    }
+
+    #[test]
+    fn yaml_quoted_scalars_can_span_lines() {
+        assert_eq!(check("yaml", "a: \"first\n  second \\\" still\n  third\"\nb: 1"), Ok(()));
+        assert_eq!(check("yaml", "a: 'it''s\n\tfine'\nb: 1"), Ok(()));
+        assert_eq!(check("yaml", "a: 'open\nb: 1"), error(0, 3, "unterminated single-quoted string"));
+    }
+
+    #[test]
+    fn yaml_errors_say_what_and_where() {
+        assert_eq!(check("yaml", "a:\n\tb: 1"), error(1, 0, "tabs are not allowed in indentation"));
+        assert_eq!(check("yaml", "a: [1, 2}"), error(0, 8, "unexpected '}'"));
+        assert_eq!(check("yaml", "a: [1,\n  2\nb: 3"), error(0, 3, "unclosed '['"));
+        assert_eq!(check("yaml", "a: \"abc"), error(0, 3, "unterminated double-quoted string"));
+        assert_eq!(check("yaml", "a: [1, \"x"), error(0, 3, "unclosed '['"));
+        assert_eq!(check("yaml", "a: x\nb: \"y [\nc: {"), error(1, 3, "unterminated double-quoted string"));
+    }
+
+    #[test]
+    fn deep_yaml_flow_nesting_passes() {
+        let depth = 200_000;
+        assert_eq!(check("yaml", &format!("a: {}{}", "[".repeat(depth), "]".repeat(depth))), Ok(()));
+    }
+}
@@ -0,0 +1,61 @@
+use crate::buffer::{Change, ChangeEvent};
+
+/// Words per line, kept in step with buffer edits so only edited lines are recounted.
+pub struct WordCount {
+    /// `None` for lines that changed since they were last counted.
+    per_line: Vec<Option<usize>>,
+    total: usize,
+}
+
+/// Runs of non-blank characters with at least one letter or digit, so markup such as a
+/// lone `#` or `-` is not counted.
+fn count_words(line: &str) -> usize {
+    line.split_whitespace().filter(|word| word.chars().any(char::is_alphanumeric)).count()
+}
+
+impl WordCount {
+    pub fn new() -> Self {
+        WordCount { per_line: Vec::new(), total: 0 }
+    }
+
+    fn forget(&mut self, row: usize) {
+        if let Some(count) = self.per_line.get_mut(row).and_then(Option::take) {
+            self.total -= count;
+        }
+    }
+
+    /// Follows the line structure of an edit; the text itself is read in `total`.
+    pub fn apply_change(&mut self, event: &ChangeEvent) {
+        match &event.change {
+            Change::Inserted { at: (row, _), text } => {
+                self.forget(*row);
+                let at = (row + 1).min(self.per_line.len());
+                self.per_line.splice(at..at, std::iter::repeat_n(None, text.matches('\n').count()));
+            }
+            Change::Deleted { start: (start_row, _), end: (end_row, _), .. } => {
+                for row in *start_row..=*end_row {
+                    self.forget(row);
+                }
+                let from = (start_row + 1).min(self.per_line.len());
+                let to = (end_row + 1).min(self.per_line.len());
+                self.per_line.drain(from..to);
+            }
+            Change::Reset => *self = WordCount::new(),
+        }
+    }
+
+    pub fn total(&mut self, lines: &[String]) -> usize {
+        if self.per_line.len() != lines.len() {
+            *self = WordCount::new();
+            self.per_line.resize(lines.len(), None);
+        }
+        for (count, line) in self.per_line.iter_mut().zip(lines) {
+            if count.is_none() {
+                let words = count_words(line);
+                *count = Some(words);
+                self.total += words;
+            }
+        }
+        self.total
+    }
+}
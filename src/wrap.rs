@@ -0,0 +1,39 @@
+use crate::display;
+
+/// One screen row of a soft-wrapped line: its characters `start..end`, counted in chars,
+/// drawn after `lead` cells of indentation and continuation marker.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Row {
+    pub start: usize,
+    pub end: usize,
+    pub lead: usize,
+}
+
+/// Splits `line` into rows of at most `width` cells. Rows after the first start with
+/// `marker_width` cells of marker, after the line's own indentation with `indent` set,
+/// as long as at least half the width is left for text.
+pub fn rows(line: &str, width: usize, marker_width: usize, indent: bool) -> Vec<Row> {
+    let width = width.max(1);
+    let indentation = if indent { display::columns_width(line, 0, line.chars().take_while(|c| c.is_whitespace()).count()) } else { 0 };
+    let continued = [indentation + marker_width, marker_width].into_iter().find(|&lead| lead * 2 <= width).unwrap_or(0);
+    let mut rows = Vec::new();
+    let (mut start, mut used, mut lead) = (0, 0, 0);
+    let mut count = 0;
+    for (col, c) in line.chars().enumerate() {
+        let cells = display::char_width(c);
+        if col > start && used + cells > width - lead {
+            rows.push(Row { start, end: col, lead });
+            (start, used, lead) = (col, 0, continued);
+        }
+        used += cells;
+        count = col + 1;
+    }
+    rows.push(Row { start, end: count, lead });
+    rows
+}
+
+/// Which of `rows` the char column `col` is drawn on. A column where one row ends and
+/// the next starts belongs to the next.
+pub fn locate(rows: &[Row], col: usize) -> usize {
+    rows.iter().rposition(|row| row.start <= col).unwrap_or(0)
+}